@@ -190,7 +190,7 @@ mod tests {
         assert_eq!(atlas.tile_size, 128);
         assert_eq!(atlas.version, 1);
 
-        assert_eq!(atlas.frame_count(), 40);
+        assert_eq!(atlas.frame_count(), 55);
 
         assert!(atlas.contains_frame("floor"));
         assert!(atlas.contains_frame("target"));
@@ -0,0 +1,547 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::gamemap::{BehaviourJson, JsonMap, JsonMob, JsonObject, JsonTile, Meta, TileType};
+use super::validation::MapError;
+
+// ============================
+// Compact map format
+// ============================
+//
+// {
+//   "meta": {"name": "level1", "tile_size": 128},
+//   "legend": {
+//     "#": {"tile": {"asset": "concrete"},
+//           "object": {"asset": "wall_tile", "collidable": true, "name": "wall"}},
+//     "-": {"tile": {"asset": "floor"}},
+//     "@": {"tile": {"asset": "floor"}, "mob": {"asset": "idle_se_0", "is_player": true}}
+//   },
+//   "layout": ["#####", "#@-.#", "#####"]
+// }
+//
+// Every layout character is looked up in the legend; ' ' is an empty cell unless the
+// legend defines it, and short rows are padded with empty cells. Entities are named
+// `<name>_<n>` in row order, players `player` (`player_<n>` if there are several).
+
+/// Name of tiles whose legend entry has no name.
+const TILE_NAME: &str = "tile";
+/// Name of objects whose legend entry has no name.
+const OBJECT_NAME: &str = "object";
+/// Name of (non-player) mobs whose legend entry has no name.
+const MOB_NAME: &str = "box";
+/// Name of player mobs.
+const PLAYER_NAME: &str = "player";
+/// Characters given to cells without a conventional character, in order.
+const SPARE_CHARS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!%&=?^~";
+
+/// Tile part of a legend entry.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LegendTile {
+    /// Asset identifier for the tile's appearance
+    pub asset: String,
+
+    /// Logical tile type (default is `Empty`)
+    #[serde(default)]
+    pub tile_type: TileType,
+
+    /// Height level of the tile (default is `0`)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub z: i32,
+
+    /// Name the tiles are numbered under (default `tile`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Color of a target tile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+/// Object part of a legend entry.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LegendObject {
+    /// Asset identifier for the object's appearance
+    pub asset: String,
+
+    /// Indicates if the object can be collided with
+    #[serde(default)]
+    pub collidable: bool,
+
+    /// Indicates if the object casts a shadow
+    #[serde(default)]
+    pub shadow: bool,
+
+    /// Name the objects are numbered under (default `object`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Mob part of a legend entry.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LegendMob {
+    /// Asset identifier for the mob's appearance
+    pub asset: String,
+
+    /// Indicates if this mob represents the player character
+    #[serde(default)]
+    pub is_player: bool,
+
+    /// Behaviour configuration for the mob
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub behaviour: Option<BehaviourJson>,
+
+    /// Name the mobs are numbered under (default `box`, ignored for players)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Color of a box
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+/// Contents of the cells marked by a legend character.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LegendEntry {
+    /// Tile of the cell (the top one if the cell has layers)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tile: Option<LegendTile>,
+
+    /// Further tiles stacked under the top one, bottom to top (e.g. the blocks of a
+    /// raised platform)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<LegendTile>,
+
+    /// Object standing on the cell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object: Option<LegendObject>,
+
+    /// Mob starting on the cell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mob: Option<LegendMob>,
+}
+
+/// Map in the compact JSON format: one string per row plus a legend of the characters.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CompactMap {
+    /// Map meta information; the size may be left out, it follows from the layout
+    pub meta: Meta,
+    /// Contents of the cells marked by each character
+    pub legend: BTreeMap<char, LegendEntry>,
+    /// Rows of the map, top to bottom
+    pub layout: Vec<String>,
+}
+
+impl CompactMap {
+    /// Expands the map into the full JSON form.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<JsonMap, MapError>` - Map with one named entry per tile, object and mob,
+    ///   or [`MapError::Layout`] for characters missing from the legend and a
+    ///   `meta.size` that differs from the layout.
+    pub fn to_json_map(&self) -> Result<JsonMap, MapError> {
+        let height = self.layout.len() as u32;
+        let width = self.layout.iter().map(|row| row.chars().count()).max().unwrap_or(0) as u32;
+        if self.meta.size != [0, 0] && self.meta.size != [width, height] {
+            let [w, h] = self.meta.size;
+            return Err(MapError::Layout(format!(
+                "meta.size is {w}x{h}, but the layout is {width}x{height}"
+            )));
+        }
+
+        let players = self
+            .layout
+            .iter()
+            .flat_map(|row| row.chars())
+            .filter_map(|c| self.legend.get(&c)?.mob.as_ref())
+            .filter(|mob| mob.is_player)
+            .count();
+
+        let mut counters = HashMap::new();
+        let mut map = JsonMap {
+            mobs: HashMap::new(),
+            objects: HashMap::new(),
+            tiles: HashMap::new(),
+            meta: Meta { size: [width, height], ..self.meta.clone() },
+        };
+        for (y, row) in (0..).zip(&self.layout) {
+            for (x, c) in (0..).zip(row.chars()) {
+                let entry = match self.legend.get(&c) {
+                    Some(entry) => entry,
+                    None if c == ' ' => continue,
+                    None => {
+                        return Err(MapError::Layout(format!(
+                            "character '{c}' at ({x}, {y}) is not in the legend"
+                        )))
+                    }
+                };
+
+                for tile in entry.layers.iter().chain(&entry.tile) {
+                    let name = numbered(&mut counters, tile.name.as_deref().unwrap_or(TILE_NAME));
+                    let json = JsonTile {
+                        x,
+                        y,
+                        asset: tile.asset.clone(),
+                        tile_type: tile.tile_type.clone(),
+                        z: tile.z,
+                        color: tile.color.clone(),
+                    };
+                    map.tiles.insert(name, json);
+                }
+                if let Some(object) = &entry.object {
+                    let name =
+                        numbered(&mut counters, object.name.as_deref().unwrap_or(OBJECT_NAME));
+                    let json = JsonObject {
+                        x,
+                        y,
+                        asset: object.asset.clone(),
+                        collidable: object.collidable,
+                        shadow: object.shadow,
+                    };
+                    map.objects.insert(name, json);
+                }
+                if let Some(mob) = &entry.mob {
+                    let name = match (mob.is_player, players) {
+                        (true, 1) => PLAYER_NAME.to_string(),
+                        (true, _) => numbered(&mut counters, PLAYER_NAME),
+                        (false, _) => {
+                            numbered(&mut counters, mob.name.as_deref().unwrap_or(MOB_NAME))
+                        }
+                    };
+                    let json = JsonMob {
+                        x_start: x,
+                        y_start: y,
+                        asset: mob.asset.clone(),
+                        is_player: mob.is_player,
+                        behaviour: mob.behaviour.clone(),
+                        color: mob.color.clone(),
+                    };
+                    map.mobs.insert(name, json);
+                }
+            }
+        }
+        Ok(map)
+    }
+
+    /// Packs a map in the full JSON form into the compact one.
+    ///
+    /// Entity names are kept up to their number suffix (`wall_12` is numbered under
+    /// `wall`). Conventional characters are used where they fit (`#` for collidable
+    /// objects, `.` targets, `@` / `+` the player, `$` / `*` boxes, `-` floor, digits for
+    /// links), other cells get spare letters.
+    ///
+    /// # Arguments
+    ///
+    /// * `map` - Map to convert
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, MapError>` - The compact map, or [`MapError::Layout`] if entries
+    ///   lie outside the map, share a cell with entries of the same kind (tiles: at the
+    ///   same height) or there are more different cells than characters.
+    pub fn from_json_map(map: &JsonMap) -> Result<Self, MapError> {
+        let [width, height] = map.meta.size;
+        let mut cells = vec![LegendEntry::default(); (width * height) as usize];
+        let cell = |kind: &str, name: &str, (x, y): (u32, u32)| {
+            if x < width && y < height {
+                Ok((y * width + x) as usize)
+            } else {
+                Err(MapError::Layout(format!("{kind}.{name} at ({x}, {y}) is outside the map")))
+            }
+        };
+        let occupied = |kind: &str, name: &str, (x, y): (u32, u32)| {
+            MapError::Layout(format!("{kind}.{name} at ({x}, {y}) shares the cell with another"))
+        };
+
+        // Bottom to top, so every tile covers the ones already in its cell
+        let mut tiles = sorted(&map.tiles);
+        tiles.sort_by_key(|(_, tile)| tile.z);
+        for (name, tile) in tiles {
+            let idx = cell("tiles", name, (tile.x, tile.y))?;
+            let legend_tile = LegendTile {
+                asset: tile.asset.clone(),
+                tile_type: tile.tile_type.clone(),
+                z: tile.z,
+                name: base_name(name, TILE_NAME),
+                color: tile.color.clone(),
+            };
+            match cells[idx].tile.replace(legend_tile) {
+                Some(below) if below.z == tile.z => {
+                    return Err(occupied("tiles", name, (tile.x, tile.y)))
+                }
+                Some(below) => cells[idx].layers.push(below),
+                None => {}
+            }
+        }
+        for (name, object) in sorted(&map.objects) {
+            let idx = cell("objects", name, (object.x, object.y))?;
+            if cells[idx].object.is_some() {
+                return Err(occupied("objects", name, (object.x, object.y)));
+            }
+            cells[idx].object = Some(LegendObject {
+                asset: object.asset.clone(),
+                collidable: object.collidable,
+                shadow: object.shadow,
+                name: base_name(name, OBJECT_NAME),
+            });
+        }
+        for (name, mob) in sorted(&map.mobs) {
+            let idx = cell("mobs", name, (mob.x_start, mob.y_start))?;
+            if cells[idx].mob.is_some() {
+                return Err(occupied("mobs", name, (mob.x_start, mob.y_start)));
+            }
+            cells[idx].mob = Some(LegendMob {
+                asset: mob.asset.clone(),
+                is_player: mob.is_player,
+                behaviour: mob.behaviour.clone(),
+                name: if mob.is_player { None } else { base_name(name, MOB_NAME) },
+                color: mob.color.clone(),
+            });
+        }
+
+        let empty = LegendEntry::default();
+        let mut chars: Vec<(&LegendEntry, char)> = Vec::new();
+        let mut layout = Vec::with_capacity(height as usize);
+        for row in cells.chunks(width.max(1) as usize).take(height as usize) {
+            let mut line = String::new();
+            for entry in row {
+                let c = match chars.iter().find(|(known, _)| *known == entry) {
+                    Some(&(_, c)) => c,
+                    None if *entry == empty => ' ',
+                    None => {
+                        let taken = |c: &char| *c == ' ' || chars.iter().any(|(_, t)| t == c);
+                        let c = conventional_char(entry)
+                            .filter(|c| !taken(c))
+                            .or_else(|| SPARE_CHARS.chars().find(|c| !taken(c)))
+                            .ok_or_else(|| {
+                                MapError::Layout(format!(
+                                    "more than {} different cells",
+                                    SPARE_CHARS.len()
+                                ))
+                            })?;
+                        chars.push((entry, c));
+                        c
+                    }
+                };
+                line.push(c);
+            }
+            layout.push(line);
+        }
+
+        let legend = chars.into_iter().map(|(entry, c)| (c, entry.clone())).collect();
+        Ok(CompactMap { meta: map.meta.clone(), legend, layout })
+    }
+}
+
+/// Returns the next numbered name under a base name.
+///
+/// # Arguments
+///
+/// * `counters` - Number of names handed out per base name
+/// * `base` - Base name, e.g. `wall`
+fn numbered(counters: &mut HashMap<String, u32>, base: &str) -> String {
+    let count = counters.entry(base.to_string()).or_default();
+    *count += 1;
+    format!("{base}_{count}")
+}
+
+/// Strips the number suffix from an entity name.
+///
+/// # Arguments
+///
+/// * `name` - Entity name, e.g. `wall_12`
+/// * `default` - Base name that is left out of the legend
+///
+/// # Returns
+///
+/// * `Option<String>` - The base name, `None` if it is the default.
+fn base_name(name: &str, default: &str) -> Option<String> {
+    let base = match name.rsplit_once('_') {
+        Some((base, number)) if number.parse::<u32>().is_ok() => base,
+        _ => name,
+    };
+    (base != default).then(|| base.to_string())
+}
+
+/// Checks whether a height level is the ground level (skips it when serializing).
+fn is_zero(z: &i32) -> bool {
+    *z == 0
+}
+
+/// Returns the entries of a map sorted by name.
+fn sorted<T>(entries: &HashMap<String, T>) -> Vec<(&String, &T)> {
+    let mut entries: Vec<_> = entries.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+/// Returns the character conventionally used for a cell, if there is one.
+///
+/// # Arguments
+///
+/// * `entry` - Contents of the cell
+fn conventional_char(entry: &LegendEntry) -> Option<char> {
+    let tile_type = entry.tile.as_ref().map(|tile| &tile.tile_type);
+    let on_target = tile_type == Some(&TileType::Target);
+    // Colors have no conventional character
+    let colored = entry.mob.as_ref().is_some_and(|mob| mob.color.is_some())
+        || entry.tile.as_ref().is_some_and(|tile| tile.color.is_some());
+    if colored {
+        return None;
+    }
+
+    match (&entry.mob, &entry.object, &entry.tile) {
+        (Some(mob), _, _) => Some(match (mob.is_player, on_target) {
+            (true, true) => '+',
+            (true, false) => '@',
+            (false, true) => '*',
+            (false, false) => '$',
+        }),
+        (None, Some(object), _) => object.collidable.then_some('#'),
+        (None, None, Some(tile)) => match tile.tile_type {
+            TileType::Target => Some('.'),
+            TileType::Link(id) => char::from_digit(id, 10),
+            TileType::Ramp => Some('/'),
+            TileType::Wall => None,
+            // Mechanisms have no conventional character
+            TileType::Ice
+            | TileType::OneWay(_)
+            | TileType::Hole
+            | TileType::Teleporter(_)
+            | TileType::Plate(_)
+            | TileType::Door(_) => None,
+            // Raised and sunken floor has no conventional character
+            TileType::Empty if tile.z != 0 || !entry.layers.is_empty() => None,
+            // Letter tiles show their letter
+            TileType::Empty => match tile.asset.rsplit_once('_') {
+                Some((_, letter)) if letter.len() == 1 => letter.chars().next(),
+                _ => Some('-'),
+            },
+        },
+        (None, None, None) => None,
+    }
+}
+
+// ============================
+// Tests
+// ============================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::GameMap;
+
+    fn names<T>(entries: &HashMap<String, T>) -> Vec<&str> {
+        sorted(entries).into_iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_compact_map_is_expanded() {
+        let compact: CompactMap = serde_json::from_value(serde_json::json!({
+            "meta": {"name": "small", "tile_size": 128},
+            "legend": {
+                "#": {"tile": {"asset": "concrete"},
+                      "object": {"asset": "wall_tile", "collidable": true, "name": "wall"}},
+                "-": {"tile": {"asset": "floor"}},
+                ".": {"tile": {"asset": "target", "tile_type": "target"}},
+                "$": {"tile": {"asset": "floor"}, "mob": {"asset": "box"}},
+                "@": {"tile": {"asset": "floor"},
+                      "mob": {"asset": "idle_se_0", "is_player": true,
+                              "behaviour": {"type": "controlled"}}}
+            },
+            "layout": ["#####", "#@$.#", "# -#"]
+        }))
+        .unwrap();
+        let map = GameMap::from_json_map(compact.to_json_map().unwrap());
+
+        assert_eq!(map.size, [5, 3]);
+        assert_eq!(map.tile_count(), 13);
+        assert_eq!(map.object_count(), 9);
+        assert_eq!(map.get_object("wall_9").unwrap().position(), (3, 2));
+        assert_eq!(map.get_tile("tile_13").unwrap().position(), (3, 2));
+        assert_eq!(names(&map.mobs), ["box_1", "player"]);
+        assert_eq!(map.get_mob("player").unwrap().start_position(), (1, 1));
+        assert_eq!(map.get_mob("box_1").unwrap().start_position(), (2, 1));
+        // Blanks and padding are empty cells
+        assert!(map.iter_tiles().all(|tile| ![(1, 2), (4, 2)].contains(&tile.position())));
+        assert_eq!(map.target_positions.iter().collect::<Vec<_>>(), [&(3, 1)]);
+    }
+
+    #[test]
+    fn test_layout_errors() {
+        let mut compact = CompactMap {
+            meta: Meta { name: "bad".into(), tile_size: 128, size: [0, 0] },
+            legend: BTreeMap::new(),
+            layout: vec!["x".into()],
+        };
+        let err = compact.to_json_map().unwrap_err();
+        assert!(err.to_string().contains("'x' at (0, 0)"), "{err}");
+
+        compact.layout = vec![" ".into()];
+        compact.meta.size = [2, 1];
+        assert!(matches!(compact.to_json_map(), Err(MapError::Layout(_))));
+    }
+
+    #[test]
+    fn test_layers_and_heights() {
+        let compact: CompactMap = serde_json::from_value(serde_json::json!({
+            "meta": {"name": "raised", "tile_size": 128},
+            "legend": {
+                "-": {"tile": {"asset": "floor"}},
+                "/": {"layers": [{"asset": "concrete"}],
+                      "tile": {"asset": "ramp", "tile_type": "ramp", "z": 1}},
+                "^": {"layers": [{"asset": "concrete"}], "tile": {"asset": "floor", "z": 1}}
+            },
+            "layout": ["-/^", "--^"]
+        }))
+        .unwrap();
+        let map = GameMap::from_json_map(compact.to_json_map().unwrap());
+
+        assert_eq!(map.tile_count(), 9);
+        assert_eq!(map.height_map, [0, 1, 1, 0, 0, 1]);
+        // Ramps connect levels, plain steps do not
+        assert!(map.is_walkable_from((0, 0), (1, 0)));
+        assert!(map.is_walkable_from((2, 0), (1, 0)));
+        assert!(!map.is_walkable_from((1, 1), (2, 1)));
+        assert!(map.is_walkable_from((2, 0), (2, 1)));
+        assert_eq!(map.elevation_at(0.5, 0.0), 0.5);
+
+        let again = CompactMap::from_json_map(&map.to_json_map()).unwrap();
+        // Raised floor has no conventional character
+        assert_eq!(again.layout, ["-/a", "--a"]);
+        assert_eq!(again.legend[&'a'].layers.len(), 1);
+        assert_eq!(GameMap::from_json_map(again.to_json_map().unwrap()).height_map, map.height_map);
+    }
+
+    #[test]
+    fn test_levels_round_trip() {
+        let paths = [
+            "../game_levels/level2.json",
+            "../game_levels/level5.json",
+            "assets/testdata/menu.json",
+        ];
+        for path in paths {
+            let map = GameMap::load(path).unwrap();
+            let compact = CompactMap::from_json_map(&map.to_json_map()).unwrap();
+            let text = serde_json::to_string(&compact).unwrap();
+            let expanded: CompactMap = serde_json::from_str(&text).unwrap();
+            let again = GameMap::from_json_map(expanded.to_json_map().unwrap());
+
+            assert_eq!(again.to_json_map(), map.to_json_map(), "{path}");
+        }
+
+        let map = GameMap::from_xsb("test", &["#####", "#@$.#", "#####"]).unwrap();
+        let compact = CompactMap::from_json_map(&map.to_json_map()).unwrap();
+        assert_eq!(compact.layout, ["#####", "#@$.#", "#####"]);
+        assert_eq!(compact.legend[&'#'].object.as_ref().unwrap().name.as_deref(), Some("wall"));
+
+        // Colored boxes and targets have no conventional character
+        let mut json = map.to_json_map();
+        json.mobs.get_mut("box_1").unwrap().color = Some("red".to_string());
+        json.tiles.values_mut().find(|t| t.x == 3 && t.y == 1).unwrap().color = Some("red".into());
+        let compact = CompactMap::from_json_map(&json).unwrap();
+        assert!(!compact.layout[1].contains(['$', '.']), "{:?}", compact.layout);
+        assert_eq!(compact.to_json_map().unwrap(), json);
+    }
+}
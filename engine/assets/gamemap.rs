@@ -1,11 +1,15 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, LinkedList};
-use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
 use std::vec;
 
+use super::atlas::Atlas;
+use super::compact::CompactMap;
+use super::validation::MapError;
+use crate::world::Direction;
+
 // TODO: delete mobs from json!
 
 // ============================
@@ -13,23 +17,23 @@ use std::vec;
 // ============================
 
 /// Behaviour data from JSON.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct BehaviourJson {
     /// Type of behaviour
     #[serde(rename = "type")]
     pub behaviour_type: String,
 
     /// Direction for the behaviour
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub direction: Option<String>,
 
     /// Speed value for the behaviour
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub speed: Option<f32>,
 }
 
 /// Mob data from JSON.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct JsonMob {
     /// Starting X coordinate of the mob
     pub x_start: u32,
@@ -43,12 +47,16 @@ pub struct JsonMob {
     pub is_player: bool,
 
     /// Behaviour configuration for the mob
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub behaviour: Option<BehaviourJson>,
+
+    /// Color of a box, which then only counts as placed on a target of the same color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
 }
 
 /// Object data from JSON.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct JsonObject {
     /// X coordinate of the object
     pub x: u32,
@@ -67,7 +75,7 @@ pub struct JsonObject {
 }
 
 /// Tile type that defines its properties in game logic.
-#[derive(Default, Deserialize, Debug, Clone)]
+#[derive(Default, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum TileType {
     /// Passable empty tile
     #[default]
@@ -83,10 +91,49 @@ pub enum TileType {
     /// Link to some id (e.g. map id )
     #[serde(rename = "link")]
     Link(u32),
+
+    /// Passable slope or stairs, connecting cells one height level apart
+    #[serde(rename = "ramp")]
+    Ramp,
+
+    /// Slippery floor: a unit stepping onto it slides on until something stops it
+    #[serde(rename = "ice")]
+    Ice,
+    /// Arrow that can only be entered and left in its direction
+    #[serde(rename = "one_way")]
+    OneWay(Direction),
+    /// Pit the player cannot enter; the first box pushed into it fills it up
+    #[serde(rename = "hole")]
+    Hole,
+    /// Teleporter to the other teleporter with the same id
+    #[serde(rename = "teleporter")]
+    Teleporter(u32),
+    /// Pressure plate, opening the doors with the same id while something stands on it
+    #[serde(rename = "plate")]
+    Plate(u32),
+    /// Door, passable only while a plate with the same id is pressed
+    #[serde(rename = "door")]
+    Door(u32),
+}
+
+impl TileType {
+    /// Checks whether the tile changes how units move (ice, one-way arrows, holes,
+    /// teleporters, plates and doors).
+    pub fn is_mechanism(&self) -> bool {
+        matches!(
+            self,
+            TileType::Ice
+                | TileType::OneWay(_)
+                | TileType::Hole
+                | TileType::Teleporter(_)
+                | TileType::Plate(_)
+                | TileType::Door(_)
+        )
+    }
 }
 
 /// Tile data from JSON.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct JsonTile {
     /// X coordinate of the tile
     pub x: u32,
@@ -98,10 +145,19 @@ pub struct JsonTile {
     /// Logical tile type (default is `Empty`)
     #[serde(default)]
     pub tile_type: TileType,
+
+    /// Height level the tile lies at (default is `0`, negative for pits); several tiles
+    /// may be stacked in one cell at different levels
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub z: i32,
+
+    /// Color of a target tile, only boxes of the same color count as placed on it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
 }
 
 /// Meta information about the game map from JSON.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Meta {
     /// Name of the map
     pub name: String,
@@ -116,7 +172,7 @@ pub struct Meta {
 }
 
 /// Complete map structure from JSON.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct JsonMap {
     /// Mapping of mobs' names to their definitions
     pub mobs: HashMap<String, JsonMob>,
@@ -171,6 +227,8 @@ pub struct Mob {
     pub is_player: bool,
     /// Behaviour configuration for the mob
     pub behaviour: Option<Behaviour>,
+    /// Color of a box (`None` for plain boxes)
+    pub color: Option<String>,
 }
 
 /// Static object in the game world.
@@ -205,6 +263,10 @@ pub struct Tile {
     pub asset: String,
     /// Logical type of tile
     pub tile_type: TileType,
+    /// Height level the tile lies at
+    pub z: i32,
+    /// Color of a target tile (`None` for plain targets)
+    pub color: Option<String>,
 }
 
 /// Game map, as parsed and ready to use.
@@ -227,8 +289,12 @@ pub struct GameMap {
     pub target_positions: LinkedList<(u32, u32)>,
     /// Mapping of (link) tyle coordinates x,y to due id
     pub links: HashMap<(u32, u32), u32>,
-    /// 1D vector representing the map's logical tile types
+    /// Mapping of colored target coordinates x,y to their color
+    pub target_colors: HashMap<(u32, u32), String>,
+    /// 1D vector representing the map's logical tile types (of the top tile of each cell)
     pub walk_map: Vec<TileType>,
+    /// 1D vector holding the height level of each cell (of its top tile, `0` without tiles)
+    pub height_map: Vec<i32>,
     /// 1D vector indicating if a tile is occupied by a collidable static object
     pub object_collidable_map: Vec<bool>,
 }
@@ -240,18 +306,92 @@ pub struct GameMap {
 impl GameMap {
     /// Loads and parses a game map from a JSON file.
     ///
+    /// Both the full format (named `tiles`, `objects` and `mobs` entries) and the compact
+    /// one (a `layout` of rows plus a `legend`, see [`CompactMap`]) are accepted.
+    /// Files with an `.xsb` or `.sok` extension are read as XSB text instead
+    /// (first level of the collection), see [`GameMap::load_xsb`].
+    /// The map is validated (see [`GameMap::validate`]) and rejected if it has any problem.
+    ///
     /// # Arguments
     ///
     /// * `json_path` - Path to the JSON file containing map data
     ///
     /// # Returns
     ///
-    /// * `Result<Self, Box<dyn Error>>` - Parsed GameMap on success, error on failure.
-    pub fn load<P: AsRef<Path>>(json_path: P) -> Result<Self, Box<dyn Error>> {
+    /// * `Result<Self, MapError>` - Parsed GameMap on success, error on failure.
+    pub fn load<P: AsRef<Path>>(json_path: P) -> Result<Self, MapError> {
+        let map = Self::load_unchecked(json_path)?;
+        let issues = map.validate();
+        if !issues.is_empty() {
+            return Err(MapError::Invalid(issues));
+        }
+        Ok(map)
+    }
+
+    /// Loads and validates a game map like [`GameMap::load`], also rejecting it if any of
+    /// its assets has no frame in the atlases (see [`GameMap::check_assets`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `json_path` - Path to the JSON or XSB file containing map data
+    /// * `tiles_atlas` - Atlas of the tiles and objects
+    /// * `entities_atlas` - Atlas of the mobs
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, MapError>` - Parsed GameMap on success, error on failure.
+    pub fn load_with_atlases<P: AsRef<Path>>(
+        json_path: P,
+        tiles_atlas: &Atlas,
+        entities_atlas: &Atlas,
+    ) -> Result<Self, MapError> {
+        let map = Self::load_unchecked(json_path)?;
+        let issues = map.validate_with_atlases(tiles_atlas, entities_atlas);
+        if !issues.is_empty() {
+            return Err(MapError::Invalid(issues));
+        }
+        Ok(map)
+    }
+
+    /// Loads and parses a game map like [`GameMap::load`], without validating it.
+    ///
+    /// # Arguments
+    ///
+    /// * `json_path` - Path to the JSON or XSB file containing map data
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, MapError>` - Parsed GameMap on success, read or parse error on
+    ///   failure.
+    pub fn load_unchecked<P: AsRef<Path>>(json_path: P) -> Result<Self, MapError> {
+        let extension = json_path.as_ref().extension().and_then(|e| e.to_str());
+        if extension.is_some_and(|e| e.eq_ignore_ascii_case("xsb") || e.eq_ignore_ascii_case("sok"))
+        {
+            return Self::load_xsb(json_path).map_err(|err| MapError::Xsb(err.to_string()));
+        }
+
         let file = File::open(json_path)?;
         let reader = BufReader::new(file);
-        let map_json: JsonMap = serde_json::from_reader(reader)?;
+        let value: serde_json::Value = serde_json::from_reader(reader)?;
+        let map_json = if value.get("layout").is_some() {
+            serde_json::from_value::<CompactMap>(value)?.to_json_map()?
+        } else {
+            serde_json::from_value(value)?
+        };
+
+        Ok(Self::from_json_map(map_json))
+    }
 
+    /// Builds a game map from its full JSON form.
+    ///
+    /// # Arguments
+    ///
+    /// * `map_json` - Parsed JSON map
+    ///
+    /// # Returns
+    ///
+    /// * `GameMap` - The map, not validated.
+    pub fn from_json_map(map_json: JsonMap) -> Self {
         let width = map_json.meta.size[0] as usize;
         let height = map_json.meta.size[1] as usize;
 
@@ -275,6 +415,7 @@ impl GameMap {
                 asset: mob_data.asset,
                 is_player: mob_data.is_player,
                 behaviour,
+                color: mob_data.color,
             };
             mobs.insert(name, mob);
         }
@@ -301,35 +442,51 @@ impl GameMap {
             objects.insert(name, object);
         }
 
-        // Process Tiles and build Walk map
+        // Process Tiles (bottom to top, so the top tile of a cell wins) and build Walk map
         let mut tiles = HashMap::new();
         let mut walk_map = vec![TileType::Empty; width * height];
-        let mut target_positions = LinkedList::new();
+        let mut height_map = vec![0; width * height];
+        let mut top_colors = vec![None; width * height];
         let mut links = HashMap::new();
-        for (name, tile_data) in map_json.tiles {
+        let mut target_colors = HashMap::new();
+        let mut tiles_json: Vec<_> = map_json.tiles.into_iter().collect();
+        tiles_json.sort_by(|(a_name, a), (b_name, b)| (a.z, a_name).cmp(&(b.z, b_name)));
+        for (name, tile_data) in tiles_json {
             let tile = Tile {
                 name: name.clone(),
                 x: tile_data.x,
                 y: tile_data.y,
                 asset: tile_data.asset,
                 tile_type: tile_data.tile_type,
+                z: tile_data.z,
+                color: tile_data.color,
             };
 
             let idx = tile.y as usize * width + tile.x as usize;
             if idx < (width * height) {
                 walk_map[idx] = tile.tile_type.clone();
+                height_map[idx] = tile.z;
+                top_colors[idx] = tile.color.clone();
             }
-            match tile.tile_type {
-                TileType::Target => target_positions.push_front((tile.x, tile.y)),
-                TileType::Link(id) => {
-                    links.insert((tile_data.x, tile_data.y), id);
-                }
-                _ => (),
+            if let TileType::Link(id) = tile.tile_type {
+                links.insert((tile_data.x, tile_data.y), id);
             }
             tiles.insert(name, tile);
         }
 
-        Ok(GameMap {
+        // Only targets on top of their cell can be reached (buried ones are covered)
+        let mut target_positions = LinkedList::new();
+        for (idx, tile_type) in walk_map.iter().enumerate() {
+            if *tile_type == TileType::Target {
+                let position = ((idx % width) as u32, (idx / width) as u32);
+                target_positions.push_back(position);
+                if let Some(color) = top_colors[idx].take() {
+                    target_colors.insert(position, color);
+                }
+            }
+        }
+
+        GameMap {
             name: map_json.meta.name,
             tile_size: map_json.meta.tile_size,
             size: map_json.meta.size,
@@ -337,10 +494,69 @@ impl GameMap {
             objects,
             tiles,
             walk_map,
+            height_map,
             object_collidable_map,
             target_positions,
             links,
-        })
+            target_colors,
+        }
+    }
+
+    /// Converts the map (with mobs at their start positions) to its full JSON form.
+    ///
+    /// # Returns
+    ///
+    /// * `JsonMap` - Map that [`GameMap::from_json_map`] turns back into this one.
+    pub fn to_json_map(&self) -> JsonMap {
+        let mobs = self.mobs.values().map(|mob| {
+            let behaviour = mob.behaviour.as_ref().map(|b| BehaviourJson {
+                behaviour_type: match b.behaviour_type {
+                    BehaviourType::Controlled => "controlled",
+                    BehaviourType::Walker => "walker",
+                    BehaviourType::Unknown => "unknown",
+                }
+                .to_string(),
+                direction: b.direction.clone(),
+                speed: b.speed,
+            });
+            let json = JsonMob {
+                x_start: mob.x_start,
+                y_start: mob.y_start,
+                asset: mob.asset.clone(),
+                is_player: mob.is_player,
+                behaviour,
+                color: mob.color.clone(),
+            };
+            (mob.name.clone(), json)
+        });
+        let objects = self.objects.values().map(|object| {
+            let json = JsonObject {
+                x: object.x,
+                y: object.y,
+                asset: object.asset.clone(),
+                collidable: object.collidable,
+                shadow: object.shadow,
+            };
+            (object.name.clone(), json)
+        });
+        let tiles = self.tiles.values().map(|tile| {
+            let json = JsonTile {
+                x: tile.x,
+                y: tile.y,
+                asset: tile.asset.clone(),
+                tile_type: tile.tile_type.clone(),
+                z: tile.z,
+                color: tile.color.clone(),
+            };
+            (tile.name.clone(), json)
+        });
+
+        JsonMap {
+            mobs: mobs.collect(),
+            objects: objects.collect(),
+            tiles: tiles.collect(),
+            meta: Meta { name: self.name.clone(), tile_size: self.tile_size, size: self.size },
+        }
     }
 
     /// Retrieves a mob by name.
@@ -466,6 +682,75 @@ impl GameMap {
         !matches!(self.walk_map[idx], TileType::Wall)
     }
 
+    /// Returns the logical type of the top tile of a cell.
+    ///
+    /// # Arguments
+    ///
+    /// * `tile_x`, `tile_y` - tile coordinates
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&TileType>` - Type of the cell, `None` outside the map.
+    pub fn tile_type_at(&self, tile_x: i32, tile_y: i32) -> Option<&TileType> {
+        let [width, height] = self.size.map(|v| v as i32);
+        if tile_x < 0 || tile_y < 0 || tile_x >= width || tile_y >= height {
+            return None;
+        }
+        self.walk_map.get((tile_y * width + tile_x) as usize)
+    }
+
+    /// Checks whether a box of the given color counts as placed on a cell: the cell is a
+    /// target of the same color (plain boxes go on plain targets).
+    ///
+    /// # Arguments
+    ///
+    /// * `tile_x`, `tile_y` - tile coordinates
+    /// * `color` - color of the box, `None` for a plain box
+    ///
+    /// # Returns
+    ///
+    /// * `true` if the cell is a target matching the box.
+    /// * `false` — otherwise.
+    pub fn accepts_box(&self, tile_x: u32, tile_y: u32, color: Option<&str>) -> bool {
+        self.target_positions.contains(&(tile_x, tile_y))
+            && self.target_colors.get(&(tile_x, tile_y)).map(String::as_str) == color
+    }
+
+    /// Checks whether any box or target of the map has a color.
+    pub fn has_colors(&self) -> bool {
+        !self.target_colors.is_empty() || self.mobs.values().any(|mob| mob.color.is_some())
+    }
+
+    /// Checks whether the map has any tile that changes how units move
+    /// (see [`TileType::is_mechanism`]).
+    pub fn has_mechanisms(&self) -> bool {
+        self.walk_map.iter().any(TileType::is_mechanism)
+    }
+
+    /// Returns the cell a teleporter leads to.
+    ///
+    /// # Arguments
+    ///
+    /// * `tile_x`, `tile_y` - tile coordinates of the teleporter
+    ///
+    /// # Returns
+    ///
+    /// * `Option<(i32, i32)>` - The other teleporter with the same id, `None` if the cell
+    ///   is not a teleporter or has no partner.
+    pub fn teleporter_exit(&self, tile_x: i32, tile_y: i32) -> Option<(i32, i32)> {
+        let Some(&TileType::Teleporter(id)) = self.tile_type_at(tile_x, tile_y) else {
+            return None;
+        };
+        let width = self.size[0] as i32;
+        let entrance = (tile_y * width + tile_x) as usize;
+
+        self.walk_map
+            .iter()
+            .enumerate()
+            .find(|&(idx, tile_type)| idx != entrance && *tile_type == TileType::Teleporter(id))
+            .map(|(idx, _)| (idx as i32 % width, idx as i32 / width))
+    }
+
     /// Checks whether there is a collidable object at the given tile coordinates.
     ///
     /// # Returns
@@ -477,6 +762,109 @@ impl GameMap {
         let idx = (tile_y as usize) * (width as usize) + (tile_x as usize);
         self.object_collidable_map[idx]
     }
+
+    /// Returns the height level of a cell.
+    ///
+    /// # Arguments
+    ///
+    /// * `tile_x`, `tile_y` - tile coordinates
+    ///
+    /// # Returns
+    ///
+    /// * `i32` - Height level of the top tile of the cell, `0` outside the map.
+    pub fn height_at(&self, tile_x: i32, tile_y: i32) -> i32 {
+        let [width, height] = self.size;
+        if tile_x < 0 || tile_y < 0 || tile_x >= width as i32 || tile_y >= height as i32 {
+            return 0;
+        }
+        self.height_map[tile_y as usize * width as usize + tile_x as usize]
+    }
+
+    /// Checks whether the height difference between two neighbouring cells can be crossed.
+    ///
+    /// Cells at the same level are always connected; cells one level apart only if one of
+    /// them is a ramp.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - tile coordinates of the cell the step starts on
+    /// * `to` - tile coordinates of the cell the step ends on
+    ///
+    /// # Returns
+    ///
+    /// * `true` if the step does not need to climb or drop more than the map allows.
+    /// * `false` — otherwise, or if either cell is outside the map.
+    pub fn can_climb(&self, from: (i32, i32), to: (i32, i32)) -> bool {
+        let [width, height] = self.size.map(|v| v as i32);
+        let inside = |(x, y): (i32, i32)| x >= 0 && y >= 0 && x < width && y < height;
+        if !inside(from) || !inside(to) {
+            return false;
+        }
+
+        let is_ramp =
+            |(x, y): (i32, i32)| matches!(self.walk_map[(y * width + x) as usize], TileType::Ramp);
+        match (self.height_at(to.0, to.1) - self.height_at(from.0, from.1)).abs() {
+            0 => true,
+            1 => is_ramp(from) || is_ramp(to),
+            _ => false,
+        }
+    }
+
+    /// Checks whether a step from a cell onto its neighbour is possible: the neighbour is
+    /// passable (see [`GameMap::is_walkable`]) and the height difference can be crossed
+    /// (see [`GameMap::can_climb`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - tile coordinates of the cell the step starts on
+    /// * `to` - tile coordinates of the cell the step ends on
+    pub fn is_walkable_from(&self, from: (i32, i32), to: (i32, i32)) -> bool {
+        self.is_walkable(to.0, to.1) && self.can_climb(from, to)
+    }
+
+    /// Returns the height level at a point between cell centers, interpolated between the
+    /// surrounding cells (so units glide up ramps instead of jumping).
+    ///
+    /// # Arguments
+    ///
+    /// * `tile_x`, `tile_y` - fractional tile coordinates
+    pub fn elevation_at(&self, tile_x: f32, tile_y: f32) -> f32 {
+        let (x0, y0) = (tile_x.floor(), tile_y.floor());
+        let (fx, fy) = (tile_x - x0, tile_y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+        let height = |x: i32, y: i32| self.height_at(x, y) as f32;
+
+        let top = height(x0, y0) * (1.0 - fx) + height(x0 + 1, y0) * fx;
+        let bottom = height(x0, y0 + 1) * (1.0 - fx) + height(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+/// Checks whether a height level is the ground level (skips it when serializing).
+fn is_zero(z: &i32) -> bool {
+    *z == 0
+}
+
+/// Returns the sprite of a box in the entities atlas.
+///
+/// Plain boxes use `box` and `green_box` (placed), colored ones `box_<color>` and
+/// `box_<color>_placed`.
+///
+/// # Arguments
+///
+/// * `color` - color of the box, `None` for a plain box
+/// * `placed` - whether the box stands on a matching target
+///
+/// # Returns
+///
+/// * `String` - Name of the frame.
+pub fn box_sprite(color: Option<&str>, placed: bool) -> String {
+    match (color, placed) {
+        (None, false) => "box".to_string(),
+        (None, true) => "green_box".to_string(),
+        (Some(color), false) => format!("box_{color}"),
+        (Some(color), true) => format!("box_{color}_placed"),
+    }
 }
 
 impl Mob {
@@ -526,7 +914,7 @@ mod tests {
     // Test game map parsing on example
     #[test]
     fn test_load_game_map() {
-        let game_map = GameMap::load("../game_levels/menu.json").unwrap();
+        let game_map = GameMap::load("assets/testdata/menu.json").unwrap();
 
         assert_eq!(game_map.name, "menu");
         assert_eq!(game_map.tile_size, 128);
@@ -574,4 +962,75 @@ mod tests {
         assert_eq!(tile_names.len(), 16);
         assert!(tile_names.contains(&"letter_1".to_string()));
     }
+
+    #[test]
+    fn test_colored_boxes_and_targets() {
+        let json = r#"{
+            "mobs": {
+                "player": {"x_start": 0, "y_start": 0, "asset": "idle_se_0", "is_player": true},
+                "box_1": {"x_start": 1, "y_start": 0, "asset": "box", "color": "red"}},
+            "objects": {},
+            "tiles": {
+                "t_1": {"x": 0, "y": 0, "asset": "floor"},
+                "t_2": {"x": 1, "y": 0, "asset": "floor"},
+                "t_3": {"x": 2, "y": 0, "asset": "target_red", "tile_type": "target",
+                        "color": "red"}},
+            "meta": {"name": "colors", "size": [3, 1]}}"#;
+        let map_json: JsonMap = serde_json::from_str(json).unwrap();
+        let game_map = GameMap::from_json_map(map_json);
+
+        assert!(game_map.has_colors());
+        assert_eq!(game_map.get_mob("box_1").unwrap().color.as_deref(), Some("red"));
+        assert!(game_map.accepts_box(2, 0, Some("red")));
+        assert!(!game_map.accepts_box(2, 0, None));
+        assert!(!game_map.accepts_box(1, 0, Some("red")));
+        assert_eq!(game_map.to_json_map().tiles["t_3"].color.as_deref(), Some("red"));
+
+        assert_eq!(box_sprite(None, true), "green_box");
+        assert_eq!(box_sprite(Some("red"), false), "box_red");
+        assert_eq!(box_sprite(Some("red"), true), "box_red_placed");
+    }
+
+    #[test]
+    fn test_buried_targets_are_not_counted() {
+        let json = r#"{
+            "mobs": {
+                "player": {"x_start": 0, "y_start": 0, "asset": "idle_se_0", "is_player": true},
+                "box_1": {"x_start": 1, "y_start": 0, "asset": "box"}},
+            "objects": {},
+            "tiles": {
+                "t_1": {"x": 0, "y": 0, "asset": "floor"},
+                "t_2": {"x": 1, "y": 0, "asset": "floor"},
+                "t_3": {"x": 2, "y": 0, "asset": "target", "tile_type": "target",
+                        "color": "red"},
+                "t_4": {"x": 2, "y": 0, "asset": "floor", "z": 1},
+                "t_5": {"x": 3, "y": 0, "asset": "target", "tile_type": "target"}},
+            "meta": {"name": "buried", "size": [4, 1]}}"#;
+        let map_json: JsonMap = serde_json::from_str(json).unwrap();
+        let game_map = GameMap::from_json_map(map_json);
+
+        assert_eq!(game_map.target_positions.iter().collect::<Vec<_>>(), [&(3, 0)]);
+        assert!(!game_map.accepts_box(2, 0, Some("red")));
+        assert!(!game_map.has_colors());
+        assert!(game_map.accepts_box(3, 0, None));
+    }
+
+    #[test]
+    fn test_mechanism_tile_types() {
+        let json = r#"["ice", "hole", {"one_way": "ne"}, {"teleporter": 1}, {"plate": 2}]"#;
+        let tile_types: Vec<TileType> = serde_json::from_str(json).unwrap();
+        assert_eq!(tile_types[2], TileType::OneWay(Direction::NE));
+        assert_eq!(tile_types[3], TileType::Teleporter(1));
+        assert!(tile_types.iter().all(TileType::is_mechanism));
+        assert!(!TileType::Ramp.is_mechanism());
+
+        let mut game_map = GameMap::from_xsb("test", &["#####", "#@$.#", "#####"]).unwrap();
+        assert!(!game_map.has_mechanisms());
+        game_map.walk_map[6] = TileType::Teleporter(1);
+        assert_eq!(game_map.teleporter_exit(1, 1), None);
+        game_map.walk_map[8] = TileType::Teleporter(1);
+        assert!(game_map.has_mechanisms());
+        assert_eq!(game_map.teleporter_exit(1, 1), Some((3, 1)));
+        assert_eq!(game_map.teleporter_exit(3, 1), Some((1, 1)));
+    }
 }
@@ -1,8 +1,15 @@
 mod atlas;
+mod compact;
 mod gamemap;
+mod pack;
+mod validation;
+mod xsb;
 
 pub use atlas::{Atlas, Frame};
-pub use gamemap::{GameMap, Object, Tile};
-
-#[cfg(test)]
-pub use gamemap::{Behaviour, BehaviourType, Mob, TileType};
+pub use compact::{CompactMap, LegendEntry, LegendMob, LegendObject, LegendTile};
+pub use gamemap::{
+    box_sprite, Behaviour, BehaviourType, GameMap, JsonMap, Mob, Object, Tile, TileType,
+};
+pub use pack::{LevelPack, LevelSource, PackLevel, UnlockRule, MANIFEST_NAME};
+pub use validation::{MapError, MapIssue};
+pub use xsb::{XsbCollection, XsbLevel};
@@ -0,0 +1,381 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use super::gamemap::GameMap;
+use super::xsb::XsbCollection;
+
+/// File name of a pack manifest.
+pub const MANIFEST_NAME: &str = "pack.json";
+
+// ============================
+// JSON-level structs
+// ============================
+
+/// Level entry of a pack manifest.
+#[derive(Deserialize, Debug, Clone)]
+pub struct JsonPackLevel {
+    /// Path to the level file, relative to the manifest
+    pub path: String,
+
+    /// Number of solved levels of the pack required to unlock this level
+    #[serde(default)]
+    pub requires_solved: Option<usize>,
+}
+
+/// Pack manifest from JSON.
+#[derive(Deserialize, Debug, Clone)]
+pub struct JsonPack {
+    /// Name of the pack
+    pub name: String,
+
+    /// Author of the pack
+    #[serde(default)]
+    pub author: String,
+
+    /// Unlock rule for the whole pack
+    #[serde(default)]
+    pub unlock: UnlockRule,
+
+    /// Levels in play order
+    pub levels: Vec<JsonPackLevel>,
+}
+
+// ============================
+// Game-level structs
+// ============================
+
+/// Rule that decides which levels of a pack can be played.
+#[derive(Default, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum UnlockRule {
+    /// Every level can be played right away
+    #[default]
+    #[serde(rename = "all")]
+    All,
+    /// A level is unlocked once the previous one is solved
+    #[serde(rename = "sequential")]
+    Sequential,
+}
+
+/// Where the map of a pack level comes from.
+#[derive(Debug, Clone)]
+pub enum LevelSource {
+    /// Level file loaded on demand (JSON, or the first level of an XSB file)
+    File(PathBuf),
+    /// Level already parsed from a collection
+    Map(Box<GameMap>),
+}
+
+/// Single level of a pack.
+#[derive(Debug, Clone)]
+pub struct PackLevel {
+    /// Source of the level map
+    pub source: LevelSource,
+    /// Number of solved levels of the pack required to unlock this level
+    pub requires_solved: usize,
+}
+
+/// Ordered collection of levels with metadata.
+#[derive(Debug, Clone)]
+pub struct LevelPack {
+    /// Name of the pack
+    pub name: String,
+    /// Author of the pack
+    pub author: String,
+    /// Unlock rule for the whole pack
+    pub unlock: UnlockRule,
+    /// Levels in play order
+    pub levels: Vec<PackLevel>,
+}
+
+// ============================
+// Implementation
+// ============================
+
+impl LevelPack {
+    /// Loads a pack from a JSON manifest.
+    ///
+    /// Level paths are resolved relative to the directory of the manifest.
+    ///
+    /// # Arguments
+    ///
+    /// * `manifest_path` - Path to the manifest file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Box<dyn Error>>` - Parsed pack on success, error on failure.
+    pub fn load<P: AsRef<Path>>(manifest_path: P) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(&manifest_path)?;
+        let reader = BufReader::new(file);
+        let pack_json: JsonPack = serde_json::from_reader(reader)?;
+
+        if pack_json.levels.is_empty() {
+            return Err("pack has no levels".into());
+        }
+
+        let root = manifest_path.as_ref().parent().unwrap_or(Path::new("."));
+        let levels = pack_json
+            .levels
+            .into_iter()
+            .map(|level| PackLevel {
+                source: LevelSource::File(root.join(level.path)),
+                requires_solved: level.requires_solved.unwrap_or(0),
+            })
+            .collect();
+
+        Ok(LevelPack {
+            name: pack_json.name,
+            author: pack_json.author,
+            unlock: pack_json.unlock,
+            levels,
+        })
+    }
+
+    /// Turns an XSB / SOK collection file into a pack with all levels unlocked.
+    ///
+    /// The pack is named after the file; `Author:` lines of the collection header are
+    /// used as the author.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the collection file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Box<dyn Error>>` - Pack on success, error on failure.
+    pub fn from_collection<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let collection = XsbCollection::load(&path)?;
+
+        let name = path.as_ref().file_stem().and_then(|s| s.to_str()).unwrap_or("pack");
+        let author = collection
+            .comments
+            .iter()
+            .find_map(|line| line.strip_prefix("Author:"))
+            .map(|author| author.trim().to_string())
+            .unwrap_or_default();
+
+        let levels = collection
+            .levels
+            .into_iter()
+            .map(|level| PackLevel {
+                source: LevelSource::Map(Box::new(level.map)),
+                requires_solved: 0,
+            })
+            .collect();
+
+        Ok(LevelPack { name: name.to_string(), author, unlock: UnlockRule::All, levels })
+    }
+
+    /// Discovers all packs in a directory.
+    ///
+    /// A pack is either a `pack.json` manifest in the directory itself or in one of its
+    /// subdirectories, or an `.xsb` / `.sok` collection file in the directory. Packs are
+    /// returned in path order.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Directory to scan
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Result<Self, Box<dyn Error>>>` - One entry per pack found; errors name the
+    ///   offending file.
+    pub fn discover<P: AsRef<Path>>(dir: P) -> Vec<Result<Self, Box<dyn Error>>> {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return vec![Err(format!("{}: cannot read directory", dir.as_ref().display()).into())];
+        };
+
+        let mut candidates: Vec<PathBuf> = Vec::new();
+        for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+            if path.is_dir() {
+                let manifest = path.join(MANIFEST_NAME);
+                if manifest.is_file() {
+                    candidates.push(manifest);
+                }
+            } else if path.file_name().is_some_and(|name| name == MANIFEST_NAME)
+                || is_collection_file(&path)
+            {
+                candidates.push(path);
+            }
+        }
+        candidates.sort();
+
+        candidates
+            .into_iter()
+            .map(|path| {
+                let pack = if is_collection_file(&path) {
+                    Self::from_collection(&path)
+                } else {
+                    Self::load(&path)
+                };
+                pack.map_err(|err| format!("{}: {err}", path.display()).into())
+            })
+            .collect()
+    }
+
+    /// Returns the number of levels in the pack.
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Checks whether the pack has no levels.
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Loads the map of a level.
+    ///
+    /// # Arguments
+    ///
+    /// * `idx` - Index of the level within the pack
+    ///
+    /// # Returns
+    ///
+    /// * `Result<GameMap, Box<dyn Error>>` - Level map on success, error on failure.
+    pub fn load_level(&self, idx: usize) -> Result<GameMap, Box<dyn Error>> {
+        match &self.levels.get(idx).ok_or("no such level in the pack")?.source {
+            LevelSource::File(path) => {
+                GameMap::load(path).map_err(|err| format!("{}: {err}", path.display()).into())
+            }
+            LevelSource::Map(map) => Ok(map.as_ref().clone()),
+        }
+    }
+
+    /// Returns a stable identifier of a level, independent of its position in the pack.
+    ///
+    /// # Arguments
+    ///
+    /// * `idx` - Index of the level within the pack
+    ///
+    /// # Returns
+    ///
+    /// * `Option<String>` - `"<pack>/<file name>"` for level files, `"<pack>/<map name>"` for
+    ///   levels of a collection; `None` if there is no such level.
+    pub fn level_id(&self, idx: usize) -> Option<String> {
+        let level = match &self.levels.get(idx)?.source {
+            LevelSource::File(path) => path.file_name()?.to_string_lossy().into_owned(),
+            LevelSource::Map(map) => map.name.clone(),
+        };
+        Some(format!("{}/{level}", self.name))
+    }
+
+    /// Checks whether a level can be played.
+    ///
+    /// # Arguments
+    ///
+    /// * `idx` - Index of the level within the pack
+    /// * `solved` - Solved flag of every level of the pack
+    ///
+    /// # Returns
+    ///
+    /// * `true` if both the pack rule and the level's own requirement are satisfied.
+    pub fn is_unlocked(&self, idx: usize, solved: &[bool]) -> bool {
+        let Some(level) = self.levels.get(idx) else {
+            return false;
+        };
+
+        let is_solved = |i: usize| solved.get(i).copied().unwrap_or(false);
+        let by_rule = match self.unlock {
+            UnlockRule::All => true,
+            UnlockRule::Sequential => idx == 0 || is_solved(idx - 1),
+        };
+        let solved_count = (0..self.levels.len()).filter(|&i| is_solved(i)).count();
+
+        by_rule && solved_count >= level.requires_solved
+    }
+}
+
+/// Checks whether a file is an XSB / SOK collection.
+fn is_collection_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("xsb") || e.eq_ignore_ascii_case("sok"))
+}
+
+// ============================
+// Tests
+// ============================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty scratch directory for a test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ferari_pack_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_load_game_pack() {
+        let pack = LevelPack::load("../game_levels/pack.json").unwrap();
+
+        assert_eq!(pack.name, "Ferari");
+        assert_eq!(pack.len(), 5);
+        assert_eq!(pack.load_level(1).unwrap().name, "level2");
+        assert!(pack.load_level(5).is_err());
+        assert_eq!(pack.level_id(0).as_deref(), Some("Ferari/level1.json"));
+        assert_eq!(pack.level_id(5), None);
+    }
+
+    #[test]
+    fn test_unlock_rules() {
+        let level = |requires_solved| PackLevel {
+            source: LevelSource::File(PathBuf::new()),
+            requires_solved,
+        };
+        let mut pack = LevelPack {
+            name: "test".into(),
+            author: String::new(),
+            unlock: UnlockRule::Sequential,
+            levels: vec![level(0), level(0), level(2)],
+        };
+
+        assert!(pack.is_unlocked(0, &[]));
+        assert!(!pack.is_unlocked(1, &[false, false, false]));
+        assert!(pack.is_unlocked(1, &[true, false, false]));
+        assert!(!pack.is_unlocked(2, &[true, false, false]));
+        assert!(pack.is_unlocked(2, &[true, true, false]));
+        assert!(!pack.is_unlocked(3, &[true, true, true]));
+
+        pack.unlock = UnlockRule::All;
+        assert!(pack.is_unlocked(1, &[]));
+        assert!(!pack.is_unlocked(2, &[false, true, false]));
+    }
+
+    #[test]
+    fn test_discover_manifests_and_collections() {
+        let dir = scratch_dir("discover");
+        fs::create_dir(dir.join("classic")).unwrap();
+        fs::write(
+            dir.join("classic").join(MANIFEST_NAME),
+            r#"{"name": "Classic", "author": "me", "unlock": "sequential",
+                "levels": [{"path": "one.xsb"}, {"path": "two.json", "requires_solved": 1}]}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("extra.xsb"), "Author: you\n\n####\n#@.#\n#$ #\n####\n").unwrap();
+        fs::write(dir.join("broken.sok"), "no levels here\n").unwrap();
+        fs::write(dir.join("notes.txt"), "ignored\n").unwrap();
+
+        let packs = LevelPack::discover(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(packs.len(), 3);
+        assert!(packs[0].is_err());
+
+        let classic = packs[1].as_ref().unwrap();
+        assert_eq!(classic.name, "Classic");
+        assert_eq!(classic.unlock, UnlockRule::Sequential);
+        assert_eq!(classic.levels[1].requires_solved, 1);
+
+        let extra = packs[2].as_ref().unwrap();
+        assert_eq!(extra.name, "extra");
+        assert_eq!(extra.author, "you");
+        assert_eq!(extra.load_level(0).unwrap().mob_count(), 2);
+        assert_eq!(extra.level_id(0).as_deref(), Some("extra/extra 1"));
+    }
+}
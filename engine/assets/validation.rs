@@ -0,0 +1,646 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io;
+
+use super::atlas::Atlas;
+use super::gamemap::{box_sprite, GameMap, TileType};
+
+// ============================
+// Errors
+// ============================
+
+/// Error of a map that cannot be loaded.
+#[derive(Debug)]
+pub enum MapError {
+    /// The file cannot be read
+    Io(io::Error),
+    /// The file is not valid JSON or does not follow the map schema
+    Json(serde_json::Error),
+    /// The XSB board cannot be parsed
+    Xsb(String),
+    /// The layout of a compact map does not match its legend or size
+    Layout(String),
+    /// The map has been parsed, but fails validation (see [`GameMap::validate`])
+    Invalid(Vec<MapIssue>),
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Io(err) => write!(f, "cannot read map: {err}"),
+            MapError::Json(err) => write!(f, "invalid map JSON: {err}"),
+            MapError::Xsb(err) => write!(f, "invalid XSB board: {err}"),
+            MapError::Layout(err) => write!(f, "invalid map layout: {err}"),
+            MapError::Invalid(issues) => {
+                write!(f, "invalid map ({} problems)", issues.len())?;
+                for issue in issues {
+                    write!(f, "\n  {issue}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MapError::Io(err) => Some(err),
+            MapError::Json(err) => Some(err),
+            MapError::Xsb(_) | MapError::Layout(_) | MapError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for MapError {
+    fn from(err: io::Error) -> Self {
+        MapError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for MapError {
+    fn from(err: serde_json::Error) -> Self {
+        MapError::Json(err)
+    }
+}
+
+/// Problem found by the validation of a map.
+///
+/// Keys name the offending entry the way it is written in the JSON file, e.g.
+/// `mobs.box_1` or `tiles.tile_12`; positions are tile coordinates `(x, y)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapIssue {
+    /// `meta.size` has a zero width or height
+    EmptySize,
+    /// An entry lies outside `meta.size`
+    OutOfBounds {
+        /// Key of the entry
+        key: String,
+        /// Position of the entry
+        position: (u32, u32),
+        /// Size of the map
+        size: [u32; 2],
+    },
+    /// Two tiles share a cell at the same height level
+    DuplicateTile {
+        /// Key of the tile
+        key: String,
+        /// Key of another tile at the same cell
+        other: String,
+        /// Position of both tiles
+        position: (u32, u32),
+    },
+    /// Two mobs (boxes or the player) start on the same cell
+    OverlappingMobs {
+        /// Key of the mob
+        key: String,
+        /// Key of another mob at the same cell
+        other: String,
+        /// Position of both mobs
+        position: (u32, u32),
+    },
+    /// No mob is the player
+    MissingPlayer,
+    /// More than one mob is the player
+    MultiplePlayers {
+        /// Keys of all player mobs
+        keys: Vec<String>,
+    },
+    /// There are fewer boxes than targets, or more than the targets and holes can take
+    BoxTargetMismatch {
+        /// Number of boxes
+        boxes: usize,
+        /// Number of targets
+        targets: usize,
+        /// Number of holes (each swallows one box)
+        holes: usize,
+    },
+    /// The boxes of one color do not match up with the targets of that color
+    ColorMismatch {
+        /// Color of the boxes and targets, `None` for plain ones
+        color: Option<String>,
+        /// Number of boxes of the color
+        boxes: usize,
+        /// Number of targets of the color
+        targets: usize,
+    },
+    /// A teleporter without exactly one partner, or a plate or door without a counterpart
+    Unpaired {
+        /// Key of the tile
+        key: String,
+        /// Position of the tile
+        position: (u32, u32),
+        /// Id shared by the tiles that belong together
+        id: u32,
+    },
+    /// The player can walk onto a cell without any tile
+    FloorHole {
+        /// Position of the cell
+        position: (u32, u32),
+    },
+    /// A box or target the player cannot reach
+    Unreachable {
+        /// Key of the box or target
+        key: String,
+        /// Position of the box or target
+        position: (u32, u32),
+    },
+    /// An asset has no frame in its atlas (see [`GameMap::check_assets`])
+    UnknownFrame {
+        /// Key of the entry
+        key: String,
+        /// Name of the missing frame
+        asset: String,
+    },
+}
+
+impl fmt::Display for MapIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapIssue::EmptySize => write!(f, "meta.size: the map has no cells"),
+            MapIssue::OutOfBounds { key, position: (x, y), size: [w, h] } => {
+                write!(f, "{key} at ({x}, {y}): outside the {w}x{h} map")
+            }
+            MapIssue::DuplicateTile { key, other, position: (x, y) } => {
+                write!(f, "{key} at ({x}, {y}): cell already has {other}")
+            }
+            MapIssue::OverlappingMobs { key, other, position: (x, y) } => {
+                write!(f, "{key} at ({x}, {y}): overlaps {other}")
+            }
+            MapIssue::MissingPlayer => write!(f, "mobs: no mob has \"is_player\": true"),
+            MapIssue::MultiplePlayers { keys } => {
+                write!(f, "mobs: {} players ({})", keys.len(), keys.join(", "))
+            }
+            MapIssue::BoxTargetMismatch { boxes, targets, holes: 0 } => {
+                write!(f, "mobs / tiles: {boxes} boxes but {targets} targets")
+            }
+            MapIssue::BoxTargetMismatch { boxes, targets, holes } => {
+                write!(f, "mobs / tiles: {boxes} boxes but {targets} targets and {holes} holes")
+            }
+            MapIssue::ColorMismatch { color, boxes, targets } => {
+                let color = color.as_deref().unwrap_or("plain");
+                write!(f, "mobs / tiles: {boxes} {color} boxes but {targets} {color} targets")
+            }
+            MapIssue::Unpaired { key, position: (x, y), id } => {
+                write!(f, "{key} at ({x}, {y}): no matching partner with id {id}")
+            }
+            MapIssue::FloorHole { position: (x, y) } => {
+                write!(f, "tiles at ({x}, {y}): walkable cell without a tile")
+            }
+            MapIssue::Unreachable { key, position: (x, y) } => {
+                write!(f, "{key} at ({x}, {y}): unreachable for the player")
+            }
+            MapIssue::UnknownFrame { key, asset } => {
+                write!(f, "{key}: unknown asset \"{asset}\"")
+            }
+        }
+    }
+}
+
+// ============================
+// Validation
+// ============================
+
+impl GameMap {
+    /// Checks the map for problems that would break or spoil a level.
+    ///
+    /// Reports entries outside the map, tiles sharing a cell, overlapping mobs, a
+    /// missing or duplicated player, a box / target count mismatch (per color on maps
+    /// with colored boxes or targets), teleporters, plates and doors without a partner,
+    /// walkable cells without a tile reachable by the player, and boxes or targets the
+    /// player cannot reach. Menus (maps with links) have no targets and may leave the
+    /// floor bare, so the counts and holes are not checked on them.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<MapIssue>` - Every problem found, empty for a valid map.
+    pub fn validate(&self) -> Vec<MapIssue> {
+        let [width, height] = self.size;
+        if width == 0 || height == 0 {
+            return vec![MapIssue::EmptySize];
+        }
+
+        let mut issues = Vec::new();
+        let inside = |(x, y): (u32, u32)| x < width && y < height;
+
+        // Entries outside the map
+        let mut positions: Vec<(String, (u32, u32))> = Vec::new();
+        positions.extend(
+            self.mobs.values().map(|m| (format!("mobs.{}", m.name), (m.x_start, m.y_start))),
+        );
+        positions
+            .extend(self.objects.values().map(|o| (format!("objects.{}", o.name), (o.x, o.y))));
+        positions.extend(self.tiles.values().map(|t| (format!("tiles.{}", t.name), (t.x, t.y))));
+        positions.sort();
+        for (key, position) in positions {
+            if !inside(position) {
+                issues.push(MapIssue::OutOfBounds { key, position, size: self.size });
+            }
+        }
+
+        // Tiles sharing a cell at the same height
+        let mut tiles: Vec<_> = self.tiles.values().collect();
+        tiles.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut tile_at = HashMap::new();
+        for tile in tiles {
+            match tile_at.entry((tile.position(), tile.z)) {
+                Entry::Occupied(other) => issues.push(MapIssue::DuplicateTile {
+                    key: format!("tiles.{}", tile.name),
+                    other: format!("tiles.{}", other.get()),
+                    position: tile.position(),
+                }),
+                Entry::Vacant(cell) => {
+                    cell.insert(&tile.name);
+                }
+            }
+        }
+
+        // Overlapping mobs and players
+        let mut mobs: Vec<_> = self.mobs.values().collect();
+        mobs.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut mob_at = HashMap::new();
+        for mob in &mobs {
+            match mob_at.entry(mob.start_position()) {
+                Entry::Occupied(other) => issues.push(MapIssue::OverlappingMobs {
+                    key: format!("mobs.{}", mob.name),
+                    other: format!("mobs.{}", other.get()),
+                    position: mob.start_position(),
+                }),
+                Entry::Vacant(cell) => {
+                    cell.insert(&mob.name);
+                }
+            }
+        }
+
+        let players: Vec<_> = mobs.iter().filter(|mob| mob.is_player).collect();
+        match players.as_slice() {
+            [] => issues.push(MapIssue::MissingPlayer),
+            [_] => {}
+            _ => issues.push(MapIssue::MultiplePlayers {
+                keys: players.iter().map(|mob| format!("mobs.{}", mob.name)).collect(),
+            }),
+        }
+
+        let boxes = mobs.len() - players.len();
+        let targets = self.target_positions.len();
+        let holes = self.walk_map.iter().filter(|&tile_type| *tile_type == TileType::Hole).count();
+        let is_menu = !self.links.is_empty();
+        if !is_menu && (boxes < targets || boxes > targets + holes) {
+            issues.push(MapIssue::BoxTargetMismatch { boxes, targets, holes });
+        }
+
+        // Every color needs its own boxes (surplus ones may only go into holes)
+        if !is_menu && self.has_colors() {
+            let mut counts: BTreeMap<Option<&str>, (usize, usize)> = BTreeMap::new();
+            for mob in mobs.iter().filter(|mob| !mob.is_player) {
+                counts.entry(mob.color.as_deref()).or_default().0 += 1;
+            }
+            for position in &self.target_positions {
+                let color = self.target_colors.get(position).map(String::as_str);
+                counts.entry(color).or_default().1 += 1;
+            }
+            for (color, (boxes, targets)) in counts {
+                if boxes < targets || boxes > targets + holes {
+                    let color = color.map(str::to_string);
+                    issues.push(MapIssue::ColorMismatch { color, boxes, targets });
+                }
+            }
+        }
+
+        // Teleporters come in pairs, plates and doors need each other
+        let count = |wanted: TileType| self.walk_map.iter().filter(|&t| *t == wanted).count();
+        let mut tiles: Vec<_> = self.tiles.values().collect();
+        tiles.sort_by(|a, b| a.name.cmp(&b.name));
+        for tile in tiles {
+            let (id, paired) = match tile.tile_type {
+                TileType::Teleporter(id) => (id, count(TileType::Teleporter(id)) == 2),
+                TileType::Plate(id) => (id, count(TileType::Door(id)) > 0),
+                TileType::Door(id) => (id, count(TileType::Plate(id)) > 0),
+                _ => continue,
+            };
+            if !paired {
+                let key = format!("tiles.{}", tile.name);
+                issues.push(MapIssue::Unpaired { key, position: tile.position(), id });
+            }
+        }
+
+        // Holes and unreachable entries, as seen from the (single) player
+        if let [player] = players.as_slice() {
+            if inside(player.start_position()) {
+                let reachable = self.reachable_from(player.start_position());
+                let is_reachable =
+                    |(x, y): (u32, u32)| inside((x, y)) && reachable[(y * width + x) as usize];
+                let floored: HashSet<(u32, u32)> =
+                    tile_at.keys().map(|&(position, _)| position).collect();
+
+                for y in 0..height {
+                    for x in 0..width {
+                        if !is_menu && is_reachable((x, y)) && !floored.contains(&(x, y)) {
+                            issues.push(MapIssue::FloorHole { position: (x, y) });
+                        }
+                    }
+                }
+
+                let mut targets: Vec<_> = self
+                    .tiles
+                    .values()
+                    .filter(|tile| {
+                        matches!(tile.tile_type, TileType::Target)
+                            && self.target_positions.contains(&tile.position())
+                            && self.height_at(tile.x as i32, tile.y as i32) == tile.z
+                    })
+                    .map(|tile| (format!("tiles.{}", tile.name), tile.position()))
+                    .collect();
+                targets.sort();
+                let boxes = mobs
+                    .iter()
+                    .filter(|mob| !mob.is_player)
+                    .map(|mob| (format!("mobs.{}", mob.name), mob.start_position()));
+                for (key, position) in boxes.chain(targets) {
+                    if inside(position) && !is_reachable(position) {
+                        issues.push(MapIssue::Unreachable { key, position });
+                    }
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// Checks the map like [`GameMap::validate`] and its assets like
+    /// [`GameMap::check_assets`].
+    ///
+    /// # Arguments
+    ///
+    /// * `tiles_atlas` - Atlas of the tiles and objects
+    /// * `entities_atlas` - Atlas of the mobs
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<MapIssue>` - Every problem found, empty for a valid map.
+    pub fn validate_with_atlases(
+        &self,
+        tiles_atlas: &Atlas,
+        entities_atlas: &Atlas,
+    ) -> Vec<MapIssue> {
+        let mut issues = self.validate();
+        issues.extend(self.check_assets(tiles_atlas, entities_atlas));
+        issues
+    }
+
+    /// Checks that every asset of the map has a frame in its atlas, including the sprites of
+    /// colored boxes (see [`box_sprite`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `tiles_atlas` - Atlas of the tiles and objects
+    /// * `entities_atlas` - Atlas of the mobs
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<MapIssue>` - One [`MapIssue::UnknownFrame`] per asset without a frame.
+    pub fn check_assets(&self, tiles_atlas: &Atlas, entities_atlas: &Atlas) -> Vec<MapIssue> {
+        // colored boxes are drawn with the sprites of their color
+        let box_sprites: Vec<(&str, String)> = self
+            .mobs
+            .values()
+            .filter_map(|m| Some((m.name.as_str(), m.color.as_deref()?)))
+            .flat_map(|(name, color)| [false, true].map(|p| (name, box_sprite(Some(color), p))))
+            .collect();
+
+        let mut assets: Vec<(String, &str, &Atlas)> = Vec::new();
+        assets.extend(
+            self.mobs
+                .values()
+                .map(|m| (format!("mobs.{}", m.name), m.asset.as_str(), entities_atlas)),
+        );
+        assets.extend(
+            box_sprites
+                .iter()
+                .map(|(name, sprite)| (format!("mobs.{name}"), sprite.as_str(), entities_atlas)),
+        );
+        assets.extend(
+            self.objects
+                .values()
+                .map(|o| (format!("objects.{}", o.name), o.asset.as_str(), tiles_atlas)),
+        );
+        assets.extend(
+            self.tiles
+                .values()
+                .map(|t| (format!("tiles.{}", t.name), t.asset.as_str(), tiles_atlas)),
+        );
+        assets.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assets
+            .into_iter()
+            .filter(|(_, asset, atlas)| atlas.get_frame(asset).is_none())
+            .map(|(key, asset, _)| MapIssue::UnknownFrame { key, asset: asset.to_string() })
+            .collect()
+    }
+
+    /// Returns the cells the player can walk to from a start cell, ignoring boxes.
+    ///
+    /// Teleporters lead to their partner; doors, holes and one-way arrows are assumed to
+    /// let the player pass.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Start cell, inside the map
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<bool>` - Reachability flag of every cell, indexed like `walk_map`.
+    pub fn reachable_from(&self, start: (u32, u32)) -> Vec<bool> {
+        let width = self.size[0] as i32;
+        let passable = |from: (i32, i32), (x, y): (i32, i32)| {
+            self.is_walkable_from(from, (x, y)) && !self.has_collidable_object_at(x, y)
+        };
+
+        let mut reachable = vec![false; self.walk_map.len()];
+        let start = (start.0 as i32, start.1 as i32);
+        reachable[(start.1 * width + start.0) as usize] = true;
+        let mut queue = VecDeque::from([start]);
+        while let Some((x, y)) = queue.pop_front() {
+            for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if passable((x, y), (nx, ny)) && !reachable[(ny * width + nx) as usize] {
+                    reachable[(ny * width + nx) as usize] = true;
+                    queue.push_back((nx, ny));
+                }
+            }
+            if let Some((nx, ny)) = self.teleporter_exit(x, y) {
+                if !reachable[(ny * width + nx) as usize] {
+                    reachable[(ny * width + nx) as usize] = true;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        reachable
+    }
+}
+
+// ============================
+// Tests
+// ============================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(rows: &[&str]) -> GameMap {
+        GameMap::from_xsb("test", rows).unwrap()
+    }
+
+    #[test]
+    fn test_levels_are_valid() {
+        for name in ["level1", "level2", "level3", "level4", "level5"] {
+            let map = GameMap::load(format!("../game_levels/{name}.json")).unwrap();
+            assert_eq!(map.validate(), [], "{name}");
+        }
+        let menu = GameMap::load("assets/testdata/menu.json").unwrap();
+        assert_eq!(menu.validate(), []);
+        assert_eq!(load(&["#####", "#@$.#", "#####"]).validate(), []);
+    }
+
+    #[test]
+    fn test_validate_reports_entities_and_counts() {
+        let mut map = load(&["######", "#@$..#", "######"]);
+        let mut player = map.mobs["player"].clone();
+        player.name = "player_2".to_string();
+        player.x_start = 2;
+        map.mobs.insert(player.name.clone(), player);
+        let mut stray = map.mobs["box_1"].clone();
+        stray.name = "box_9".to_string();
+        stray.x_start = 9;
+        map.mobs.insert(stray.name.clone(), stray);
+
+        assert_eq!(
+            map.validate(),
+            [
+                MapIssue::OutOfBounds {
+                    key: "mobs.box_9".to_string(),
+                    position: (9, 1),
+                    size: [6, 3],
+                },
+                MapIssue::OverlappingMobs {
+                    key: "mobs.player_2".to_string(),
+                    other: "mobs.box_1".to_string(),
+                    position: (2, 1),
+                },
+                MapIssue::MultiplePlayers {
+                    keys: vec!["mobs.player".to_string(), "mobs.player_2".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_holes_and_unreachable_areas() {
+        let mut map = load(&["#######", "#@$.#.#", "#######"]);
+        let hole = map.tiles.iter().find(|(_, tile)| tile.position() == (2, 1)).unwrap();
+        let hole = hole.0.clone();
+        map.tiles.remove(&hole);
+
+        assert_eq!(
+            map.validate(),
+            [
+                MapIssue::BoxTargetMismatch { boxes: 1, targets: 2, holes: 0 },
+                MapIssue::FloorHole { position: (2, 1) },
+                MapIssue::Unreachable { key: "tiles.tile_13".to_string(), position: (5, 1) },
+            ]
+        );
+        assert!(MapIssue::FloorHole { position: (2, 1) }.to_string().contains("(2, 1)"));
+    }
+
+    #[test]
+    fn test_validate_counts_holes_and_pairs_mechanisms() {
+        let mut map = load(&["#######", "#@$ $.#", "#######"]);
+        let set = |map: &mut GameMap, tile_type: TileType| {
+            let tile = map.tiles.values_mut().find(|tile| tile.position() == (3, 1)).unwrap();
+            tile.tile_type = tile_type.clone();
+            map.walk_map[map.size[0] as usize + 3] = tile_type;
+        };
+
+        set(&mut map, TileType::Hole);
+        assert_eq!(map.validate(), []);
+
+        set(&mut map, TileType::Teleporter(4));
+        let key = map.tiles.values().find(|tile| tile.position() == (3, 1)).unwrap().name.clone();
+        let unpaired = MapIssue::Unpaired { key: format!("tiles.{key}"), position: (3, 1), id: 4 };
+        assert_eq!(
+            map.validate(),
+            [MapIssue::BoxTargetMismatch { boxes: 2, targets: 1, holes: 0 }, unpaired.clone()]
+        );
+        assert!(unpaired.to_string().contains("no matching partner with id 4"));
+    }
+
+    #[test]
+    fn test_validate_counts_boxes_per_color() {
+        let mut map = load(&["#######", "#@$$..#", "#######"]);
+        map.mobs.get_mut("box_1").unwrap().color = Some("red".to_string());
+        map.target_colors.insert((4, 1), "red".to_string());
+        assert_eq!(map.validate(), []);
+
+        map.target_colors.insert((5, 1), "red".to_string());
+        let red = MapIssue::ColorMismatch { color: Some("red".to_string()), boxes: 1, targets: 2 };
+        let plain = MapIssue::ColorMismatch { color: None, boxes: 1, targets: 0 };
+        assert_eq!(map.validate(), [plain.clone(), red.clone()]);
+        assert_eq!(red.to_string(), "mobs / tiles: 1 red boxes but 2 red targets");
+        assert!(plain.to_string().contains("1 plain boxes"));
+    }
+
+    #[test]
+    fn test_load_reports_missing_player() {
+        let path = std::env::temp_dir().join("ferari_validation_no_player.json");
+        let json = r#"{"mobs": {}, "objects": {}, "tiles": {
+            "t": {"x": 0, "y": 0, "asset": "floor"}}, "meta": {"name": "x", "size": [1, 1]}}"#;
+        std::fs::write(&path, json).unwrap();
+
+        let err = GameMap::load(&path).unwrap_err();
+        assert!(matches!(&err, MapError::Invalid(issues) if issues == &[MapIssue::MissingPlayer]));
+        assert!(err.to_string().contains("is_player"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_check_assets_reports_unknown_frames() {
+        let map = load(&["#####", "#@$.#", "#####"]);
+        let empty = Atlas {
+            image: image::RgbaImage::new(1, 1),
+            frames: HashMap::new(),
+            tile_size: 1,
+            version: 1,
+        };
+
+        let issues = map.check_assets(&empty, &empty);
+        assert!(issues.contains(&MapIssue::UnknownFrame {
+            key: "mobs.player".to_string(),
+            asset: "idle_se_0".to_string(),
+        }));
+        assert_eq!(issues.len(), map.mobs.len() + map.objects.len() + map.tiles.len());
+    }
+
+    #[test]
+    fn test_load_with_atlases_rejects_unknown_frames() {
+        let path = std::env::temp_dir().join("ferari_unknown_frames_test.json");
+        let json = r#"{
+            "mobs": {
+                "player": {"x_start": 0, "y_start": 0, "asset": "idle_se_0", "is_player": true}},
+            "objects": {},
+            "tiles": {"t": {"x": 0, "y": 0, "asset": "floor"}},
+            "meta": {"name": "x", "size": [1, 1]}}"#;
+        std::fs::write(&path, json).unwrap();
+        let empty = Atlas {
+            image: image::RgbaImage::new(1, 1),
+            frames: HashMap::new(),
+            tile_size: 1,
+            version: 1,
+        };
+
+        assert!(GameMap::load(&path).is_ok());
+        let err = GameMap::load_with_atlases(&path, &empty, &empty).unwrap_err();
+        assert!(matches!(&err, MapError::Invalid(issues) if issues.len() == 2));
+        assert!(err.to_string().contains("idle_se_0"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,565 @@
+use std::collections::{HashMap, LinkedList, VecDeque};
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use super::gamemap::{Behaviour, BehaviourType, GameMap, Mob, Object, Tile, TileType};
+
+// ============================
+// XSB / SOK level text format
+// ============================
+//
+// '#' wall, ' ' / '-' / '_' floor, '.' target, '$' box, '*' box on target,
+// '@' player, '+' player on target.
+//
+// A collection file holds any number of boards separated by text lines. A text line
+// right above a board is its title; `Title:` lines below a board override it.
+// `;` lines, `Comment:` blocks and any other text below a board are its comments.
+
+/// Tile size of imported maps, in pixels.
+const XSB_TILE_SIZE: u32 = 128;
+
+/// Single level of an XSB collection.
+#[derive(Debug, Clone)]
+pub struct XsbLevel {
+    /// Level title (also used as the map name)
+    pub title: String,
+    /// Comment lines attached to the level
+    pub comments: Vec<String>,
+    /// Parsed map of the level
+    pub map: GameMap,
+}
+
+/// Collection of levels read from an XSB / SOK file.
+#[derive(Debug, Clone, Default)]
+pub struct XsbCollection {
+    /// Text lines preceding the first level (collection title, author, ...)
+    pub comments: Vec<String>,
+    /// Levels in file order
+    pub levels: Vec<XsbLevel>,
+}
+
+impl XsbCollection {
+    /// Loads a level collection from an XSB / SOK file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the text file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Box<dyn Error>>` - Parsed collection on success, error on failure.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(&path)?;
+        let fallback = path.as_ref().file_stem().and_then(|s| s.to_str()).unwrap_or("level");
+
+        Self::parse(&text, fallback)
+    }
+
+    /// Parses a level collection from XSB / SOK text.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Contents of the collection file
+    /// * `fallback_name` - Base name for untitled levels (`"<name> <n>"`)
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Box<dyn Error>>` - Parsed collection, or an error if there is no
+    ///   level in the text or a board is malformed.
+    pub fn parse(text: &str, fallback_name: &str) -> Result<Self, Box<dyn Error>> {
+        let mut header = Vec::new();
+        let mut levels: Vec<PendingLevel> = Vec::new();
+        // Plain text lines since the last board or blank line
+        let mut text_block: Vec<&str> = Vec::new();
+        let mut in_board = false;
+        let mut in_comment = false;
+
+        for line in text.lines().map(|l| l.trim_end_matches('\r')) {
+            if !in_comment && is_board_line(line) {
+                if !in_board {
+                    // A text line right above the board is its title
+                    let title = text_block.pop().map(str::to_string);
+                    attach_comments(text_block.drain(..), &mut levels, &mut header);
+                    levels.push(PendingLevel { title, ..Default::default() });
+                    in_board = true;
+                }
+                if let Some(level) = levels.last_mut() {
+                    level.rows.push(line.to_string());
+                }
+                continue;
+            }
+            in_board = false;
+
+            let trimmed = line.trim();
+            if in_comment {
+                if trimmed.eq_ignore_ascii_case("comment-end:") {
+                    in_comment = false;
+                } else {
+                    attach_comments([trimmed], &mut levels, &mut header);
+                }
+            } else if let Some(title) = strip_key(trimmed, "title:") {
+                match levels.last_mut() {
+                    Some(level) => level.title = Some(title.to_string()),
+                    None => header.push(trimmed.to_string()),
+                }
+            } else if let Some(rest) = strip_key(trimmed, "comment:") {
+                in_comment = rest.is_empty();
+                if !rest.is_empty() {
+                    attach_comments([rest], &mut levels, &mut header);
+                }
+            } else if let Some(rest) = trimmed.strip_prefix(';') {
+                attach_comments([rest.trim()], &mut levels, &mut header);
+            } else if trimmed.is_empty() {
+                attach_comments(text_block.drain(..), &mut levels, &mut header);
+            } else {
+                text_block.push(trimmed);
+            }
+        }
+        attach_comments(text_block.drain(..), &mut levels, &mut header);
+
+        let mut collection = XsbCollection { comments: header, levels: Vec::new() };
+        for (n, level) in levels.into_iter().enumerate() {
+            let title = level.title.unwrap_or_else(|| format!("{fallback_name} {}", n + 1));
+            let map = GameMap::from_xsb(&title, &level.rows)
+                .map_err(|err| format!("level \"{title}\": {err}"))?;
+            collection.levels.push(XsbLevel { title, comments: level.comments, map });
+        }
+
+        if collection.levels.is_empty() {
+            return Err("no level found".into());
+        }
+
+        Ok(collection)
+    }
+
+    /// Serializes the collection back to XSB text with `Title:` and `Comment:` lines.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Collection text, one board per level.
+    pub fn to_xsb(&self) -> String {
+        let mut text = String::new();
+
+        for line in &self.comments {
+            let _ = writeln!(text, "{line}");
+        }
+
+        for level in &self.levels {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(&level.map.to_xsb());
+            let _ = writeln!(text, "Title: {}", level.title);
+            if !level.comments.is_empty() {
+                text.push_str("Comment:\n");
+                for line in &level.comments {
+                    let _ = writeln!(text, "{line}");
+                }
+                text.push_str("Comment-End:\n");
+            }
+        }
+
+        text
+    }
+}
+
+impl GameMap {
+    /// Loads the first level of an XSB / SOK file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the text file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Box<dyn Error>>` - Parsed GameMap on success, error on failure.
+    pub fn load_xsb<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let collection = XsbCollection::load(path)?;
+        let first = collection.levels.into_iter().next().ok_or("no level found")?;
+        Ok(first.map)
+    }
+
+    /// Builds a map from the rows of a single XSB board.
+    ///
+    /// Mirrors `game_levels/genlevel.py`: walls become a concrete tile with a collidable
+    /// `wall_tile` object, the player starts as `idle_se_0` and boxes use the `box` asset.
+    /// Floor outside the walls gets no tile and is not walkable.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the map
+    /// * `rows` - Board rows, top to bottom
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Box<dyn Error>>` - Parsed GameMap, or an error for unknown characters
+    ///   or a missing or duplicated player.
+    pub fn from_xsb<S: AsRef<str>>(name: &str, rows: &[S]) -> Result<Self, Box<dyn Error>> {
+        let height = rows.len();
+        let width = rows.iter().map(|row| row.as_ref().chars().count()).max().unwrap_or(0);
+
+        let mut grid = vec![' '; width * height];
+        let mut player = None;
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.as_ref().chars().enumerate() {
+                let c = match c {
+                    '-' | '_' => ' ',
+                    '#' | ' ' | '.' | '$' | '*' | '@' | '+' => c,
+                    _ => return Err(format!("unknown character '{c}' at ({x}, {y})").into()),
+                };
+                if c == '@' || c == '+' {
+                    if player.is_some() {
+                        return Err(format!("second player at ({x}, {y})").into());
+                    }
+                    player = Some(y * width + x);
+                }
+                grid[y * width + x] = c;
+            }
+        }
+        let player = player.ok_or("no player on the board")?;
+
+        // Floor reachable from the player is the inside of the level
+        let mut inside = vec![false; width * height];
+        let mut queue = VecDeque::from([player]);
+        inside[player] = true;
+        while let Some(idx) = queue.pop_front() {
+            let (x, y) = (idx % width, idx / width);
+            let neighbours = [
+                (x > 0).then(|| idx - 1),
+                (x + 1 < width).then_some(idx + 1),
+                (y > 0).then(|| idx - width),
+                (y + 1 < height).then_some(idx + width),
+            ];
+            for next in neighbours.into_iter().flatten() {
+                if !inside[next] && grid[next] != '#' {
+                    inside[next] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let mut mobs = HashMap::new();
+        let mut objects = HashMap::new();
+        let mut tiles = HashMap::new();
+        let mut target_positions = LinkedList::new();
+        let mut walk_map = vec![TileType::Empty; width * height];
+        let mut object_collidable_map = vec![false; width * height];
+        let mut box_count = 0;
+
+        for (idx, &c) in grid.iter().enumerate() {
+            let (x, y) = ((idx % width) as u32, (idx / width) as u32);
+
+            let (asset, tile_type) = match c {
+                '#' => ("concrete", TileType::Empty),
+                '.' | '*' | '+' => ("target", TileType::Target),
+                _ if inside[idx] || c == '$' => ("floor", TileType::Empty),
+                _ => {
+                    walk_map[idx] = TileType::Wall;
+                    continue;
+                }
+            };
+
+            let tile_name = format!("tile_{}", tiles.len() + 1);
+            let tile = Tile {
+                name: tile_name.clone(),
+                x,
+                y,
+                asset: asset.to_string(),
+                tile_type: tile_type.clone(),
+                z: 0,
+                color: None,
+            };
+            tiles.insert(tile_name, tile);
+            if matches!(tile_type, TileType::Target) {
+                target_positions.push_back((x, y));
+            }
+            walk_map[idx] = tile_type;
+
+            if c == '#' {
+                let wall_name = format!("wall_{}", objects.len() + 1);
+                let wall = Object {
+                    name: wall_name.clone(),
+                    x,
+                    y,
+                    asset: "wall_tile".to_string(),
+                    collidable: true,
+                    shadow: false,
+                };
+                objects.insert(wall_name, wall);
+                object_collidable_map[idx] = true;
+            }
+
+            let (mob_name, asset, behaviour) = match c {
+                '@' | '+' => (
+                    "player".to_string(),
+                    "idle_se_0",
+                    Some(Behaviour {
+                        behaviour_type: BehaviourType::Controlled,
+                        direction: None,
+                        speed: None,
+                    }),
+                ),
+                '$' | '*' => {
+                    box_count += 1;
+                    (format!("box_{box_count}"), "box", None)
+                }
+                _ => continue,
+            };
+            let mob = Mob {
+                name: mob_name.clone(),
+                x_start: x,
+                y_start: y,
+                asset: asset.to_string(),
+                is_player: behaviour.is_some(),
+                behaviour,
+                color: None,
+            };
+            mobs.insert(mob_name, mob);
+        }
+
+        Ok(GameMap {
+            name: name.to_string(),
+            tile_size: XSB_TILE_SIZE,
+            size: [width as u32, height as u32],
+            mobs,
+            objects,
+            tiles,
+            target_positions,
+            links: HashMap::new(),
+            target_colors: HashMap::new(),
+            walk_map,
+            height_map: vec![0; width * height],
+            object_collidable_map,
+        })
+    }
+
+    /// Exports the map (with mobs at their start positions) as an XSB board.
+    ///
+    /// Tiles the XSB format cannot express (links, letters) are written as floor,
+    /// cells without any tile as blanks. If floor touches the map edge, the board is
+    /// framed by walls, since XSB boards have to be closed. Trailing blanks are trimmed.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Board rows, each terminated by a newline.
+    pub fn to_xsb(&self) -> String {
+        let width = self.size[0] as usize;
+        let height = self.size[1] as usize;
+
+        let mut has_tile = vec![false; width * height];
+        for tile in self.tiles.values() {
+            if (tile.x as usize) < width && (tile.y as usize) < height {
+                has_tile[tile.y as usize * width + tile.x as usize] = true;
+            }
+        }
+
+        let mut grid: Vec<char> = (0..width * height)
+            .map(|idx| {
+                if self.object_collidable_map[idx]
+                    || (has_tile[idx] && matches!(self.walk_map[idx], TileType::Wall))
+                {
+                    '#'
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+
+        let cell = |x: u32, y: u32| {
+            ((x as usize) < width && (y as usize) < height).then(|| y as usize * width + x as usize)
+        };
+        for &(x, y) in &self.target_positions {
+            if let Some(idx) = cell(x, y) {
+                grid[idx] = '.';
+            }
+        }
+        for mob in self.mobs.values() {
+            if let Some(idx) = cell(mob.x_start, mob.y_start) {
+                grid[idx] = match (mob.is_player, grid[idx] == '.') {
+                    (true, true) => '+',
+                    (true, false) => '@',
+                    (false, true) => '*',
+                    (false, false) => '$',
+                };
+            }
+        }
+
+        let on_edge = |idx: usize| {
+            let (x, y) = (idx % width, idx / width);
+            x == 0 || y == 0 || x + 1 == width || y + 1 == height
+        };
+        let open_edge =
+            (0..width * height).any(|idx| on_edge(idx) && has_tile[idx] && grid[idx] != '#');
+
+        let mut rows: Vec<String> =
+            grid.chunks(width.max(1)).take(height).map(String::from_iter).collect();
+        if open_edge {
+            let border = "#".repeat(width + 2);
+            rows = std::iter::once(border.clone())
+                .chain(rows.iter().map(|row| format!("#{row}#")))
+                .chain(std::iter::once(border))
+                .collect();
+        }
+
+        let mut text = String::new();
+        for row in rows {
+            text.push_str(row.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// Checks whether a line is a board row: only board characters and at least one wall.
+fn is_board_line(line: &str) -> bool {
+    line.contains('#') && line.chars().all(|c| " -_#.$*@+".contains(c))
+}
+
+/// Strips a case-insensitive `Key:` prefix from a metadata line.
+fn strip_key<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let head = line.get(..key.len())?;
+    head.eq_ignore_ascii_case(key).then(|| line[key.len()..].trim())
+}
+
+/// Level of a collection being parsed.
+#[derive(Default)]
+struct PendingLevel {
+    /// Title, if one has been found yet
+    title: Option<String>,
+    /// Comment lines
+    comments: Vec<String>,
+    /// Board rows
+    rows: Vec<String>,
+}
+
+/// Attaches comment lines to the last level, or to the collection before the first level.
+fn attach_comments<'a>(
+    lines: impl IntoIterator<Item = &'a str>,
+    levels: &mut [PendingLevel],
+    header: &mut Vec<String>,
+) {
+    let target = match levels.last_mut() {
+        Some(level) => &mut level.comments,
+        None => header,
+    };
+    target.extend(lines.into_iter().map(str::to_string));
+}
+
+// ============================
+// Tests
+// ============================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLLECTION: &str = "\
+Example collection
+Author: someone
+
+Corridor
+#######
+#@ $ .#
+#######
+; easy one
+
+#####
+#+* #
+#####
+Title: Done
+Comment:
+already solved
+Comment-End:
+";
+
+    #[test]
+    fn test_from_xsb_builds_map() {
+        let map = GameMap::from_xsb("test", &["  #####", "###@$.#", "  #####"]).unwrap();
+
+        assert_eq!(map.name, "test");
+        assert_eq!(map.size, [7, 3]);
+        assert_eq!(map.target_positions.iter().collect::<Vec<_>>(), vec![&(5, 1)]);
+
+        let player = map.get_mob("player").unwrap();
+        assert!(player.is_player);
+        assert_eq!(player.start_position(), (3, 1));
+        assert_eq!(map.get_mob("box_1").unwrap().start_position(), (4, 1));
+
+        assert!(map.has_collidable_object_at(2, 1));
+        assert!(map.is_walkable(3, 1));
+        // blank cells outside the walls are neither walkable nor rendered
+        assert!(!map.is_walkable(0, 0));
+        assert!(map.iter_tiles().all(|tile| tile.position() != (0, 0)));
+    }
+
+    #[test]
+    fn test_from_xsb_rejects_invalid_boards() {
+        assert!(GameMap::from_xsb("test", &["#####", "# $.#", "#####"]).is_err());
+        assert!(GameMap::from_xsb("test", &["#####", "#@$@#", "#####"]).is_err());
+        assert!(GameMap::from_xsb("test", &["#####", "#@$x#", "#####"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_collection_titles_and_comments() {
+        let collection = XsbCollection::parse(COLLECTION, "pack").unwrap();
+
+        assert_eq!(collection.comments, vec!["Example collection", "Author: someone"]);
+        assert_eq!(collection.levels.len(), 2);
+
+        let first = &collection.levels[0];
+        assert_eq!(first.title, "Corridor");
+        assert_eq!(first.map.name, "Corridor");
+        assert_eq!(first.comments, vec!["easy one"]);
+
+        let second = &collection.levels[1];
+        assert_eq!(second.title, "Done");
+        assert_eq!(second.comments, vec!["already solved"]);
+        assert_eq!(second.map.get_mob("player").unwrap().start_position(), (1, 1));
+        assert_eq!(second.map.target_positions.len(), 2);
+    }
+
+    #[test]
+    fn test_untitled_levels_use_fallback_name() {
+        let collection =
+            XsbCollection::parse("####\n#@.#\n####\n\n####\n#.@#\n####\n", "pack").unwrap();
+
+        let titles: Vec<&str> = collection.levels.iter().map(|l| l.title.as_str()).collect();
+        assert_eq!(titles, vec!["pack 1", "pack 2"]);
+    }
+
+    #[test]
+    fn test_parse_without_levels_fails() {
+        assert!(XsbCollection::parse("just a comment\n", "pack").is_err());
+    }
+
+    #[test]
+    fn test_to_xsb_round_trip() {
+        let collection = XsbCollection::parse(COLLECTION, "pack").unwrap();
+        let text = collection.to_xsb();
+        let reparsed = XsbCollection::parse(&text, "pack").unwrap();
+
+        assert_eq!(reparsed.to_xsb(), text);
+        assert_eq!(collection.levels[0].map.to_xsb(), "#######\n#@ $ .#\n#######\n");
+    }
+
+    #[test]
+    fn test_to_xsb_frames_open_json_map() {
+        let map = GameMap::load("../game_levels/level2.json").unwrap();
+
+        assert_eq!(map.to_xsb(), "######\n##  ##\n##  ##\n#.$$ #\n# $@.#\n##. ##\n######\n");
+    }
+
+    #[test]
+    fn test_load_dispatches_on_extension() {
+        let path = std::env::temp_dir().join(format!("ferari_xsb_{}.xsb", std::process::id()));
+        fs::write(&path, COLLECTION).unwrap();
+
+        let map = GameMap::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(map.name, "Corridor");
+        assert_eq!(map.mob_count(), 2);
+    }
+}
@@ -0,0 +1,252 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageResult, Rgba, RgbaImage};
+
+/// Encoder speed of recorded GIFs (1 best quality … 30 fastest).
+const GIF_SPEED: i32 = 20;
+
+/// File format of a frame recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureFormat {
+    /// Single animated GIF file
+    Gif,
+    /// Directory of numbered PNG files (`frame_00000.png`, `frame_00001.png`, ...)
+    PngSequence,
+}
+
+impl CaptureFormat {
+    /// Picks the format of a recording from its path: `.gif` files are animated GIFs,
+    /// anything else is a directory of PNG files.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("gif") => CaptureFormat::Gif,
+            _ => CaptureFormat::PngSequence,
+        }
+    }
+}
+
+/// Converts a framebuffer (`0xAARRGGBB` pixels, row by row) into an opaque image.
+///
+/// # Arguments
+///
+/// * `frame` - pixel buffer of `width * height` pixels
+/// * `width` - width of the frame in pixels
+/// * `height` - height of the frame in pixels
+pub fn frame_to_image(frame: &[u32], width: usize, height: usize) -> RgbaImage {
+    RgbaImage::from_fn(width as u32, height as u32, |x, y| {
+        let px = frame[y as usize * width + x as usize];
+        Rgba([(px >> 16) as u8, (px >> 8) as u8, px as u8, 255])
+    })
+}
+
+/// Writes a framebuffer to a PNG file, creating missing directories.
+///
+/// # Arguments
+///
+/// * `frame` - pixel buffer of `width * height` pixels
+/// * `width` - width of the frame in pixels
+/// * `height` - height of the frame in pixels
+/// * `path` - location of the PNG file
+pub fn save_png<P: AsRef<Path>>(
+    frame: &[u32],
+    width: usize,
+    height: usize,
+    path: P,
+) -> ImageResult<()> {
+    if let Some(dir) = path.as_ref().parent() {
+        fs::create_dir_all(dir)?;
+    }
+    frame_to_image(frame, width, height).save(path)
+}
+
+/// Output of a recording.
+enum Sink {
+    /// Animated GIF being encoded
+    Gif(Box<GifEncoder<BufWriter<File>>>),
+    /// Directory the numbered PNG files are written to
+    PngSequence(PathBuf),
+}
+
+/// Records a sequence of framebuffers to an animated GIF or numbered PNG files.
+///
+/// Frames are written as they are pushed, so a recording does not grow in memory.
+/// With a frame rate limit, frames arriving faster are dropped and the time they covered
+/// is added to the next written frame.
+pub struct Recorder {
+    /// Output of the recording
+    sink: Sink,
+    /// Width of the frames in pixels
+    width: usize,
+    /// Height of the frames in pixels
+    height: usize,
+    /// Minimal time between two written frames (sec)
+    min_interval: f32,
+    /// Time covered by the frames since the last written one (sec)
+    pending: f32,
+    /// Number of written frames
+    frames: usize,
+}
+
+impl Recorder {
+    /// Starts a recording.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - GIF file or PNG directory, see [`CaptureFormat::from_path`]
+    /// * `width` - width of the frames in pixels
+    /// * `height` - height of the frames in pixels
+    ///
+    /// # Returns
+    ///
+    /// * `ImageResult<Self>` - Recorder writing every pushed frame, or an error if the
+    ///   output cannot be created.
+    pub fn create<P: AsRef<Path>>(path: P, width: usize, height: usize) -> ImageResult<Self> {
+        let path = path.as_ref();
+        let sink = match CaptureFormat::from_path(path) {
+            CaptureFormat::Gif => {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                let file = BufWriter::new(File::create(path)?);
+                let mut encoder = GifEncoder::new_with_speed(file, GIF_SPEED);
+                encoder.set_repeat(Repeat::Infinite)?;
+                Sink::Gif(Box::new(encoder))
+            }
+            CaptureFormat::PngSequence => {
+                fs::create_dir_all(path)?;
+                Sink::PngSequence(path.to_path_buf())
+            }
+        };
+
+        Ok(Self { sink, width, height, min_interval: 0.0, pending: 0.0, frames: 0 })
+    }
+
+    /// Limits the number of written frames per second of recorded time.
+    pub fn with_fps(mut self, fps: f32) -> Self {
+        self.min_interval = 1.0 / fps;
+        self
+    }
+
+    /// Adds a frame to the recording.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - pixel buffer of `width * height` pixels
+    /// * `delta` - time elapsed since the previous frame (sec)
+    pub fn push(&mut self, frame: &[u32], delta: f32) -> ImageResult<()> {
+        self.pending += delta;
+        if self.frames > 0 && self.pending < self.min_interval {
+            return Ok(());
+        }
+
+        let image = frame_to_image(frame, self.width, self.height);
+        match &mut self.sink {
+            Sink::Gif(encoder) => {
+                let delay = Delay::from_numer_denom_ms((self.pending * 1000.0).round() as u32, 1);
+                encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?;
+            }
+            Sink::PngSequence(dir) => {
+                image.save(dir.join(format!("frame_{:05}.png", self.frames)))?;
+            }
+        }
+
+        self.pending = 0.0;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Returns the number of written frames.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Ends the recording, flushing the output.
+    ///
+    /// # Returns
+    ///
+    /// The number of written frames.
+    pub fn finish(self) -> usize {
+        // the GIF trailer is written when the encoder is dropped
+        self.frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+    use std::env;
+    use std::io::BufReader;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("ferari_capture_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(CaptureFormat::from_path("demo.GIF"), CaptureFormat::Gif);
+        assert_eq!(CaptureFormat::from_path("frames"), CaptureFormat::PngSequence);
+        assert_eq!(CaptureFormat::from_path("frames.d/"), CaptureFormat::PngSequence);
+    }
+
+    #[test]
+    fn test_save_png_keeps_colors() {
+        let dir = scratch_dir("png");
+        let path = dir.join("shot.png");
+
+        save_png(&[0xFF112233, 0x00445566], 2, 1, &path).unwrap();
+        let image = image::open(&path).unwrap().to_rgba8();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(image.get_pixel(0, 0).0, [0x11, 0x22, 0x33, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [0x44, 0x55, 0x66, 255]);
+    }
+
+    #[test]
+    fn test_png_sequence_respects_fps() {
+        let dir = scratch_dir("sequence");
+        let mut recorder = Recorder::create(&dir, 2, 2).unwrap().with_fps(10.0);
+
+        for i in 0..12 {
+            recorder.push(&[i; 4], 0.025).unwrap();
+        }
+        let frames = recorder.finish();
+        let mut files: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        files.sort();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // the first frame is always written, then one every 4 frames of 25 ms
+        assert_eq!(frames, 3);
+        assert_eq!(files, ["frame_00000.png", "frame_00001.png", "frame_00002.png"]);
+    }
+
+    #[test]
+    fn test_gif_recording_has_all_frames() {
+        let dir = scratch_dir("gif");
+        let path = dir.join("clip.gif");
+        let mut recorder = Recorder::create(&path, 4, 4).unwrap();
+
+        for color in [0xFFFF0000, 0xFF00FF00, 0xFF0000FF] {
+            recorder.push(&[color; 16], 0.05).unwrap();
+        }
+        assert_eq!(recorder.finish(), 3);
+
+        let decoder = GifDecoder::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[1].delay().numer_denom_ms(), (50, 1));
+        assert_eq!(frames[1].buffer().get_pixel(0, 0).0, [0, 255, 0, 255]);
+    }
+}
@@ -1,5 +1,5 @@
 use crossbeam_channel::Receiver;
-use minifb::{Key, Window, WindowOptions};
+use minifb::{Window, WindowOptions};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
@@ -12,7 +12,7 @@ use crate::input::InputState;
 /// This function creates a window and runs a loop that:
 /// - Updates the input state from window events
 /// - Receives and renders frames
-/// - Handles escape key event
+/// - Handles the quit action
 /// - Maintains target FPS
 ///
 /// # Arguments
@@ -27,8 +27,9 @@ use crate::input::InputState;
 /// # Behaviour
 ///
 /// The thread will run until `running` is set to false, either by external code
-/// or by user pressing Escape or closing the window. The window is created with
-/// the title "Ferari" and uses the specified dimensions scaled by the upscale factor.
+/// or by user pressing the quit key (Escape by default) or closing the window. The window
+/// is created with the title "Ferari" and uses the specified dimensions scaled by the
+/// upscale factor.
 pub fn run_draw_thread(
     rx_frame: Receiver<Vec<u32>>,
     input_state: Arc<InputState>,
@@ -52,7 +53,7 @@ pub fn run_draw_thread(
             window.update();
         }
 
-        if window.is_key_down(Key::Escape) || !window.is_open() {
+        if input_state.read().quit || !window.is_open() {
             running.store(false, Ordering::Release);
             break;
         }
@@ -0,0 +1,449 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use minifb::Key;
+use serde::{Deserialize, Serialize};
+
+// ============================
+// Actions
+// ============================
+
+/// Game action a key can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Move up (`NE` on screen)
+    MoveUp,
+    /// Move down (`SW` on screen)
+    MoveDown,
+    /// Move left (`NW` on screen)
+    MoveLeft,
+    /// Move right (`SE` on screen)
+    MoveRight,
+    /// Undo the last step
+    Undo,
+    /// Redo the last undone step
+    Redo,
+    /// Restart the current level
+    Restart,
+    /// Go to the next level of the pack
+    NextLevel,
+    /// Go to the previous level of the pack
+    PreviousLevel,
+    /// Go back to the menu
+    Menu,
+    /// Play the next move of the solution
+    Hint,
+    /// Play the whole solution
+    Solution,
+    /// Save a screenshot
+    Screenshot,
+    /// Start / stop a recording
+    Record,
+    /// Pause / resume the game
+    Pause,
+    /// Slow the game down
+    SlowDown,
+    /// Speed the game up
+    SpeedUp,
+    /// Turn instant moves (no animations) on / off
+    InstantMoves,
+    /// Open / close the settings screen
+    Settings,
+    /// Close the game
+    Quit,
+}
+
+impl Action {
+    /// All actions in display order.
+    pub const ALL: [Action; 20] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Undo,
+        Action::Redo,
+        Action::Restart,
+        Action::NextLevel,
+        Action::PreviousLevel,
+        Action::Menu,
+        Action::Hint,
+        Action::Solution,
+        Action::Screenshot,
+        Action::Record,
+        Action::Pause,
+        Action::SlowDown,
+        Action::SpeedUp,
+        Action::InstantMoves,
+        Action::Settings,
+        Action::Quit,
+    ];
+
+    /// Returns the position of the action in [`Action::ALL`].
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns the human-readable name of the action (letters and spaces only).
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveUp => "MOVE UP",
+            Action::MoveDown => "MOVE DOWN",
+            Action::MoveLeft => "MOVE LEFT",
+            Action::MoveRight => "MOVE RIGHT",
+            Action::Undo => "UNDO",
+            Action::Redo => "REDO",
+            Action::Restart => "RESTART",
+            Action::NextLevel => "NEXT LEVEL",
+            Action::PreviousLevel => "PREVIOUS LEVEL",
+            Action::Menu => "MENU",
+            Action::Hint => "HINT",
+            Action::Solution => "SOLUTION",
+            Action::Screenshot => "SCREENSHOT",
+            Action::Record => "RECORD",
+            Action::Pause => "PAUSE",
+            Action::SlowDown => "SLOW DOWN",
+            Action::SpeedUp => "SPEED UP",
+            Action::InstantMoves => "INSTANT MOVES",
+            Action::Settings => "SETTINGS",
+            Action::Quit => "QUIT",
+        }
+    }
+}
+
+// ============================
+// Key names
+// ============================
+
+/// Every bindable key, in the order of [`Key`].
+const KEYS: [Key; 106] = [
+    Key::Key0,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::Down,
+    Key::Left,
+    Key::Right,
+    Key::Up,
+    Key::Apostrophe,
+    Key::Backquote,
+    Key::Backslash,
+    Key::Comma,
+    Key::Equal,
+    Key::LeftBracket,
+    Key::Minus,
+    Key::Period,
+    Key::RightBracket,
+    Key::Semicolon,
+    Key::Slash,
+    Key::Backspace,
+    Key::Delete,
+    Key::End,
+    Key::Enter,
+    Key::Escape,
+    Key::Home,
+    Key::Insert,
+    Key::Menu,
+    Key::PageDown,
+    Key::PageUp,
+    Key::Pause,
+    Key::Space,
+    Key::Tab,
+    Key::NumLock,
+    Key::CapsLock,
+    Key::ScrollLock,
+    Key::LeftShift,
+    Key::RightShift,
+    Key::LeftCtrl,
+    Key::RightCtrl,
+    Key::NumPad0,
+    Key::NumPad1,
+    Key::NumPad2,
+    Key::NumPad3,
+    Key::NumPad4,
+    Key::NumPad5,
+    Key::NumPad6,
+    Key::NumPad7,
+    Key::NumPad8,
+    Key::NumPad9,
+    Key::NumPadDot,
+    Key::NumPadSlash,
+    Key::NumPadAsterisk,
+    Key::NumPadMinus,
+    Key::NumPadPlus,
+    Key::NumPadEnter,
+    Key::LeftAlt,
+    Key::RightAlt,
+    Key::LeftSuper,
+    Key::RightSuper,
+];
+
+/// Returns the name of a key as written in the settings file (`W`, `Up`, `F12`, `0`, ...).
+pub fn key_name(key: Key) -> String {
+    let name = format!("{key:?}");
+    match name.strip_prefix("Key") {
+        Some(digit) if !digit.is_empty() => digit.to_string(),
+        _ => name,
+    }
+}
+
+/// Parses a key name written by [`key_name`] (case-insensitive).
+///
+/// # Returns
+///
+/// The key, or `None` for an unknown name.
+pub fn parse_key(name: &str) -> Option<Key> {
+    KEYS.iter().copied().find(|&key| key_name(key).eq_ignore_ascii_case(name))
+}
+
+// ============================
+// Bindings
+// ============================
+
+/// Error of a settings file naming an unknown key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownKey(pub String);
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown key '{}'", self.0)
+    }
+}
+
+impl std::error::Error for UnknownKey {}
+
+/// Keys bound to every action; an action may have several keys.
+///
+/// Serialized as a map from action to key names, e.g. `{"move_up": ["W", "Up"]}`.
+/// Actions missing from a settings file keep their default keys.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "BTreeMap<Action, Vec<String>>", into = "BTreeMap<Action, Vec<String>>")]
+pub struct KeyBindings {
+    /// Bound keys by action
+    keys: BTreeMap<Action, Vec<Key>>,
+}
+
+impl KeyBindings {
+    /// Creates bindings without any key.
+    pub fn empty() -> Self {
+        Self { keys: BTreeMap::new() }
+    }
+
+    /// Returns the keys bound to an action.
+    pub fn keys(&self, action: Action) -> &[Key] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds an additional key to an action (no-op if already bound).
+    pub fn bind(&mut self, action: Action, key: Key) {
+        let keys = self.keys.entry(action).or_default();
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    /// Removes a key from an action.
+    pub fn unbind(&mut self, action: Action, key: Key) {
+        if let Some(keys) = self.keys.get_mut(&action) {
+            keys.retain(|&bound| bound != key);
+        }
+    }
+
+    /// Removes all keys of an action.
+    pub fn clear(&mut self, action: Action) {
+        self.keys.remove(&action);
+    }
+
+    /// Returns the actions a key is bound to.
+    pub fn actions(&self, key: Key) -> Vec<Action> {
+        Action::ALL.into_iter().filter(|&action| self.keys(action).contains(&key)).collect()
+    }
+
+    /// Finds keys bound to more than one action.
+    ///
+    /// # Returns
+    ///
+    /// Every conflicting key with the actions it is bound to, ordered by key.
+    pub fn conflicts(&self) -> Vec<(Key, Vec<Action>)> {
+        let mut keys: Vec<Key> = self.keys.values().flatten().copied().collect();
+        keys.sort();
+        keys.dedup();
+
+        keys.into_iter()
+            .map(|key| (key, self.actions(key)))
+            .filter(|(_, actions)| actions.len() > 1)
+            .collect()
+    }
+
+    /// Returns the names of the keys bound to an action, separated by spaces.
+    pub fn describe(&self, action: Action) -> String {
+        self.keys(action).iter().map(|&key| key_name(key)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+impl Default for KeyBindings {
+    /// WASD / arrow movement, `Z` / `Y` undo / redo, `R` restart, `N` / `B` next / previous
+    /// level, `M` menu, `H` / `J` hints, `F12` screenshot, `F10` recording, `F1` settings and
+    /// `Escape` to quit.
+    fn default() -> Self {
+        let defaults: [(Action, &[Key]); 20] = [
+            (Action::MoveUp, &[Key::W, Key::Up]),
+            (Action::MoveDown, &[Key::S, Key::Down]),
+            (Action::MoveLeft, &[Key::A, Key::Left]),
+            (Action::MoveRight, &[Key::D, Key::Right]),
+            (Action::Undo, &[Key::Z]),
+            (Action::Redo, &[Key::Y]),
+            (Action::Restart, &[Key::R]),
+            (Action::NextLevel, &[Key::N]),
+            (Action::PreviousLevel, &[Key::B]),
+            (Action::Menu, &[Key::M]),
+            (Action::Hint, &[Key::H]),
+            (Action::Solution, &[Key::J]),
+            (Action::Screenshot, &[Key::F12]),
+            (Action::Record, &[Key::F10]),
+            (Action::Pause, &[Key::P]),
+            (Action::SlowDown, &[Key::Minus]),
+            (Action::SpeedUp, &[Key::Equal]),
+            (Action::InstantMoves, &[Key::I]),
+            (Action::Settings, &[Key::F1]),
+            (Action::Quit, &[Key::Escape]),
+        ];
+
+        Self { keys: defaults.into_iter().map(|(action, keys)| (action, keys.to_vec())).collect() }
+    }
+}
+
+impl TryFrom<BTreeMap<Action, Vec<String>>> for KeyBindings {
+    type Error = UnknownKey;
+
+    fn try_from(names: BTreeMap<Action, Vec<String>>) -> Result<Self, Self::Error> {
+        let mut bindings = Self::default();
+        for (action, names) in names {
+            bindings.clear(action);
+            for name in names {
+                let key = parse_key(&name).ok_or(UnknownKey(name))?;
+                bindings.bind(action, key);
+            }
+        }
+        Ok(bindings)
+    }
+}
+
+impl From<KeyBindings> for BTreeMap<Action, Vec<String>> {
+    fn from(bindings: KeyBindings) -> Self {
+        Action::ALL
+            .into_iter()
+            .map(|action| {
+                (action, bindings.keys(action).iter().map(|&key| key_name(key)).collect())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_names_round_trip() {
+        for key in KEYS {
+            assert_eq!(parse_key(&key_name(key)), Some(key), "{key:?}");
+        }
+        assert_eq!(key_name(Key::Key7), "7");
+        assert_eq!(key_name(Key::F12), "F12");
+        assert_eq!(parse_key("left"), Some(Key::Left));
+        assert_eq!(parse_key("Unknown"), None);
+    }
+
+    #[test]
+    fn test_action_index() {
+        for (i, action) in Action::ALL.into_iter().enumerate() {
+            assert_eq!(action.index(), i);
+        }
+    }
+
+    #[test]
+    fn test_defaults_have_no_conflicts() {
+        let bindings = KeyBindings::default();
+
+        assert!(Action::ALL.iter().all(|&action| !bindings.keys(action).is_empty()));
+        assert!(bindings.conflicts().is_empty());
+        assert_eq!(bindings.describe(Action::MoveUp), "W Up");
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let mut bindings = KeyBindings::default();
+        bindings.bind(Action::Undo, Key::W);
+        bindings.bind(Action::Undo, Key::W);
+
+        assert_eq!(bindings.keys(Action::Undo), [Key::Z, Key::W]);
+        assert_eq!(bindings.conflicts(), [(Key::W, vec![Action::MoveUp, Action::Undo])]);
+
+        bindings.unbind(Action::MoveUp, Key::W);
+        assert!(bindings.conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_serde_keeps_defaults_of_missing_actions() {
+        let bindings: KeyBindings =
+            serde_json::from_str(r#"{"undo": ["u", "Backspace"], "hint": []}"#).unwrap();
+
+        assert_eq!(bindings.keys(Action::Undo), [Key::U, Key::Backspace]);
+        assert!(bindings.keys(Action::Hint).is_empty());
+        assert_eq!(bindings.keys(Action::MoveUp), [Key::W, Key::Up]);
+
+        let text = serde_json::to_string(&bindings).unwrap();
+        assert_eq!(serde_json::from_str::<KeyBindings>(&text).unwrap(), bindings);
+
+        let err = serde_json::from_str::<KeyBindings>(r#"{"undo": ["Hyper"]}"#).unwrap_err();
+        assert!(err.to_string().contains("unknown key 'Hyper'"));
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use super::bindings::Action;
+
+/// Maximum number of queued events; the oldest ones are dropped when nobody reads them.
+const EVENT_QUEUE_LIMIT: usize = 256;
+
+/// What happened to an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEventKind {
+    /// The action went down
+    Pressed,
+    /// The action went up
+    Released,
+    /// The action is held long enough to repeat (see [`RepeatSettings`])
+    Repeated,
+}
+
+/// A change of an action, timestamped when the keyboard was polled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputEvent {
+    /// Action the event belongs to
+    pub action: Action,
+    /// Kind of the event
+    pub kind: InputEventKind,
+    /// Time of the event in seconds, counted from the creation of the input state
+    pub time: f64,
+}
+
+impl InputEvent {
+    /// Checks whether the event is a press or a repeat of an action.
+    pub fn is_press_or_repeat(&self, action: Action) -> bool {
+        self.action == action && self.kind != InputEventKind::Released
+    }
+}
+
+/// Key repeat of held actions.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepeatSettings {
+    /// Time an action has to be held before it starts repeating, in seconds
+    pub delay: f32,
+    /// Repeats per second once repeating, `0` disables the repeat
+    pub rate: f32,
+}
+
+impl Default for RepeatSettings {
+    fn default() -> Self {
+        Self { delay: 0.4, rate: 12.0 }
+    }
+}
+
+/// Turns the polled action states into events.
+#[derive(Debug)]
+pub(super) struct EventTracker {
+    /// Key repeat settings
+    repeat: RepeatSettings,
+    /// Time of the next repeat of every held action (indexed like [`Action::ALL`])
+    next_repeat: [Option<f64>; Action::ALL.len()],
+    /// Events not read yet
+    queue: VecDeque<InputEvent>,
+}
+
+impl EventTracker {
+    /// Creates a tracker with all actions released.
+    pub(super) fn new(repeat: RepeatSettings) -> Self {
+        Self { repeat, next_repeat: [None; Action::ALL.len()], queue: VecDeque::new() }
+    }
+
+    /// Changes the key repeat, taking effect on the next press.
+    pub(super) fn set_repeat(&mut self, repeat: RepeatSettings) {
+        self.repeat = repeat;
+    }
+
+    /// Compares the polled action states with the previous ones and queues the changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `down` - whether every action (indexed like [`Action::ALL`]) is down
+    /// * `now` - time of the poll in seconds
+    pub(super) fn update(&mut self, down: &[bool; Action::ALL.len()], now: f64) {
+        for (i, action) in Action::ALL.into_iter().enumerate() {
+            let kind = match (down[i], self.next_repeat[i]) {
+                (true, None) => {
+                    self.next_repeat[i] = Some(now + self.repeat.delay as f64);
+                    InputEventKind::Pressed
+                }
+                (false, Some(_)) => {
+                    self.next_repeat[i] = None;
+                    InputEventKind::Released
+                }
+                (true, Some(next)) if self.repeat.rate > 0.0 && now >= next => {
+                    // a slow poll repeats once and catches up with the rate
+                    let interval = 1.0 / self.repeat.rate as f64;
+                    self.next_repeat[i] = Some((next + interval).max(now));
+                    InputEventKind::Repeated
+                }
+                _ => continue,
+            };
+
+            if self.queue.len() == EVENT_QUEUE_LIMIT {
+                self.queue.pop_front();
+            }
+            self.queue.push_back(InputEvent { action, kind, time: now });
+        }
+    }
+
+    /// Returns the queued events in order and empties the queue.
+    pub(super) fn take(&mut self) -> Vec<InputEvent> {
+        self.queue.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn down(actions: &[Action]) -> [bool; Action::ALL.len()] {
+        Action::ALL.map(|action| actions.contains(&action))
+    }
+
+    fn kinds(events: &[InputEvent]) -> Vec<(Action, InputEventKind)> {
+        events.iter().map(|event| (event.action, event.kind)).collect()
+    }
+
+    #[test]
+    fn test_press_and_release() {
+        let mut tracker = EventTracker::new(RepeatSettings::default());
+
+        tracker.update(&down(&[Action::MoveLeft]), 0.0);
+        tracker.update(&down(&[Action::MoveLeft, Action::MoveRight]), 0.1);
+        tracker.update(&down(&[]), 0.2);
+        let events = tracker.take();
+
+        assert_eq!(
+            kinds(&events),
+            [
+                (Action::MoveLeft, InputEventKind::Pressed),
+                (Action::MoveRight, InputEventKind::Pressed),
+                (Action::MoveLeft, InputEventKind::Released),
+                (Action::MoveRight, InputEventKind::Released),
+            ]
+        );
+        assert_eq!(events[1].time, 0.1);
+        assert!(tracker.take().is_empty());
+    }
+
+    #[test]
+    fn test_repeat_after_delay() {
+        let mut tracker = EventTracker::new(RepeatSettings { delay: 0.5, rate: 10.0 });
+
+        for time in [0.0, 0.3, 0.55, 0.62, 0.66, 0.9] {
+            tracker.update(&down(&[Action::Undo]), time);
+        }
+        let events = tracker.take();
+
+        // due at 0.5, 0.6 and 0.7; the late poll at 0.9 repeats only once
+        let times: Vec<f64> = events[1..].iter().map(|event| event.time).collect();
+        assert_eq!(events[0].kind, InputEventKind::Pressed);
+        assert!(events[1..].iter().all(|event| event.kind == InputEventKind::Repeated));
+        assert_eq!(times, [0.55, 0.62, 0.9]);
+    }
+
+    #[test]
+    fn test_zero_rate_disables_repeat() {
+        let mut tracker = EventTracker::new(RepeatSettings { delay: 0.0, rate: 0.0 });
+
+        for frame in 0..10 {
+            tracker.update(&down(&[Action::Hint]), frame as f64);
+        }
+
+        assert_eq!(kinds(&tracker.take()), [(Action::Hint, InputEventKind::Pressed)]);
+    }
+}
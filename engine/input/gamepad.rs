@@ -0,0 +1,373 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use super::bindings::Action;
+use super::source::InputSource;
+
+// ============================
+// Device events
+// ============================
+
+/// Gamepad button, named after its position (Xbox `A` is [`GamepadButton::South`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadButton {
+    /// Up on the d-pad
+    DPadUp,
+    /// Down on the d-pad
+    DPadDown,
+    /// Left on the d-pad
+    DPadLeft,
+    /// Right on the d-pad
+    DPadRight,
+    /// Bottom face button (Xbox `A`, PlayStation cross)
+    South,
+    /// Right face button (Xbox `B`, PlayStation circle)
+    East,
+    /// Left face button (Xbox `X`, PlayStation square)
+    West,
+    /// Top face button (Xbox `Y`, PlayStation triangle)
+    North,
+    /// Left shoulder button
+    LeftShoulder,
+    /// Right shoulder button
+    RightShoulder,
+    /// Select / back / share button
+    Select,
+    /// Start / menu / options button
+    Start,
+}
+
+/// Input reported by a gamepad device.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GamepadEvent {
+    /// A button went down
+    Pressed(GamepadButton),
+    /// A button went up
+    Released(GamepadButton),
+    /// The left stick moved; both axes range from `-1` to `1`, `y` points up
+    Stick {
+        /// Horizontal position, right is positive
+        x: f32,
+        /// Vertical position, up is positive
+        y: f32,
+    },
+    /// The gamepad has been disconnected, everything it held is released
+    Disconnected,
+}
+
+/// Source of gamepad events, e.g. the connected controllers or [`FakeGamepad`].
+pub trait GamepadDevice: Send {
+    /// Returns the next pending event and the index of the pad it comes from, `None` once
+    /// all events have been read.
+    fn next_event(&mut self) -> Option<(usize, GamepadEvent)>;
+}
+
+// ============================
+// Mapping
+// ============================
+
+/// Assignment of gamepad buttons and the left stick to actions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadMapping {
+    /// Action of every mapped button
+    pub buttons: BTreeMap<GamepadButton, Action>,
+    /// Stick deflection below which the stick holds no direction, from `0` to `1`
+    pub dead_zone: f32,
+}
+
+impl GamepadMapping {
+    /// Returns the movement action of a stick position.
+    ///
+    /// The stick follows the isometric view: its diagonals are the four directions the
+    /// player walks in on screen (up-right is [`Action::MoveUp`], down-right is
+    /// [`Action::MoveRight`] and so on).
+    ///
+    /// # Returns
+    ///
+    /// The movement action, or `None` inside the dead zone.
+    pub fn stick_action(&self, x: f32, y: f32) -> Option<Action> {
+        if x.hypot(y) < self.dead_zone {
+            return None;
+        }
+
+        Some(match (x >= 0.0, y >= 0.0) {
+            (true, true) => Action::MoveUp,
+            (true, false) => Action::MoveRight,
+            (false, false) => Action::MoveDown,
+            (false, true) => Action::MoveLeft,
+        })
+    }
+}
+
+impl Default for GamepadMapping {
+    /// D-pad movement, `South` / `East` undo / redo, `West` restart, `North` hint,
+    /// `Start` menu and `Select` settings.
+    fn default() -> Self {
+        let buttons = [
+            (GamepadButton::DPadUp, Action::MoveUp),
+            (GamepadButton::DPadDown, Action::MoveDown),
+            (GamepadButton::DPadLeft, Action::MoveLeft),
+            (GamepadButton::DPadRight, Action::MoveRight),
+            (GamepadButton::South, Action::Undo),
+            (GamepadButton::East, Action::Redo),
+            (GamepadButton::West, Action::Restart),
+            (GamepadButton::North, Action::Hint),
+            (GamepadButton::Start, Action::Menu),
+            (GamepadButton::Select, Action::Settings),
+        ];
+
+        Self { buttons: buttons.into_iter().collect(), dead_zone: 0.5 }
+    }
+}
+
+// ============================
+// Input source
+// ============================
+
+/// Input source turning the events of a gamepad device into held actions.
+///
+/// Every pad is tracked on its own, so disconnecting one pad releases only what it held.
+pub struct GamepadSource<D: GamepadDevice> {
+    /// Device the events are read from
+    device: D,
+    /// Assignment of buttons and stick to actions
+    mapping: GamepadMapping,
+    /// Buttons currently held, by pad
+    held: BTreeMap<usize, BTreeSet<GamepadButton>>,
+    /// Current left stick position `(x, y)`, by pad
+    sticks: BTreeMap<usize, (f32, f32)>,
+}
+
+impl<D: GamepadDevice> GamepadSource<D> {
+    /// Creates a source with the default mapping.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - device the events are read from
+    pub fn new(device: D) -> Self {
+        Self::with_mapping(device, GamepadMapping::default())
+    }
+
+    /// Creates a source with a custom mapping.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - device the events are read from
+    /// * `mapping` - assignment of buttons and stick to actions
+    pub fn with_mapping(device: D, mapping: GamepadMapping) -> Self {
+        Self { device, mapping, held: BTreeMap::new(), sticks: BTreeMap::new() }
+    }
+}
+
+impl<D: GamepadDevice> InputSource for GamepadSource<D> {
+    fn poll(&mut self, down: &mut [bool; Action::ALL.len()]) {
+        while let Some((pad, event)) = self.device.next_event() {
+            match event {
+                GamepadEvent::Pressed(button) => {
+                    self.held.entry(pad).or_default().insert(button);
+                }
+                GamepadEvent::Released(button) => {
+                    self.held.entry(pad).or_default().remove(&button);
+                }
+                GamepadEvent::Stick { x, y } => {
+                    self.sticks.insert(pad, (x, y));
+                }
+                GamepadEvent::Disconnected => {
+                    self.held.remove(&pad);
+                    self.sticks.remove(&pad);
+                }
+            }
+        }
+
+        let buttons =
+            self.held.values().flatten().filter_map(|button| self.mapping.buttons.get(button));
+        let sticks = self.sticks.values().filter_map(|&(x, y)| self.mapping.stick_action(x, y));
+        for action in buttons.copied().chain(sticks) {
+            down[action.index()] = true;
+        }
+    }
+}
+
+/// Gamepads fed by hand, for tests and scripted input.
+///
+/// Clones share their event queue: keep a clone to push events into a device that has
+/// been moved into a [`GamepadSource`].
+#[derive(Debug, Clone, Default)]
+pub struct FakeGamepad {
+    /// Events not read yet and the pads they come from
+    events: Arc<Mutex<VecDeque<(usize, GamepadEvent)>>>,
+}
+
+impl FakeGamepad {
+    /// Creates a device without pending events.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues an event of the first pad for the next poll.
+    pub fn push(&self, event: GamepadEvent) {
+        self.push_from(0, event);
+    }
+
+    /// Queues an event of a pad for the next poll.
+    ///
+    /// # Arguments
+    ///
+    /// * `pad` - index of the pad the event comes from
+    /// * `event` - the event
+    pub fn push_from(&self, pad: usize, event: GamepadEvent) {
+        self.events.lock().unwrap().push_back((pad, event));
+    }
+}
+
+impl GamepadDevice for FakeGamepad {
+    fn next_event(&mut self) -> Option<(usize, GamepadEvent)> {
+        self.events.lock().unwrap().pop_front()
+    }
+}
+
+// ============================
+// Connected controllers
+// ============================
+
+/// All controllers connected to the system, read with `gilrs`.
+#[cfg(feature = "gamepad")]
+pub struct GilrsGamepad {
+    /// Gamepad library context
+    gilrs: gilrs::Gilrs,
+}
+
+#[cfg(feature = "gamepad")]
+impl GilrsGamepad {
+    /// Opens the gamepad subsystem.
+    ///
+    /// # Returns
+    ///
+    /// The device, or an error if the platform has no gamepad support.
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self { gilrs: gilrs::Gilrs::new()? })
+    }
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadDevice for GilrsGamepad {
+    fn next_event(&mut self) -> Option<(usize, GamepadEvent)> {
+        use gilrs::{Axis, Button, EventType};
+
+        let button = |button| match button {
+            Button::DPadUp => Some(GamepadButton::DPadUp),
+            Button::DPadDown => Some(GamepadButton::DPadDown),
+            Button::DPadLeft => Some(GamepadButton::DPadLeft),
+            Button::DPadRight => Some(GamepadButton::DPadRight),
+            Button::South => Some(GamepadButton::South),
+            Button::East => Some(GamepadButton::East),
+            Button::West => Some(GamepadButton::West),
+            Button::North => Some(GamepadButton::North),
+            Button::LeftTrigger => Some(GamepadButton::LeftShoulder),
+            Button::RightTrigger => Some(GamepadButton::RightShoulder),
+            Button::Select => Some(GamepadButton::Select),
+            Button::Start => Some(GamepadButton::Start),
+            _ => None,
+        };
+
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            let event = match event {
+                EventType::ButtonPressed(pressed, _) => button(pressed).map(GamepadEvent::Pressed),
+                EventType::ButtonReleased(released, _) => {
+                    button(released).map(GamepadEvent::Released)
+                }
+                EventType::AxisChanged(Axis::LeftStickX | Axis::LeftStickY, _, _) => {
+                    let gamepad = self.gilrs.gamepad(id);
+                    Some(GamepadEvent::Stick {
+                        x: gamepad.value(Axis::LeftStickX),
+                        y: gamepad.value(Axis::LeftStickY),
+                    })
+                }
+                EventType::Disconnected => Some(GamepadEvent::Disconnected),
+                _ => None,
+            };
+
+            if let Some(event) = event {
+                return Some((usize::from(id), event));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll(source: &mut impl InputSource) -> Vec<Action> {
+        let mut down = [false; Action::ALL.len()];
+        source.poll(&mut down);
+        Action::ALL.into_iter().filter(|action| down[action.index()]).collect()
+    }
+
+    #[test]
+    fn test_buttons_hold_mapped_actions() {
+        let gamepad = FakeGamepad::new();
+        let mut source = GamepadSource::new(gamepad.clone());
+
+        gamepad.push(GamepadEvent::Pressed(GamepadButton::DPadLeft));
+        gamepad.push(GamepadEvent::Pressed(GamepadButton::West));
+        gamepad.push(GamepadEvent::Pressed(GamepadButton::LeftShoulder));
+        assert_eq!(poll(&mut source), [Action::MoveLeft, Action::Restart]);
+        assert_eq!(poll(&mut source), [Action::MoveLeft, Action::Restart]);
+
+        gamepad.push(GamepadEvent::Released(GamepadButton::DPadLeft));
+        gamepad.push(GamepadEvent::Pressed(GamepadButton::Start));
+        assert_eq!(poll(&mut source), [Action::Restart, Action::Menu]);
+
+        gamepad.push(GamepadEvent::Disconnected);
+        assert!(poll(&mut source).is_empty());
+    }
+
+    #[test]
+    fn test_disconnecting_a_pad_keeps_the_others() {
+        let gamepad = FakeGamepad::new();
+        let mut source = GamepadSource::new(gamepad.clone());
+
+        gamepad.push_from(0, GamepadEvent::Pressed(GamepadButton::DPadLeft));
+        gamepad.push_from(1, GamepadEvent::Pressed(GamepadButton::West));
+        gamepad.push_from(1, GamepadEvent::Stick { x: 0.0, y: 1.0 });
+        assert_eq!(poll(&mut source), [Action::MoveUp, Action::MoveLeft, Action::Restart]);
+
+        gamepad.push_from(1, GamepadEvent::Disconnected);
+        assert_eq!(poll(&mut source), [Action::MoveLeft]);
+    }
+
+    #[test]
+    fn test_stick_follows_isometric_directions() {
+        let mapping = GamepadMapping::default();
+
+        assert_eq!(mapping.stick_action(0.7, 0.7), Some(Action::MoveUp));
+        assert_eq!(mapping.stick_action(0.9, -0.2), Some(Action::MoveRight));
+        assert_eq!(mapping.stick_action(-0.6, -0.6), Some(Action::MoveDown));
+        assert_eq!(mapping.stick_action(-0.8, 0.1), Some(Action::MoveLeft));
+        assert_eq!(mapping.stick_action(0.3, 0.2), None);
+
+        let gamepad = FakeGamepad::new();
+        let mut source = GamepadSource::new(gamepad.clone());
+        gamepad.push(GamepadEvent::Stick { x: 0.1, y: -1.0 });
+        gamepad.push(GamepadEvent::Stick { x: -0.1, y: -1.0 });
+        assert_eq!(poll(&mut source), [Action::MoveDown]);
+
+        gamepad.push(GamepadEvent::Stick { x: 0.0, y: 0.0 });
+        assert!(poll(&mut source).is_empty());
+    }
+
+    #[test]
+    fn test_mapping_serde() {
+        let mapping: GamepadMapping =
+            serde_json::from_str(r#"{"buttons": {"south": "restart"}, "dead_zone": 0.3}"#).unwrap();
+
+        assert_eq!(mapping.buttons, BTreeMap::from([(GamepadButton::South, Action::Restart)]));
+        assert_eq!(mapping.stick_action(0.0, 0.4), Some(Action::MoveUp));
+    }
+}
@@ -0,0 +1,412 @@
+mod bindings;
+mod events;
+mod gamepad;
+mod source;
+
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
+use serde::{Deserialize, Serialize};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, RwLock,
+};
+use std::time::Instant;
+
+pub use bindings::{key_name, parse_key, Action, KeyBindings, UnknownKey};
+pub use events::{InputEvent, InputEventKind, RepeatSettings};
+#[cfg(feature = "gamepad")]
+pub use gamepad::GilrsGamepad;
+pub use gamepad::{
+    FakeGamepad, GamepadButton, GamepadDevice, GamepadEvent, GamepadMapping, GamepadSource,
+};
+pub use source::InputSource;
+
+use events::EventTracker;
+
+/// A snapshot of the input state at a specific moment in time.
+///
+/// This struct provides a view of all actions: an action is down while any of its
+/// bound keys is held (see [`KeyBindings`]).
+/// Snapshots are serializable, so input sessions can be recorded and replayed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputSnapshot {
+    /// Indicates if [`Action::MoveUp`] was down when the snapshot was taken
+    pub up: bool,
+    /// Indicates if [`Action::MoveLeft`] was down when the snapshot was taken
+    pub left: bool,
+    /// Indicates if [`Action::MoveDown`] was down when the snapshot was taken
+    pub down: bool,
+    /// Indicates if [`Action::MoveRight`] was down when the snapshot was taken
+    pub right: bool,
+    /// Indicates if [`Action::Quit`] was down when the snapshot was taken
+    #[serde(alias = "escape")]
+    pub quit: bool,
+    /// Indicates if [`Action::Undo`] (undo last step) was down when the snapshot was taken
+    pub undo: bool,
+    /// Indicates if [`Action::Redo`] (redo undone step) was down when the snapshot was taken
+    pub redo: bool,
+    /// Indicates if [`Action::Restart`] (restart the level) was down when the snapshot was taken
+    pub restart: bool,
+    /// Indicates if [`Action::NextLevel`] was down when the snapshot was taken
+    pub next_level: bool,
+    /// Indicates if [`Action::PreviousLevel`] was down when the snapshot was taken
+    pub previous_level: bool,
+    /// Indicates if [`Action::Menu`] (back to the menu) was down when the snapshot was taken
+    pub menu: bool,
+    /// Indicates if [`Action::Hint`] (play the next solution move) was down when the snapshot was taken
+    pub hint: bool,
+    /// Indicates if [`Action::Solution`] (play the whole solution) was down when the snapshot was taken
+    pub solution: bool,
+    /// Indicates if [`Action::Screenshot`] was down when the snapshot was taken
+    pub screenshot: bool,
+    /// Indicates if [`Action::Record`] (start / stop a recording) was down when the snapshot was taken
+    pub record: bool,
+    /// Indicates if [`Action::Pause`] was down when the snapshot was taken
+    pub pause: bool,
+    /// Indicates if [`Action::SlowDown`] was down when the snapshot was taken
+    pub slow_down: bool,
+    /// Indicates if [`Action::SpeedUp`] was down when the snapshot was taken
+    pub speed_up: bool,
+    /// Indicates if [`Action::InstantMoves`] was down when the snapshot was taken
+    pub instant_moves: bool,
+    /// Indicates if [`Action::Settings`] (settings screen) was down when the snapshot was taken
+    pub settings: bool,
+}
+
+impl InputSnapshot {
+    /// Checks whether an action is down.
+    pub fn is_down(&self, action: Action) -> bool {
+        match action {
+            Action::MoveUp => self.up,
+            Action::MoveDown => self.down,
+            Action::MoveLeft => self.left,
+            Action::MoveRight => self.right,
+            Action::Undo => self.undo,
+            Action::Redo => self.redo,
+            Action::Restart => self.restart,
+            Action::NextLevel => self.next_level,
+            Action::PreviousLevel => self.previous_level,
+            Action::Menu => self.menu,
+            Action::Hint => self.hint,
+            Action::Solution => self.solution,
+            Action::Screenshot => self.screenshot,
+            Action::Record => self.record,
+            Action::Pause => self.pause,
+            Action::SlowDown => self.slow_down,
+            Action::SpeedUp => self.speed_up,
+            Action::InstantMoves => self.instant_moves,
+            Action::Settings => self.settings,
+            Action::Quit => self.quit,
+        }
+    }
+
+    /// Sets whether an action is down.
+    pub fn set(&mut self, action: Action, down: bool) {
+        let flag = match action {
+            Action::MoveUp => &mut self.up,
+            Action::MoveDown => &mut self.down,
+            Action::MoveLeft => &mut self.left,
+            Action::MoveRight => &mut self.right,
+            Action::Undo => &mut self.undo,
+            Action::Redo => &mut self.redo,
+            Action::Restart => &mut self.restart,
+            Action::NextLevel => &mut self.next_level,
+            Action::PreviousLevel => &mut self.previous_level,
+            Action::Menu => &mut self.menu,
+            Action::Hint => &mut self.hint,
+            Action::Solution => &mut self.solution,
+            Action::Screenshot => &mut self.screenshot,
+            Action::Record => &mut self.record,
+            Action::Pause => &mut self.pause,
+            Action::SlowDown => &mut self.slow_down,
+            Action::SpeedUp => &mut self.speed_up,
+            Action::InstantMoves => &mut self.instant_moves,
+            Action::Settings => &mut self.settings,
+            Action::Quit => &mut self.quit,
+        };
+        *flag = down;
+    }
+}
+
+/// Represents the current state of the game actions.
+///
+/// The window thread resolves held keys to actions through the current [`KeyBindings`]
+/// and polls the other [`InputSource`]s (e.g. gamepads); the game thread reads the actions as an [`InputSnapshot`] and their changes as
+/// [`InputEvent`]s. Clones share their state.
+#[derive(Clone)]
+pub struct InputState {
+    /// Tracks whether every action (indexed like [`Action::ALL`]) is currently down
+    actions: Arc<[AtomicBool; Action::ALL.len()]>,
+    /// Keys bound to the actions
+    bindings: Arc<RwLock<KeyBindings>>,
+    /// Keys pressed since the last [`InputState::take_pressed_keys`], in press order
+    pressed_keys: Arc<Mutex<Vec<Key>>>,
+    /// Pressed / released / repeated actions since the last [`InputState::take_events`]
+    events: Arc<Mutex<EventTracker>>,
+    /// Clock the event timestamps are measured with
+    clock: Instant,
+    /// Devices polled next to the keyboard
+    sources: Arc<Mutex<Vec<Box<dyn InputSource>>>>,
+    /// Whether the left mouse button was down at the last update
+    mouse_down: Arc<AtomicBool>,
+    /// Left clicks since the last [`InputState::take_clicks`], relative to the window size
+    clicks: Arc<Mutex<Vec<(f32, f32)>>>,
+}
+
+impl InputState {
+    /// Creates a new `InputState` with the default key bindings and no action down.
+    ///
+    /// # Returns
+    ///
+    /// A new `InputState` instance with all actions released.
+    pub fn new() -> Self {
+        Self::with_bindings(KeyBindings::default())
+    }
+
+    /// Creates a new `InputState` with custom key bindings and no action down.
+    ///
+    /// # Arguments
+    ///
+    /// * `bindings` - keys bound to the actions
+    pub fn with_bindings(bindings: KeyBindings) -> Self {
+        Self {
+            actions: Arc::new(std::array::from_fn(|_| AtomicBool::new(false))),
+            bindings: Arc::new(RwLock::new(bindings)),
+            pressed_keys: Arc::new(Mutex::new(Vec::new())),
+            events: Arc::new(Mutex::new(EventTracker::new(RepeatSettings::default()))),
+            clock: Instant::now(),
+            sources: Arc::new(Mutex::new(Vec::new())),
+            mouse_down: Arc::new(AtomicBool::new(false)),
+            clicks: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Replaces the key bindings, e.g. after they have been changed in the settings.
+    pub fn set_bindings(&self, bindings: KeyBindings) {
+        *self.bindings.write().unwrap() = bindings;
+    }
+
+    /// Adds a device polled next to the keyboard.
+    pub fn add_source(&self, source: Box<dyn InputSource>) {
+        self.sources.lock().unwrap().push(source);
+    }
+
+    /// Replaces the key repeat of held actions.
+    pub fn set_key_repeat(&self, repeat: RepeatSettings) {
+        self.events.lock().unwrap().set_repeat(repeat);
+    }
+
+    /// Returns a copy of the current key bindings.
+    pub fn bindings(&self) -> KeyBindings {
+        self.bindings.read().unwrap().clone()
+    }
+
+    /// Updates the action states by querying the current key states from the window.
+    ///
+    /// An action is down while any key bound to it is held or an input source holds it
+    /// (see [`InputState::add_source`]). Changes of the actions are
+    /// queued as events for [`InputState::take_events`], newly pressed keys for
+    /// [`InputState::take_pressed_keys`] (used to rebind keys) and left clicks for
+    /// [`InputState::take_clicks`].
+    ///
+    /// # Parameters
+    ///
+    /// * `window` - A reference to the minifb `Window` to query for key states
+    pub fn update(&self, window: &Window) {
+        let bindings = self.bindings.read().unwrap();
+        let mut down = Action::ALL
+            .map(|action| bindings.keys(action).iter().any(|&key| window.is_key_down(key)));
+        self.poll_sources(&mut down);
+        self.update_actions(&down);
+
+        let pressed = window.get_keys_pressed(KeyRepeat::No);
+        if !pressed.is_empty() {
+            self.pressed_keys.lock().unwrap().extend(pressed);
+        }
+
+        let mouse_down = window.get_mouse_down(MouseButton::Left);
+        let was_down = self.mouse_down.swap(mouse_down, Ordering::Relaxed);
+        if mouse_down && !was_down {
+            let (width, height) = window.get_size();
+            if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+                self.click(x / width as f32, y / height as f32);
+            }
+        }
+    }
+
+    /// Queues a left click, independently of the mouse.
+    ///
+    /// # Parameters
+    ///
+    /// * `x`, `y` - click position relative to the window size, from `0` to `1`
+    pub fn click(&self, x: f32, y: f32) {
+        self.clicks.lock().unwrap().push((x, y));
+    }
+
+    /// Returns the left clicks since the last call, relative to the window size.
+    pub fn take_clicks(&self) -> Vec<(f32, f32)> {
+        std::mem::take(&mut *self.clicks.lock().unwrap())
+    }
+
+    /// Updates the action states from the input sources only, without a window.
+    pub fn poll(&self) {
+        let mut down = [false; Action::ALL.len()];
+        self.poll_sources(&mut down);
+        self.update_actions(&down);
+    }
+
+    /// Marks the actions held by the input sources.
+    fn poll_sources(&self, down: &mut [bool; Action::ALL.len()]) {
+        for source in self.sources.lock().unwrap().iter_mut() {
+            source.poll(down);
+        }
+    }
+
+    /// Sets the state of every action at once and queues the resulting events.
+    ///
+    /// # Parameters
+    ///
+    /// * `down` - whether every action (indexed like [`Action::ALL`]) is down
+    pub fn update_actions(&self, down: &[bool; Action::ALL.len()]) {
+        for (action, &down) in Action::ALL.into_iter().zip(down) {
+            self.set_action(action, down);
+        }
+        self.events.lock().unwrap().update(down, self.clock.elapsed().as_secs_f64());
+    }
+
+    /// Sets whether an action is down, independently of the keyboard (no event is queued).
+    pub fn set_action(&self, action: Action, down: bool) {
+        self.actions[action.index()].store(down, Ordering::Relaxed);
+    }
+
+    /// Returns the action events since the last call, in order.
+    pub fn take_events(&self) -> Vec<InputEvent> {
+        self.events.lock().unwrap().take()
+    }
+
+    /// Returns the keys pressed since the last call, in press order.
+    pub fn take_pressed_keys(&self) -> Vec<Key> {
+        std::mem::take(&mut *self.pressed_keys.lock().unwrap())
+    }
+
+    /// Reads the current state of all actions and returns an `InputSnapshot`.
+    ///
+    /// # Returns
+    ///
+    /// An `InputSnapshot` containing the current state of all actions.
+    pub fn read(&self) -> InputSnapshot {
+        let mut snapshot = InputSnapshot::default();
+        for action in Action::ALL {
+            snapshot.set(action, self.actions[action.index()].load(Ordering::Relaxed));
+        }
+        snapshot
+    }
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that InputState initializes with all actions released
+    #[test]
+    fn test_new_input_state_initializes_all_false() {
+        let input_state = InputState::new();
+        let snapshot = input_state.read();
+
+        assert_eq!(snapshot, InputSnapshot::default());
+        assert!(Action::ALL.iter().all(|&action| !snapshot.is_down(action)));
+    }
+
+    /// Test that InputState can be cloned and both instances share state
+    #[test]
+    fn test_input_state_clone_shares_state() {
+        let input_state1 = InputState::new();
+        let input_state2 = input_state1.clone();
+
+        input_state1.set_action(Action::Undo, true);
+        input_state1.set_bindings(KeyBindings::empty());
+
+        let snapshot1 = input_state1.read();
+        let snapshot2 = input_state2.read();
+
+        assert_eq!(snapshot1, snapshot2);
+        assert!(snapshot2.undo);
+        assert_eq!(input_state2.bindings(), KeyBindings::empty());
+    }
+
+    /// Test that action updates queue events shared between clones
+    #[test]
+    fn test_update_actions_queues_events() {
+        let input_state = InputState::new();
+        let reader = input_state.clone();
+        let mut down = [false; Action::ALL.len()];
+
+        down[Action::Redo.index()] = true;
+        input_state.update_actions(&down);
+        input_state.update_actions(&[false; Action::ALL.len()]);
+
+        let events = reader.take_events();
+        let kinds: Vec<_> = events.iter().map(|event| (event.action, event.kind)).collect();
+        assert_eq!(
+            kinds,
+            [(Action::Redo, InputEventKind::Pressed), (Action::Redo, InputEventKind::Released)]
+        );
+        assert!(events[0].time <= events[1].time);
+        assert!(!reader.read().redo);
+        assert!(input_state.take_events().is_empty());
+    }
+
+    /// Test that a fake gamepad drives the actions and their events
+    #[test]
+    fn test_gamepad_source_drives_actions() {
+        let input_state = InputState::new();
+        let gamepad = FakeGamepad::new();
+        input_state.add_source(Box::new(GamepadSource::new(gamepad.clone())));
+
+        gamepad.push(GamepadEvent::Pressed(GamepadButton::DPadRight));
+        input_state.poll();
+        assert_eq!(input_state.read(), InputSnapshot { right: true, ..Default::default() });
+
+        gamepad.push(GamepadEvent::Released(GamepadButton::DPadRight));
+        gamepad.push(GamepadEvent::Pressed(GamepadButton::South));
+        input_state.poll();
+        assert_eq!(input_state.read(), InputSnapshot { undo: true, ..Default::default() });
+
+        let kinds: Vec<_> =
+            input_state.take_events().iter().map(|event| (event.action, event.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (Action::MoveRight, InputEventKind::Pressed),
+                (Action::MoveRight, InputEventKind::Released),
+                (Action::Undo, InputEventKind::Pressed),
+            ]
+        );
+    }
+
+    /// Test that every action maps to its own snapshot flag
+    #[test]
+    fn test_snapshot_flags_per_action() {
+        for action in Action::ALL {
+            let mut snapshot = InputSnapshot::default();
+            snapshot.set(action, true);
+
+            let down: Vec<Action> =
+                Action::ALL.into_iter().filter(|&a| snapshot.is_down(a)).collect();
+            assert_eq!(down, [action]);
+        }
+    }
+
+    /// Test that snapshots recorded before the rename of `escape` still load
+    #[test]
+    fn test_snapshot_reads_legacy_escape() {
+        let snapshot: InputSnapshot = serde_json::from_str(r#"{"escape": true}"#).unwrap();
+        assert!(snapshot.quit);
+    }
+}
@@ -0,0 +1,15 @@
+use super::bindings::Action;
+
+/// Device that holds actions down, polled next to the keyboard by [`super::InputState`].
+///
+/// An action is down while the keyboard or any source holds it, so several devices can
+/// be used at the same time.
+pub trait InputSource: Send {
+    /// Reads the pending device input and marks the actions the device holds down.
+    ///
+    /// # Arguments
+    ///
+    /// * `down` - state of every action (indexed like [`Action::ALL`]); the source only
+    ///   sets the actions it holds and leaves the others untouched
+    fn poll(&mut self, down: &mut [bool; Action::ALL.len()]);
+}
@@ -1,4 +1,8 @@
+// the original tests spell out some values on purpose
+#![cfg_attr(test, allow(clippy::bool_assert_comparison))]
+
 pub mod assets;
+pub mod capture;
 pub mod draw;
 pub mod input;
 pub mod render;
@@ -6,3 +10,6 @@ pub mod time;
 pub mod world;
 
 pub use render::{Render, RenderableEntity};
+
+/// Version of the engine, stored in recorded sessions.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,195 @@
+//! Golden-image regression tests of the isometric renderer.
+//!
+//! Every test renders a fixed scene with [`Render::init`] and [`Render::render_frame`]
+//! and compares it with a reference PNG in `render/golden`. A mismatch writes the
+//! rendered frame and a diff image (differing pixels in red over the dimmed reference)
+//! to `ferari_golden` in the temporary directory.
+//!
+//! Set `FERARI_UPDATE_GOLDEN=1` to (re)write the reference images after an intended
+//! change of the renderer or the atlases.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::{Rgba, RgbaImage};
+
+use super::render::{Render, RenderableEntity};
+use crate::assets::{Atlas, GameMap};
+use crate::capture::frame_to_image;
+use crate::world::{Camera, State};
+
+/// Directory of the reference images.
+const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/render/golden");
+/// Environment variable that makes the tests write the reference images.
+const UPDATE_ENV: &str = "FERARI_UPDATE_GOLDEN";
+/// Maximal difference of a single color channel that still counts as equal.
+const CHANNEL_TOLERANCE: u8 = 2;
+/// Maximal share of differing pixels before a frame counts as changed.
+const MAX_DIFF_RATIO: f32 = 0.001;
+/// Size of the rendered frames in pixels.
+const VIEW_WIDTH: u16 = 320;
+const VIEW_HEIGHT: u16 = 240;
+/// Background of the world buffer, as used by the game.
+const WORLD_BACKGROUND: u32 = 195213255;
+
+/// Loads the `(tiles, entities)` atlases shipped with the game.
+fn atlases() -> (Atlas, Atlas) {
+    let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets");
+    let tiles = Atlas::load(assets.join("tiles/atlas.json")).unwrap();
+    let entities = Atlas::load(assets.join("entities/atlas.json")).unwrap();
+    (tiles, entities)
+}
+
+/// Pre-renders a level the same way the game does and returns its initial state.
+fn init_scene(rows: &[&str]) -> (Render, State, Camera) {
+    let (tiles_atlas, entities_atlas) = atlases();
+    let game = GameMap::from_xsb("golden", rows).unwrap();
+
+    let world_width = (game.size[0] * game.tile_size * 2) as usize;
+    let world_height = (game.size[1] * game.tile_size * 2) as usize;
+    let mut render = Render::new(
+        vec![WORLD_BACKGROUND; world_width * world_height],
+        world_height,
+        world_width,
+        entities_atlas,
+        vec![0; world_width * world_height],
+    );
+    render.init(&game, &tiles_atlas);
+
+    let state = State::new(&game);
+    let camera = Camera::new(
+        state.player.unit.pixel_x.floor(),
+        state.player.unit.pixel_y.floor(),
+        VIEW_WIDTH,
+        VIEW_HEIGHT,
+    );
+    (render, state, camera)
+}
+
+/// Renders a frame and converts it to an image.
+fn render_image(render: &mut Render, entities: &[RenderableEntity], camera: &Camera) -> RgbaImage {
+    let mut buf = vec![0; VIEW_WIDTH as usize * VIEW_HEIGHT as usize];
+    render.render_frame(entities, camera, &mut buf);
+
+    frame_to_image(&buf, VIEW_WIDTH as usize, VIEW_HEIGHT as usize)
+}
+
+/// Compares two images channel by channel.
+///
+/// # Returns
+///
+/// The number of pixels differing by more than `CHANNEL_TOLERANCE` and a diff image:
+/// differing pixels in red, the others dimmed from the expected image.
+fn diff_images(actual: &RgbaImage, expected: &RgbaImage) -> (usize, RgbaImage) {
+    let mut differing = 0;
+    let diff = RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let want = expected.get_pixel(x, y).0;
+        let same = actual.get_pixel_checked(x, y).is_some_and(|got| {
+            got.0.iter().zip(want).all(|(&a, b)| a.abs_diff(b) <= CHANNEL_TOLERANCE)
+        });
+
+        if same {
+            let luma = (want[0] as u32 + want[1] as u32 + want[2] as u32) / 3 / 4;
+            Rgba([luma as u8, luma as u8, luma as u8, 255])
+        } else {
+            differing += 1;
+            Rgba([255, 0, 0, 255])
+        }
+    });
+    (differing, diff)
+}
+
+/// Checks a rendered frame against its reference image.
+///
+/// # Arguments
+///
+/// * `name` - name of the reference image, without extension
+/// * `actual` - rendered frame
+fn assert_golden(name: &str, actual: &RgbaImage) {
+    let path = Path::new(GOLDEN_DIR).join(format!("{name}.png"));
+    if env::var_os(UPDATE_ENV).is_some() {
+        fs::create_dir_all(GOLDEN_DIR).unwrap();
+        actual.save(&path).unwrap();
+        return;
+    }
+
+    let expected = match image::open(&path) {
+        Ok(expected) => expected.to_rgba8(),
+        Err(err) => panic!("{}: {err} (run with {UPDATE_ENV}=1 to create it)", path.display()),
+    };
+    let (differing, diff) = diff_images(actual, &expected);
+    let allowed = (expected.width() * expected.height()) as f32 * MAX_DIFF_RATIO;
+    let size_matches = actual.dimensions() == expected.dimensions();
+
+    if !size_matches || differing as f32 > allowed {
+        let out_dir: PathBuf = env::temp_dir().join("ferari_golden");
+        fs::create_dir_all(&out_dir).unwrap();
+        actual.save(out_dir.join(format!("{name}.actual.png"))).unwrap();
+        diff.save(out_dir.join(format!("{name}.diff.png"))).unwrap();
+        panic!(
+            "{name}: {differing} pixels differ from {} (size {:?} vs {:?}), \
+             see {}/{name}.{{actual,diff}}.png",
+            path.display(),
+            actual.dimensions(),
+            expected.dimensions(),
+            out_dir.display(),
+        );
+    }
+}
+
+/// Level covering every static tile kind: floor, targets, concrete and wall objects.
+const ROOM: [&str; 6] = ["#######", "#.  $ #", "# #   #", "# $@* #", "#.    #", "#######"];
+
+#[test]
+fn test_golden_tiles_and_objects() {
+    let (mut render, _, camera) = init_scene(&ROOM);
+
+    assert_golden("tiles_and_objects", &render_image(&mut render, &[], &camera));
+}
+
+#[test]
+fn test_golden_units_depth_order_and_shadows() {
+    let (mut render, state, camera) = init_scene(&ROOM);
+    let player = &state.player.unit;
+
+    // overlapping units in reverse depth order; the renderer has to sort them
+    let mut entities: Vec<RenderableEntity> = state
+        .mobs
+        .iter()
+        .map(|unit| RenderableEntity::with_sprite(unit.pixel_x, unit.pixel_y, "box"))
+        .collect();
+    entities.push(RenderableEntity::with_sprite(player.pixel_x, player.pixel_y, "idle_se_0"));
+    entities.push(
+        RenderableEntity::with_sprite(player.pixel_x + 40.0, player.pixel_y + 20.0, "green_box")
+            .with_tint(0xFF6060),
+    );
+    entities.sort_by(|a, b| b.y.total_cmp(&a.y));
+
+    assert_golden("units_depth_order", &render_image(&mut render, &entities, &camera));
+}
+
+#[test]
+fn test_golden_camera_clips_world_edge() {
+    let (mut render, state, mut camera) = init_scene(&ROOM);
+    let player = &state.player.unit;
+    camera.center_x -= 300.0;
+    camera.center_y -= 150.0;
+    let entities = [RenderableEntity::with_sprite(player.pixel_x, player.pixel_y, "idle_se_0")];
+
+    assert_golden("camera_at_edge", &render_image(&mut render, &entities, &camera));
+}
+
+#[test]
+fn test_diff_images_respects_tolerance() {
+    let expected = RgbaImage::from_pixel(2, 2, Rgba([100, 100, 100, 255]));
+    let mut actual = expected.clone();
+    actual.put_pixel(0, 0, Rgba([100 + CHANNEL_TOLERANCE, 100, 100, 255]));
+    actual.put_pixel(1, 1, Rgba([100, 100, 100 + CHANNEL_TOLERANCE + 1, 255]));
+
+    let (differing, diff) = diff_images(&actual, &expected);
+
+    assert_eq!(differing, 1);
+    assert_eq!(diff.get_pixel(1, 1).0, [255, 0, 0, 255]);
+    assert_ne!(diff.get_pixel(0, 0).0, [255, 0, 0, 255]);
+}
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+use crate::assets::Atlas;
+
+/// Prefix of the glyph frames in the tile atlas (`letter_A`, `letter_7`, ...).
+const GLYPH_PREFIX: &str = "letter_";
+/// Brightness of the panel drawn behind HUD text.
+const PANEL_BRIGHTNESS: f32 = 0.45;
+/// Padding of the panel around HUD text, in pixels.
+const PANEL_PADDING: i32 = 2;
+
+/// Bitmap font built from the isometric letter and number tiles of an atlas.
+///
+/// Glyphs are cut from the atlas and scaled once when the font is created. Characters
+/// without a glyph (spaces, punctuation) only advance the pen.
+#[derive(Debug, Clone)]
+pub struct BitmapFont {
+    /// Scaled glyph images by (uppercase) character
+    glyphs: HashMap<char, RgbaImage>,
+    /// Width of a glyph in pixels
+    glyph_w: u32,
+    /// Horizontal distance between two characters, in pixels
+    pub advance: i32,
+    /// Vertical distance between two lines, in pixels
+    pub line_height: i32,
+}
+
+impl BitmapFont {
+    /// Builds a font from the `letter_*` frames of an atlas.
+    ///
+    /// # Arguments
+    ///
+    /// * `atlas` - atlas containing the glyph frames
+    /// * `scale` - scaling factor applied to every glyph
+    ///
+    /// # Returns
+    ///
+    /// A font with one glyph per `letter_*` frame whose suffix is a single character.
+    pub fn from_atlas(atlas: &Atlas, scale: f32) -> Self {
+        let mut glyphs = HashMap::new();
+        let (mut glyph_w, mut glyph_h) = (0, 0);
+
+        for frame in atlas.iter_frames() {
+            let Some(suffix) = frame.name.strip_prefix(GLYPH_PREFIX) else {
+                continue;
+            };
+            let mut chars = suffix.chars();
+            let (Some(ch), None) = (chars.next(), chars.next()) else {
+                continue;
+            };
+
+            let w = ((frame.w as f32 * scale).round() as u32).max(1);
+            let h = ((frame.h as f32 * scale).round() as u32).max(1);
+            let source = imageops::crop_imm(&atlas.image, frame.x, frame.y, frame.w, frame.h);
+            let glyph = imageops::resize(&source.to_image(), w, h, FilterType::Triangle);
+
+            glyph_w = glyph_w.max(w);
+            glyph_h = glyph_h.max(h);
+            glyphs.insert(ch.to_ascii_uppercase(), glyph);
+        }
+
+        Self {
+            glyphs,
+            glyph_w,
+            // isometric tiles overlap: only the raised letter block has to stay visible
+            advance: (glyph_w * 3 / 4) as i32,
+            line_height: (glyph_h / 2) as i32,
+        }
+    }
+
+    /// Checks whether the font has a glyph for a character.
+    pub fn has_glyph(&self, ch: char) -> bool {
+        self.glyphs.contains_key(&ch.to_ascii_uppercase())
+    }
+
+    /// Returns the size `(width, height)` of a single line of text in pixels.
+    pub fn text_size(&self, text: &str) -> (i32, i32) {
+        let count = text.chars().count() as i32;
+        if count == 0 {
+            return (0, 0);
+        }
+
+        ((count - 1) * self.advance + self.glyph_w as i32, self.line_height)
+    }
+
+    /// Draws a single line of text into a pixel buffer.
+    ///
+    /// Glyphs are alpha-blended left to right, so every glyph overlaps the base of the
+    /// previous one the same way neighbouring tiles overlap in the world.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - text to draw (letters are drawn uppercase)
+    /// * `x` - X position of the top-left corner of the text
+    /// * `y` - Y position of the top-left corner of the text
+    /// * `buf` - pixel buffer (`0xAARRGGBB`) to draw into
+    /// * `buf_width` - width of the buffer in pixels
+    /// * `buf_height` - height of the buffer in pixels
+    pub fn draw_text(
+        &self,
+        text: &str,
+        x: i32,
+        y: i32,
+        buf: &mut [u32],
+        buf_width: usize,
+        buf_height: usize,
+    ) {
+        for (i, ch) in text.chars().enumerate() {
+            if let Some(glyph) = self.glyphs.get(&ch.to_ascii_uppercase()) {
+                blend_image(glyph, x + i as i32 * self.advance, y, buf, buf_width, buf_height);
+            }
+        }
+    }
+}
+
+/// Single line of HUD text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HudText {
+    /// X position of the top-left corner in screen pixels
+    pub x: i32,
+    /// Y position of the top-left corner in screen pixels
+    pub y: i32,
+    /// Text to draw
+    pub text: String,
+}
+
+/// Overlay drawn on top of the rendered world and entities.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Hud {
+    /// Lines of text in drawing order
+    pub texts: Vec<HudText>,
+}
+
+impl Hud {
+    /// Creates an empty overlay.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a line of text at the given screen position.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - X position of the top-left corner in screen pixels
+    /// * `y` - Y position of the top-left corner in screen pixels
+    /// * `text` - text to draw
+    pub fn text(&mut self, x: i32, y: i32, text: impl Into<String>) -> &mut Self {
+        self.texts.push(HudText { x, y, text: text.into() });
+        self
+    }
+
+    /// Checks whether the overlay has nothing to draw.
+    pub fn is_empty(&self) -> bool {
+        self.texts.is_empty()
+    }
+
+    /// Draws the overlay into a pixel buffer.
+    ///
+    /// Every line gets a darkened panel behind it so it stays readable on any background.
+    ///
+    /// # Arguments
+    ///
+    /// * `font` - font the text is drawn with
+    /// * `buf` - pixel buffer (`0xAARRGGBB`) to draw into
+    /// * `buf_width` - width of the buffer in pixels
+    /// * `buf_height` - height of the buffer in pixels
+    pub fn draw(&self, font: &BitmapFont, buf: &mut [u32], buf_width: usize, buf_height: usize) {
+        for line in &self.texts {
+            let (w, h) = font.text_size(&line.text);
+            if w > 0 {
+                darken_rect(
+                    line.x - PANEL_PADDING,
+                    line.y - PANEL_PADDING,
+                    w + 2 * PANEL_PADDING,
+                    h + 2 * PANEL_PADDING,
+                    buf,
+                    buf_width,
+                    buf_height,
+                );
+            }
+        }
+
+        for line in &self.texts {
+            font.draw_text(&line.text, line.x, line.y, buf, buf_width, buf_height);
+        }
+    }
+}
+
+/// Alpha-blends an image into a pixel buffer, clipping it to the buffer bounds.
+fn blend_image(
+    image: &RgbaImage,
+    x: i32,
+    y: i32,
+    buf: &mut [u32],
+    buf_width: usize,
+    buf_height: usize,
+) {
+    for (dx, dy, color) in image.enumerate_pixels() {
+        let [r, g, b, a] = color.0;
+        if a == 0 {
+            continue;
+        }
+
+        let dest_x = x + dx as i32;
+        let dest_y = y + dy as i32;
+        if dest_x < 0 || dest_y < 0 || dest_x >= buf_width as i32 || dest_y >= buf_height as i32 {
+            continue;
+        }
+
+        let dest_idx = dest_y as usize * buf_width + dest_x as usize;
+        let dst = buf[dest_idx];
+
+        let alpha = a as f32 / 255.0;
+        let mix = |src: u8, shift: u32| {
+            let dst = ((dst >> shift) & 0xFF) as f32;
+            ((src as f32 * alpha + dst * (1.0 - alpha)).min(255.0) as u32) << shift
+        };
+
+        buf[dest_idx] = (0xFF << 24) | mix(r, 16) | mix(g, 8) | mix(b, 0);
+    }
+}
+
+/// Darkens a rectangle of a pixel buffer, clipping it to the buffer bounds.
+fn darken_rect(
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+    buf: &mut [u32],
+    buf_width: usize,
+    buf_height: usize,
+) {
+    let left = x.max(0);
+    let top = y.max(0);
+    let right = (x + w).min(buf_width as i32);
+    let bottom = (y + h).min(buf_height as i32);
+
+    for py in top..bottom {
+        for px in left..right {
+            let idx = py as usize * buf_width + px as usize;
+            let dst = buf[idx];
+            let r = ((dst >> 16) & 0xFF) as f32 * PANEL_BRIGHTNESS;
+            let g = ((dst >> 8) & 0xFF) as f32 * PANEL_BRIGHTNESS;
+            let b = (dst & 0xFF) as f32 * PANEL_BRIGHTNESS;
+            buf[idx] = (0xFF << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::Frame;
+    use image::Rgba;
+
+    fn glyph_atlas() -> Atlas {
+        let mut image = RgbaImage::new(8, 4);
+        for y in 0..4 {
+            for x in 0..4 {
+                image.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+            }
+        }
+
+        let mut frames = HashMap::new();
+        for (name, x) in [("letter_A", 0), ("letter_1", 4), ("floor", 0)] {
+            frames.insert(name.into(), Frame { name: name.into(), x, y: 0, w: 4, h: 4 });
+        }
+
+        Atlas { image, frames, tile_size: 4, version: 1 }
+    }
+
+    #[test]
+    fn test_font_uses_letter_frames_only() {
+        let font = BitmapFont::from_atlas(&glyph_atlas(), 1.0);
+
+        assert!(font.has_glyph('A'));
+        assert!(font.has_glyph('a'));
+        assert!(font.has_glyph('1'));
+        assert!(!font.has_glyph('F'));
+        assert_eq!(font.advance, 3);
+        assert_eq!(font.text_size("A1 A"), (13, 2));
+        assert_eq!(font.text_size(""), (0, 0));
+    }
+
+    #[test]
+    fn test_draw_text_skips_missing_glyphs() {
+        let font = BitmapFont::from_atlas(&glyph_atlas(), 1.0);
+        let mut buf = vec![0; 20 * 4];
+
+        font.draw_text("A 1", 0, 0, &mut buf, 20, 4);
+
+        // `A` is opaque white, the space leaves a gap and the `1` frame is transparent
+        assert_eq!(buf[0], 0xFFFFFFFF);
+        assert_eq!(buf[3], 0xFFFFFFFF);
+        assert_eq!(buf[4], 0);
+        assert!(buf[6..20].iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn test_hud_draws_panel_behind_text_and_clips() {
+        let font = BitmapFont::from_atlas(&glyph_atlas(), 1.0);
+        let mut buf = vec![0xFF646464; 10 * 10];
+        let mut hud = Hud::new();
+        hud.text(4, 4, "1").text(8, 8, "AA");
+
+        hud.draw(&font, &mut buf, 10, 10);
+
+        // panel of the transparent `1` glyph darkens the background
+        assert_eq!(buf[4 * 10 + 4], 0xFF2D2D2D);
+        // the partly visible `A` is drawn over its panel
+        assert_eq!(buf[9 * 10 + 9], 0xFFFFFFFF);
+        // pixels far from any line stay untouched
+        assert_eq!(buf[0], 0xFF646464);
+    }
+}
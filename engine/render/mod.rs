@@ -1,4 +1,8 @@
+#[cfg(test)]
+mod golden;
+mod hud;
 #[allow(clippy::module_inception)]
 mod render;
+pub use hud::{BitmapFont, Hud, HudText};
 pub use render::Render;
 pub use render::RenderableEntity;
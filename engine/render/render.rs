@@ -1,5 +1,6 @@
+use super::hud::{BitmapFont, Hud};
 use crate::assets::{Atlas, Frame, GameMap, Object, Tile};
-use crate::world::Camera;
+use crate::world::{height_step, Camera};
 
 /// Represents an entity that can be rendered
 #[derive(Clone)]
@@ -7,16 +8,32 @@ pub struct RenderableEntity {
     pub x: f32,
     pub y: f32,
     pub sprite_name: String,
+    /// Optional `0xRRGGBB` color the sprite is multiplied with
+    pub tint: Option<u32>,
+    /// Height above the ground in pixels; lifts the sprite without changing its depth order
+    pub elevation: f32,
 }
 
 impl RenderableEntity {
     pub fn new(x: f32, y: f32, sprite_name: String) -> Self {
-        Self { x, y, sprite_name }
+        Self { x, y, sprite_name, tint: None, elevation: 0.0 }
     }
 
     pub fn with_sprite(x: f32, y: f32, sprite_name: &str) -> Self {
         Self::new(x, y, sprite_name.to_string())
     }
+
+    /// Tints the sprite by multiplying every pixel with `tint` (`0xRRGGBB`).
+    pub fn with_tint(mut self, tint: u32) -> Self {
+        self.tint = Some(tint);
+        self
+    }
+
+    /// Lifts the sprite by `elevation` pixels (e.g. a unit on a raised platform).
+    pub fn with_elevation(mut self, elevation: f32) -> Self {
+        self.elevation = elevation;
+        self
+    }
 }
 
 /// The `Render` struct handles isometric projection rendering with shadow mapping
@@ -71,7 +88,9 @@ impl Render {
     /// Initializes the world buffer by rendering static map elements
     ///
     /// Renders tiles and objects from the game map building isometric projection.
-    /// Sorts elements by their (x+y) coordinate for depth ordering.
+    /// Sorts elements by their (x+y) coordinate for depth ordering, and stacked tiles of a
+    /// cell bottom to top. Tiles are lifted by their height level, objects by the height
+    /// level of their cell.
     ///
     /// # Arguments
     ///
@@ -79,10 +98,11 @@ impl Render {
     /// * `static_atlas` - Sprite atlas for static map elements
     pub fn init(&mut self, game: &GameMap, static_atlas: &Atlas) {
         let mut tiles: Vec<Tile> = (*game).clone().tiles.into_values().collect();
-        tiles.sort_by(|a, b| (a.x + a.y).cmp(&(b.x + b.y)));
+        tiles.sort_by_key(|a| (a.x + a.y, a.z));
         // Base screen offsets
         let offset_x = self.world_width as i32 / 2;
         let offset_y = (self.world_height as i32 / 2) - (static_atlas.tile_size as i32);
+        let step = height_step(static_atlas.tile_size) as i32;
 
         for tile in tiles {
             if let Some(frame) = static_atlas.get_frame(&tile.asset) {
@@ -91,14 +111,17 @@ impl Render {
 
                 // Isometric projection
                 let screen_x = (tile.x as i32 - tile.y as i32) * (fw / 2) + offset_x;
-                let screen_y = (tile.x as i32 + tile.y as i32) * (fh / 4) + offset_y - (fh / 2);
+                let screen_y = (tile.x as i32 + tile.y as i32) * (fh / 4) + offset_y
+                    - (fh / 2)
+                    - tile.z * step;
 
                 self.render_tile(frame, screen_x, screen_y, static_atlas);
             }
         }
 
         let mut objects: Vec<Object> = (*game).clone().objects.into_values().collect();
-        objects.sort_by(|a, b| (a.x + a.y).cmp(&(b.x + b.y)));
+        let lift = |object: &Object| game.height_at(object.x as i32, object.y as i32) * step;
+        objects.sort_by_key(|a| (a.x + a.y, lift(a)));
         let offset_x = self.world_width as i32 / 2;
         let offset_y = (self.world_height as i32 / 2) - (static_atlas.tile_size as i32);
 
@@ -114,7 +137,8 @@ impl Render {
                 let screen_x = (object.x as i32 - object.y as i32) * (fw / 2) + offset_x;
                 let screen_y = (object.x as i32 + object.y as i32) * (fh / 4) + offset_y
                     - (fh / 2)
-                    - TEXTURE_OFFSET;
+                    - TEXTURE_OFFSET
+                    - lift(object);
                 self.render_shadow(frame, screen_x, screen_y, static_atlas);
             }
         }
@@ -131,7 +155,8 @@ impl Render {
                 let screen_x = (object.x as i32 - object.y as i32) * (fw / 2) + offset_x;
                 let screen_y = (object.x as i32 + object.y as i32) * (fh / 4) + offset_y
                     - (fh / 2)
-                    - TEXTURE_OFFSET;
+                    - TEXTURE_OFFSET
+                    - lift(object);
                 self.render_object(frame, screen_x, screen_y, static_atlas);
             }
         }
@@ -203,7 +228,8 @@ impl Render {
 
         // Dynamic objects
 
-        // Sort entities by depth for correct rendering order
+        // Sort entities by depth for correct rendering order (by ground position, so a
+        // lifted entity stays behind the ones in front of it)
         let mut sorted_entities: Vec<&RenderableEntity> = visible_entities.iter().collect();
         sorted_entities.sort_by(|a, b| {
             // Primary sort by Y coordinate (higher Y = closer to camera)
@@ -228,21 +254,61 @@ impl Render {
 
                 let screen_x =
                     (entity.x as i32 - camera.center_x as i32) + camera.width as i32 / 2 - fw / 2;
-                let screen_y =
-                    (entity.y as i32 - camera.center_y as i32) + camera.height as i32 / 2 - fh;
+                let screen_y = ((entity.y - entity.elevation) as i32 - camera.center_y as i32)
+                    + camera.height as i32 / 2
+                    - fh;
 
-                shadow_render_data.push((frame.clone(), screen_x, screen_y));
+                shadow_render_data.push((frame.clone(), screen_x, screen_y, entity.tint));
             }
         }
 
         // Render shadows
-        for (frame, screen_x, screen_y) in &shadow_render_data {
+        for (frame, screen_x, screen_y, _) in &shadow_render_data {
             self.render_shadow_unit(frame, *screen_x, *screen_y, buf, camera);
         }
 
         // Then render all objects
-        for (frame, screen_x, screen_y) in &shadow_render_data {
-            self.render_unit(frame, *screen_x, *screen_y, buf, camera);
+        for (frame, screen_x, screen_y, tint) in &shadow_render_data {
+            self.render_unit(frame, *screen_x, *screen_y, buf, camera, *tint);
+        }
+    }
+
+    /// Renders the HUD overlay on top of a rendered frame
+    ///
+    /// Must be called after [`Render::render_frame`], so the overlay covers the world
+    /// and all entities. The overlay is positioned in screen coordinates and does not
+    /// move with the camera.
+    ///
+    /// # Arguments
+    ///
+    /// * `hud` - Overlay to draw
+    /// * `font` - Bitmap font the overlay text is drawn with
+    /// * `camera` - Camera configuration defining viewport size
+    /// * `buf` - Output pixel buffer to render into
+    pub fn render_hud(&self, hud: &Hud, font: &BitmapFont, camera: &Camera, buf: &mut [u32]) {
+        hud.draw(font, buf, camera.width as usize, camera.height as usize);
+    }
+
+    /// Fades a rendered frame to black
+    ///
+    /// Must be called last, so the fade covers the world, the entities and the HUD.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - Fade strength, `0.0` leaves the frame unchanged and `1.0` turns it black
+    /// * `buf` - Output pixel buffer to fade
+    pub fn render_fade(&self, amount: f32, buf: &mut [u32]) {
+        let amount = amount.clamp(0.0, 1.0);
+        if amount == 0.0 {
+            return;
+        }
+
+        let brightness = 1.0 - amount;
+        for px in buf.iter_mut() {
+            let r = ((*px >> 16) & 0xFF) as f32 * brightness;
+            let g = ((*px >> 8) & 0xFF) as f32 * brightness;
+            let b = (*px & 0xFF) as f32 * brightness;
+            *px = (*px & 0xFF000000) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32);
         }
     }
 
@@ -269,6 +335,7 @@ impl Render {
     /// * `screen_y` - Y position in screen coordinates (output buffer space)  
     /// * `buf` - Output pixel buffer to render into
     /// * `camera` - Camera configuration defining viewport and position
+    /// * `tint` - Optional `0xRRGGBB` color the sprite pixels are multiplied with
     fn render_unit(
         &self,
         frame: &Frame,
@@ -276,7 +343,17 @@ impl Render {
         screen_y: i32,
         buf: &mut [u32],
         camera: &Camera,
+        tint: Option<u32>,
     ) {
+        let (tint_r, tint_g, tint_b) = match tint {
+            Some(t) => (
+                ((t >> 16) & 0xFF) as f32 / 255.0,
+                ((t >> 8) & 0xFF) as f32 / 255.0,
+                (t & 0xFF) as f32 / 255.0,
+            ),
+            None => (1.0, 1.0, 1.0),
+        };
+
         let (atlas_w, atlas_h) = self.entity_atlas.image.dimensions();
 
         for dy in 0..frame.h as i32 {
@@ -314,9 +391,9 @@ impl Render {
                 let brightness = 1.0 - 0.6 * shadow_intensity;
 
                 let [r, g, b, _] = color.0;
-                let src_r = (r as f32 * brightness) as u32;
-                let src_g = (g as f32 * brightness) as u32;
-                let src_b = (b as f32 * brightness) as u32;
+                let src_r = (r as f32 * brightness * tint_r) as u32;
+                let src_g = (g as f32 * brightness * tint_g) as u32;
+                let src_b = (b as f32 * brightness * tint_b) as u32;
 
                 let dst_pixel = buf[dest_idx];
 
@@ -652,11 +729,40 @@ mod tests {
         let cam = dummy_camera();
 
         let render = Render::new(vec![0; 100], 10, 10, atlas.clone(), vec![0; 100]);
-        render.render_unit(frame, 3, 3, &mut buf, &cam);
+        render.render_unit(frame, 3, 3, &mut buf, &cam, None);
 
         assert!(buf.iter().any(|&p| p != 0), "Buffer must have changed pixels");
     }
 
+    #[test]
+    fn test_render_unit_applies_tint() {
+        let atlas = dummy_atlas([200, 200, 200, 255]);
+        let mut buf = vec![0; 100];
+        let frame = atlas.get_frame("dummy").unwrap();
+        let cam = dummy_camera();
+
+        let render = Render::new(vec![0; 100], 10, 10, atlas.clone(), vec![0; 100]);
+        render.render_unit(frame, 3, 3, &mut buf, &cam, Some(0xFF0000));
+
+        let pixel = buf[3 * 10 + 3];
+        assert_eq!((pixel >> 16) & 0xFF, 200);
+        assert_eq!((pixel >> 8) & 0xFF, 0);
+        assert_eq!(pixel & 0xFF, 0);
+    }
+
+    #[test]
+    fn test_render_fade_darkens_frame() {
+        let render = Render::new(vec![0; 100], 10, 10, dummy_atlas([0, 0, 0, 255]), vec![0; 100]);
+        let mut buf = vec![0xFF80C0FF; 4];
+
+        render.render_fade(0.0, &mut buf);
+        assert_eq!(buf[0], 0xFF80C0FF);
+        render.render_fade(0.5, &mut buf);
+        assert_eq!(buf[0], 0xFF40607F);
+        render.render_fade(2.0, &mut buf);
+        assert!(buf.iter().all(|&p| p == 0xFF000000));
+    }
+
     #[test]
     fn test_render_shadow_modifies_shadow_map() {
         let atlas = dummy_atlas([255, 255, 255, 255]);
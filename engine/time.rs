@@ -1,17 +1,101 @@
-use std::time::Instant;
+use std::collections::VecDeque;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Duration of a simulation step (sec), the simulation runs at 60 steps per second.
+pub const FIXED_STEP: f32 = 1.0 / 60.0;
+/// Longest frame the clock accounts for (sec), longer frames (e.g. after a breakpoint or
+/// while the window is dragged) are clamped so the simulation does not spiral.
+pub const MAX_FRAME_DELTA: f32 = 0.25;
+/// Game speeds offered by [`TimeScale::slower`] and [`TimeScale::faster`], slowest first.
+pub const TIME_SCALES: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+
+// ============================
+// Time scale
+// ============================
+
+/// Speed of the game relative to real time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeScale {
+    /// Simulated seconds per real second, from the first to the last of [`TIME_SCALES`]
+    pub scale: f32,
+    /// Whether animations are skipped: the game finishes the running ones every step, the
+    /// game time still runs at `scale`
+    pub instant: bool,
+}
+
+impl TimeScale {
+    /// Returns the next slower speed of [`TIME_SCALES`] (the slowest stays as is).
+    pub fn slower(self) -> Self {
+        let scale = TIME_SCALES.iter().rev().find(|&&scale| scale < self.scale);
+        Self { scale: scale.copied().unwrap_or(TIME_SCALES[0]), ..self }
+    }
+
+    /// Returns the next faster speed of [`TIME_SCALES`] (the fastest stays as is).
+    pub fn faster(self) -> Self {
+        let scale = TIME_SCALES.iter().find(|&&scale| scale > self.scale);
+        Self { scale: scale.copied().unwrap_or(TIME_SCALES[TIME_SCALES.len() - 1]), ..self }
+    }
+
+    /// Returns the speed as a percentage of real time, e.g. `25` for a quarter speed.
+    pub fn percent(self) -> u32 {
+        (self.scale * 100.0).round() as u32
+    }
+
+    /// Returns the speed with the scale limited to the range of [`TIME_SCALES`].
+    fn clamped(self) -> Self {
+        let scale = self.scale.clamp(TIME_SCALES[0], TIME_SCALES[TIME_SCALES.len() - 1]);
+        Self { scale: if scale.is_nan() { 1.0 } else { scale }, ..self }
+    }
+}
+
+impl Default for TimeScale {
+    /// Real time with animations.
+    fn default() -> Self {
+        Self { scale: 1.0, instant: false }
+    }
+}
+
+// ============================
+// Clock
+// ============================
 
 /// Time tracking structure for game loops and frame timing.
 ///
-/// Tracks delta time (time between frames) and total elapsed time.
+/// The simulation advances in fixed steps: [`Time::update`] measures the real time of a
+/// frame and [`Time::tick`] consumes it step by step, so animations do not depend on the
+/// frame rate. Rendering interpolates between the last two steps with [`Time::alpha`].
+/// Steps take the recorded deltas instead of [`FIXED_STEP`] while a recorded delta stream
+/// is queued (see [`Time::from_deltas`]), which makes a session reproducible.
+///
+/// The game can be paused, slowed down or sped up with a [`TimeScale`]: steps keep their
+/// real-time rate and [`Time::scaled_delta`] tells how much game time a step stands for.
 pub struct Time {
-    /// Interval between frames (sec)
+    /// Duration of the last simulation step (sec)
     pub delta: f32,
-    /// Total time elapsed (sec)
+    /// Total simulated time (sec)
     pub total: f32,
+    /// Total game time, following the time scale and stopping while paused (sec)
+    pub scaled_total: f32,
+    /// Real time of the last frame, clamped to [`MAX_FRAME_DELTA`] (sec)
+    pub frame_delta: f32,
+    /// Real time not simulated yet (sec)
+    accumulator: f32,
+    /// Position of the frame between the last two steps, from `0` to `1`
+    alpha: f32,
     /// Delta calucalution
     last_instant: Instant,
     /// FPS print
     fps_timer: f32,
+    /// Recorded deltas used instead of the fixed step until exhausted
+    recorded: VecDeque<f32>,
+    /// Speed of the game
+    speed: TimeScale,
+    /// Whether the game is paused
+    paused: bool,
 }
 
 impl Time {
@@ -22,26 +106,177 @@ impl Time {
     /// A new `Time` instance with zero values of delta and total
     // and last_instant set to the current time.
     pub fn new() -> Self {
-        Self { delta: 0.0, total: 0.0, last_instant: Instant::now(), fps_timer: 0.0 }
+        Self {
+            delta: 0.0,
+            total: 0.0,
+            scaled_total: 0.0,
+            frame_delta: 0.0,
+            accumulator: 0.0,
+            alpha: 1.0,
+            last_instant: Instant::now(),
+            fps_timer: 0.0,
+            recorded: VecDeque::new(),
+            speed: TimeScale::default(),
+            paused: false,
+        }
+    }
+
+    /// Creates a Time instance driven by a recorded delta stream.
+    ///
+    /// Every step consumes the next recorded delta instead of [`FIXED_STEP`] (steps are
+    /// still taken at the fixed rate). Once the stream is exhausted the fixed step takes
+    /// over.
+    ///
+    /// # Arguments
+    ///
+    /// * `deltas` - recorded game time of the simulation steps (sec), see
+    ///   [`Time::scaled_delta`]
+    ///
+    /// # Returns
+    ///
+    /// A new `Time` instance with zero values of delta and total.
+    pub fn from_deltas<I: IntoIterator<Item = f32>>(deltas: I) -> Self {
+        Self { recorded: deltas.into_iter().collect(), ..Self::new() }
+    }
+
+    /// Checks whether recorded deltas are still queued.
+    pub fn is_recorded(&self) -> bool {
+        !self.recorded.is_empty()
+    }
+
+    /// Returns the speed of the game.
+    pub fn time_scale(&self) -> TimeScale {
+        self.speed
+    }
+
+    /// Changes the speed of the game.
+    ///
+    /// # Arguments
+    ///
+    /// * `speed` - new speed, its scale is limited to the range of [`TIME_SCALES`]
+    pub fn set_time_scale(&mut self, speed: TimeScale) {
+        self.speed = speed.clamped();
+    }
+
+    /// Checks whether the game is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pauses or resumes the game.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Returns the game time the last step stands for.
+    ///
+    /// # Returns
+    ///
+    /// `0` while paused, or the duration of the step multiplied by the time scale (with
+    /// or without instant moves).
+    pub fn scaled_delta(&self) -> f32 {
+        if self.paused {
+            0.0
+        } else {
+            self.delta * self.speed.scale
+        }
+    }
+
+    /// Returns the position of the frame between the previous and the last step.
+    ///
+    /// # Returns
+    ///
+    /// `0` if the frame shows the previous step, `1` if it shows the last one.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Advances the simulation by one step of a given duration without looking at the
+    /// system clock.
+    ///
+    /// Used to drive the game loop with a synthetic clock (headless runs, tests). The
+    /// frame shows the step as is, without interpolation.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - duration of the step (sec)
+    pub fn advance(&mut self, delta: f32) {
+        self.step(delta);
+        self.alpha = 1.0;
+    }
+
+    /// Advances the simulation by the next recorded delta, see [`Time::advance`].
+    ///
+    /// # Arguments
+    ///
+    /// * `fallback` - duration of the step once the recorded stream is exhausted (sec)
+    pub fn advance_recorded(&mut self, fallback: f32) {
+        let delta = self.next_delta(fallback);
+        self.advance(delta);
     }
 
     /// Updates time measurements.
     ///
-    /// Calculates the time elapsed since the last update and updates
-    /// both delta and total time values.
+    /// Measures the time elapsed since the last update, clamps it to [`MAX_FRAME_DELTA`]
+    /// and adds it to the time waiting to be simulated by [`Time::tick`].
     pub fn update(&mut self) {
         let now = Instant::now();
-        self.delta = now.duration_since(self.last_instant).as_secs_f32();
-        self.total += self.delta;
+        let elapsed = now.duration_since(self.last_instant).as_secs_f32();
         self.last_instant = now;
-        self.fps_timer += self.delta;
+        self.add_frame(elapsed);
 
+        self.fps_timer += self.frame_delta;
         if self.fps_timer >= 1.0 {
-            let fps = 1.0 / self.delta.max(1e-6);
+            let fps = 1.0 / self.frame_delta.max(1e-6);
             println!("FPS: {:.1}", fps);
             self.fps_timer = 0.0;
         }
     }
+
+    /// Runs the next simulation step if enough real time has accumulated.
+    ///
+    /// Call it in a loop after [`Time::update`] and update the simulation once per
+    /// `true`; a slow frame runs several steps, a fast one none.
+    ///
+    /// # Returns
+    ///
+    /// `true` if a step has been taken (`delta` and `total` describe it).
+    pub fn tick(&mut self) -> bool {
+        if self.accumulator < FIXED_STEP {
+            self.alpha = (self.accumulator / FIXED_STEP).min(1.0);
+            return false;
+        }
+
+        self.accumulator -= FIXED_STEP;
+        let delta = self.next_delta(FIXED_STEP);
+        self.step(delta);
+        true
+    }
+
+    /// Takes the duration of the next step: the next recorded delta, or `fallback` once
+    /// the stream is exhausted. Recorded deltas are kept while the game is paused.
+    fn next_delta(&mut self, fallback: f32) -> f32 {
+        if self.paused {
+            fallback
+        } else {
+            self.recorded.pop_front().unwrap_or(fallback)
+        }
+    }
+
+    /// Advances the simulated and the game time by a step.
+    fn step(&mut self, delta: f32) {
+        self.delta = delta;
+        self.total += delta;
+        if !self.paused {
+            self.scaled_total += delta * self.speed.scale;
+        }
+    }
+
+    /// Adds the real time of a frame to the time waiting to be simulated.
+    fn add_frame(&mut self, elapsed: f32) {
+        self.frame_delta = elapsed.min(MAX_FRAME_DELTA);
+        self.accumulator += self.frame_delta;
+    }
 }
 
 impl Default for Time {
@@ -50,11 +285,49 @@ impl Default for Time {
     }
 }
 
+// ============================
+// Frame limiter
+// ============================
+
+/// Caps the frame rate by sleeping away the rest of every frame.
+///
+/// The time spent working on a frame counts towards it, so frames are evenly spaced as
+/// long as the work fits in the frame time.
+pub struct FrameLimiter {
+    /// Target duration of a frame
+    frame_time: Duration,
+    /// Time the next frame should start at
+    next_frame: Instant,
+}
+
+impl FrameLimiter {
+    /// Creates a limiter; the first frame starts now.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_time` - target duration of a frame
+    pub fn new(frame_time: Duration) -> Self {
+        Self { frame_time, next_frame: Instant::now() + frame_time }
+    }
+
+    /// Sleeps until the next frame is due.
+    ///
+    /// A frame that took longer than the frame time does not sleep, and the following
+    /// frames are not rushed to make up for it.
+    pub fn wait(&mut self) {
+        let now = Instant::now();
+        if let Some(remaining) = self.next_frame.checked_duration_since(now) {
+            thread::sleep(remaining);
+            self.next_frame += self.frame_time;
+        } else {
+            self.next_frame = now + self.frame_time;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::thread;
-    use std::time::Duration;
 
     /// Test that Time initializes with correct default values
     #[test]
@@ -62,54 +335,142 @@ mod tests {
         let time = Time::new();
         assert_eq!(time.delta, 0.0);
         assert_eq!(time.total, 0.0);
+        assert_eq!(time.alpha(), 1.0);
     }
 
-    /// Test that update() method properly calculates delta time and updates total time
+    /// Test that update() measures the frame without advancing the simulation
     #[test]
-    fn test_update_updates_values() {
+    fn test_update_measures_frame() {
         let mut time = Time::new();
-        let initial_total = time.total;
 
         thread::sleep(Duration::from_millis(10));
         time.update();
 
-        assert!(time.delta > 0.0, "Delta should be positive after update");
-        assert!(time.total > initial_total, "Total time should increase after update");
-        assert!(time.total >= time.delta, "Total should be at least as large as delta");
+        assert!(time.frame_delta > 0.0, "Frame delta should be positive after update");
+        assert_eq!(time.total, 0.0, "Update alone should not advance the simulation");
     }
 
-    /// Test that multiple consecutive updates work correctly
+    /// Test that ticks consume the accumulated time in fixed steps
     #[test]
-    fn test_multiple_updates() {
+    fn test_fixed_steps() {
         let mut time = Time::new();
 
-        time.update();
-        let first_total = time.total;
+        time.add_frame(2.5 * FIXED_STEP);
+        assert!(time.tick());
+        assert!(time.tick());
+        assert!(!time.tick());
+        assert_eq!(time.delta, FIXED_STEP);
+        assert!((time.total - 2.0 * FIXED_STEP).abs() < 1e-6);
+        assert!((time.alpha() - 0.5).abs() < 1e-4);
+
+        time.add_frame(0.25 * FIXED_STEP);
+        assert!(!time.tick());
+        assert!((time.alpha() - 0.75).abs() < 1e-4);
+    }
+
+    /// Test that a long frame is clamped
+    #[test]
+    fn test_max_frame_delta() {
+        let mut time = Time::new();
+
+        time.add_frame(10.0);
+        assert_eq!(time.frame_delta, MAX_FRAME_DELTA);
+
+        let mut steps = 0;
+        while time.tick() {
+            steps += 1;
+        }
+        assert_eq!(steps, (MAX_FRAME_DELTA / FIXED_STEP).round() as usize);
+    }
+
+    /// Test that recorded deltas are used before the fixed step
+    #[test]
+    fn test_recorded_deltas() {
+        let mut time = Time::from_deltas([0.05, 0.025]);
+        assert!(time.is_recorded());
+
+        time.add_frame(1.5 * FIXED_STEP);
+        assert!(time.tick());
+        assert_eq!(time.delta, 0.05);
+        assert!(!time.tick());
+
+        time.add_frame(0.5 * FIXED_STEP);
+        assert!(time.tick());
+        assert_eq!(time.delta, 0.025);
+        assert!(!time.is_recorded());
+        assert!(!time.tick());
+
+        time.advance_recorded(0.5);
+        assert_eq!(time.delta, 0.5);
+        assert!((time.total - 0.575).abs() < 1e-6);
+    }
+
+    /// Test that a synthetic clock ignores the system clock
+    #[test]
+    fn test_advance() {
+        let mut time = Time::new();
 
         thread::sleep(Duration::from_millis(5));
-        time.update();
+        time.advance(0.25);
+        time.advance(0.5);
 
-        assert!(time.delta > 0.0, "Second delta should be positive");
-        assert!(time.total > first_total, "Total should increase with each update");
-        assert!(time.total == first_total + time.delta, "Total should be sum of all deltas");
+        assert_eq!(time.delta, 0.5);
+        assert_eq!(time.total, 0.75);
+        assert!(!time.tick(), "A synthetic clock accumulates no real time");
     }
 
-    /// Test that total time accurately accumulates delta values over multiple updates
+    /// Test that the time scale changes the game time of a step
     #[test]
-    fn test_total_accumulation() {
+    fn test_time_scale() {
         let mut time = Time::new();
-        let mut expected_total = 0.0;
-
-        for i in 0..3 {
-            let sleep_duration = Duration::from_millis(10 * (i + 1));
-            thread::sleep(sleep_duration);
-            time.update();
-            expected_total += time.delta;
-
-            assert!(
-                (time.total - expected_total).abs() < f32::EPSILON,
-                "Total should accurately accumulate delta values"
-            );
+        let speed = TimeScale::default().faster();
+        assert_eq!(speed.percent(), 200);
+        time.set_time_scale(speed);
+
+        time.advance(0.5);
+        assert_eq!(time.scaled_delta(), 1.0);
+        assert_eq!(time.scaled_total, 1.0);
+
+        time.set_paused(true);
+        time.advance(0.5);
+        assert_eq!(time.scaled_delta(), 0.0);
+        assert_eq!((time.total, time.scaled_total), (1.0, 1.0));
+
+        time.set_paused(false);
+        time.set_time_scale(TimeScale { scale: 10.0, instant: true });
+        assert_eq!(time.time_scale().scale, 4.0);
+        assert_eq!(time.scaled_delta(), 2.0);
+    }
+
+    /// Test that speeds step through the offered scales
+    #[test]
+    fn test_time_scale_steps() {
+        let mut speed = TimeScale::default();
+        for expected in [50, 25, 25] {
+            speed = speed.slower();
+            assert_eq!(speed.percent(), expected);
         }
+
+        let speed = TimeScale { scale: 3.0, instant: true }.faster();
+        assert_eq!(speed, TimeScale { scale: 4.0, instant: true });
+        assert_eq!(speed.faster(), speed);
+        assert_eq!(TimeScale { scale: 3.0, instant: false }.slower().percent(), 200);
+    }
+
+    /// Test that the limiter paces frames including the work time
+    #[test]
+    fn test_frame_limiter() {
+        let frame_time = Duration::from_millis(10);
+        let mut limiter = FrameLimiter::new(frame_time);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            thread::sleep(Duration::from_millis(4));
+            limiter.wait();
+        }
+
+        let elapsed = start.elapsed();
+        assert!(elapsed >= 3 * frame_time, "Frames should not be shorter than the frame time");
+        assert!(elapsed < 6 * frame_time, "Work time should count towards the frame");
     }
 }
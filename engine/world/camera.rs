@@ -47,6 +47,22 @@ impl Camera {
         ((self.center_x - x).abs() < self.width as f32)
             && ((self.center_y - y).abs() < self.height as f32)
     }
+
+    /// Converts a position on screen to world coordinates.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The x-coordinate on screen, in pixels from the left edge of the viewport
+    /// * `y` - The y-coordinate on screen, in pixels from the top edge of the viewport
+    ///
+    /// # Returns
+    ///
+    /// The world position `(x, y)` drawn at that screen position.
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        let left = (self.center_x - self.width as f32 / 2.0).floor();
+        let top = (self.center_y - self.height as f32 / 2.0).floor();
+        (left + x, top + y)
+    }
 }
 
 #[cfg(test)]
@@ -135,4 +151,12 @@ mod tests {
         assert!(!negative_center_camera.is_visible(-600.0, -200.0));
         assert!(!negative_center_camera.is_visible(400.0, -200.0));
     }
+
+    /// Test that screen positions are offset by the top-left corner of the viewport
+    #[test]
+    fn test_screen_to_world() {
+        let camera = Camera::new(100.5, 200.0, 40, 30);
+        assert_eq!(camera.screen_to_world(0.0, 0.0), (80.0, 185.0));
+        assert_eq!(camera.screen_to_world(20.0, 15.0), (100.0, 200.0));
+    }
 }
@@ -1,10 +1,12 @@
+use serde::{Deserialize, Serialize};
+
 use crate::assets::GameMap;
 
 /// Represents the current game state containing all units.
 ///
 /// The `State` struct manages the player unit and all mob units in the game,
 /// tracking their positions and movement speeds for game simulation.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct State {
     /// The player-controlled unit
     pub player: Player,
@@ -18,9 +20,14 @@ pub struct State {
 
     /// Width of the game map (in tiles), used for calculating 1D index from 2D coordinates
     pub grid_width: i32,
+
+    /// 1D vector representing the 2D map. Stores the index of the box that fell into the
+    /// hole of the tile (the hole is floor from then on), or `None`
+    pub filled_holes: Vec<Option<usize>>,
 }
 
-#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Direction {
     NE, // North East (Up)
     #[default]
@@ -123,6 +130,43 @@ pub enum UnitMovement {
         /// Total movement time
         duration: f32,
     },
+
+    /// Moving onto a teleporter, then reappearing on its partner. same as `Moving`, but
+    /// jumps to the exit position at the end
+    Warping {
+        /// Initial position
+        start_x: f32,
+        start_y: f32,
+
+        /// Position of the teleporter
+        target_x: f32,
+        target_y: f32,
+
+        /// Movement progress since starting
+        elapsed_time: f32,
+        /// Total movement time
+        duration: f32,
+
+        /// Position of the partner teleporter
+        exit_x: f32,
+        exit_y: f32,
+    },
+
+    /// Walking back to the previous tile while a step is being undone. same as `Moving`, but with different animation
+    Rewinding {
+        /// Initial position
+        start_x: f32,
+        start_y: f32,
+
+        /// Target position
+        target_x: f32,
+        target_y: f32,
+
+        /// Movement progress since starting
+        elapsed_time: f32,
+        /// Total movement time
+        duration: f32,
+    },
 }
 
 /// Represents a unit entity in the game world with position and movement capabilities.
@@ -148,6 +192,8 @@ pub struct Unit {
     pub movement: UnitMovement,
     /// Current facing direction
     pub direction: Direction,
+    /// Color of a box (see `Mob::color`), `None` for plain boxes and the player
+    pub color: Option<String>,
 }
 
 impl Unit {
@@ -179,7 +225,7 @@ impl Unit {
 }
 
 /// Represents the player's state. Primarily wraps a `Unit`.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Player {
     /// Player's data
     pub unit: Unit,
@@ -218,6 +264,83 @@ fn tile_to_world_buf_pos(
     (center_x as f32, center_y as f32)
 }
 
+/// Converts pixel coordinates in the world buffer to the tile whose floor is drawn there.
+///
+/// Inverse of the isometric projection of [`State::new`] and `Render::init`: every point
+/// of a tile's floor diamond maps back to that tile. The result may lie outside the map.
+///
+/// # Arguments
+///
+/// * `x`, `y` - position in the world buffer, in pixels
+/// * `tile_size` - size of a tile in the atlas, in pixels
+/// * `world_width`, `world_height` - size of the world buffer, in pixels
+///
+/// # Returns
+///
+/// Tile coordinates `(tile_x, tile_y)`.
+pub fn world_buf_pos_to_tile(
+    x: f32,
+    y: f32,
+    tile_size: u32,
+    world_width: u32,
+    world_height: u32,
+) -> (i32, i32) {
+    let ts = tile_size as f32;
+
+    // center of the floor diamond of tile (0, 0)
+    let origin_x = (world_width / 2) as f32 + ts / 2.0;
+    let origin_y = (world_height / 2) as f32 - ts - ts / 2.0 + ts / 4.0;
+
+    // `u = tile_x - tile_y`, `v = tile_x + tile_y`
+    let u = (x - origin_x) / (ts / 2.0);
+    let v = (y - origin_y) / (ts / 4.0);
+
+    (((u + v) / 2.0).round() as i32, ((v - u) / 2.0).round() as i32)
+}
+
+/// Converts the pixel position of a unit back to fractional tile coordinates.
+///
+/// Inverse of the projection units are placed with by [`State::new`]: a unit standing on
+/// a tile gives the tile coordinates, a unit halfway through a step gives `.5`.
+///
+/// # Arguments
+///
+/// * `x`, `y` - position of the unit in the world buffer, in pixels
+/// * `tile_size` - size of a tile in the atlas, in pixels
+/// * `world_width`, `world_height` - size of the world buffer, in pixels
+///
+/// # Returns
+///
+/// Fractional tile coordinates `(tile_x, tile_y)`.
+pub fn world_buf_pos_to_tile_coords(
+    x: f32,
+    y: f32,
+    tile_size: u32,
+    world_width: u32,
+    world_height: u32,
+) -> (f32, f32) {
+    let ts = tile_size as f32;
+    let (origin_x, origin_y) = tile_to_world_buf_pos(0, 0, tile_size, world_width, world_height);
+
+    // `u = tile_x - tile_y`, `v = tile_x + tile_y`
+    let u = (x - origin_x) / (ts / 2.0);
+    let v = (y - origin_y) / (ts / 4.0);
+
+    ((u + v) / 2.0, (v - u) / 2.0)
+}
+
+/// Returns the vertical screen distance between two height levels, in pixels.
+///
+/// Units move on the ground plane; their elevation (see `GameMap::elevation_at`) is
+/// added when they are drawn, as it is for raised tiles and objects.
+///
+/// # Arguments
+///
+/// * `tile_size` - size of a tile in the atlas, in pixels
+pub fn height_step(tile_size: u32) -> f32 {
+    (tile_size / 4) as f32
+}
+
 impl State {
     /// Creates a new `State` by getting unit data from a `GameMap`.
     ///
@@ -266,6 +389,7 @@ impl State {
                 pixel_y: world_y,
                 tile_x: mob_data.x_start as i32,
                 tile_y: mob_data.y_start as i32,
+                color: mob_data.color.clone(),
                 ..Default::default()
             };
 
@@ -303,7 +427,9 @@ impl State {
             mob_grid[idx] = Some(i);
         }
 
-        Self { player: Player::new(player.unwrap()), mobs, mob_grid, grid_width }
+        let player = player.expect("map without a player, see GameMap::validate");
+        let filled_holes = vec![None; width * height];
+        Self { player: Player::new(player), mobs, mob_grid, grid_width, filled_holes }
     }
 
     /// Updates the `mob_grid` to reflect a mob's movement from one tile to another.
@@ -382,6 +508,7 @@ mod state_tests {
                 asset: "knight".to_string(),
                 is_player: true,
                 behaviour: None,
+                color: None,
             },
         );
 
@@ -398,6 +525,7 @@ mod state_tests {
                     direction: Some("right".to_string()),
                     speed: Some(1.0),
                 }),
+                color: None,
             },
         );
 
@@ -414,6 +542,7 @@ mod state_tests {
                     direction: Some("up".to_string()),
                     speed: Some(0.5),
                 }),
+                color: None,
             },
         );
 
@@ -425,8 +554,10 @@ mod state_tests {
             objects: std::collections::HashMap::new(),
             tiles: std::collections::HashMap::new(),
             walk_map: vec![TileType::Empty; 25],
+            height_map: vec![0; 25],
             target_positions: LinkedList::new(),
             links: HashMap::new(),
+            target_colors: HashMap::new(),
             object_collidable_map: vec![false; 0],
         }
     }
@@ -456,6 +587,41 @@ mod state_tests {
         assert_eq!(mob_up.tile_y, 1);
     }
 
+    #[test]
+    fn test_world_buf_pos_to_tile_inverts_projection() {
+        use super::{tile_to_world_buf_pos, world_buf_pos_to_tile};
+
+        let (ts, ww, wh) = (128, 5 * 128 * 2, 4 * 128 * 2);
+        for tile in [(0, 0), (4, 0), (0, 3), (2, 1), (-1, 5)] {
+            let (x, y) = tile_to_world_buf_pos(tile.0, tile.1, ts, ww, wh);
+            assert_eq!(world_buf_pos_to_tile(x, y, ts, ww, wh), tile);
+
+            // points near the corners of the floor diamond
+            let (cx, cy) = (x, y - 16.0);
+            assert_eq!(world_buf_pos_to_tile(cx + 60.0, cy, ts, ww, wh), tile);
+            assert_eq!(world_buf_pos_to_tile(cx - 60.0, cy, ts, ww, wh), tile);
+            assert_eq!(world_buf_pos_to_tile(cx, cy + 30.0, ts, ww, wh), tile);
+            assert_eq!(world_buf_pos_to_tile(cx, cy - 30.0, ts, ww, wh), tile);
+        }
+
+        let (x, y) = tile_to_world_buf_pos(2, 1, ts, ww, wh);
+        assert_eq!(world_buf_pos_to_tile(x + 64.0, y - 16.0 + 32.0, ts, ww, wh), (3, 1));
+    }
+
+    #[test]
+    fn test_world_buf_pos_to_tile_coords_inverts_unit_positions() {
+        use super::{tile_to_world_buf_pos, world_buf_pos_to_tile_coords};
+
+        let (ts, ww, wh) = (128, 5 * 128 * 2, 4 * 128 * 2);
+        let (x, y) = tile_to_world_buf_pos(2, 1, ts, ww, wh);
+        assert_eq!(world_buf_pos_to_tile_coords(x, y, ts, ww, wh), (2.0, 1.0));
+
+        // halfway through a step towards (3, 1)
+        let (next_x, next_y) = tile_to_world_buf_pos(3, 1, ts, ww, wh);
+        let (mid_x, mid_y) = ((x + next_x) / 2.0, (y + next_y) / 2.0);
+        assert_eq!(world_buf_pos_to_tile_coords(mid_x, mid_y, ts, ww, wh), (2.5, 1.0));
+    }
+
     #[test]
     fn test_state_with_no_mobs_other_than_player() {
         let mut map = make_test_map();
@@ -480,6 +646,7 @@ mod state_tests {
                 asset: "knight".to_string(),
                 is_player: true,
                 behaviour: None,
+                color: None,
             },
         );
         mobs.insert(
@@ -491,6 +658,7 @@ mod state_tests {
                 asset: "dummy".to_string(),
                 is_player: false,
                 behaviour: None,
+                color: None,
             },
         );
         mobs.insert(
@@ -506,6 +674,7 @@ mod state_tests {
                     direction: Some("left".to_string()),
                     speed: Some(2.0),
                 }),
+                color: None,
             },
         );
 
@@ -517,8 +686,10 @@ mod state_tests {
             objects: std::collections::HashMap::new(),
             tiles: std::collections::HashMap::new(),
             walk_map: vec![TileType::Empty; 25],
+            height_map: vec![0; 25],
             target_positions: LinkedList::new(),
             links: HashMap::new(),
+            target_colors: HashMap::new(),
             object_collidable_map: vec![false; 0],
         };
 
@@ -1,5 +1,14 @@
-use crate::{initiator::lerp, input::InputSnapshot, MOVEMENT_SPEEDUP, TILE_SIZE};
+use crate::{
+    deadlock::DeadlockDetector,
+    history::{History, PushRecord, StepRecord},
+    initiator::lerp,
+    input::InputSnapshot,
+    TILE_SIZE,
+};
 
+use std::collections::HashSet;
+
+use ferari::assets::{GameMap, TileType};
 use ferari::world::{Direction, State, Unit, UnitMovement};
 
 /// Data required to start a box-pushing animation.
@@ -20,17 +29,132 @@ struct PlayerAnimationResult {
 }
 
 /// Time to walk one tile (player walking).
-const MOVE_DURATION: f32 = 0.35 / MOVEMENT_SPEEDUP;
+const MOVE_DURATION: f32 = 0.35;
 /// Time to approach a box before pushing.
-const PRE_PUSH_DURATION: f32 = 0.40 / MOVEMENT_SPEEDUP;
-/// Recoil animation time after pushing (not speed-scaled).
+const PRE_PUSH_DURATION: f32 = 0.40;
+/// Recoil animation time after pushing.
 const POST_PUSH_DURATION: f32 = 0.50;
 /// Time for a box to slide one tile.
-const BOX_MOVE_DURATION: f32 = 1.25 / MOVEMENT_SPEEDUP;
+const BOX_MOVE_DURATION: f32 = 1.25;
 /// Duration of the player's pushing animation.
 const PUSH_DURATION: f32 = BOX_MOVE_DURATION;
 /// Offset between a box and the pushing player.
 const PUSH_OFFSET: f32 = 0.25;
+/// Time to slide one more tile across ice.
+const SLIDE_DURATION: f32 = 0.15;
+
+/// Passability of the mechanism tiles, as of the start of a frame or of a push.
+///
+/// Doors are closed unless a plate with the same id is pressed (by the player or a box) or
+/// something stands in the doorway; holes are open until a box falls in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gates {
+    /// Map width in tiles
+    width: i32,
+    /// Cells nothing can enter: closed doors
+    closed: Vec<bool>,
+    /// Holes no box has filled yet: the player cannot enter them, a box falls in
+    open_holes: Vec<bool>,
+}
+
+impl Gates {
+    /// Evaluates the plates, doors and holes of the current state.
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - the level map
+    /// * `state` - current positions of the player and the boxes
+    pub fn new(game: &GameMap, state: &State) -> Self {
+        Self::with_player_at(game, state, (state.player.unit.tile_x, state.player.unit.tile_y))
+    }
+
+    /// Evaluates the plates, doors and holes with the player standing on another cell, e.g.
+    /// the one it steps onto when a push starts.
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - the level map
+    /// * `state` - current positions of the boxes
+    /// * `(player_x, player_y)` - tile coordinates of the player
+    fn with_player_at(game: &GameMap, state: &State, (player_x, player_y): (i32, i32)) -> Self {
+        let width = game.size[0] as i32;
+        let player = player_y * width + player_x;
+        let occupied = |idx: usize| {
+            idx as i32 == player || state.mob_grid.get(idx).copied().flatten().is_some()
+        };
+
+        let pressed: HashSet<u32> = game
+            .walk_map
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, tile_type)| match tile_type {
+                TileType::Plate(id) if occupied(idx) => Some(*id),
+                _ => None,
+            })
+            .collect();
+
+        let closed = game
+            .walk_map
+            .iter()
+            .enumerate()
+            .map(|(idx, tile_type)| match tile_type {
+                TileType::Door(id) => !pressed.contains(id) && !occupied(idx),
+                _ => false,
+            })
+            .collect();
+        let open_holes = game
+            .walk_map
+            .iter()
+            .enumerate()
+            .map(|(idx, tile_type)| {
+                *tile_type == TileType::Hole
+                    && state.filled_holes.get(idx).copied().flatten().is_none()
+            })
+            .collect();
+
+        Self { width, closed, open_holes }
+    }
+
+    /// Returns the cell index of tile coordinates inside the map.
+    fn cell(&self, x: i32, y: i32) -> Option<usize> {
+        (x >= 0 && y >= 0 && x < self.width)
+            .then_some((y * self.width + x) as usize)
+            .filter(|&idx| idx < self.closed.len())
+    }
+
+    /// Checks whether the tile is a closed door.
+    pub fn is_closed(&self, x: i32, y: i32) -> bool {
+        self.cell(x, y).is_some_and(|idx| self.closed[idx])
+    }
+
+    /// Checks whether the tile is a hole no box has filled yet.
+    pub fn is_open_hole(&self, x: i32, y: i32) -> bool {
+        self.cell(x, y).is_some_and(|idx| self.open_holes[idx])
+    }
+}
+
+/// Where a unit ends up after stepping onto a cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Landing {
+    /// Cell the unit stops on: the entered cell, or the end of a slide across ice
+    stop: (i32, i32),
+    /// Partner teleporter the unit reappears on, if it stops on a teleporter
+    exit: Option<(i32, i32)>,
+    /// Whether the unit (a box) falls into the open hole at `stop`
+    sinks: bool,
+}
+
+impl Landing {
+    /// Landing of a unit that simply stays on the entered cell.
+    fn at(stop: (i32, i32)) -> Self {
+        Self { stop, exit: None, sinks: false }
+    }
+
+    /// Returns the tile the unit ends up on.
+    fn end(&self) -> (i32, i32) {
+        self.exit.unwrap_or(self.stop)
+    }
+}
 
 /// Computes the pixel offset (in isometric coordinates) for moving in a given direction by a specified magnitude.
 ///
@@ -62,6 +186,17 @@ fn get_offset(dir_x: i32, dir_y: i32, magnitude: f32) -> (f32, f32) {
     }
 }
 
+/// Computes the pixel offset (in isometric coordinates) between two tiles.
+///
+/// # Arguments
+///
+/// * `(dx, dy)` - difference of the tile coordinates
+fn tile_offset((dx, dy): (i32, i32)) -> (f32, f32) {
+    let (x1, y1) = get_offset(1, 0, dx as f32);
+    let (x2, y2) = get_offset(0, 1, dy as f32);
+    (x1 + x2, y1 + y2)
+}
+
 /// Maps a logical `Direction` enum value to its corresponding tile-based movement delta.
 ///
 /// # Arguments
@@ -73,7 +208,7 @@ fn get_offset(dir_x: i32, dir_y: i32, magnitude: f32) -> (f32, f32) {
 /// A tuple `(dx, dy)` where:
 /// - `dx`: change in tile X-coordinate (+1 for SE/NW axis, 0 otherwise)
 /// - `dy`: change in tile Y-coordinate (+1 for SW/NE axis, 0 otherwise)
-fn get_dir_delta(dir: Direction) -> (i32, i32) {
+pub fn get_dir_delta(dir: Direction) -> (i32, i32) {
     match dir {
         Direction::SE => (1, 0),
         Direction::NW => (-1, 0),
@@ -82,6 +217,146 @@ fn get_dir_delta(dir: Direction) -> (i32, i32) {
     }
 }
 
+/// Computes in which directions a step can leave each cell of the map.
+///
+/// A step is open if the height difference to the neighbour can be crossed (see
+/// `GameMap::can_climb`); whether the neighbour itself is passable is not checked.
+///
+/// # Arguments
+///
+/// * `game` - the level map
+///
+/// # Returns
+///
+/// One entry per cell (indexed by `y * width + x`), holding a flag per direction
+/// (indexed by `Direction as usize`).
+pub fn climb_map(game: &GameMap) -> Vec<[bool; 4]> {
+    let [width, height] = game.size.map(|v| v as i32);
+    let directions = [Direction::NE, Direction::SE, Direction::SW, Direction::NW];
+
+    let mut climb = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let mut open = [false; 4];
+            for direction in directions {
+                let (dx, dy) = get_dir_delta(direction);
+                open[direction as usize] = game.can_climb((x, y), (x + dx, y + dy));
+            }
+            climb.push(open);
+        }
+    }
+    climb
+}
+
+/// Checks whether a unit can step from a cell onto its neighbour.
+///
+/// Besides the static map (walls, collidable objects and height differences) a step has
+/// to follow the one-way arrows of both cells and must not enter a closed door; the
+/// player cannot enter an open hole either. Boxes and the player standing in the way are
+/// not checked.
+///
+/// # Arguments
+///
+/// * `game` - the level map
+/// * `gates` - current state of the doors and holes
+/// * `from`, `to` - tile coordinates of the cell the step starts on and of its neighbour
+/// * `direction` - direction of the step
+/// * `is_box` - whether the unit is a box (boxes fall into holes)
+fn can_enter(
+    game: &GameMap,
+    gates: &Gates,
+    from: (i32, i32),
+    to: (i32, i32),
+    direction: Direction,
+    is_box: bool,
+) -> bool {
+    let follows_arrow = |(x, y): (i32, i32)| match game.tile_type_at(x, y) {
+        Some(TileType::OneWay(arrow)) => *arrow == direction,
+        _ => true,
+    };
+
+    game.is_walkable_from(from, to)
+        && !game.has_collidable_object_at(to.0, to.1)
+        && follows_arrow(from)
+        && follows_arrow(to)
+        && !gates.is_closed(to.0, to.1)
+        && (is_box || !gates.is_open_hole(to.0, to.1))
+}
+
+/// Follows a unit that steps onto a cell across ice, into a hole or through a teleporter.
+///
+/// A unit slides across ice until it cannot enter the next cell or another unit is in the
+/// way. A box stopping on an open hole falls in; otherwise a unit stopping on a teleporter
+/// reappears on its partner if nothing stands there.
+///
+/// # Arguments
+///
+/// * `game` - the level map
+/// * `gates` - current state of the doors and holes
+/// * `state` - current positions of the player and the boxes
+/// * `cell` - tile coordinates of the entered cell
+/// * `direction` - direction of the step
+/// * `is_box` - whether the unit is a box
+fn find_landing(
+    game: &GameMap,
+    gates: &Gates,
+    state: &State,
+    cell: (i32, i32),
+    direction: Direction,
+    is_box: bool,
+) -> Landing {
+    let player = (state.player.unit.tile_x, state.player.unit.tile_y);
+    let occupied = |(x, y): (i32, i32)| (x, y) == player || state.get_mob_at(x, y).is_some();
+    let (dx, dy) = get_dir_delta(direction);
+
+    let mut stop = cell;
+    loop {
+        if is_box && gates.is_open_hole(stop.0, stop.1) {
+            return Landing { sinks: true, ..Landing::at(stop) };
+        }
+        if game.tile_type_at(stop.0, stop.1) != Some(&TileType::Ice) {
+            break;
+        }
+        let next = (stop.0 + dx, stop.1 + dy);
+        if !can_enter(game, gates, stop, next, direction, is_box) || occupied(next) {
+            break;
+        }
+        stop = next;
+    }
+
+    let exit = game.teleporter_exit(stop.0, stop.1).filter(|&exit| !occupied(exit));
+    Landing { exit, ..Landing::at(stop) }
+}
+
+/// Builds an input snapshot that holds only the movement key for `dir`.
+///
+/// Used to feed automatically generated moves (e.g. solver hints) into [`make_step`]
+/// as if the player pressed the key.
+///
+/// # Arguments
+///
+/// * `dir` - the direction to move in
+///
+/// # Returns
+///
+/// An [`InputSnapshot`] with exactly one directional key pressed.
+pub fn input_for_direction(dir: Direction) -> InputSnapshot {
+    let mut input = InputSnapshot::default();
+    match dir {
+        Direction::SE => input.right = true,
+        Direction::NW => input.left = true,
+        Direction::NE => input.up = true,
+        Direction::SW => input.down = true,
+    }
+    input
+}
+
+/// Checks whether the input asks to go back to the menu: the menu action or the
+/// left + right combination.
+pub fn back_pressed(input: &InputSnapshot) -> bool {
+    input.menu || (input.left && input.right)
+}
+
 /// Updates the player's animation state based on elapsed time and input.
 ///
 /// This function handles all player animation phases: idle, walking, pre-pushing,
@@ -94,6 +369,7 @@ fn get_dir_delta(dir: Direction) -> (i32, i32) {
 /// * `input_state` - current input snapshot used to detect continuous push input
 /// * `mob_grid` - read-only grid indicating which mob (if any) occupies each tile
 /// * `game` - game map used for walkability and collision checks during chain pushes
+/// * `gates` - current state of the doors and holes, checked during chain pushes
 /// * `map_width` - width of the map in tiles
 ///
 /// # Returns
@@ -109,7 +385,8 @@ fn update_player_animation(
     delta: f32,
     input_state: &InputSnapshot,
     mob_grid: &[Option<usize>],
-    game: &ferari::assets::GameMap,
+    game: &GameMap,
+    gates: &Gates,
     map_width: usize,
 ) -> PlayerAnimationResult {
     let mut player_is_busy = false;
@@ -134,6 +411,30 @@ fn update_player_animation(
             }
         }
 
+        UnitMovement::Warping {
+            start_x,
+            start_y,
+            target_x,
+            target_y,
+            elapsed_time,
+            duration,
+            exit_x,
+            exit_y,
+        } => {
+            *elapsed_time += delta;
+            let progress = (*elapsed_time / *duration).min(1.0);
+            unit.pixel_x = lerp(*start_x, *target_x, progress);
+            unit.pixel_y = lerp(*start_y, *target_y, progress);
+
+            if progress >= 1.0 {
+                unit.pixel_x = *exit_x;
+                unit.pixel_y = *exit_y;
+                unit.movement = UnitMovement::Idle;
+            } else {
+                player_is_busy = true;
+            }
+        }
+
         UnitMovement::PrePushing {
             start_x,
             start_y,
@@ -212,8 +513,22 @@ fn update_player_animation(
                     let next_p_tx = current_p_tx + dx;
                     let next_p_ty = current_p_ty + dy;
 
-                    let walkable = game.is_walkable(next_box_tx, next_box_ty)
-                        && !game.has_collidable_object_at(next_box_tx, next_box_ty);
+                    let direction = unit.direction;
+                    let walkable = can_enter(
+                        game,
+                        gates,
+                        (current_p_tx, current_p_ty),
+                        (next_p_tx, next_p_ty),
+                        direction,
+                        false,
+                    ) && can_enter(
+                        game,
+                        gates,
+                        (next_p_tx, next_p_ty),
+                        (next_box_tx, next_box_ty),
+                        direction,
+                        true,
+                    );
 
                     if next_box_ty >= 0 && next_box_tx >= 0 {
                         let next_box_idx =
@@ -258,6 +573,14 @@ fn update_player_animation(
             target_y,
             elapsed_time,
             duration,
+        }
+        | UnitMovement::Rewinding {
+            start_x,
+            start_y,
+            target_x,
+            target_y,
+            elapsed_time,
+            duration,
         } => {
             *elapsed_time += delta;
             let progress = (*elapsed_time / *duration).min(1.0);
@@ -282,8 +605,10 @@ fn update_player_animation(
 /// Applies the state changes required to begin a box-pushing animation sequence.
 ///
 /// This function:
-/// 1. Updates `mob_grid` to reflect the box's new position.
-/// 2. Starts the box's `Moving` animation toward its destination.
+/// 1. Updates `mob_grid` to reflect the box's new position (or `filled_holes` if the box
+///    falls into a hole).
+/// 2. Starts the box's `Moving` animation toward its destination (`Warping` if it is
+///    teleported).
 /// 3. Starts the player's `Pushing` animation (with recoil target for post-push).
 ///
 /// # Arguments
@@ -291,38 +616,65 @@ fn update_player_animation(
 /// * `state` - mutable game state to update player, mobs, and grid
 /// * `box_idx` - index of the box in `state.mobs` to be pushed
 /// * `(p_tx, p_ty)` - player's new tile coordinates after moving adjacent to the box
-/// * `(b_tx, b_ty)` - box's new tile coordinates after being pushed
+/// * `landing` - where the box ends up after being pushed
 /// * `(dx, dy)` - direction of the push as tile deltas
 /// * `map_width` - map width in tiles
 /// * `delta` - frame time
+///
+/// # Returns
+///
+/// `true` if the push has been applied, `false` if the box is no longer at `(p_tx, p_ty)`.
 fn apply_push_transition(
     state: &mut State,
     box_idx: usize,
     (p_tx, p_ty): (i32, i32),
-    (b_tx, b_ty): (i32, i32),
+    landing: Landing,
     (dx, dy): (i32, i32),
     map_width: usize,
     delta: f32,
-) {
+) -> bool {
     let old_box_idx = (p_ty as usize) * map_width + (p_tx as usize);
+    let (b_tx, b_ty) = landing.end();
     let new_box_idx = (b_ty as usize) * map_width + (b_tx as usize);
 
     if state.mob_grid[old_box_idx] == Some(box_idx) {
         state.mob_grid[old_box_idx] = None;
-        state.mob_grid[new_box_idx] = Some(box_idx);
+        if landing.sinks {
+            state.filled_holes[new_box_idx] = Some(box_idx);
+        } else {
+            state.mob_grid[new_box_idx] = Some(box_idx);
+        }
 
         let box_unit = &mut state.mobs[box_idx];
         let (bx, by) = (box_unit.pixel_x.round(), box_unit.pixel_y.round());
-        let (offset_x, offset_y) = get_offset(dx, dy, 1.0);
+        let (stop_x, stop_y) = landing.stop;
+        let (offset_x, offset_y) = tile_offset((stop_x - p_tx, stop_y - p_ty));
         let (target_bx, target_by) = (bx + offset_x, by + offset_y);
-
-        box_unit.movement = UnitMovement::Moving {
-            start_x: bx,
-            start_y: by,
-            target_x: target_bx,
-            target_y: target_by,
-            elapsed_time: 0.0 - delta,
-            duration: BOX_MOVE_DURATION,
+        let slid = ((stop_x - p_tx).abs() + (stop_y - p_ty).abs() - 1) as f32;
+        let duration = BOX_MOVE_DURATION + slid * SLIDE_DURATION;
+
+        box_unit.movement = match landing.exit {
+            Some((exit_tx, exit_ty)) => {
+                let (exit_x, exit_y) = tile_offset((exit_tx - stop_x, exit_ty - stop_y));
+                UnitMovement::Warping {
+                    start_x: bx,
+                    start_y: by,
+                    target_x: target_bx,
+                    target_y: target_by,
+                    elapsed_time: 0.0 - delta,
+                    duration,
+                    exit_x: target_bx + exit_x,
+                    exit_y: target_by + exit_y,
+                }
+            }
+            None => UnitMovement::Moving {
+                start_x: bx,
+                start_y: by,
+                target_x: target_bx,
+                target_y: target_by,
+                elapsed_time: 0.0 - delta,
+                duration,
+            },
         };
         box_unit.tile_x = b_tx;
         box_unit.tile_y = b_ty;
@@ -347,7 +699,11 @@ fn apply_push_transition(
         };
         player.tile_x = p_tx;
         player.tile_y = p_ty;
+
+        return true;
     }
+
+    false
 }
 
 /// Initiates the player's recoil animation after completing a push.
@@ -377,10 +733,11 @@ fn apply_post_push_transition(
 
 /// Updates animations for all non-player mobile units (e.g., boxes).
 ///
-/// For each mob in `Moving` state, this function:
+/// For each mob in `Moving` (or `Warping`) state, this function:
 /// - Advances the animation by `delta` seconds.
 /// - Interpolates the pixel position using linear interpolation (`lerp`).
-/// - Sets the mob to `Idle` when the animation completes.
+/// - Sets the mob to `Idle` when the animation completes (moving a warping mob to the
+///   exit of the teleporter).
 ///
 /// # Arguments
 ///
@@ -388,26 +745,41 @@ fn apply_post_push_transition(
 /// * `delta` - time elapsed since the last frame
 fn update_mob_animations(mobs: &mut [Unit], delta: f32) {
     for unit in mobs.iter_mut() {
-        if let UnitMovement::Moving {
-            start_x,
-            start_y,
-            target_x,
-            target_y,
-            elapsed_time,
-            duration,
-        } = &mut unit.movement
-        {
-            *elapsed_time += delta;
-            let progress = (*elapsed_time / *duration).min(1.0);
+        let (start_x, start_y, target_x, target_y, elapsed_time, duration, end) =
+            match &mut unit.movement {
+                UnitMovement::Moving {
+                    start_x,
+                    start_y,
+                    target_x,
+                    target_y,
+                    elapsed_time,
+                    duration,
+                } => (start_x, start_y, target_x, target_y, elapsed_time, duration, None),
+                UnitMovement::Warping {
+                    start_x,
+                    start_y,
+                    target_x,
+                    target_y,
+                    elapsed_time,
+                    duration,
+                    exit_x,
+                    exit_y,
+                } => {
+                    let exit = Some((*exit_x, *exit_y));
+                    (start_x, start_y, target_x, target_y, elapsed_time, duration, exit)
+                }
+                _ => continue,
+            };
 
-            unit.pixel_x = lerp(*start_x, *target_x, progress);
-            unit.pixel_y = lerp(*start_y, *target_y, progress);
+        *elapsed_time += delta;
+        let progress = (*elapsed_time / *duration).min(1.0);
 
-            if progress >= 1.0 {
-                unit.pixel_x = *target_x;
-                unit.pixel_y = *target_y;
-                unit.movement = UnitMovement::Idle;
-            }
+        unit.pixel_x = lerp(*start_x, *target_x, progress);
+        unit.pixel_y = lerp(*start_y, *target_y, progress);
+
+        if progress >= 1.0 {
+            (unit.pixel_x, unit.pixel_y) = end.unwrap_or((*target_x, *target_y));
+            unit.movement = UnitMovement::Idle;
         }
     }
 }
@@ -456,32 +828,156 @@ fn start_pre_push_animation(
 
 /// Initiates a standard walking animation for the player.
 ///
-/// Moves the player by one full tile in the specified direction.
+/// Moves the player by one full tile in the specified direction, plus the tiles slid
+/// across ice (`Warping` to the partner if the player stops on a teleporter).
 /// Updates both the animation state (pixel position over time) and the logical tile position.
 ///
 /// # Arguments
 ///
 /// * `player` - mutable reference to the player's unit
-/// * `(dx, dy)` - movement direction as tile deltas
-/// * `(next_tx, next_ty)` - destination tile coordinates
-fn start_walking_animation(
-    player: &mut Unit,
-    (dx, dy): (i32, i32),
-    (next_tx, next_ty): (i32, i32),
-) {
+/// * `landing` - where the player ends up
+fn start_walking_animation(player: &mut Unit, landing: Landing) {
     let (px, py) = (player.pixel_x, player.pixel_y);
-    let (offset_x, offset_y) = get_offset(dx, dy, 1.0);
+    let (stop_x, stop_y) = landing.stop;
+    let (dx, dy) = (stop_x - player.tile_x, stop_y - player.tile_y);
+    let (offset_x, offset_y) = tile_offset((dx, dy));
+    let (target_x, target_y) = (px + offset_x, py + offset_y);
+    let duration = MOVE_DURATION + (dx.abs() + dy.abs() - 1) as f32 * SLIDE_DURATION;
+
+    player.movement = match landing.exit {
+        Some((exit_tx, exit_ty)) => {
+            let (exit_x, exit_y) = tile_offset((exit_tx - stop_x, exit_ty - stop_y));
+            UnitMovement::Warping {
+                start_x: px,
+                start_y: py,
+                target_x,
+                target_y,
+                elapsed_time: 0.0,
+                duration,
+                exit_x: target_x + exit_x,
+                exit_y: target_y + exit_y,
+            }
+        }
+        None => UnitMovement::Moving {
+            start_x: px,
+            start_y: py,
+            target_x,
+            target_y,
+            elapsed_time: 0.0,
+            duration,
+        },
+    };
+    (player.tile_x, player.tile_y) = landing.end();
+}
 
-    player.movement = UnitMovement::Moving {
-        start_x: px,
-        start_y: py,
-        target_x: px + offset_x,
-        target_y: py + offset_y,
-        elapsed_time: 0.0,
-        duration: MOVE_DURATION,
+/// Checks whether a unit went from one tile to another in a straight line: a single step,
+/// or a slide across ice in the direction of the step (and not through a teleporter).
+///
+/// # Arguments
+///
+/// * `game` - the level map
+/// * `from`, `to` - tile coordinates before and after the step
+/// * `direction` - direction of the step
+fn moved_straight(game: &GameMap, from: (i32, i32), to: (i32, i32), direction: Direction) -> bool {
+    let (dx, dy) = get_dir_delta(direction);
+    let mut cell = (from.0 + dx, from.1 + dy);
+    while cell != to {
+        // leaving the map ends the walk as well
+        if game.tile_type_at(cell.0, cell.1) != Some(&TileType::Ice) {
+            return false;
+        }
+        cell = (cell.0 + dx, cell.1 + dy);
+    }
+    true
+}
+
+/// Builds the movement that takes a unit back from one tile to another.
+///
+/// # Arguments
+///
+/// * `unit` - the unit at rest on `to`
+/// * `from`, `to` - tile coordinates to return to and to leave
+/// * `straight` - whether the unit reached `to` in a straight line (see [`moved_straight`])
+/// * `duration` - length of the animation
+fn rewind_movement(
+    unit: &Unit,
+    from: (i32, i32),
+    to: (i32, i32),
+    straight: bool,
+    duration: f32,
+) -> UnitMovement {
+    let (x, y) = (unit.pixel_x, unit.pixel_y);
+    let (back_x, back_y) = tile_offset((from.0 - to.0, from.1 - to.1));
+
+    if straight {
+        UnitMovement::Moving {
+            start_x: x,
+            start_y: y,
+            target_x: x + back_x,
+            target_y: y + back_y,
+            elapsed_time: 0.0,
+            duration,
+        }
+    } else {
+        UnitMovement::Warping {
+            start_x: x,
+            start_y: y,
+            target_x: x,
+            target_y: y,
+            elapsed_time: 0.0,
+            duration,
+            exit_x: x + back_x,
+            exit_y: y + back_y,
+        }
+    }
+}
+
+/// Initiates the animation that reverts a committed step.
+///
+/// The player walks backwards to the tile the step started from. If the step pushed a box,
+/// the box slides back to its previous tile alongside the player over the push duration,
+/// so the push is played in reverse instead of snapping (a box that fell into a hole
+/// climbs out of it again). Slides are reverted along the ice they crossed; a unit that
+/// went through a teleporter waits on its exit and reappears on the tile it came from.
+/// Tile coordinates and `mob_grid` are restored immediately, as for any other movement.
+///
+/// # Arguments
+///
+/// * `state` - mutable game state to update player, mobs, and grid
+/// * `game` - the level map
+/// * `step` - the step being undone
+fn start_undo_animation(state: &mut State, game: &GameMap, step: &StepRecord) {
+    let duration = match step.push {
+        Some(PushRecord { box_idx, from, to }) => {
+            let hole = (to.1 * state.grid_width + to.0) as usize;
+            if state.filled_holes.get(hole) == Some(&Some(box_idx)) {
+                state.filled_holes[hole] = None;
+            }
+
+            let box_unit = &mut state.mobs[box_idx];
+            let straight = moved_straight(game, from, to, step.direction);
+            box_unit.movement = rewind_movement(box_unit, from, to, straight, PUSH_DURATION);
+            box_unit.tile_x = from.0;
+            box_unit.tile_y = from.1;
+            state.update_mob_pos(box_idx, to.0, to.1, from.0, from.1);
+
+            PUSH_DURATION
+        }
+        None => MOVE_DURATION,
+    };
+
+    let player = &mut state.player.unit;
+    let (from, to) = (step.player_from, step.player_to);
+    let straight = moved_straight(game, from, to, step.direction);
+    player.movement = match rewind_movement(player, from, to, straight, duration) {
+        UnitMovement::Moving { start_x, start_y, target_x, target_y, elapsed_time, duration } => {
+            UnitMovement::Rewinding { start_x, start_y, target_x, target_y, elapsed_time, duration }
+        }
+        warping => warping,
     };
-    player.tile_x = next_tx;
-    player.tile_y = next_ty;
+    player.direction = step.direction;
+    player.tile_x = step.player_from.0;
+    player.tile_y = step.player_from.1;
 }
 
 /// Advances the game simulation by one time step.
@@ -489,18 +985,30 @@ fn start_walking_animation(
 /// This is the core game loop function that:
 /// 1. Updates all animations (player and mobs).
 /// 2. Processes player input (if not busy with animations).
-/// 3. Handles movement and box-pushing logic with collision detection.
+/// 3. Handles undo/redo requests.
+/// 4. Handles movement and box-pushing logic with collision detection.
+///
+/// Units stepping onto mechanism tiles follow their rules (see [`TileType::is_mechanism`]):
+/// a walking player and a pushed box slide across ice, a box pushed into an open hole
+/// fills it, a unit stopping on a teleporter reappears on its partner, one-way arrows are
+/// crossed in their direction only, and doors are open while one of their plates is
+/// pressed. The pushing player itself neither slides nor teleports.
+///
+/// Every committed step (a walk or a single push of a chain) is recorded in `history`.
+/// After every committed or undone push the box configuration is re-checked for deadlocks.
 ///
 /// # Arguments
 ///
 /// * `curr_state` - mutable game state (player, mobs, grid)
-/// * `input_state` - current player input (directional buttons)
+/// * `input_state` - current player input (directional buttons, undo/redo)
 /// * `delta` - time elapsed since last frame (for animation timing)
 /// * `game` - static game map (walkability, collidable objects)
+/// * `history` - move history of the current level
+/// * `deadlocks` - deadlock detector of the current level
 ///
 /// # Returns
 ///
-/// * `Some(0)` if a special key combination is pressed.
+/// * `Some(0)` if the menu action or the left + right combination is pressed.
 /// * `None` otherwise (successful step with or without movement).
 ///
 /// # Animation States Handled
@@ -510,13 +1018,17 @@ fn start_walking_animation(
 /// - `PrePushing`: approaching a box to push.
 /// - `Pushing`: actively pushing a box (supports chain-pushes).
 /// - `PostPushing`: recoiling after a push.
+/// - `Rewinding`: walking back while a step is undone.
 pub fn make_step(
     curr_state: &mut State,
     input_state: &InputSnapshot,
     delta: f32,
-    game: &ferari::assets::GameMap,
+    game: &GameMap,
+    history: &mut History,
+    deadlocks: &mut DeadlockDetector,
 ) -> Option<u32> {
     let map_width = game.size[0] as usize;
+    let mut gates = Gates::new(game, curr_state);
 
     // ============================================
     // ANIMATION UPDATE (player + all mobs)
@@ -530,21 +1042,38 @@ pub fn make_step(
             input_state,
             &curr_state.mob_grid,
             game,
+            &gates,
             map_width,
         );
 
     if let Some((box_idx, p_tx, p_ty, b_tx, b_ty, dx, dy)) = transition_to_push {
         player_is_busy = true;
 
-        apply_push_transition(
+        // the player steps off its cell as the push starts, which may release a plate
+        let direction = curr_state.player.unit.direction;
+        let push_gates = Gates::with_player_at(game, curr_state, (p_tx, p_ty));
+        let landing = find_landing(game, &push_gates, curr_state, (b_tx, b_ty), direction, true);
+        let pushed = apply_push_transition(
             curr_state,
             box_idx,
             (p_tx, p_ty),
-            (b_tx, b_ty),
+            landing,
             (dx, dy),
             map_width,
             delta,
         );
+
+        if pushed {
+            history.record(StepRecord {
+                player_from: (p_tx - dx, p_ty - dy),
+                player_to: (p_tx, p_ty),
+                direction,
+                push: Some(PushRecord { box_idx, from: (p_tx, p_ty), to: landing.end() }),
+            });
+            deadlocks.update(curr_state);
+            // the rest of the frame sees the doors and holes as the push left them
+            gates = Gates::new(game, curr_state);
+        }
     }
 
     if let Some((sx, sy, tx, ty)) = transition_to_post {
@@ -556,11 +1085,16 @@ pub fn make_step(
     // Mob (box) update
     update_mob_animations(&mut curr_state.mobs, delta);
 
+    // A box sliding across ice moves on after the push; the next step waits for it
+    if curr_state.mobs.iter().any(|mob| !matches!(mob.movement, UnitMovement::Idle)) {
+        player_is_busy = true;
+    }
+
     // ============================================
     // INPUT PROCESSING
     // ============================================
 
-    if input_state.left && input_state.right {
+    if back_pressed(input_state) {
         return Some(0);
     }
 
@@ -569,10 +1103,25 @@ pub fn make_step(
         return None;
     }
 
+    if input_state.undo {
+        if let Some(step) = history.undo() {
+            start_undo_animation(curr_state, game, &step);
+            if step.push.is_some() {
+                deadlocks.update(curr_state);
+            }
+        }
+        return None;
+    }
+
     let (mut dx, mut dy) = (0, 0);
     let mut new_dir = curr_state.player.unit.direction;
 
-    if input_state.right {
+    if input_state.redo {
+        if let Some(step) = history.peek_redo() {
+            (dx, dy) = get_dir_delta(step.direction);
+            new_dir = step.direction;
+        }
+    } else if input_state.right {
         dx = 1;
         dy = 0;
         new_dir = Direction::SE;
@@ -608,8 +1157,8 @@ pub fn make_step(
     // COLLISION AND MOVEMENT LOGIC
     // ============================================
 
-    // Checking map boundaries and static walls
-    if !game.is_walkable(next_tx, next_ty) || game.has_collidable_object_at(next_tx, next_ty) {
+    // Checking map boundaries, static walls, height differences and mechanisms
+    if !can_enter(game, &gates, (p_tx, p_ty), (next_tx, next_ty), new_dir, false) {
         return None;
     }
 
@@ -624,9 +1173,7 @@ pub fn make_step(
         let behind_ty = next_ty + dy;
 
         // Checking the statics behind the box
-        if !game.is_walkable(behind_tx, behind_ty)
-            || game.has_collidable_object_at(behind_tx, behind_ty)
-        {
+        if !can_enter(game, &gates, (next_tx, next_ty), (behind_tx, behind_ty), new_dir, true) {
             return None;
         }
 
@@ -645,12 +1192,68 @@ pub fn make_step(
             (behind_tx, behind_ty),
         );
     } else {
-        start_walking_animation(&mut curr_state.player.unit, (dx, dy), (next_tx, next_ty));
+        let landing = find_landing(game, &gates, curr_state, (next_tx, next_ty), new_dir, false);
+        start_walking_animation(&mut curr_state.player.unit, landing);
+        history.record(StepRecord {
+            player_from: (p_tx, p_ty),
+            player_to: landing.end(),
+            direction: new_dir,
+            push: None,
+        });
     }
 
     None
 }
 
+/// Plays the running animations of the player and the boxes to their end (instant moves).
+///
+/// Every phase is advanced by its remaining time through [`make_step`] without input, so
+/// an approach still turns into a push (recorded in `history`) and the recoil and the
+/// slide of the box follow; no new step is started.
+///
+/// # Arguments
+///
+/// * `curr_state` - mutable game state (player, mobs, grid)
+/// * `game` - static game map
+/// * `history` - move history of the current level
+/// * `deadlocks` - deadlock detector of the current level
+pub fn finish_animations(
+    curr_state: &mut State,
+    game: &GameMap,
+    history: &mut History,
+    deadlocks: &mut DeadlockDetector,
+) {
+    let longest_remaining = |state: &State| {
+        let player = std::iter::once(&state.player.unit);
+        player.chain(&state.mobs).filter_map(remaining_time).reduce(f32::max)
+    };
+    while let Some(remaining) = longest_remaining(curr_state) {
+        make_step(curr_state, &InputSnapshot::default(), remaining, game, history, deadlocks);
+    }
+}
+
+/// Returns the time left until the current animation phase of a unit ends.
+///
+/// # Arguments
+///
+/// * `unit` - the player or a box
+///
+/// # Returns
+///
+/// The remaining time in seconds (at least `0`), `None` if the unit is idle.
+fn remaining_time(unit: &Unit) -> Option<f32> {
+    let (elapsed_time, duration) = match unit.movement {
+        UnitMovement::Idle => return None,
+        UnitMovement::Moving { elapsed_time, duration, .. }
+        | UnitMovement::Pushing { elapsed_time, duration, .. }
+        | UnitMovement::PrePushing { elapsed_time, duration, .. }
+        | UnitMovement::PostPushing { elapsed_time, duration, .. }
+        | UnitMovement::Warping { elapsed_time, duration, .. }
+        | UnitMovement::Rewinding { elapsed_time, duration, .. } => (elapsed_time, duration),
+    };
+    Some((duration - elapsed_time).max(0.0))
+}
+
 #[cfg(test)]
 mod offset_dir_tests {
     use super::*;
@@ -691,6 +1294,19 @@ mod offset_dir_tests {
         assert_eq!(get_dir_delta(Direction::SW), (0, 1));
     }
 
+    #[test]
+    fn test_input_for_direction_matches_dir_delta() {
+        for direction in [Direction::SE, Direction::NW, Direction::NE, Direction::SW] {
+            let input = input_for_direction(direction);
+            let (dx, dy) = get_dir_delta(direction);
+
+            assert_eq!(input.right, (dx, dy) == (1, 0));
+            assert_eq!(input.left, (dx, dy) == (-1, 0));
+            assert_eq!(input.up, (dx, dy) == (0, -1));
+            assert_eq!(input.down, (dx, dy) == (0, 1));
+        }
+    }
+
     #[test]
     fn test_get_dir_delta_all_directions() {
         let test_cases = vec![
@@ -721,6 +1337,7 @@ mod update_mod_tests {
             y_speed: 10.0,
             movement,
             direction: Direction::SE,
+            color: None,
         }
     }
 
@@ -760,6 +1377,7 @@ mod pre_push_tests {
             y_speed: 10.0,
             movement: UnitMovement::Idle,
             direction: Direction::SE,
+            color: None,
         };
         let original_x = player.pixel_x;
         let original_y = player.pixel_y;
@@ -828,6 +1446,7 @@ mod post_push_tests {
             y_speed: 10.0,
             movement: UnitMovement::Idle,
             direction: Direction::SE,
+            color: None,
         };
 
         let start = (10.0, 20.0);
@@ -881,6 +1500,7 @@ mod push_tests {
                     direction: Direction::SE,
                     x_speed: 10.0,
                     y_speed: 10.0,
+                    color: None,
                 },
             },
             mobs: vec![
@@ -893,6 +1513,7 @@ mod push_tests {
                     direction: Direction::SE,
                     x_speed: 10.0,
                     y_speed: 10.0,
+                    color: None,
                 },
                 Unit {
                     pixel_x: 200.0,
@@ -903,6 +1524,7 @@ mod push_tests {
                     direction: Direction::SE,
                     x_speed: 10.0,
                     y_speed: 10.0,
+                    color: None,
                 },
             ],
             mob_grid: vec![None; map_width * map_width],
@@ -925,7 +1547,7 @@ mod push_tests {
             &mut state,
             box_idx,
             player_tile,
-            box_tile,
+            Landing::at(box_tile),
             direction,
             map_width,
             delta,
@@ -1003,7 +1625,6 @@ mod walking_tests {
     #[test]
     fn test_start_walking_animation() {
         let next_tile = (6, 5);
-        let direction = (1, 0);
 
         let mut player = Unit {
             pixel_x: 100.0,
@@ -1014,9 +1635,10 @@ mod walking_tests {
             y_speed: 10.0,
             movement: UnitMovement::Idle,
             direction: Direction::SE,
+            color: None,
         };
 
-        start_walking_animation(&mut player, direction, next_tile);
+        start_walking_animation(&mut player, Landing::at(next_tile));
 
         match &player.movement {
             UnitMovement::Moving {
@@ -1051,7 +1673,6 @@ mod walking_tests {
 #[cfg(test)]
 mod update_player_tests {
     use super::*;
-    use ferari::assets::GameMap;
 
     fn create_test_map() -> GameMap {
         GameMap::load("../game_levels/level1.json").unwrap()
@@ -1067,6 +1688,7 @@ mod update_player_tests {
             y_speed: 10.0,
             movement,
             direction: Direction::SW,
+            color: None,
         }
     }
 
@@ -1089,8 +1711,14 @@ mod update_player_tests {
         });
 
         let delta = 0.3;
-        let input_state =
-            InputSnapshot { up: false, left: false, down: true, right: false, escape: false };
+        let input_state = InputSnapshot {
+            up: false,
+            left: false,
+            down: true,
+            right: false,
+            quit: false,
+            ..Default::default()
+        };
 
         let game_map = create_test_map();
         let map_width = game_map.size[0] as usize;
@@ -1102,6 +1730,7 @@ mod update_player_tests {
             &input_state,
             &mob_grid,
             &game_map,
+            &Gates::new(&game_map, &State::new(&game_map)),
             map_width,
         );
 
@@ -1140,8 +1769,14 @@ mod update_player_tests {
         });
 
         let delta = 0.2;
-        let input_state =
-            InputSnapshot { up: false, left: false, down: true, right: false, escape: false };
+        let input_state = InputSnapshot {
+            up: false,
+            left: false,
+            down: true,
+            right: false,
+            quit: false,
+            ..Default::default()
+        };
 
         let game_map = create_test_map();
         let map_width = game_map.size[0] as usize;
@@ -1156,6 +1791,7 @@ mod update_player_tests {
             &input_state,
             &mob_grid,
             &game_map,
+            &Gates::new(&game_map, &State::new(&game_map)),
             map_width,
         );
 
@@ -1195,10 +1831,23 @@ mod make_step_tests {
         let game_map = create_test_map();
         let mut state = State::new(&game_map);
 
-        let input_state =
-            InputSnapshot { up: false, left: false, down: false, right: true, escape: false };
+        let input_state = InputSnapshot {
+            up: false,
+            left: false,
+            down: false,
+            right: true,
+            quit: false,
+            ..Default::default()
+        };
         let delta = 0.016;
-        let result = make_step(&mut state, &input_state, delta, &game_map);
+        let result = make_step(
+            &mut state,
+            &input_state,
+            delta,
+            &game_map,
+            &mut History::new(),
+            &mut DeadlockDetector::default(),
+        );
         assert!(result.is_none());
 
         match &state.player.unit.movement {
@@ -1220,10 +1869,23 @@ mod make_step_tests {
         let box_front_idx = 1 * map_width + 2;
         state.mob_grid[box_front_idx] = Some(0);
 
-        let input_state =
-            InputSnapshot { up: false, left: true, down: false, right: false, escape: false };
+        let input_state = InputSnapshot {
+            up: false,
+            left: true,
+            down: false,
+            right: false,
+            quit: false,
+            ..Default::default()
+        };
         let delta = 0.016;
-        let result = make_step(&mut state, &input_state, delta, &game_map);
+        let result = make_step(
+            &mut state,
+            &input_state,
+            delta,
+            &game_map,
+            &mut History::new(),
+            &mut DeadlockDetector::default(),
+        );
         assert!(result.is_none());
 
         match &state.player.unit.movement {
@@ -1235,4 +1897,402 @@ mod make_step_tests {
         assert_eq!(state.player.unit.tile_x, 2);
         assert_eq!(state.player.unit.tile_y, 3);
     }
+
+    #[test]
+    fn test_make_step_cannot_climb_without_ramp() {
+        let mut game_map = create_test_map();
+        let map_width = game_map.size[0] as usize;
+        game_map.height_map[3 * map_width + 3] = 1;
+        let mut state = State::new(&game_map);
+
+        let input_state = input_for_direction(Direction::SE);
+        make_step(
+            &mut state,
+            &input_state,
+            0.016,
+            &game_map,
+            &mut History::new(),
+            &mut DeadlockDetector::default(),
+        );
+        assert!(matches!(state.player.unit.movement, UnitMovement::Idle));
+        assert_eq!((state.player.unit.tile_x, state.player.unit.tile_y), (2, 3));
+        assert!(!climb_map(&game_map)[3 * map_width + 2][Direction::SE as usize]);
+    }
+}
+
+#[cfg(test)]
+mod undo_tests {
+    use super::*;
+    use ferari::assets::GameMap;
+
+    fn create_test_map() -> GameMap {
+        GameMap::load("../game_levels/level2.json").unwrap()
+    }
+
+    fn input(left: bool, undo: bool, redo: bool) -> InputSnapshot {
+        InputSnapshot { left, undo, redo, ..Default::default() }
+    }
+
+    fn run(
+        state: &mut State,
+        input_state: &InputSnapshot,
+        game_map: &GameMap,
+        history: &mut History,
+        deadlocks: &mut DeadlockDetector,
+    ) {
+        for _ in 0..60 {
+            make_step(state, input_state, 0.05, game_map, history, deadlocks);
+        }
+    }
+
+    fn positions(state: &State) -> Vec<(i32, i32, f32, f32)> {
+        let player = &state.player.unit;
+        let mut positions = vec![(player.tile_x, player.tile_y, player.pixel_x, player.pixel_y)];
+        positions.extend(state.mobs.iter().map(|m| (m.tile_x, m.tile_y, m.pixel_x, m.pixel_y)));
+        positions
+    }
+
+    #[test]
+    fn test_undo_walk_restores_player() {
+        let game_map = create_test_map();
+        let mut state = State::new(&game_map);
+        let mut history = History::new();
+        let mut deadlocks = DeadlockDetector::new(&game_map);
+        let initial = positions(&state);
+
+        let walk_right = InputSnapshot { right: true, ..input(false, false, false) };
+        make_step(&mut state, &walk_right, 0.016, &game_map, &mut history, &mut deadlocks);
+        run(&mut state, &input(false, false, false), &game_map, &mut history, &mut deadlocks);
+        assert_eq!(history.moves(), 1);
+        assert_ne!(positions(&state), initial);
+
+        make_step(
+            &mut state,
+            &input(false, true, false),
+            0.016,
+            &game_map,
+            &mut history,
+            &mut deadlocks,
+        );
+        assert!(matches!(state.player.unit.movement, UnitMovement::Rewinding { .. }));
+
+        run(&mut state, &input(false, false, false), &game_map, &mut history, &mut deadlocks);
+        assert!(matches!(state.player.unit.movement, UnitMovement::Idle));
+        assert_eq!(positions(&state), initial);
+        assert_eq!(history.moves(), 0);
+    }
+
+    #[test]
+    fn test_undo_and_redo_push() {
+        let game_map = create_test_map();
+        let mut state = State::new(&game_map);
+        let mut history = History::new();
+        let mut deadlocks = DeadlockDetector::new(&game_map);
+        let initial = positions(&state);
+        let initial_grid = state.mob_grid.clone();
+
+        run(&mut state, &input(true, false, false), &game_map, &mut history, &mut deadlocks);
+        run(&mut state, &input(false, false, false), &game_map, &mut history, &mut deadlocks);
+        assert_eq!(history.moves(), 1);
+        assert_eq!(history.pushes(), 1);
+        assert_eq!(state.get_mob_at(0, 3).map(|i| state.mobs[i].tile_x), Some(0));
+        let pushed = positions(&state);
+        let pushed_grid = state.mob_grid.clone();
+        // the box cannot leave the (0, 3) corner pocket anymore
+        assert!(deadlocks.is_dead_box(0, 3));
+        assert!(deadlocks.take_fresh_deadlock());
+
+        make_step(
+            &mut state,
+            &input(false, true, false),
+            0.016,
+            &game_map,
+            &mut history,
+            &mut deadlocks,
+        );
+        assert!(matches!(state.player.unit.movement, UnitMovement::Rewinding { .. }));
+        run(&mut state, &input(false, false, false), &game_map, &mut history, &mut deadlocks);
+        assert_eq!(positions(&state), initial);
+        assert_eq!(state.mob_grid, initial_grid);
+        assert!(!deadlocks.is_dead_box(0, 3));
+
+        run(&mut state, &input(false, false, true), &game_map, &mut history, &mut deadlocks);
+        run(&mut state, &input(false, false, false), &game_map, &mut history, &mut deadlocks);
+        assert_eq!(positions(&state), pushed);
+        assert_eq!(state.mob_grid, pushed_grid);
+        assert_eq!(history.pushes(), 1);
+        assert_eq!(history.peek_redo(), None);
+    }
+}
+
+#[cfg(test)]
+mod mechanism_tests {
+    use super::*;
+    use ferari::assets::GameMap;
+
+    /// Corridor with the player at (1, 1) and a box at (1, 2) next to its target.
+    fn create_test_map() -> GameMap {
+        GameMap::from_xsb("mechanisms", &["#########", "#@      #", "#$.     #", "#########"])
+            .unwrap()
+    }
+
+    fn set_tile(game_map: &mut GameMap, (x, y): (i32, i32), tile_type: TileType) {
+        let idx = (y * game_map.size[0] as i32 + x) as usize;
+        game_map.walk_map[idx] = tile_type;
+    }
+
+    fn play(state: &mut State, game_map: &GameMap, history: &mut History, input: InputSnapshot) {
+        let mut deadlocks = DeadlockDetector::default();
+        make_step(state, &input, 0.016, game_map, history, &mut deadlocks);
+        for _ in 0..60 {
+            make_step(state, &InputSnapshot::default(), 0.05, game_map, history, &mut deadlocks);
+        }
+    }
+
+    fn step(state: &mut State, game_map: &GameMap, history: &mut History, dir: Direction) {
+        play(state, game_map, history, input_for_direction(dir));
+    }
+
+    fn player_tile(state: &State) -> (i32, i32) {
+        (state.player.unit.tile_x, state.player.unit.tile_y)
+    }
+
+    #[test]
+    fn test_player_slides_across_ice() {
+        let mut game_map = create_test_map();
+        set_tile(&mut game_map, (2, 1), TileType::Ice);
+        set_tile(&mut game_map, (3, 1), TileType::Ice);
+        let mut state = State::new(&game_map);
+        let mut history = History::new();
+
+        step(&mut state, &game_map, &mut history, Direction::SE);
+        assert!(matches!(state.player.unit.movement, UnitMovement::Idle));
+        assert_eq!(player_tile(&state), (4, 1));
+        assert_eq!(history.moves(), 1);
+    }
+
+    #[test]
+    fn test_pushed_box_slides_to_first_blocker() {
+        let rows = ["#########", "#@$   $.#", "#      .#", "#########"];
+        let mut game_map = GameMap::from_xsb("ice", &rows).unwrap();
+        for x in 3..6 {
+            set_tile(&mut game_map, (x, 1), TileType::Ice);
+        }
+        let mut state = State::new(&game_map);
+        let mut history = History::new();
+        let box_idx = state.get_mob_at(2, 1).unwrap();
+
+        step(&mut state, &game_map, &mut history, Direction::SE);
+        assert!(matches!(state.mobs[box_idx].movement, UnitMovement::Idle));
+        assert_eq!(state.get_mob_at(5, 1), Some(box_idx));
+        assert_eq!(state.get_mob_at(2, 1), None);
+        assert_eq!(player_tile(&state), (2, 1));
+        assert_eq!(history.pushes(), 1);
+    }
+
+    #[test]
+    fn test_undo_waits_for_a_long_slide() {
+        let rows = ["############", "#@$        #", "#         .#", "############"];
+        let mut game_map = GameMap::from_xsb("ice", &rows).unwrap();
+        for x in 3..10 {
+            set_tile(&mut game_map, (x, 1), TileType::Ice);
+        }
+        let mut state = State::new(&game_map);
+        let mut history = History::new();
+        let mut deadlocks = DeadlockDetector::default();
+        let box_idx = state.get_mob_at(2, 1).unwrap();
+        let start = (state.mobs[box_idx].pixel_x, state.mobs[box_idx].pixel_y);
+
+        // undo is held from the start, so it fires as soon as the step is over
+        let right = input_for_direction(Direction::SE);
+        make_step(&mut state, &right, 0.016, &game_map, &mut history, &mut deadlocks);
+        let undo = InputSnapshot { undo: true, ..Default::default() };
+        let mut pushed = false;
+        for _ in 0..100 {
+            make_step(&mut state, &undo, 0.05, &game_map, &mut history, &mut deadlocks);
+            pushed |= history.pushes() > 0;
+            if pushed && history.moves() == 0 {
+                break;
+            }
+        }
+        assert!(pushed && history.moves() == 0);
+        play(&mut state, &game_map, &mut history, InputSnapshot::default());
+
+        let box_unit = &state.mobs[box_idx];
+        assert_eq!(state.get_mob_at(2, 1), Some(box_idx));
+        assert!((box_unit.pixel_x - start.0).abs() < 0.01);
+        assert!((box_unit.pixel_y - start.1).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_finish_animations_plays_a_push_through() {
+        let rows = ["##########", "#@$      #", "#        #", "#       .#", "##########"];
+        let mut game_map = GameMap::from_xsb("ice", &rows).unwrap();
+        for x in 3..7 {
+            set_tile(&mut game_map, (x, 1), TileType::Ice);
+        }
+        let mut state = State::new(&game_map);
+        let mut history = History::new();
+        let mut deadlocks = DeadlockDetector::default();
+        let box_idx = state.get_mob_at(2, 1).unwrap();
+
+        let right = input_for_direction(Direction::SE);
+        make_step(&mut state, &right, 0.016, &game_map, &mut history, &mut deadlocks);
+        finish_animations(&mut state, &game_map, &mut history, &mut deadlocks);
+
+        assert!(matches!(state.player.unit.movement, UnitMovement::Idle));
+        assert!(matches!(state.mobs[box_idx].movement, UnitMovement::Idle));
+        assert_eq!((history.moves(), history.pushes()), (1, 1));
+        assert_eq!(state.get_mob_at(7, 1), Some(box_idx));
+        assert_eq!(player_tile(&state), (2, 1));
+    }
+
+    #[test]
+    fn test_pushed_box_lands_on_partner_teleporter() {
+        let rows = ["#########", "#@$     #", "#      .#", "#########"];
+        let mut game_map = GameMap::from_xsb("teleporter", &rows).unwrap();
+        set_tile(&mut game_map, (3, 1), TileType::Teleporter(1));
+        set_tile(&mut game_map, (6, 1), TileType::Teleporter(1));
+        let mut state = State::new(&game_map);
+        let mut history = History::new();
+        let box_idx = state.get_mob_at(2, 1).unwrap();
+
+        let start = (state.mobs[box_idx].pixel_x, state.mobs[box_idx].pixel_y);
+
+        step(&mut state, &game_map, &mut history, Direction::SE);
+        assert!(matches!(state.mobs[box_idx].movement, UnitMovement::Idle));
+        assert_eq!(state.get_mob_at(6, 1), Some(box_idx));
+        assert_eq!(state.get_mob_at(3, 1), None);
+        assert_eq!(player_tile(&state), (2, 1));
+
+        // the undone box goes back through the teleporter instead of across the floor
+        let undo = InputSnapshot { undo: true, ..Default::default() };
+        let mut deadlocks = DeadlockDetector::default();
+        make_step(&mut state, &undo, 0.016, &game_map, &mut history, &mut deadlocks);
+        assert!(matches!(state.mobs[box_idx].movement, UnitMovement::Warping { .. }));
+        play(&mut state, &game_map, &mut history, InputSnapshot::default());
+        assert_eq!(state.get_mob_at(2, 1), Some(box_idx));
+        assert!((state.mobs[box_idx].pixel_x - start.0).abs() < 0.01);
+        assert!((state.mobs[box_idx].pixel_y - start.1).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_pushed_box_stays_on_teleporter_with_occupied_exit() {
+        let rows = ["#########", "#@$   $ #", "#     ..#", "#########"];
+        let mut game_map = GameMap::from_xsb("teleporter", &rows).unwrap();
+        set_tile(&mut game_map, (3, 1), TileType::Teleporter(1));
+        set_tile(&mut game_map, (6, 1), TileType::Teleporter(1));
+        let mut state = State::new(&game_map);
+        let mut history = History::new();
+        let box_idx = state.get_mob_at(2, 1).unwrap();
+        let blocker = state.get_mob_at(6, 1).unwrap();
+
+        step(&mut state, &game_map, &mut history, Direction::SE);
+        assert_eq!(state.get_mob_at(3, 1), Some(box_idx));
+        assert_eq!(state.get_mob_at(6, 1), Some(blocker));
+    }
+
+    #[test]
+    fn test_one_way_blocks_other_directions() {
+        let mut game_map = create_test_map();
+        set_tile(&mut game_map, (2, 1), TileType::OneWay(Direction::NW));
+        let mut state = State::new(&game_map);
+        let mut history = History::new();
+
+        step(&mut state, &game_map, &mut history, Direction::SE);
+        assert_eq!(player_tile(&state), (1, 1));
+
+        set_tile(&mut game_map, (2, 1), TileType::OneWay(Direction::SE));
+        step(&mut state, &game_map, &mut history, Direction::SE);
+        assert_eq!(player_tile(&state), (2, 1));
+        step(&mut state, &game_map, &mut history, Direction::NW);
+        assert_eq!(player_tile(&state), (2, 1));
+    }
+
+    #[test]
+    fn test_player_cannot_enter_open_hole() {
+        let mut game_map = create_test_map();
+        set_tile(&mut game_map, (2, 1), TileType::Hole);
+        let mut state = State::new(&game_map);
+
+        step(&mut state, &game_map, &mut History::new(), Direction::SE);
+        assert_eq!(player_tile(&state), (1, 1));
+    }
+
+    #[test]
+    fn test_box_fills_hole_and_undo_empties_it() {
+        let mut game_map = GameMap::from_xsb("hole", &["#######", "#@$ $.#", "#######"]).unwrap();
+        set_tile(&mut game_map, (3, 1), TileType::Hole);
+        let mut state = State::new(&game_map);
+        let mut history = History::new();
+        let box_idx = state.get_mob_at(2, 1).unwrap();
+        let hole = game_map.size[0] as usize + 3;
+
+        step(&mut state, &game_map, &mut history, Direction::SE);
+        assert_eq!(state.filled_holes[hole], Some(box_idx));
+        assert_eq!(state.get_mob_at(3, 1), None);
+        assert!(!Gates::new(&game_map, &state).is_open_hole(3, 1));
+
+        // the filled hole is floor now
+        step(&mut state, &game_map, &mut history, Direction::SE);
+        assert_eq!(player_tile(&state), (3, 1));
+
+        let undo = InputSnapshot { undo: true, ..Default::default() };
+        play(&mut state, &game_map, &mut history, undo);
+        play(&mut state, &game_map, &mut history, undo);
+        assert_eq!(player_tile(&state), (1, 1));
+        assert_eq!(state.filled_holes[hole], None);
+        assert_eq!(state.get_mob_at(2, 1), Some(box_idx));
+    }
+
+    #[test]
+    fn test_teleporter_moves_player_to_partner() {
+        let mut game_map = create_test_map();
+        set_tile(&mut game_map, (2, 1), TileType::Teleporter(1));
+        set_tile(&mut game_map, (6, 1), TileType::Teleporter(1));
+        let mut state = State::new(&game_map);
+
+        step(&mut state, &game_map, &mut History::new(), Direction::SE);
+        assert!(matches!(state.player.unit.movement, UnitMovement::Idle));
+        assert_eq!(player_tile(&state), (6, 1));
+    }
+
+    #[test]
+    fn test_door_opens_while_plate_is_pressed() {
+        let mut game_map = create_test_map();
+        set_tile(&mut game_map, (2, 1), TileType::Door(1));
+        set_tile(&mut game_map, (4, 2), TileType::Plate(1));
+        let mut state = State::new(&game_map);
+        let mut history = History::new();
+
+        assert!(Gates::new(&game_map, &state).is_closed(2, 1));
+        step(&mut state, &game_map, &mut history, Direction::SE);
+        assert_eq!(player_tile(&state), (1, 1));
+
+        // the box already stands on the plate
+        set_tile(&mut game_map, (4, 2), TileType::Empty);
+        set_tile(&mut game_map, (1, 2), TileType::Plate(1));
+        assert!(!Gates::new(&game_map, &state).is_closed(2, 1));
+        step(&mut state, &game_map, &mut history, Direction::SE);
+        assert_eq!(player_tile(&state), (2, 1));
+    }
+
+    #[test]
+    fn test_door_closes_as_the_player_pushes_off_its_plate() {
+        let rows = ["#########", "#@$    .#", "#########"];
+        let mut game_map = GameMap::from_xsb("door", &rows).unwrap();
+        set_tile(&mut game_map, (1, 1), TileType::Plate(1));
+        set_tile(&mut game_map, (3, 1), TileType::Ice);
+        set_tile(&mut game_map, (4, 1), TileType::Ice);
+        set_tile(&mut game_map, (5, 1), TileType::Door(1));
+        let mut state = State::new(&game_map);
+        let mut history = History::new();
+        let box_idx = state.get_mob_at(2, 1).unwrap();
+        assert!(!Gates::new(&game_map, &state).is_closed(5, 1));
+
+        // the box slides towards the door, which closes as the player leaves the plate
+        step(&mut state, &game_map, &mut history, Direction::SE);
+        assert_eq!(state.get_mob_at(4, 1), Some(box_idx));
+        assert!(Gates::new(&game_map, &state).is_closed(5, 1));
+    }
 }
@@ -0,0 +1,206 @@
+use std::path::PathBuf;
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crossbeam_channel::{bounded, Sender, TrySendError};
+use ferari::capture::{save_png, Recorder};
+use ferari::input::InputSnapshot;
+use image::ImageResult;
+
+use crate::save::data_dir;
+use crate::{LOGIC_HEIGHT, LOGIC_WIDTH};
+
+/// Frames per second written to recordings.
+pub const RECORDING_FPS: f32 = 30.0;
+/// Directory inside the data directory screenshots are saved to.
+const SCREENSHOT_DIR_NAME: &str = "screenshots";
+/// Directory inside the data directory recordings are saved to.
+const RECORDING_DIR_NAME: &str = "recordings";
+/// Frames waiting for the encoder before new ones are dropped.
+const RECORDING_QUEUE_FRAMES: usize = 8;
+
+/// Recording in progress, encoded on a worker thread.
+///
+/// Quantizing and encoding a GIF frame takes longer than a game frame, so the game thread
+/// only hands the frames over. When the encoder falls behind, frames are dropped and their
+/// time is added to the next frame handed over.
+struct Recording {
+    /// Sends the frames and the time they cover to the encoder
+    frames: Sender<(Vec<u32>, f32)>,
+    /// Encoder thread, returns the number of written frames
+    encoder: JoinHandle<ImageResult<usize>>,
+    /// Time covered by dropped frames since the last handed over one, in seconds
+    dropped: f32,
+    /// File of the recording
+    path: PathBuf,
+}
+
+impl Recording {
+    /// Starts the encoder thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `recorder` - recorder writing the frames
+    /// * `path` - file of the recording
+    fn start(mut recorder: Recorder, path: PathBuf) -> Self {
+        let (frames, received) = bounded::<(Vec<u32>, f32)>(RECORDING_QUEUE_FRAMES);
+        let encoder = thread::spawn(move || {
+            for (frame, delta) in received {
+                recorder.push(&frame, delta)?;
+            }
+            Ok(recorder.finish())
+        });
+        Self { frames, encoder, dropped: 0.0, path }
+    }
+
+    /// Hands a frame over to the encoder.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the encoder has stopped (after an error).
+    fn push(&mut self, frame: &[u32], delta: f32) -> bool {
+        match self.frames.try_send((frame.to_vec(), self.dropped + delta)) {
+            Ok(()) => self.dropped = 0.0,
+            Err(TrySendError::Full(_)) => self.dropped += delta,
+            Err(TrySendError::Disconnected(_)) => return false,
+        }
+        true
+    }
+
+    /// Ends the recording and waits for the encoder to write the queued frames.
+    ///
+    /// # Returns
+    ///
+    /// The number of written frames.
+    fn finish(self) -> ImageResult<usize> {
+        drop(self.frames);
+        self.encoder.join().expect("the recording encoder panicked")
+    }
+}
+
+/// Screenshot and recording hotkeys of the windowed game.
+///
+/// `F12` saves the current frame as a PNG file and `F10` starts or stops recording the
+/// frames to an animated GIF, both in the user data directory.
+#[derive(Default)]
+pub struct Capture {
+    /// Whether the screenshot key was held last frame
+    screenshot_held: bool,
+    /// Whether the recording key was held last frame
+    record_held: bool,
+    /// Recording in progress
+    recording: Option<Recording>,
+}
+
+impl Capture {
+    /// Creates the capture hotkeys without a recording in progress.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles the capture hotkeys and records the frame if a recording is in progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - keyboard input of this frame
+    /// * `frame` - rendered frame of `LOGIC_WIDTH * LOGIC_HEIGHT` pixels
+    /// * `delta` - time elapsed since the previous frame, in seconds
+    pub fn update(&mut self, input: &InputSnapshot, frame: &[u32], delta: f32) {
+        if input.screenshot && !self.screenshot_held {
+            match capture_path(SCREENSHOT_DIR_NAME, "png") {
+                Some(path) => match save_png(frame, LOGIC_WIDTH, LOGIC_HEIGHT, &path) {
+                    Ok(()) => println!("Screenshot saved to {}", path.display()),
+                    Err(err) => eprintln!("Cannot save screenshot {}: {err}", path.display()),
+                },
+                None => eprintln!("Cannot save screenshot: no data directory"),
+            }
+        }
+        self.screenshot_held = input.screenshot;
+
+        if input.record && !self.record_held {
+            if self.recording.is_some() {
+                self.finish();
+            } else {
+                self.start_recording();
+            }
+        }
+        self.record_held = input.record;
+
+        if let Some(recording) = &mut self.recording {
+            if !recording.push(frame, delta) {
+                self.finish();
+            }
+        }
+    }
+
+    /// Stops the recording in progress, if any, and waits until it is written.
+    pub fn finish(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            let path = recording.path.clone();
+            match recording.finish() {
+                Ok(frames) => println!("Recording of {frames} frames saved to {}", path.display()),
+                Err(err) => eprintln!("Cannot record {}: {err}", path.display()),
+            }
+        }
+    }
+
+    /// Starts recording to a new file in the data directory.
+    fn start_recording(&mut self) {
+        let Some(path) = capture_path(RECORDING_DIR_NAME, "gif") else {
+            eprintln!("Cannot start recording: no data directory");
+            return;
+        };
+
+        match Recorder::create(&path, LOGIC_WIDTH, LOGIC_HEIGHT) {
+            Ok(recorder) => {
+                println!("Recording to {}", path.display());
+                self.recording = Some(Recording::start(recorder.with_fps(RECORDING_FPS), path));
+            }
+            Err(err) => eprintln!("Cannot start recording {}: {err}", path.display()),
+        }
+    }
+}
+
+/// Returns an unused file path named after the current time.
+///
+/// # Arguments
+///
+/// * `dir_name` - directory inside the data directory
+/// * `extension` - file extension
+fn capture_path(dir_name: &str, extension: &str) -> Option<PathBuf> {
+    let dir = data_dir()?.join(dir_name);
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+
+    (0..)
+        .map(|i| match i {
+            0 => dir.join(format!("ferari_{secs}.{extension}")),
+            _ => dir.join(format!("ferari_{secs}_{i}.{extension}")),
+        })
+        .find(|path| !path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_recording_is_encoded_on_a_worker_thread() {
+        let dir = env::temp_dir().join(format!("ferari_recording_{}", std::process::id()));
+        let path = dir.join("demo.gif");
+        let recorder = Recorder::create(&path, 4, 2).unwrap();
+        let mut recording = Recording::start(recorder, path.clone());
+
+        let frame = [0xFF0000; 8];
+        for _ in 0..3 {
+            assert!(recording.push(&frame, 0.1));
+        }
+        let frames = recording.finish();
+        let written = fs::metadata(&path).map(|meta| meta.len());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(frames.unwrap(), 3);
+        assert!(written.unwrap() > 0);
+    }
+}
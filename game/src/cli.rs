@@ -0,0 +1,484 @@
+use std::path::PathBuf;
+
+use ferari::assets::{CompactMap, GameMap, XsbCollection, XsbLevel};
+use ferari::capture::Recorder;
+use ferari::world::State;
+
+use crate::capture::RECORDING_FPS;
+use crate::headless::{Headless, MOVE_FRAME_LIMIT};
+use crate::lint::lint_dir;
+use crate::replay::Replay;
+use crate::save::Progress;
+use crate::session::{discover_packs, find_level, load_atlases, project_root, Session, LEVELS_DIR};
+use crate::settings::Settings;
+use crate::solver::{from_lurd, solve, to_lurd, Solution, SolveMode};
+use crate::{LOGIC_HEIGHT, LOGIC_WIDTH};
+
+/// Command-line usage help.
+const USAGE: &str = "\
+Usage:
+    game                               start the game
+    game --solve <level> [--moves]     solve a level (push-optimal, or move-optimal with --moves)
+    game --export <level>...           print levels (JSON, XSB or SOK) as an XSB collection
+    game --convert <level> [--full]    print a level as compact JSON (or the full JSON format)
+    game --check [dir]                 check every level in a directory (default: game_levels)
+    game --replay <file>               play back a recorded level session
+    game --headless <level> [--moves <lurd>] [--screenshot <png>] [--record <gif|dir>]
+                                       play moves (LURD) in a level without a window
+    game --headless --replay <file> [--screenshot <png>] [--record <gif|dir>]
+                                       play back a recorded level session without a window
+    game --help                        print this help";
+
+/// What the process does once the command line has been handled.
+#[derive(Debug)]
+pub enum Launch {
+    /// A tool has been run, the process exits with the code
+    Exit(i32),
+    /// The game starts, playing back the replay if one was given
+    Game(Option<Replay>),
+}
+
+/// Runs a command-line tool if one was requested instead of the game.
+///
+/// # Arguments
+///
+/// * `args` - command-line arguments without the program name
+///
+/// # Returns
+///
+/// * `Launch::Exit(exit_code)` if a tool has been run (or the arguments are invalid).
+/// * `Launch::Game(replay)` if the game should start, with the replay given by `--replay`.
+pub fn run(args: &[String]) -> Launch {
+    let Some(command) = args.first() else {
+        return Launch::Game(None);
+    };
+
+    let code = match command.as_str() {
+        "--solve" => match args.get(1) {
+            Some(path) => run_solve(path, args[2..].iter().any(|a| a == "--moves")),
+            None => usage_error(),
+        },
+        "--export" if args.len() > 1 => run_export(&args[1..]),
+        "--convert" => match &args[1..] {
+            [path] => run_convert(path, false),
+            [path, flag] if flag == "--full" => run_convert(path, true),
+            _ => usage_error(),
+        },
+        "--check" if args.len() <= 2 => run_check(args.get(1)),
+        "--headless" => match parse_headless_args(&args[1..]) {
+            Some(headless_args) => run_headless(&headless_args),
+            None => usage_error(),
+        },
+        "--replay" => match &args[1..] {
+            [path] => match Replay::load(path) {
+                Ok(replay) => return Launch::Game(Some(replay)),
+                Err(err) => {
+                    eprintln!("{path}: {err}");
+                    1
+                }
+            },
+            _ => usage_error(),
+        },
+        "--help" | "-h" => {
+            println!("{USAGE}");
+            0
+        }
+        _ => usage_error(),
+    };
+
+    Launch::Exit(code)
+}
+
+/// Prints the usage help to stderr.
+///
+/// # Returns
+///
+/// Exit code for invalid usage.
+fn usage_error() -> i32 {
+    eprintln!("{USAGE}");
+    2
+}
+
+/// Solves a level file and prints the solution in LURD notation.
+///
+/// # Arguments
+///
+/// * `path` - path to the level file
+/// * `by_moves` - search for a move-optimal instead of a push-optimal solution
+///
+/// # Returns
+///
+/// `0` if the level has been solved, `1` otherwise.
+fn run_solve(path: &str, by_moves: bool) -> i32 {
+    let game = match GameMap::load(path) {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            return 1;
+        }
+    };
+    let state = State::new(&game);
+    let mode = if by_moves { SolveMode::Moves } else { SolveMode::Pushes };
+
+    match solve(&game, &state, mode) {
+        Solution::Solved(steps) => {
+            let pushes = steps.iter().filter(|step| step.push).count();
+            println!("{}: solved in {} moves, {} pushes", game.name, steps.len(), pushes);
+            println!("{}", to_lurd(&steps));
+            0
+        }
+        Solution::Unsolvable => {
+            println!("{}: unsolvable", game.name);
+            1
+        }
+        Solution::LimitReached => {
+            println!("{}: no solution found within the search limit", game.name);
+            1
+        }
+        Solution::Unsupported => {
+            println!("{}: the solver does not support the tile mechanisms of the level", game.name);
+            1
+        }
+    }
+}
+
+/// Prints levels as a single XSB collection.
+///
+/// XSB / SOK files contribute all of their levels, other files are loaded as JSON maps.
+///
+/// # Arguments
+///
+/// * `paths` - paths to the level files
+///
+/// # Returns
+///
+/// `0` if every file has been exported, `1` otherwise.
+fn run_export(paths: &[String]) -> i32 {
+    let mut collection = XsbCollection::default();
+
+    for path in paths {
+        let is_xsb = path.to_ascii_lowercase().ends_with(".xsb")
+            || path.to_ascii_lowercase().ends_with(".sok");
+        let levels = if is_xsb {
+            XsbCollection::load(path).map(|c| c.levels)
+        } else {
+            GameMap::load(path)
+                .map(|map| vec![XsbLevel { title: map.name.clone(), comments: Vec::new(), map }])
+                .map_err(Into::into)
+        };
+
+        match levels {
+            Ok(levels) => collection.levels.extend(levels),
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                return 1;
+            }
+        }
+    }
+
+    print!("{}", collection.to_xsb());
+    0
+}
+
+/// Prints a level (JSON in either format, XSB or SOK) as JSON.
+///
+/// # Arguments
+///
+/// * `path` - path to the level file
+/// * `full` - print the full format (one named entry per tile, object and mob) instead
+///   of the compact one
+///
+/// # Returns
+///
+/// `0` if the level has been converted, `1` otherwise.
+fn run_convert(path: &str, full: bool) -> i32 {
+    let json = GameMap::load_unchecked(path).and_then(|map| {
+        let json = map.to_json_map();
+        if full {
+            // Through a JSON value, whose keys are sorted
+            Ok(serde_json::to_string_pretty(&serde_json::to_value(json)?)?)
+        } else {
+            Ok(serde_json::to_string_pretty(&CompactMap::from_json_map(&json)?)?)
+        }
+    });
+
+    match json {
+        Ok(json) => {
+            println!("{json}");
+            0
+        }
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            1
+        }
+    }
+}
+
+/// Checks every level in a directory and prints the errors, warnings and statistics.
+///
+/// # Arguments
+///
+/// * `dir` - directory to check, the level packs directory if `None`
+///
+/// # Returns
+///
+/// `0` if no level has errors, `1` otherwise.
+fn run_check(dir: Option<&String>) -> i32 {
+    let (tiles_atlas, entities_atlas) = match load_atlases() {
+        Ok(atlases) => atlases,
+        Err(err) => {
+            eprintln!("Cannot load atlases: {err}");
+            return 1;
+        }
+    };
+    let dir = dir.map(PathBuf::from).unwrap_or_else(|| project_root().join(LEVELS_DIR));
+
+    let reports = lint_dir(&dir, &tiles_atlas, &entities_atlas);
+    println!("{}", dir.display());
+    let (mut errors, mut warnings) = (0, 0);
+    for report in &reports {
+        match &report.stats {
+            Some(stats) => println!("{}: {stats}", report.source),
+            None => println!("{}", report.source),
+        }
+        for error in &report.errors {
+            println!("  error: {error}");
+        }
+        for warning in &report.warnings {
+            println!("  warning: {warning}");
+        }
+        errors += report.errors.len();
+        warnings += report.warnings.len();
+    }
+    println!("{} levels checked: {errors} errors, {warnings} warnings", reports.len());
+
+    if reports.is_empty() {
+        eprintln!("No levels found in {}", dir.display());
+        return 1;
+    }
+    i32::from(errors > 0)
+}
+
+/// Options of a headless run.
+#[derive(Debug, Default, PartialEq)]
+struct HeadlessArgs {
+    /// Level file to play the moves in
+    level: Option<String>,
+    /// Replay file to play back instead of a level
+    replay: Option<String>,
+    /// Moves in LURD notation
+    moves: String,
+    /// PNG file the last frame is saved to
+    screenshot: Option<String>,
+    /// GIF file or PNG directory the frames are recorded to
+    record: Option<String>,
+}
+
+/// Parses the arguments following `--headless`.
+///
+/// # Returns
+///
+/// The options, or `None` if the arguments are invalid.
+fn parse_headless_args(args: &[String]) -> Option<HeadlessArgs> {
+    let mut parsed = HeadlessArgs::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--replay" => parsed.replay = Some(args.next()?.clone()),
+            "--moves" => parsed.moves = args.next()?.clone(),
+            "--screenshot" => parsed.screenshot = Some(args.next()?.clone()),
+            "--record" => parsed.record = Some(args.next()?.clone()),
+            path if !path.starts_with("--") && parsed.level.is_none() => {
+                parsed.level = Some(path.to_string())
+            }
+            _ => return None,
+        }
+    }
+
+    let valid = match (&parsed.level, &parsed.replay) {
+        (Some(_), None) => true,
+        (None, Some(_)) => parsed.moves.is_empty(),
+        _ => false,
+    };
+    valid.then_some(parsed)
+}
+
+/// Plays moves in a level, or a recorded level session, without a window and prints the
+/// outcome.
+///
+/// # Arguments
+///
+/// * `args` - options of the run
+///
+/// # Returns
+///
+/// `0` if the level has been solved, `1` otherwise.
+fn run_headless(args: &HeadlessArgs) -> i32 {
+    let (tiles_atlas, entities_atlas) = match load_atlases() {
+        Ok(atlases) => atlases,
+        Err(err) => {
+            eprintln!("Cannot load atlases: {err}");
+            return 1;
+        }
+    };
+
+    let (mut headless, steps) = if let Some(path) = &args.replay {
+        let replay = match Replay::load(path) {
+            Ok(replay) => replay,
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                return 1;
+            }
+        };
+        let packs = discover_packs();
+        let Some((pack, level)) = find_level(&packs, &replay.level) else {
+            eprintln!("Level {} of the replay not found in {LEVELS_DIR}", replay.level);
+            return 1;
+        };
+
+        let start = Some((pack, Some(level)));
+        let settings = Settings::default();
+        let session =
+            Session::new(packs, tiles_atlas, entities_atlas, Progress::default(), settings, start);
+        (Headless::new(session).with_replay(&replay), None)
+    } else {
+        let path = args.level.as_deref().unwrap_or_default();
+        let game = match GameMap::load(path) {
+            Ok(game) => game,
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                return 1;
+            }
+        };
+        let steps = match from_lurd(&args.moves) {
+            Ok(steps) => steps,
+            Err(c) => {
+                eprintln!("Invalid move '{c}'");
+                return 2;
+            }
+        };
+        (Headless::for_level(game, tiles_atlas, entities_atlas), Some(steps))
+    };
+
+    if let Some(path) = &args.record {
+        match Recorder::create(path, LOGIC_WIDTH, LOGIC_HEIGHT) {
+            Ok(recorder) => headless = headless.with_recorder(recorder.with_fps(RECORDING_FPS)),
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                return 1;
+            }
+        }
+    }
+
+    match steps {
+        Some(steps) => {
+            if !headless.play_moves(&steps) {
+                eprintln!("Moves did not finish within {MOVE_FRAME_LIMIT} frames");
+            }
+        }
+        None => headless.play_replay(),
+    }
+
+    if let Some(path) = &args.screenshot {
+        if let Err(err) = headless.save_screenshot(path) {
+            eprintln!("{path}: {err}");
+        }
+    }
+    if let (Some(path), Some(frames)) = (&args.record, headless.finish_recording()) {
+        println!("Recording of {frames} frames saved to {path}");
+    }
+
+    report_headless(&headless)
+}
+
+/// Prints the outcome of a headless run.
+///
+/// # Returns
+///
+/// `0` if the level has been solved, `1` otherwise.
+fn report_headless(headless: &Headless) -> i32 {
+    let session = headless.session();
+    let seconds = headless.time().total;
+
+    match session.last_result() {
+        Some(result) => {
+            println!(
+                "{}: solved in {} moves, {} pushes, {:.1}s ({} frames)",
+                session.game().name,
+                result.moves,
+                result.pushes,
+                result.time,
+                headless.frames()
+            );
+            0
+        }
+        None => {
+            let game = session.game();
+            let placed = session
+                .state()
+                .mobs
+                .iter()
+                .filter(|unit| {
+                    game.accepts_box(unit.tile_x as u32, unit.tile_y as u32, unit.color.as_deref())
+                })
+                .count();
+            println!(
+                "{}: not solved, {placed} of {} boxes on target after {} moves, {} pushes, \
+                 {seconds:.1}s ({} frames)",
+                game.name,
+                game.target_positions.len(),
+                session.history().moves(),
+                session.history().pushes(),
+                headless.frames()
+            );
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_replay_arguments() {
+        assert!(matches!(run(&[]), Launch::Game(None)));
+        assert!(matches!(run(&args(&["--replay"])), Launch::Exit(2)));
+        assert!(matches!(run(&args(&["--replay", "a", "b"])), Launch::Exit(2)));
+        assert!(matches!(run(&args(&["--replay", "no_such_replay.json"])), Launch::Exit(1)));
+    }
+
+    #[test]
+    fn test_parse_headless_args() {
+        let parsed =
+            parse_headless_args(&args(&["level.json", "--moves", "rRR", "--record", "a.gif"]));
+        assert_eq!(
+            parsed,
+            Some(HeadlessArgs {
+                level: Some("level.json".into()),
+                moves: "rRR".into(),
+                record: Some("a.gif".into()),
+                ..Default::default()
+            })
+        );
+
+        let parsed =
+            parse_headless_args(&args(&["--replay", "last.json", "--screenshot", "a.png"]));
+        assert_eq!(parsed.unwrap().screenshot.as_deref(), Some("a.png"));
+    }
+
+    #[test]
+    fn test_parse_headless_args_rejects_invalid_combinations() {
+        assert_eq!(parse_headless_args(&args(&[])), None);
+        assert_eq!(parse_headless_args(&args(&["a.json", "b.json"])), None);
+        assert_eq!(parse_headless_args(&args(&["a.json", "--replay", "r.json"])), None);
+        assert_eq!(parse_headless_args(&args(&["--replay", "r.json", "--moves", "r"])), None);
+        assert_eq!(parse_headless_args(&args(&["a.json", "--moves"])), None);
+        assert_eq!(parse_headless_args(&args(&["a.json", "--fast"])), None);
+    }
+}
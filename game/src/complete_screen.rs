@@ -0,0 +1,308 @@
+use ferari::input::{Action, InputEvent, InputEventKind};
+use ferari::render::{BitmapFont, Hud};
+use minifb::Key;
+
+use crate::hud::format_time;
+use crate::save::LevelRecord;
+use crate::session::LevelResult;
+
+/// Distance of the screen text from the top-left corner of the screen, in pixels.
+const SCREEN_MARGIN: i32 = 4;
+/// Extra space between two lines, in pixels.
+const SCREEN_LINE_GAP: i32 = 6;
+/// Indentation of the selected option, in pixels.
+const SELECTED_INDENT: i32 = 16;
+/// Key that chooses the selected option.
+const CONFIRM_KEY: Key = Key::Enter;
+
+/// Option offered once a level is solved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompleteOption {
+    /// Go on to the next level (or the level menu after the last one)
+    Continue,
+    /// Watch the solution that has just been played
+    Replay,
+    /// Play the level again from its start
+    Retry,
+}
+
+impl CompleteOption {
+    /// All options in display order.
+    pub const ALL: [CompleteOption; 3] =
+        [CompleteOption::Continue, CompleteOption::Replay, CompleteOption::Retry];
+
+    /// Returns the name of the option (letters and spaces only).
+    pub fn label(self) -> &'static str {
+        match self {
+            CompleteOption::Continue => "CONTINUE",
+            CompleteOption::Replay => "REPLAY",
+            CompleteOption::Retry => "RETRY",
+        }
+    }
+}
+
+/// Statistics of a solved level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelSummary {
+    /// Name of the level
+    pub level_name: String,
+    /// Counters of the solution
+    pub result: LevelResult,
+    /// Best results before this solution, `None` if the level had not been solved yet
+    pub best: Option<LevelRecord>,
+    /// Fewest moves of any solution, `None` if the solver did not find it
+    pub optimal_moves: Option<usize>,
+    /// Fewest pushes of any solution, `None` if the solver did not find it
+    pub optimal_pushes: Option<usize>,
+}
+
+/// Screen shown once a level is solved: the statistics of the solution compared with
+/// the best results and the optimal solution, and the options to go on.
+///
+/// The up and down movement actions select an option (holding them repeats), `Enter`
+/// or the right movement action chooses it.
+#[derive(Debug, Clone)]
+pub struct CompleteScreen {
+    /// Statistics shown on the screen
+    summary: LevelSummary,
+    /// Index of the selected option in [`CompleteOption::ALL`]
+    selected: usize,
+    /// Notice shown below the options, e.g. why an option did nothing
+    notice: Option<&'static str>,
+}
+
+impl CompleteScreen {
+    /// Opens the screen with [`CompleteOption::Continue`] selected.
+    ///
+    /// # Arguments
+    ///
+    /// * `summary` - statistics of the solved level
+    pub fn new(summary: LevelSummary) -> Self {
+        Self { summary, selected: 0, notice: None }
+    }
+
+    /// Shows the optimal counts once the solver has found them.
+    ///
+    /// # Arguments
+    ///
+    /// * `moves` - fewest moves of any solution, `None` if the solver did not find it
+    /// * `pushes` - fewest pushes of any solution, `None` if the solver did not find it
+    pub fn set_optimal(&mut self, moves: Option<usize>, pushes: Option<usize>) {
+        self.summary.optimal_moves = moves;
+        self.summary.optimal_pushes = pushes;
+    }
+
+    /// Shows a notice below the options until the screen is closed.
+    ///
+    /// # Arguments
+    ///
+    /// * `notice` - text of the notice, e.g. `THE SOLUTION HAS NOT BEEN RECORDED`
+    pub fn set_notice(&mut self, notice: &'static str) {
+        self.notice = Some(notice);
+    }
+
+    /// Returns the statistics shown on the screen.
+    #[cfg(test)]
+    pub fn summary(&self) -> &LevelSummary {
+        &self.summary
+    }
+
+    /// Returns the selected option.
+    pub fn selected(&self) -> CompleteOption {
+        CompleteOption::ALL[self.selected]
+    }
+
+    /// Handles the input of a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - input events of this frame, in order
+    /// * `keys` - keys pressed in this frame, in press order
+    ///
+    /// # Returns
+    ///
+    /// The chosen option, or `None` while the player is still choosing.
+    pub fn update(&mut self, events: &[InputEvent], keys: &[Key]) -> Option<CompleteOption> {
+        let pressed = |action| {
+            events
+                .iter()
+                .any(|event| event.action == action && event.kind == InputEventKind::Pressed)
+        };
+        let pressed_or_repeated =
+            |action| events.iter().any(|event| event.is_press_or_repeat(action));
+        let count = CompleteOption::ALL.len();
+
+        if keys.contains(&CONFIRM_KEY) || pressed(Action::MoveRight) {
+            return Some(self.selected());
+        }
+        if pressed_or_repeated(Action::MoveUp) {
+            self.selected = (self.selected + count - 1) % count;
+        } else if pressed_or_repeated(Action::MoveDown) {
+            self.selected = (self.selected + 1) % count;
+        }
+        None
+    }
+
+    /// Builds the overlay of the screen.
+    ///
+    /// # Arguments
+    ///
+    /// * `font` - font the overlay is drawn with (decides the line spacing)
+    pub fn hud(&self, font: &BitmapFont) -> Hud {
+        let summary = &self.summary;
+        let result = &summary.result;
+        let best = summary.best.as_ref();
+
+        let moves = compare(
+            format!("MOVES {}", result.moves),
+            best.and_then(|best| best.best_moves).map(|best| (result.moves < best, best)),
+            summary.optimal_moves,
+        );
+        let pushes = compare(
+            format!("PUSHES {}", result.pushes),
+            best.and_then(|best| best.best_pushes).map(|best| (result.pushes < best, best)),
+            summary.optimal_pushes,
+        );
+        let mut time = format!("TIME {}", format_time(result.time));
+        if let Some(best_time) = best.and_then(|best| best.best_time) {
+            time.push_str(&format!("  BEST {}", format_time(best_time)));
+            if result.time < best_time {
+                time.push_str("  NEW BEST");
+            }
+        }
+
+        let mut lines = vec![
+            (0, format!("{}  SOLVED", summary.level_name)),
+            (0, moves),
+            (0, pushes),
+            (0, time),
+            (0, String::new()),
+        ];
+        for (i, option) in CompleteOption::ALL.into_iter().enumerate() {
+            let indent = if i == self.selected { SELECTED_INDENT } else { 0 };
+            lines.push((indent, option.label().to_string()));
+        }
+        if let Some(notice) = self.notice {
+            lines.push((0, String::new()));
+            lines.push((0, notice.to_string()));
+        }
+
+        let mut hud = Hud::new();
+        for (i, (indent, line)) in lines.into_iter().enumerate() {
+            let y = SCREEN_MARGIN + i as i32 * (font.line_height + SCREEN_LINE_GAP);
+            hud.text(SCREEN_MARGIN + indent, y, line);
+        }
+        hud
+    }
+}
+
+/// Appends the previous best and the optimal value to a counter line.
+///
+/// # Arguments
+///
+/// * `line` - the counter, e.g. `MOVES 12`
+/// * `best` - `(improved, best)`: whether the counter beats the previous best, and the
+///   previous best
+/// * `optimal` - optimal value of the counter, if known
+fn compare(mut line: String, best: Option<(bool, u32)>, optimal: Option<usize>) -> String {
+    if let Some((_, best)) = best {
+        line.push_str(&format!("  BEST {best}"));
+    }
+    if let Some(optimal) = optimal {
+        line.push_str(&format!("  OPTIMAL {optimal}"));
+    }
+    if best.is_some_and(|(improved, _)| improved) {
+        line.push_str("  NEW BEST");
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferari::assets::{Atlas, Frame};
+    use image::RgbaImage;
+    use std::collections::HashMap;
+
+    fn test_font() -> BitmapFont {
+        let frame = Frame { name: "letter_A".into(), x: 0, y: 0, w: 10, h: 20 };
+        let atlas = Atlas {
+            image: RgbaImage::new(10, 20),
+            frames: HashMap::from([(frame.name.clone(), frame)]),
+            tile_size: 10,
+            version: 1,
+        };
+        BitmapFont::from_atlas(&atlas, 1.0)
+    }
+
+    fn summary(best: Option<LevelRecord>) -> LevelSummary {
+        LevelSummary {
+            level_name: "level1".to_string(),
+            result: LevelResult { level_id: None, moves: 12, pushes: 3, time: 30.0 },
+            best,
+            optimal_moves: Some(10),
+            optimal_pushes: None,
+        }
+    }
+
+    fn press(action: Action) -> InputEvent {
+        InputEvent { action, kind: InputEventKind::Pressed, time: 0.0 }
+    }
+
+    #[test]
+    fn test_options_are_selected_and_chosen() {
+        let mut screen = CompleteScreen::new(summary(None));
+
+        assert_eq!(screen.update(&[press(Action::MoveUp)], &[]), None);
+        assert_eq!(screen.selected(), CompleteOption::Retry);
+        assert_eq!(screen.update(&[press(Action::MoveDown)], &[]), None);
+        assert_eq!(screen.update(&[], &[Key::Enter]), Some(CompleteOption::Continue));
+        screen.update(&[press(Action::MoveDown)], &[]);
+        assert_eq!(screen.update(&[press(Action::MoveRight)], &[]), Some(CompleteOption::Replay));
+    }
+
+    #[test]
+    fn test_hud_compares_with_best_and_optimal() {
+        let best = LevelRecord {
+            solved: true,
+            best_moves: Some(14),
+            best_pushes: Some(3),
+            best_time: Some(20.0),
+        };
+        let hud = CompleteScreen::new(summary(Some(best))).hud(&test_font());
+
+        let lines: Vec<&str> = hud.texts.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(
+            lines[..4],
+            [
+                "level1  SOLVED",
+                "MOVES 12  BEST 14  OPTIMAL 10  NEW BEST",
+                "PUSHES 3  BEST 3",
+                "TIME 0M30S  BEST 0M20S",
+            ]
+        );
+        assert_eq!(hud.texts[5].x, SCREEN_MARGIN + SELECTED_INDENT);
+        assert_eq!(hud.texts[6].x, SCREEN_MARGIN);
+    }
+
+    #[test]
+    fn test_optimal_counts_are_filled_in() {
+        let mut screen = CompleteScreen::new(summary(None));
+        screen.set_optimal(Some(11), Some(2));
+
+        let hud = screen.hud(&test_font());
+        assert_eq!(hud.texts[1].text, "MOVES 12  OPTIMAL 11");
+        assert_eq!(hud.texts[2].text, "PUSHES 3  OPTIMAL 2");
+    }
+
+    #[test]
+    fn test_notice_is_shown_below_the_options() {
+        let mut screen = CompleteScreen::new(summary(None));
+        screen.set_notice("THE SOLUTION HAS NOT BEEN RECORDED");
+
+        let hud = screen.hud(&test_font());
+        let last = hud.texts.last().unwrap();
+        assert_eq!(last.text, "THE SOLUTION HAS NOT BEEN RECORDED");
+        assert_eq!(last.x, SCREEN_MARGIN);
+    }
+}
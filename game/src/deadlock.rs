@@ -0,0 +1,530 @@
+use std::collections::VecDeque;
+
+use ferari::assets::GameMap;
+use ferari::world::{Direction, State};
+
+use crate::behaviour::{climb_map, get_dir_delta};
+
+/// Tile axes as pairs of opposite directions.
+const AXES: [(Direction, Direction); 2] =
+    [(Direction::NW, Direction::SE), (Direction::NE, Direction::SW)];
+
+/// Numbers the target colors of a map.
+///
+/// Boxes and targets of the same color share a class; class `0` is the plain color.
+///
+/// # Arguments
+///
+/// * `game` - the level map
+///
+/// # Returns
+///
+/// The color of each class, and the class of each target in the order of
+/// `game.target_positions`.
+pub fn color_classes(game: &GameMap) -> (Vec<Option<String>>, Vec<usize>) {
+    let mut colors = vec![None];
+    let mut target_classes = Vec::with_capacity(game.target_positions.len());
+    for position in &game.target_positions {
+        let color = game.target_colors.get(position).cloned();
+        let class = colors.iter().position(|c| *c == color).unwrap_or_else(|| {
+            colors.push(color);
+            colors.len() - 1
+        });
+        target_classes.push(class);
+    }
+    (colors, target_classes)
+}
+
+/// Detects positions in which some box can never reach a target anymore.
+///
+/// Static dead squares are computed once per map and per box color (see
+/// [`color_classes`]); dynamic checks (freeze and corral deadlocks) are run on the current
+/// box configuration after every push. A box only counts as placed on a target of its
+/// color.
+///
+/// Deadlocks are only reported when there are exactly as many boxes as targets:
+/// with surplus boxes a stuck box may legitimately stay where it is. Maps with mechanisms
+/// (see `TileType::is_mechanism`) are not analysed at all.
+#[derive(Debug, Default)]
+pub struct DeadlockDetector {
+    /// Map width in tiles
+    width: i32,
+    /// Map height in tiles
+    height: i32,
+    /// Cells that neither the player nor a box can enter
+    walls: Vec<bool>,
+    /// Directions in which a step can leave each cell (see [`climb_map`])
+    climb: Vec<[bool; 4]>,
+    /// Color class of the target on each cell, `None` for cells without a target
+    targets: Vec<Option<usize>>,
+    /// Number of target cells
+    target_count: usize,
+    /// Colors of the color classes
+    colors: Vec<Option<String>>,
+    /// Cells from which a box can never be pushed to a target of its color (indexed by
+    /// color class, then by cell)
+    dead_squares: Vec<Vec<bool>>,
+    /// Tiles of the boxes found deadlocked by the last check
+    dead_boxes: Vec<(i32, i32)>,
+    /// Set when the last check found a deadlock that was not there before
+    fresh_deadlock: bool,
+}
+
+impl DeadlockDetector {
+    /// Runs the static dead-square analysis of a map.
+    ///
+    /// A box can be pulled from `b` to `b - d` if both `b - d` and `b - 2d` are free.
+    /// Every cell that cannot be reached by pulling a box away from some target of its
+    /// color is dead for boxes of that color: a box pushed there can never be brought
+    /// back to such a target.
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - the level map (walls, collidable objects and targets)
+    ///
+    /// # Returns
+    ///
+    /// A detector without any dead boxes (which never reports any on maps with mechanisms).
+    pub fn new(game: &GameMap) -> Self {
+        if game.has_mechanisms() {
+            return Self::default();
+        }
+
+        let width = game.size[0] as i32;
+        let height = game.size[1] as i32;
+        let cells = (width * height) as usize;
+
+        let mut walls = Vec::with_capacity(cells);
+        for y in 0..height {
+            for x in 0..width {
+                walls.push(!game.is_walkable(x, y) || game.has_collidable_object_at(x, y));
+            }
+        }
+
+        let (colors, target_classes) = color_classes(game);
+        let mut targets = vec![None; cells];
+        for (&(x, y), &class) in game.target_positions.iter().zip(&target_classes) {
+            targets[(y as i32 * width + x as i32) as usize] = Some(class);
+        }
+
+        let mut detector = Self {
+            width,
+            height,
+            walls,
+            climb: climb_map(game),
+            target_count: game.target_positions.len(),
+            targets,
+            ..Default::default()
+        };
+
+        detector.dead_squares = (0..colors.len()).map(|class| detector.dead_for(class)).collect();
+        detector.colors = colors;
+        detector
+    }
+
+    /// Computes the dead squares of the boxes of one color class.
+    fn dead_for(&self, class: usize) -> Vec<bool> {
+        let cells = self.walls.len();
+        let mut live: Vec<bool> = self.targets.iter().map(|&t| t == Some(class)).collect();
+        let mut queue: VecDeque<usize> = (0..cells).filter(|&c| live[c]).collect();
+        while let Some(cell) = queue.pop_front() {
+            for (a, b) in AXES {
+                for direction in [a, b] {
+                    let Some(box_cell) = self.open_neighbour(cell, direction) else {
+                        continue;
+                    };
+                    if self.open_neighbour(box_cell, direction).is_some() && !live[box_cell] {
+                        live[box_cell] = true;
+                        queue.push_back(box_cell);
+                    }
+                }
+            }
+        }
+
+        (0..cells).map(|c| !live[c] && !self.walls[c]).collect()
+    }
+
+    /// Returns the color class of a box (a class without targets for colors no target has).
+    pub fn class_of(&self, color: Option<&str>) -> usize {
+        self.colors.iter().position(|c| c.as_deref() == color).unwrap_or(self.colors.len())
+    }
+
+    /// Checks whether the tile is a static dead square for a box of the given color.
+    pub fn is_dead_square(&self, x: i32, y: i32, color: Option<&str>) -> bool {
+        self.cell(x, y).is_some_and(|c| self.is_dead(c, self.class_of(color)))
+    }
+
+    /// Checks whether the box standing on the tile is deadlocked (as of the last check).
+    pub fn is_dead_box(&self, x: i32, y: i32) -> bool {
+        self.dead_boxes.contains(&(x, y))
+    }
+
+    /// Returns `true` once after a check has found a new deadlock.
+    pub fn take_fresh_deadlock(&mut self) -> bool {
+        std::mem::take(&mut self.fresh_deadlock)
+    }
+
+    /// Re-evaluates all boxes of the current state.
+    ///
+    /// Intended to be called after every committed push (and after undoing one).
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - current game state; every mob is treated as a box
+    ///
+    /// # Returns
+    ///
+    /// `true` if at least one box is deadlocked.
+    pub fn update(&mut self, state: &State) -> bool {
+        let was_dead = !self.dead_boxes.is_empty();
+
+        let boxes: Vec<(usize, usize)> = state
+            .mobs
+            .iter()
+            .filter_map(|m| {
+                Some((self.cell(m.tile_x, m.tile_y)?, self.class_of(m.color.as_deref())))
+            })
+            .collect();
+        let player = self.cell(state.player.unit.tile_x, state.player.unit.tile_y);
+        let dead = self.find_dead_boxes(&boxes, player);
+
+        self.dead_boxes =
+            dead.into_iter().map(|c| (c as i32 % self.width, c as i32 / self.width)).collect();
+        self.fresh_deadlock = !was_dead && !self.dead_boxes.is_empty();
+
+        !self.dead_boxes.is_empty()
+    }
+
+    /// Checks whether pushing a box onto `cell` creates a dead square or freeze deadlock.
+    ///
+    /// # Arguments
+    ///
+    /// * `boxes` - color class of the box on each cell after the push (see
+    ///   [`DeadlockDetector::class_of`]), indexed by `y * width + x`
+    /// * `cell` - cell the box has been pushed to
+    pub fn is_push_deadlock(&self, boxes: &[Option<usize>], cell: usize) -> bool {
+        let Some(class) = boxes[cell] else {
+            return false;
+        };
+        if self.is_dead(cell, class) {
+            return true;
+        }
+
+        let mut group = Vec::new();
+        let mut as_wall = vec![false; boxes.len()];
+        self.is_frozen(cell, boxes, &mut as_wall, &mut group)
+            && group.iter().any(|&c| !self.is_placed(boxes, c))
+    }
+
+    /// Checks whether the cell is a dead square for boxes of a color class (every cell is
+    /// for a class without targets).
+    fn is_dead(&self, cell: usize, class: usize) -> bool {
+        self.dead_squares.get(class).map_or(!self.walls[cell], |dead| dead[cell])
+    }
+
+    /// Checks whether the box on the cell stands on a target of its color.
+    fn is_placed(&self, boxes: &[Option<usize>], cell: usize) -> bool {
+        boxes[cell].is_some_and(|class| self.targets[cell] == Some(class))
+    }
+
+    /// Returns the cells of all deadlocked boxes.
+    ///
+    /// # Arguments
+    ///
+    /// * `boxes` - cell and color class of every box
+    /// * `player` - cell of the player
+    fn find_dead_boxes(&self, boxes: &[(usize, usize)], player: Option<usize>) -> Vec<usize> {
+        if boxes.len() != self.target_count {
+            return Vec::new();
+        }
+
+        let mut classes = vec![None; self.walls.len()];
+        for &(b, class) in boxes {
+            classes[b] = Some(class);
+        }
+
+        let mut dead: Vec<usize> = Vec::new();
+        for &(b, class) in boxes {
+            if self.is_placed(&classes, b) || dead.contains(&b) {
+                continue;
+            }
+            if self.is_dead(b, class) {
+                dead.push(b);
+                continue;
+            }
+
+            let mut group = Vec::new();
+            let mut as_wall = vec![false; classes.len()];
+            if self.is_frozen(b, &classes, &mut as_wall, &mut group) {
+                dead.extend(group.into_iter().filter(|&c| !self.is_placed(&classes, c)));
+            }
+        }
+
+        // Fence boxes of a sealed corral are reported even if they stand on targets
+        for fence in self.sealed_corral_fences(&classes, player) {
+            dead.extend(fence);
+        }
+
+        dead.sort_unstable();
+        dead.dedup();
+        dead
+    }
+
+    /// Freeze test: a box is frozen if it can be moved along neither axis.
+    ///
+    /// An axis is blocked by a wall on either side, by dead squares on both sides, or by a
+    /// neighbouring box that is frozen itself. Boxes already under examination are treated
+    /// as walls to break cycles. All frozen boxes of the group are collected into `group`.
+    fn is_frozen(
+        &self,
+        cell: usize,
+        boxes: &[Option<usize>],
+        as_wall: &mut [bool],
+        group: &mut Vec<usize>,
+    ) -> bool {
+        as_wall[cell] = true;
+
+        let frozen =
+            AXES.iter().all(|&(a, b)| self.is_axis_blocked(cell, (a, b), boxes, as_wall, group));
+
+        if frozen {
+            group.push(cell);
+        }
+        frozen
+    }
+
+    /// Checks whether a box cannot be moved along the axis given by two opposite directions.
+    fn is_axis_blocked(
+        &self,
+        cell: usize,
+        (a, b): (Direction, Direction),
+        boxes: &[Option<usize>],
+        as_wall: &mut [bool],
+        group: &mut Vec<usize>,
+    ) -> bool {
+        // A side the box cannot be moved across (e.g. a height step) counts as a wall
+        let sides =
+            [a, b].map(|d| self.neighbour(cell, d).filter(|_| self.climb[cell][d as usize]));
+
+        let is_wall = |side: Option<usize>| side.is_none_or(|c| self.walls[c] || as_wall[c]);
+        if sides.iter().any(|&side| is_wall(side)) {
+            return true;
+        }
+
+        // dead squares for the color of this box
+        let class = boxes[cell].unwrap_or_default();
+        if sides.iter().all(|side| side.is_some_and(|c| self.is_dead(c, class))) {
+            return true;
+        }
+
+        sides
+            .into_iter()
+            .flatten()
+            .any(|c| boxes[c].is_some() && self.is_frozen(c, boxes, as_wall, group))
+    }
+
+    /// Finds corrals (areas the player cannot reach) enclosed by boxes that can never move.
+    ///
+    /// If every box on the border of such a corral is frozen, the corral stays closed forever,
+    /// so an empty target inside it means a deadlock.
+    ///
+    /// # Arguments
+    ///
+    /// * `boxes` - color class of the box on each cell, indexed by `y * width + x`
+    /// * `player` - cell of the player; the area around it is not a corral
+    ///
+    /// # Returns
+    ///
+    /// The fence boxes of every sealed corral that causes a deadlock.
+    fn sealed_corral_fences(
+        &self,
+        boxes: &[Option<usize>],
+        player: Option<usize>,
+    ) -> Vec<Vec<usize>> {
+        let cells = self.walls.len();
+        let mut region = vec![usize::MAX; cells];
+        let mut fences = Vec::new();
+
+        for start in 0..cells {
+            if self.walls[start] || boxes[start].is_some() || region[start] != usize::MAX {
+                continue;
+            }
+
+            // Flood the free area around `start`, collecting the boxes around it
+            let mut area = vec![start];
+            let mut fence = Vec::new();
+            let mut queue = VecDeque::from([start]);
+            region[start] = start;
+
+            while let Some(cell) = queue.pop_front() {
+                for (a, b) in AXES {
+                    for direction in [a, b] {
+                        let Some(next) = self.open_neighbour(cell, direction) else {
+                            continue;
+                        };
+                        if boxes[next].is_some() {
+                            if !fence.contains(&next) {
+                                fence.push(next);
+                            }
+                        } else if region[next] == usize::MAX {
+                            region[next] = start;
+                            area.push(next);
+                            queue.push_back(next);
+                        }
+                    }
+                }
+            }
+
+            let reachable = player.is_some_and(|p| region[p] == start);
+            let needs_access = area.iter().any(|&c| self.targets[c].is_some());
+            if reachable || !needs_access || fence.is_empty() {
+                continue;
+            }
+
+            let sealed = fence.iter().all(|&b| {
+                let mut group = Vec::new();
+                let mut as_wall = vec![false; cells];
+                self.is_frozen(b, boxes, &mut as_wall, &mut group)
+            });
+            if sealed {
+                fences.push(fence);
+            }
+        }
+
+        fences
+    }
+
+    /// Converts tile coordinates to a cell index.
+    fn cell(&self, x: i32, y: i32) -> Option<usize> {
+        (x >= 0 && y >= 0 && x < self.width && y < self.height)
+            .then_some((y * self.width + x) as usize)
+    }
+
+    /// Returns the neighbouring cell in `direction`, or `None` if it is outside the map.
+    fn neighbour(&self, cell: usize, direction: Direction) -> Option<usize> {
+        let (dx, dy) = get_dir_delta(direction);
+        self.cell(cell as i32 % self.width + dx, cell as i32 / self.width + dy)
+    }
+
+    /// Returns the neighbouring cell in `direction` if it can be entered.
+    fn open_neighbour(&self, cell: usize, direction: Direction) -> Option<usize> {
+        self.neighbour(cell, direction)
+            .filter(|&n| !self.walls[n] && self.climb[cell][direction as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::tests::map_from_layout;
+
+    #[test]
+    fn test_static_dead_squares() {
+        let game = GameMap::load("../game_levels/level2.json").unwrap();
+        let detector = DeadlockDetector::new(&game);
+
+        assert!(detector.is_dead_square(0, 3, None));
+        assert!(!detector.is_dead_square(1, 3, None));
+        assert!(!detector.is_dead_square(0, 2, None));
+        // walls are not reported as dead squares
+        assert!(!detector.is_dead_square(0, 0, None));
+    }
+
+    #[test]
+    fn test_no_deadlock_at_level_start() {
+        for level in 1..=5 {
+            let game = GameMap::load(format!("../game_levels/level{level}.json")).unwrap();
+            let mut detector = DeadlockDetector::new(&game);
+
+            assert!(!detector.update(&State::new(&game)), "level{level}");
+            assert!(!detector.take_fresh_deadlock());
+        }
+    }
+
+    #[test]
+    fn test_freeze_deadlock_along_wall() {
+        let (game, state) = map_from_layout(&["#######", "#.$$  #", "#.  @ #", "#######"]);
+        let mut detector = DeadlockDetector::new(&game);
+
+        assert!(!detector.is_dead_square(2, 1, None));
+        assert!(!detector.is_dead_square(3, 1, None));
+        assert!(detector.update(&state));
+        assert!(detector.is_dead_box(2, 1));
+        assert!(detector.is_dead_box(3, 1));
+        assert!(detector.take_fresh_deadlock());
+        assert!(!detector.take_fresh_deadlock());
+    }
+
+    #[test]
+    fn test_single_box_along_wall_is_not_frozen() {
+        let (game, state) = map_from_layout(&["#######", "#. $  #", "#.$ @ #", "#######"]);
+        let mut detector = DeadlockDetector::new(&game);
+
+        assert!(!detector.update(&state));
+    }
+
+    #[test]
+    fn test_sealed_corral_with_empty_target() {
+        let (game, state) =
+            map_from_layout(&["######", "#.*###", "#*   #", "## $@#", "##   #", "######"]);
+        let mut detector = DeadlockDetector::new(&game);
+
+        assert!(detector.update(&state));
+        assert!(detector.is_dead_box(2, 1));
+        assert!(detector.is_dead_box(1, 2));
+        assert!(!detector.is_dead_box(3, 3));
+    }
+
+    #[test]
+    fn test_surplus_boxes_are_never_deadlocked() {
+        let (game, state) = map_from_layout(&["#######", "#.$$  #", "#  $@ #", "#######"]);
+        let mut detector = DeadlockDetector::new(&game);
+
+        assert!(!detector.update(&state));
+    }
+
+    #[test]
+    fn test_box_frozen_on_target_of_another_color() {
+        // the red box is stuck in the corner on the plain target, the plain box can never
+        // reach it
+        let (mut game, mut state) =
+            map_from_layout(&["######", "#*   #", "#  $.#", "#  @ #", "######"]);
+        game.target_colors.insert((4, 2), "red".to_string());
+        let red = state.mobs.iter_mut().find(|m| (m.tile_x, m.tile_y) == (1, 1)).unwrap();
+        red.color = Some("red".to_string());
+        let mut detector = DeadlockDetector::new(&game);
+
+        assert!(detector.is_dead_square(1, 1, Some("red")));
+        assert!(!detector.is_dead_square(1, 1, None));
+        assert!(detector.update(&state));
+        assert!(detector.is_dead_box(1, 1));
+        assert!(!detector.is_dead_box(3, 2));
+
+        // on a target of its own color the frozen box is placed
+        game.target_colors.clear();
+        game.target_colors.insert((1, 1), "red".to_string());
+        let mut detector = DeadlockDetector::new(&game);
+        assert!(!detector.update(&state));
+    }
+
+    #[test]
+    fn test_is_push_deadlock() {
+        let (game, state) = map_from_layout(&["#######", "#. $  #", "#.  $@#", "#######"]);
+        let detector = DeadlockDetector::new(&game);
+        let width = game.size[0] as usize;
+
+        let mut boxes = vec![None; width * game.size[1] as usize];
+        for unit in &state.mobs {
+            boxes[unit.tile_y as usize * width + unit.tile_x as usize] = Some(0);
+        }
+
+        // a box in the open is fine, two boxes side by side along the wall are frozen
+        boxes[2 * width + 4] = None;
+        boxes[2 * width + 3] = Some(0);
+        assert!(!detector.is_push_deadlock(&boxes, 2 * width + 3));
+
+        boxes[2 * width + 3] = None;
+        boxes[width + 4] = Some(0);
+        assert!(detector.is_push_deadlock(&boxes, width + 4));
+    }
+}
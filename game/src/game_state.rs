@@ -0,0 +1,371 @@
+use ferari::assets::LevelPack;
+
+use crate::save::Progress;
+use crate::session::solved_levels;
+
+/// Duration of the celebration of a solved level before its statistics are shown, in
+/// seconds.
+pub const LEVEL_COMPLETE_DELAY: f32 = 1.0;
+/// Duration of the fade-out before a scene change (and of the fade-in after it), in
+/// seconds.
+pub const FADE_DURATION: f32 = 0.25;
+
+/// Scene the session can load: a menu or a level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scene {
+    /// Menu of all packs
+    PackMenu,
+    /// Level menu of a pack
+    LevelMenu {
+        /// Index of the pack
+        pack: usize,
+    },
+    /// Level of a pack
+    Level {
+        /// Index of the pack
+        pack: usize,
+        /// Index of the level within the pack
+        level: usize,
+    },
+}
+
+impl Scene {
+    /// Returns the menu the scene belongs to: the level menu of a level's pack, the
+    /// pack menu for the level menus.
+    ///
+    /// # Arguments
+    ///
+    /// * `pack_count` - number of packs; with a single pack its level menu is the main
+    ///   menu and has no parent
+    pub fn parent(self, pack_count: usize) -> Option<Scene> {
+        match self {
+            Scene::Level { pack, .. } => Some(Scene::LevelMenu { pack }),
+            Scene::LevelMenu { .. } if pack_count > 1 => Some(Scene::PackMenu),
+            Scene::LevelMenu { .. } | Scene::PackMenu => None,
+        }
+    }
+}
+
+/// Request changing the game state, coming from an action or the game itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Reload the current level from its start
+    Restart,
+    /// Go to the next level of the pack, if unlocked
+    NextLevel,
+    /// Go to the previous level of the pack, if unlocked
+    PreviousLevel,
+    /// Leave a level for the next one, or for the level menu after the last unlocked level
+    Continue,
+    /// Leave the level (or the level menu) for the menu above
+    Back,
+    /// Pause or resume the level
+    TogglePause,
+    /// The level has been solved
+    Solved,
+    /// A menu entry has been chosen
+    Select(Scene),
+}
+
+/// State of the game, driven by [`crate::session::Session`].
+///
+/// Scene changes go through [`GameState::Transition`]: once the current scene has faded
+/// out the session loads the requested scene and enters the matching state (see
+/// [`GameState::entered`]).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameState {
+    /// A menu scene is shown
+    Menu(Scene),
+    /// A level is being played
+    Playing {
+        /// Index of the pack
+        pack: usize,
+        /// Index of the level within the pack
+        level: usize,
+    },
+    /// A level is paused, the simulation stands still
+    Paused {
+        /// Index of the pack
+        pack: usize,
+        /// Index of the level within the pack
+        level: usize,
+    },
+    /// A level has been solved: it is celebrated, then its statistics are shown until
+    /// the player continues
+    LevelComplete {
+        /// Index of the pack
+        pack: usize,
+        /// Index of the level within the pack
+        level: usize,
+        /// Time left of the celebration, in seconds
+        remaining: f32,
+    },
+    /// The current scene fades out, then the next one is loaded
+    Transition {
+        /// Scene to load
+        scene: Scene,
+        /// Time left of the fade-out, in seconds
+        remaining: f32,
+    },
+}
+
+impl GameState {
+    /// Returns the state of a freshly loaded scene.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - the loaded scene
+    pub fn entered(scene: Scene) -> Self {
+        match scene {
+            Scene::Level { pack, level } => GameState::Playing { pack, level },
+            menu => GameState::Menu(menu),
+        }
+    }
+
+    /// Returns the state fading out to a scene.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - the scene to load after the fade-out
+    pub fn transition(scene: Scene) -> Self {
+        GameState::Transition { scene, remaining: FADE_DURATION }
+    }
+
+    /// Returns the level of the state as `(pack, level)` indices, `None` in the menus and
+    /// during transitions.
+    pub fn level(&self) -> Option<(usize, usize)> {
+        match *self {
+            GameState::Playing { pack, level }
+            | GameState::Paused { pack, level }
+            | GameState::LevelComplete { pack, level, .. } => Some((pack, level)),
+            GameState::Menu(_) | GameState::Transition { .. } => None,
+        }
+    }
+
+    /// Returns the scene shown in the state, `None` during transitions.
+    pub fn scene(&self) -> Option<Scene> {
+        match *self {
+            GameState::Menu(scene) => Some(scene),
+            GameState::Transition { .. } => None,
+            _ => self.level().map(|(pack, level)| Scene::Level { pack, level }),
+        }
+    }
+
+    /// Returns the scene to load once the fade-out is over.
+    pub fn pending_scene(&self) -> Option<Scene> {
+        match *self {
+            GameState::Transition { scene, remaining } if remaining <= 0.0 => Some(scene),
+            _ => None,
+        }
+    }
+
+    /// Returns how far the screen has faded out, from `0.0` to `1.0`.
+    pub fn fade(&self) -> f32 {
+        match *self {
+            GameState::Transition { remaining, .. } => 1.0 - remaining.max(0.0) / FADE_DURATION,
+            _ => 0.0,
+        }
+    }
+
+    /// Returns the time since a level has been solved while it is being celebrated, in
+    /// seconds, `None` otherwise.
+    pub fn celebration(&self) -> Option<f32> {
+        match *self {
+            GameState::LevelComplete { remaining, .. } if remaining > 0.0 => {
+                Some(LEVEL_COMPLETE_DELAY - remaining)
+            }
+            _ => None,
+        }
+    }
+
+    /// Applies a command.
+    ///
+    /// Commands that do not fit the state (e.g. pausing a menu, or going past the last
+    /// level of a pack) leave it unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - the command to apply
+    /// * `packs` - all level packs
+    /// * `progress` - player progress (decides which levels are unlocked)
+    ///
+    /// # Returns
+    ///
+    /// The new state.
+    pub fn apply(self, command: Command, packs: &[LevelPack], progress: &Progress) -> Self {
+        let unlocked = |pack: usize, level: usize| {
+            packs[pack].is_unlocked(level, &solved_levels(&packs[pack], progress))
+        };
+
+        let next_level = |pack: usize, level: usize| {
+            Some(level + 1).filter(|&next| next < packs[pack].len() && unlocked(pack, next))
+        };
+
+        match (command, self.scene()) {
+            (Command::Select(scene), Some(_)) => GameState::transition(scene),
+            (Command::Back, Some(scene)) => {
+                scene.parent(packs.len()).map_or(self, GameState::transition)
+            }
+            (Command::Restart, Some(scene @ Scene::Level { .. })) => GameState::transition(scene),
+            (Command::NextLevel, Some(Scene::Level { pack, level })) => {
+                match next_level(pack, level) {
+                    Some(next) => GameState::transition(Scene::Level { pack, level: next }),
+                    None => self,
+                }
+            }
+            (Command::PreviousLevel, Some(Scene::Level { pack, level })) => {
+                match level.checked_sub(1).filter(|&prev| unlocked(pack, prev)) {
+                    Some(prev) => GameState::transition(Scene::Level { pack, level: prev }),
+                    None => self,
+                }
+            }
+            (Command::Continue, Some(Scene::Level { pack, level })) => {
+                GameState::transition(match next_level(pack, level) {
+                    Some(next) => Scene::Level { pack, level: next },
+                    None => Scene::LevelMenu { pack },
+                })
+            }
+            (Command::TogglePause, _) => match self {
+                GameState::Playing { pack, level } => GameState::Paused { pack, level },
+                GameState::Paused { pack, level } => GameState::Playing { pack, level },
+                _ => self,
+            },
+            (Command::Solved, _) => match self {
+                GameState::Playing { pack, level } => {
+                    GameState::LevelComplete { pack, level, remaining: LEVEL_COMPLETE_DELAY }
+                }
+                _ => self,
+            },
+            _ => self,
+        }
+    }
+
+    /// Advances the timers of the state.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - real time of the step, in seconds
+    ///
+    /// # Returns
+    ///
+    /// The new state with less time left of the celebration or the fade-out.
+    pub fn tick(self, delta: f32) -> Self {
+        match self {
+            GameState::LevelComplete { pack, level, remaining } => {
+                GameState::LevelComplete { pack, level, remaining: (remaining - delta).max(0.0) }
+            }
+            GameState::Transition { scene, remaining } => {
+                GameState::Transition { scene, remaining: (remaining - delta).max(0.0) }
+            }
+            _ => self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferari::assets::{LevelSource, PackLevel, UnlockRule};
+
+    fn packs(count: usize, unlock: UnlockRule) -> Vec<LevelPack> {
+        let pack = |i| LevelPack {
+            name: format!("pack{i}"),
+            author: String::new(),
+            unlock,
+            levels: (0..3)
+                .map(|level| PackLevel {
+                    source: LevelSource::File(format!("level{level}.json").into()),
+                    requires_solved: 0,
+                })
+                .collect(),
+        };
+        (0..count).map(pack).collect()
+    }
+
+    #[test]
+    fn test_level_navigation() {
+        let packs = packs(2, UnlockRule::All);
+        let progress = Progress::default();
+        let playing = GameState::Playing { pack: 1, level: 1 };
+
+        let next = playing.apply(Command::NextLevel, &packs, &progress);
+        assert_eq!(next, GameState::transition(Scene::Level { pack: 1, level: 2 }));
+        let last = GameState::Playing { pack: 1, level: 2 };
+        assert_eq!(last.apply(Command::NextLevel, &packs, &progress), last);
+
+        let prev = playing.apply(Command::PreviousLevel, &packs, &progress);
+        assert_eq!(prev, GameState::transition(Scene::Level { pack: 1, level: 0 }));
+        let first = GameState::Playing { pack: 1, level: 0 };
+        assert_eq!(first.apply(Command::PreviousLevel, &packs, &progress), first);
+
+        let restart = playing.apply(Command::Restart, &packs, &progress);
+        assert_eq!(restart, GameState::transition(Scene::Level { pack: 1, level: 1 }));
+        let menu = GameState::Menu(Scene::PackMenu);
+        assert_eq!(menu.apply(Command::Restart, &packs, &progress), menu);
+    }
+
+    #[test]
+    fn test_locked_levels_are_skipped() {
+        let packs = packs(1, UnlockRule::Sequential);
+        let playing = GameState::Playing { pack: 0, level: 0 };
+
+        assert_eq!(playing.apply(Command::NextLevel, &packs, &Progress::default()), playing);
+    }
+
+    #[test]
+    fn test_back_leads_to_the_parent_menu() {
+        let two = packs(2, UnlockRule::All);
+        let one = packs(1, UnlockRule::All);
+        let progress = Progress::default();
+        let paused = GameState::Paused { pack: 0, level: 2 };
+        let level_menu = GameState::Menu(Scene::LevelMenu { pack: 0 });
+
+        let back = paused.apply(Command::Back, &two, &progress);
+        assert_eq!(back, GameState::transition(Scene::LevelMenu { pack: 0 }));
+        let back = level_menu.apply(Command::Back, &two, &progress);
+        assert_eq!(back, GameState::transition(Scene::PackMenu));
+        assert_eq!(level_menu.apply(Command::Back, &one, &progress), level_menu);
+    }
+
+    #[test]
+    fn test_pause_and_completion() {
+        let packs = packs(1, UnlockRule::All);
+        let progress = Progress::default();
+        let playing = GameState::Playing { pack: 0, level: 1 };
+
+        let paused = playing.apply(Command::TogglePause, &packs, &progress);
+        assert_eq!(paused, GameState::Paused { pack: 0, level: 1 });
+        assert_eq!(paused.apply(Command::TogglePause, &packs, &progress), playing);
+        assert_eq!(paused.apply(Command::Solved, &packs, &progress), paused);
+
+        let complete = playing.apply(Command::Solved, &packs, &progress);
+        assert_eq!(complete.level(), Some((0, 1)));
+        let complete = complete.tick(LEVEL_COMPLETE_DELAY / 2.0);
+        assert_eq!(complete.celebration(), Some(LEVEL_COMPLETE_DELAY / 2.0));
+        let complete = complete.tick(LEVEL_COMPLETE_DELAY);
+        assert_eq!(complete.celebration(), None);
+        assert_eq!(complete.level(), Some((0, 1)));
+
+        let next = complete.apply(Command::Continue, &packs, &progress);
+        assert_eq!(next, GameState::transition(Scene::Level { pack: 0, level: 2 }));
+        let last = GameState::LevelComplete { pack: 0, level: 2, remaining: 0.0 };
+        let menu = last.apply(Command::Continue, &packs, &progress);
+        assert_eq!(menu, GameState::transition(Scene::LevelMenu { pack: 0 }));
+        assert_eq!(GameState::entered(Scene::Level { pack: 0, level: 1 }), playing);
+    }
+
+    #[test]
+    fn test_transition_fades_out() {
+        let transition = GameState::transition(Scene::PackMenu);
+        assert_eq!(transition.scene(), None);
+        assert_eq!(transition.fade(), 0.0);
+        assert_eq!(transition.pending_scene(), None);
+
+        let half = transition.tick(FADE_DURATION / 2.0);
+        assert_eq!(half.fade(), 0.5);
+        assert_eq!(half.pending_scene(), None);
+        let done = half.tick(FADE_DURATION);
+        assert_eq!(done.fade(), 1.0);
+        assert_eq!(done.pending_scene(), Some(Scene::PackMenu));
+    }
+}
@@ -0,0 +1,697 @@
+use std::path::Path;
+
+use ferari::assets::{GameMap, LevelPack, LevelSource, PackLevel, UnlockRule};
+use ferari::capture::{save_png, Recorder};
+use ferari::input::{InputEvent, InputSnapshot};
+use ferari::time::{Time, FIXED_STEP};
+#[cfg(test)]
+use ferari::world::State;
+use image::ImageResult;
+
+use crate::behaviour::input_for_direction;
+#[cfg(test)]
+use crate::game_state::GameState;
+use crate::replay::Replay;
+use crate::save::Progress;
+use crate::session::Session;
+use crate::settings::Settings;
+use crate::solver::SolutionStep;
+use crate::{LOGIC_HEIGHT, LOGIC_WIDTH};
+
+/// Duration of a simulated frame, one simulation step of the windowed game.
+pub const FRAME_DELTA: f32 = FIXED_STEP;
+/// Frames a single scripted move may take before the run is considered stuck.
+pub const MOVE_FRAME_LIMIT: usize = 600;
+/// Name of the pack a single level is wrapped in.
+const HEADLESS_PACK_NAME: &str = "headless";
+
+/// Game loop without a window.
+///
+/// Drives a [`Session`] with a synthetic clock and scripted input: no display, no
+/// keyboard, no frame limit. Progress and replays are kept in memory, so a run leaves
+/// no trace on disk. Rendering is optional and goes to an in-memory framebuffer.
+pub struct Headless {
+    /// Session being simulated
+    session: Session,
+    /// Synthetic clock (or the recorded deltas of a replay)
+    time: Time,
+    /// Duration of a simulated frame, in seconds
+    frame_delta: f32,
+    /// Framebuffer of `LOGIC_WIDTH * LOGIC_HEIGHT` pixels, `None` if rendering is disabled
+    framebuffer: Option<Vec<u32>>,
+    /// Recording of the rendered frames
+    recorder: Option<Recorder>,
+    /// Number of simulated frames
+    frames: usize,
+}
+
+impl Headless {
+    /// Wraps a session in a headless runner.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - session to simulate; its replay files are disabled
+    ///
+    /// # Returns
+    ///
+    /// A runner with a `FRAME_DELTA` clock and rendering disabled.
+    pub fn new(mut session: Session) -> Self {
+        session.set_save_replays(false);
+        Self {
+            session,
+            time: Time::new(),
+            frame_delta: FRAME_DELTA,
+            framebuffer: None,
+            recorder: None,
+            frames: 0,
+        }
+    }
+
+    /// Builds a runner that plays a single level, bypassing the level packs.
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - level to play
+    /// * `tiles_atlas` - texture atlas containing static tile graphics
+    /// * `entities_atlas` - texture atlas containing sprites for dynamic entities
+    ///
+    /// # Returns
+    ///
+    /// A runner whose first frame enters the level.
+    pub fn for_level(
+        game: GameMap,
+        tiles_atlas: ferari::assets::Atlas,
+        entities_atlas: ferari::assets::Atlas,
+    ) -> Self {
+        let pack = LevelPack {
+            name: HEADLESS_PACK_NAME.to_string(),
+            author: String::new(),
+            unlock: UnlockRule::All,
+            levels: vec![PackLevel {
+                source: LevelSource::Map(Box::new(game)),
+                requires_solved: 0,
+            }],
+        };
+        let session = Session::new(
+            vec![pack],
+            tiles_atlas,
+            entities_atlas,
+            Progress::default(),
+            Settings::default(),
+            Some((0, Some(0))),
+        );
+        Self::new(session)
+    }
+
+    /// Enables rendering every simulated frame into the framebuffer.
+    pub fn with_framebuffer(mut self) -> Self {
+        self.framebuffer = Some(vec![0; LOGIC_WIDTH * LOGIC_HEIGHT]);
+        self
+    }
+
+    /// Records every simulated frame (enables rendering).
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self.with_framebuffer()
+    }
+
+    /// Plays back a recorded session: its inputs replace the scripted input and its frame
+    /// deltas drive the clock.
+    ///
+    /// The session has to be started in the level of the replay.
+    pub fn with_replay(mut self, replay: &Replay) -> Self {
+        self.session.start_playback(replay);
+        self.time = Time::from_deltas(replay.deltas());
+        self
+    }
+
+    /// Simulates a single frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - input of the frame
+    pub fn step(&mut self, input: InputSnapshot) {
+        self.step_with_events(input, &[]);
+    }
+
+    /// Simulates a single frame with input events (e.g. a tap shorter than a frame).
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - input of the frame
+    /// * `events` - input events since the previous frame
+    pub fn step_with_events(&mut self, input: InputSnapshot, events: &[InputEvent]) {
+        self.time.advance_recorded(self.frame_delta);
+
+        self.session.update(input, events, &[], &mut self.time);
+        if let Some(buf) = &mut self.framebuffer {
+            self.session.render(&self.time, buf);
+
+            if let Some(recorder) = &mut self.recorder {
+                if let Err(err) = recorder.push(buf, self.time.delta) {
+                    eprintln!("Cannot record frame {}: {err}", self.frames);
+                    self.recorder = None;
+                }
+            }
+        }
+        self.frames += 1;
+    }
+
+    /// Clicks on the screen, see [`Session::click`].
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y` - click position on screen, in logical pixels
+    #[cfg(test)]
+    pub fn click(&mut self, x: f32, y: f32) {
+        self.session.click(x, y);
+    }
+
+    /// Simulates several frames with the same input.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - input held during the frames
+    /// * `frames` - number of frames
+    #[cfg(test)]
+    pub fn hold(&mut self, input: InputSnapshot, frames: usize) {
+        for _ in 0..frames {
+            self.step(input);
+        }
+    }
+
+    /// Simulates frames without input until the player and all boxes stand still.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_frames` - maximal number of frames to simulate
+    ///
+    /// # Returns
+    ///
+    /// `true` if everything stands still, `false` if the frame budget ran out.
+    pub fn wait_idle(&mut self, max_frames: usize) -> bool {
+        for _ in 0..max_frames {
+            if self.session.is_idle() {
+                return true;
+            }
+            self.step(InputSnapshot::default());
+        }
+        self.session.is_idle()
+    }
+
+    /// Simulates frames without input until the current scene change is over.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_frames` - maximal number of frames to simulate
+    ///
+    /// # Returns
+    ///
+    /// `true` if the next scene has been entered, `false` if the frame budget ran out.
+    #[cfg(test)]
+    pub fn wait_scene(&mut self, max_frames: usize) -> bool {
+        let changing =
+            |session: &Session| matches!(session.game_state(), GameState::Transition { .. });
+        for _ in 0..max_frames {
+            if !changing(&self.session) {
+                return true;
+            }
+            self.step(InputSnapshot::default());
+        }
+        !changing(&self.session)
+    }
+
+    /// Plays a move sequence, pressing each direction for one frame once the previous move
+    /// has finished.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps` - moves to play, e.g. parsed by [`crate::solver::from_lurd`]
+    ///
+    /// # Returns
+    ///
+    /// `true` if every move has been played and finished, `false` if a move got stuck.
+    pub fn play_moves(&mut self, steps: &[SolutionStep]) -> bool {
+        for step in steps {
+            if !self.wait_idle(MOVE_FRAME_LIMIT) {
+                return false;
+            }
+            self.step(input_for_direction(step.direction));
+        }
+        self.wait_idle(MOVE_FRAME_LIMIT)
+    }
+
+    /// Plays the recorded inputs of the replay to the end.
+    pub fn play_replay(&mut self) {
+        while self.session.is_playing_back() {
+            self.step(InputSnapshot::default());
+        }
+    }
+
+    /// Saves the current frame as a PNG file, rendering it if rendering is disabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - location of the PNG file
+    pub fn save_screenshot<P: AsRef<Path>>(&mut self, path: P) -> ImageResult<()> {
+        let mut rendered;
+        let frame = match &self.framebuffer {
+            Some(buf) => buf,
+            None => {
+                rendered = vec![0; LOGIC_WIDTH * LOGIC_HEIGHT];
+                self.session.render(&self.time, &mut rendered);
+                &rendered
+            }
+        };
+        save_png(frame, LOGIC_WIDTH, LOGIC_HEIGHT, path)
+    }
+
+    /// Ends the recording of the rendered frames.
+    ///
+    /// # Returns
+    ///
+    /// The number of recorded frames, `None` if nothing has been recorded.
+    pub fn finish_recording(&mut self) -> Option<usize> {
+        self.recorder.take().map(Recorder::finish)
+    }
+
+    /// Returns the simulated session.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Returns the units of the current scene.
+    #[cfg(test)]
+    pub fn state(&self) -> &State {
+        self.session.state()
+    }
+
+    /// Returns the last rendered frame, `None` if rendering is disabled.
+    #[cfg(test)]
+    pub fn framebuffer(&self) -> Option<&[u32]> {
+        self.framebuffer.as_deref()
+    }
+
+    /// Returns the number of simulated frames.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Returns the synthetic clock.
+    pub fn time(&self) -> &Time {
+        &self.time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::LEVEL_COMPLETE_DELAY;
+    use crate::session::{discover_packs, find_level, load_atlases};
+    use crate::solver::{from_lurd, solve, to_lurd, Solution, SolveMode};
+    use ferari::input::{Action, InputEventKind};
+
+    /// Frames of the celebration of a solved level.
+    const CELEBRATION_FRAMES: usize = (LEVEL_COMPLETE_DELAY / FRAME_DELTA) as usize + 1;
+
+    fn press(action: Action) -> InputEvent {
+        InputEvent { action, kind: InputEventKind::Pressed, time: 0.0 }
+    }
+
+    fn level(path: &str) -> Headless {
+        let (tiles_atlas, entities_atlas) = load_atlases().unwrap();
+        Headless::for_level(GameMap::load(path).unwrap(), tiles_atlas, entities_atlas)
+    }
+
+    #[test]
+    fn test_solution_solves_level_and_returns_to_menu() {
+        let game = GameMap::load("../game_levels/level1.json").unwrap();
+        let Solution::Solved(steps) = solve(&game, &State::new(&game), SolveMode::Moves) else {
+            panic!("level1 must be solvable");
+        };
+        let mut headless = level("../game_levels/level1.json");
+
+        assert!(headless.play_moves(&steps));
+
+        let result = headless.session().last_result().expect("level must be solved");
+        assert_eq!(result.moves as usize, steps.len());
+        assert_eq!(result.pushes as usize, steps.iter().filter(|step| step.push).count());
+        assert_eq!(result.level_id, Some(format!("{HEADLESS_PACK_NAME}/{}", game.name)));
+        assert!(result.time > 0.0);
+
+        // the solved level is celebrated, then continuing leaves the only level of the pack
+        headless.step(InputSnapshot::default());
+        assert!(headless.session().game_state().celebration().is_some());
+        headless.hold(InputSnapshot::default(), CELEBRATION_FRAMES);
+        assert_eq!(headless.session().current_level(), Some((0, 0)));
+        headless.step_with_events(InputSnapshot::default(), &[press(Action::MoveRight)]);
+        assert!(headless.wait_scene(MOVE_FRAME_LIMIT));
+        assert_eq!(headless.session().current_level(), None);
+    }
+
+    #[test]
+    fn test_solution_is_replayed_from_the_complete_screen() {
+        let game = GameMap::load("../game_levels/level1.json").unwrap();
+        let Solution::Solved(steps) = solve(&game, &State::new(&game), SolveMode::Moves) else {
+            panic!("level1 must be solvable");
+        };
+        let mut headless = level("../game_levels/level1.json");
+        assert!(headless.play_moves(&steps));
+        let expected = headless.session().last_result().cloned().unwrap();
+
+        headless.hold(InputSnapshot::default(), CELEBRATION_FRAMES);
+        headless.step_with_events(InputSnapshot::default(), &[press(Action::MoveDown)]);
+        headless.step_with_events(InputSnapshot::default(), &[press(Action::MoveRight)]);
+        assert!(headless.wait_scene(MOVE_FRAME_LIMIT));
+        assert!(headless.session().is_playing_back());
+        assert_eq!(headless.session().history().moves(), 0);
+
+        headless.play_replay();
+        headless.step(InputSnapshot::default());
+        let result = headless.session().last_result().unwrap();
+        assert_eq!((result.moves, result.pushes), (expected.moves, expected.pushes));
+        assert!(headless.session().game_state().celebration().is_some());
+    }
+
+    #[test]
+    fn test_optimal_counts_arrive_on_the_complete_screen() {
+        let game = GameMap::load("../game_levels/level1.json").unwrap();
+        let Solution::Solved(steps) = solve(&game, &State::new(&game), SolveMode::Moves) else {
+            panic!("level1 must be solvable");
+        };
+        let mut headless = level("../game_levels/level1.json");
+        assert!(headless.play_moves(&steps));
+
+        // the screen is shown right away and filled in once the solver is done
+        let start = std::time::Instant::now();
+        while headless.session().complete_screen().unwrap().summary().optimal_moves.is_none() {
+            assert!(start.elapsed().as_secs() < 30, "the optimal counts must arrive");
+            headless.step(InputSnapshot::default());
+        }
+        let summary = headless.session().complete_screen().unwrap().summary();
+        assert_eq!(summary.optimal_moves, Some(steps.len()));
+        assert!(summary.optimal_pushes.is_some());
+    }
+
+    #[test]
+    fn test_unfinished_moves_do_not_solve() {
+        let mut headless = level("../game_levels/level2.json");
+
+        assert!(headless.play_moves(&from_lurd("l").unwrap()));
+
+        assert_eq!(headless.session().last_result(), None);
+        assert_eq!(headless.session().current_level(), Some((0, 0)));
+        assert_eq!(headless.session().history().moves(), 1);
+    }
+
+    #[test]
+    fn test_tap_during_animation_is_buffered() {
+        let mut headless = level("../game_levels/level2.json");
+        headless.step(InputSnapshot::default());
+        headless.step(InputSnapshot { left: true, ..Default::default() });
+        assert!(!headless.session().is_idle());
+
+        // pressed and released between two frames
+        let events = [
+            InputEvent { action: Action::MoveRight, kind: InputEventKind::Pressed, time: 0.0 },
+            InputEvent { action: Action::MoveRight, kind: InputEventKind::Released, time: 0.01 },
+        ];
+        headless.step_with_events(InputSnapshot::default(), &events);
+        assert!(headless.wait_idle(MOVE_FRAME_LIMIT));
+        headless.step(InputSnapshot::default());
+        assert!(headless.wait_idle(MOVE_FRAME_LIMIT));
+
+        assert_eq!(headless.session().history().moves(), 2);
+    }
+
+    #[test]
+    fn test_held_undo_undoes_one_step() {
+        let mut headless = level("../game_levels/level2.json");
+        assert!(headless.play_moves(&from_lurd("lr").unwrap()));
+
+        headless.hold(InputSnapshot { undo: true, ..Default::default() }, MOVE_FRAME_LIMIT);
+        assert_eq!(headless.session().history().moves(), 1);
+
+        headless.step(InputSnapshot::default());
+        headless.step(InputSnapshot { undo: true, ..Default::default() });
+        assert!(headless.wait_idle(MOVE_FRAME_LIMIT));
+        assert_eq!(headless.session().history().moves(), 0);
+    }
+
+    #[test]
+    fn test_hint_is_searched_in_the_background() {
+        let mut headless = level("../game_levels/level1.json");
+        headless.step(InputSnapshot::default());
+        headless.step(InputSnapshot { solution: true, ..Default::default() });
+
+        // the game keeps running while the solver thinks, then plays the solution
+        let start = std::time::Instant::now();
+        while headless.session().last_result().is_none() {
+            assert!(start.elapsed().as_secs() < 30, "the hint must arrive");
+            headless.step(InputSnapshot::default());
+        }
+        assert!(headless.session().history().pushes() > 0);
+    }
+
+    #[test]
+    fn test_deadlocking_push_is_undone_when_enabled() {
+        let rows = ["######", "#    #", "# $  #", "# @ .#", "######"];
+        let (tiles_atlas, entities_atlas) = load_atlases().unwrap();
+        let pack = LevelPack {
+            name: HEADLESS_PACK_NAME.to_string(),
+            author: String::new(),
+            unlock: UnlockRule::All,
+            levels: vec![PackLevel {
+                source: LevelSource::Map(Box::new(GameMap::from_xsb("dead", &rows).unwrap())),
+                requires_solved: 0,
+            }],
+        };
+        let mut settings = Settings::default();
+        settings.auto_undo_deadlock = true;
+        let session = Session::new(
+            vec![pack],
+            tiles_atlas,
+            entities_atlas,
+            Progress::default(),
+            settings,
+            Some((0, Some(0))),
+        );
+        let mut headless = Headless::new(session);
+        headless.step(InputSnapshot::default());
+
+        // pushing the box against the top wall strands it away from the target
+        headless.step(InputSnapshot { up: true, ..Default::default() });
+        assert!(headless.wait_idle(MOVE_FRAME_LIMIT));
+        headless.hold(InputSnapshot::default(), 2);
+        assert!(headless.wait_idle(MOVE_FRAME_LIMIT));
+
+        assert_eq!(headless.session().history().pushes(), 0);
+        assert_eq!(headless.state().get_mob_at(2, 2), Some(0));
+    }
+
+    #[test]
+    fn test_level_with_unknown_frames_is_not_entered() {
+        let (tiles_atlas, entities_atlas) = load_atlases().unwrap();
+        let mut game = GameMap::from_xsb("frames", &["#####", "#@$.#", "#####"]).unwrap();
+        game.mobs.get_mut("box_1").unwrap().asset = "no_such_frame".to_string();
+        let mut headless = Headless::for_level(game, tiles_atlas, entities_atlas);
+
+        headless.step(InputSnapshot::default());
+        assert!(headless.wait_scene(MOVE_FRAME_LIMIT));
+        assert_eq!(headless.session().current_level(), None);
+    }
+
+    /// Returns the screen position of a tile relative to the player, who stands in the
+    /// center of the screen.
+    fn tile_on_screen(dx: i32, dy: i32) -> (f32, f32) {
+        let x = LOGIC_WIDTH as i32 / 2 + (dx - dy) * 64;
+        let y = LOGIC_HEIGHT as i32 / 2 + (dx + dy) * 32 - 16;
+        (x as f32, y as f32)
+    }
+
+    /// Lets the session play the moves queued by clicks, one per idle frame.
+    fn play_queued_moves(headless: &mut Headless, moves: usize) {
+        for _ in 0..moves {
+            headless.step(InputSnapshot::default());
+            assert!(headless.wait_idle(MOVE_FRAME_LIMIT));
+        }
+    }
+
+    #[test]
+    fn test_instant_moves_and_pause() {
+        let rows = ["#######", "#@$  .#", "#######"];
+        let (tiles_atlas, entities_atlas) = load_atlases().unwrap();
+        let game = GameMap::from_xsb("speed", &rows).unwrap();
+        let mut headless = Headless::for_level(game, tiles_atlas, entities_atlas);
+
+        // approach, push and recoil end in the frame of the key press, at the real frame time
+        headless.step_with_events(InputSnapshot::default(), &[press(Action::InstantMoves)]);
+        assert_eq!(headless.session().notice(), Some("GAME SPEED 100 PERCENT  INSTANT MOVES"));
+        headless.step(InputSnapshot { right: true, ..Default::default() });
+        assert!(headless.session().is_idle());
+        assert_eq!(headless.session().history().pushes(), 1);
+        assert_eq!(headless.time().scaled_delta(), FRAME_DELTA);
+
+        headless.step_with_events(InputSnapshot::default(), &[press(Action::Pause)]);
+        assert!(headless.time().is_paused());
+        assert_eq!(headless.session().notice(), Some("PAUSED"));
+        headless.hold(InputSnapshot { right: true, ..Default::default() }, 10);
+        assert_eq!(headless.session().history().moves(), 1);
+
+        headless.step_with_events(InputSnapshot::default(), &[press(Action::Pause)]);
+        headless.step(InputSnapshot { right: true, ..Default::default() });
+        assert!(headless.session().is_idle());
+        assert_eq!(headless.session().history().moves(), 2);
+    }
+
+    #[test]
+    fn test_click_walks_and_pushes() {
+        let rows = ["########", "#@ $  .#", "#      #", "########"];
+        let (tiles_atlas, entities_atlas) = load_atlases().unwrap();
+        let game = GameMap::from_xsb("clicks", &rows).unwrap();
+        let mut headless = Headless::for_level(game, tiles_atlas, entities_atlas);
+        headless.step(InputSnapshot::default());
+
+        // select the box, then click two tiles to its right
+        let (x, y) = tile_on_screen(2, 0);
+        headless.click(x, y);
+        let (x, y) = tile_on_screen(4, 0);
+        headless.click(x, y);
+        play_queued_moves(&mut headless, 3);
+
+        let history = headless.session().history();
+        assert_eq!((history.moves(), history.pushes()), (3, 2));
+        assert_eq!(headless.state().get_mob_at(5, 1), Some(0));
+
+        // walk one tile down
+        let (x, y) = tile_on_screen(0, 1);
+        headless.click(x, y);
+        play_queued_moves(&mut headless, 1);
+        let player = &headless.state().player.unit;
+        assert_eq!((player.tile_x, player.tile_y), (4, 2));
+    }
+
+    #[test]
+    fn test_restart_reloads_level() {
+        let mut headless = level("../game_levels/level2.json");
+        assert!(headless.play_moves(&from_lurd("l").unwrap()));
+
+        headless.step(InputSnapshot { restart: true, ..Default::default() });
+        assert!(headless.wait_scene(MOVE_FRAME_LIMIT));
+
+        assert_eq!(headless.session().current_level(), Some((0, 0)));
+        assert_eq!(headless.session().history().moves(), 0);
+    }
+
+    #[test]
+    fn test_level_navigation_and_pause() {
+        let packs = discover_packs();
+        let (pack, idx) = find_level(&packs, "Ferari/level2.json").unwrap();
+        let (tiles_atlas, entities_atlas) = load_atlases().unwrap();
+        let session = Session::new(
+            packs,
+            tiles_atlas,
+            entities_atlas,
+            Progress::default(),
+            Settings::default(),
+            Some((pack, Some(idx))),
+        );
+        let mut headless = Headless::new(session);
+
+        headless.step(InputSnapshot { next_level: true, ..Default::default() });
+        assert!(headless.wait_scene(MOVE_FRAME_LIMIT));
+        assert_eq!(headless.session().current_level(), Some((pack, idx + 1)));
+
+        // level commands still work while paused
+        headless.step_with_events(InputSnapshot::default(), &[press(Action::Pause)]);
+        assert!(matches!(headless.session().game_state(), GameState::Paused { .. }));
+        headless.step(InputSnapshot { previous_level: true, ..Default::default() });
+        assert!(headless.wait_scene(MOVE_FRAME_LIMIT));
+        assert_eq!(headless.session().game_state(), GameState::Playing { pack, level: idx });
+        assert!(!headless.time().is_paused());
+    }
+
+    #[test]
+    fn test_framebuffer_is_rendered() {
+        let mut headless = level("../game_levels/level2.json").with_framebuffer();
+        assert!(headless.framebuffer().unwrap().iter().all(|&p| p == 0));
+
+        headless.hold(InputSnapshot::default(), 2);
+
+        let buf = headless.framebuffer().unwrap();
+        assert_eq!(buf.len(), LOGIC_WIDTH * LOGIC_HEIGHT);
+        assert!(buf.iter().any(|&p| p != 0));
+        assert_eq!(headless.frames(), 2);
+        assert!((headless.time().total - 2.0 * FRAME_DELTA).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_frames_are_recorded_and_captured() {
+        let dir = std::env::temp_dir().join(format!("ferari_headless_{}", std::process::id()));
+        let recorder = Recorder::create(dir.join("frames"), LOGIC_WIDTH, LOGIC_HEIGHT).unwrap();
+        let mut headless = level("../game_levels/level2.json").with_recorder(recorder);
+
+        headless.hold(InputSnapshot::default(), 3);
+        headless.save_screenshot(dir.join("shot.png")).unwrap();
+        let frames = headless.finish_recording();
+        let shot = image::open(dir.join("shot.png")).unwrap().to_rgba8();
+        let recorded = std::fs::read_dir(dir.join("frames")).unwrap().count();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(frames, Some(3));
+        assert_eq!(recorded, 3);
+        assert_eq!(shot.dimensions(), (LOGIC_WIDTH as u32, LOGIC_HEIGHT as u32));
+        assert_eq!(headless.finish_recording(), None);
+    }
+
+    #[test]
+    fn test_replay_is_played_back_in_its_level() {
+        let packs = discover_packs();
+        let (pack, idx) = find_level(&packs, "Ferari/level1.json").unwrap();
+        let game = packs[pack].load_level(idx).unwrap();
+        let Solution::Solved(steps) = solve(&game, &State::new(&game), SolveMode::Moves) else {
+            panic!("level1 must be solvable");
+        };
+
+        // record the solution by playing it in the first session
+        let (tiles_atlas, entities_atlas) = load_atlases().unwrap();
+        let session = Session::new(
+            packs.clone(),
+            tiles_atlas.clone(),
+            entities_atlas.clone(),
+            Progress::default(),
+            Settings::default(),
+            Some((pack, Some(idx))),
+        );
+        let mut replay = Replay::new("Ferari/level1.json");
+        let mut recorded = Headless::new(session);
+        recorded.step(InputSnapshot::default());
+        for step in from_lurd(&to_lurd(&steps)).unwrap() {
+            recorded.wait_idle(MOVE_FRAME_LIMIT);
+            let input = input_for_direction(step.direction);
+            replay.record(FRAME_DELTA, false, &input);
+            recorded.step(input);
+            while !recorded.session().is_idle() {
+                replay.record(FRAME_DELTA, false, &InputSnapshot::default());
+                recorded.step(InputSnapshot::default());
+            }
+        }
+        let expected = recorded.session().last_result().cloned().unwrap();
+
+        let session = Session::new(
+            packs,
+            tiles_atlas,
+            entities_atlas,
+            Progress::default(),
+            Settings::default(),
+            Some((pack, Some(idx))),
+        );
+        let mut played = Headless::new(session).with_replay(&replay);
+        played.play_replay();
+
+        let result = played.session().last_result().expect("replay must solve the level");
+        assert_eq!((result.moves, result.pushes), (expected.moves, expected.pushes));
+        // a played back solution is not a new result
+        assert!(!played.session().progress().is_solved("Ferari/level1.json"));
+    }
+}
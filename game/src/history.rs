@@ -0,0 +1,184 @@
+use ferari::world::Direction;
+
+/// Box displacement caused by a single push.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PushRecord {
+    /// Index of the pushed box within `State.mobs`
+    pub box_idx: usize,
+    /// Tile the box was pushed from
+    pub from: (i32, i32),
+    /// Tile the box ended up on (after sliding, teleporting or falling into a hole)
+    pub to: (i32, i32),
+}
+
+/// A single committed player step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepRecord {
+    /// Tile the player stood on before the step
+    pub player_from: (i32, i32),
+    /// Tile the player ended up on (after sliding or teleporting)
+    pub player_to: (i32, i32),
+    /// Direction of the step
+    pub direction: Direction,
+    /// Box pushed during the step, if any
+    pub push: Option<PushRecord>,
+}
+
+/// Move history of the current level with undo and redo stacks.
+///
+/// Every committed step is pushed onto the undo stack. Undoing a step moves it
+/// onto the redo stack; committing a new step that differs from the next redo
+/// step discards the whole redo stack.
+#[derive(Debug, Default)]
+pub struct History {
+    /// Committed steps, the most recent one last
+    undo_stack: Vec<StepRecord>,
+    /// Undone steps, the next one to redo last
+    redo_stack: Vec<StepRecord>,
+}
+
+impl History {
+    /// Creates an empty history.
+    ///
+    /// # Returns
+    ///
+    /// A new `History` with empty undo and redo stacks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a committed step.
+    ///
+    /// If the step is exactly the next step on the redo stack (i.e. it is being redone),
+    /// only that entry is consumed. Otherwise the redo stack is cleared.
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - the step that has just been committed
+    pub fn record(&mut self, step: StepRecord) {
+        if self.redo_stack.last() == Some(&step) {
+            self.redo_stack.pop();
+        } else {
+            self.redo_stack.clear();
+        }
+
+        self.undo_stack.push(step);
+    }
+
+    /// Takes the most recent step off the undo stack and moves it to the redo stack.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(StepRecord)` - the step that has to be reverted.
+    /// * `None` - if there is nothing to undo.
+    pub fn undo(&mut self) -> Option<StepRecord> {
+        let step = self.undo_stack.pop()?;
+        self.redo_stack.push(step);
+        Some(step)
+    }
+
+    /// Returns the step that would be replayed by a redo.
+    ///
+    /// The step stays on the redo stack until it is committed again via [`History::record`].
+    ///
+    /// # Returns
+    ///
+    /// * `Some(StepRecord)` - the next step to redo.
+    /// * `None` - if there is nothing to redo.
+    pub fn peek_redo(&self) -> Option<StepRecord> {
+        self.redo_stack.last().copied()
+    }
+
+    /// Returns the number of committed steps (moves) in the history.
+    pub fn moves(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    /// Returns the number of committed steps that pushed a box.
+    pub fn pushes(&self) -> usize {
+        self.undo_stack.iter().filter(|step| step.push.is_some()).count()
+    }
+
+    /// Forgets all recorded steps.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn walk(from: (i32, i32), direction: Direction) -> StepRecord {
+        StepRecord { player_from: from, player_to: (from.0 + 1, from.1), direction, push: None }
+    }
+
+    fn push(from: (i32, i32), direction: Direction, box_idx: usize) -> StepRecord {
+        StepRecord {
+            player_from: from,
+            player_to: (from.0 + 1, from.1),
+            direction,
+            push: Some(PushRecord {
+                box_idx,
+                from: (from.0 + 1, from.1),
+                to: (from.0 + 2, from.1),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_undo_returns_steps_in_reverse_order() {
+        let mut history = History::new();
+        history.record(walk((0, 0), Direction::SE));
+        history.record(walk((1, 0), Direction::SW));
+
+        assert_eq!(history.undo(), Some(walk((1, 0), Direction::SW)));
+        assert_eq!(history.undo(), Some(walk((0, 0), Direction::SE)));
+        assert_eq!(history.undo(), None);
+    }
+
+    #[test]
+    fn test_redo_is_consumed_by_matching_record() {
+        let mut history = History::new();
+        history.record(walk((0, 0), Direction::SE));
+        history.record(push((1, 0), Direction::SE, 3));
+
+        history.undo();
+        history.undo();
+        assert_eq!(history.peek_redo(), Some(walk((0, 0), Direction::SE)));
+
+        history.record(walk((0, 0), Direction::SE));
+        assert_eq!(history.peek_redo(), Some(push((1, 0), Direction::SE, 3)));
+        assert_eq!(history.moves(), 1);
+    }
+
+    #[test]
+    fn test_new_step_clears_redo_stack() {
+        let mut history = History::new();
+        history.record(walk((0, 0), Direction::SE));
+        history.undo();
+
+        history.record(walk((0, 0), Direction::SW));
+        assert_eq!(history.peek_redo(), None);
+    }
+
+    #[test]
+    fn test_moves_and_pushes_count() {
+        let mut history = History::new();
+        history.record(walk((0, 0), Direction::SE));
+        history.record(push((1, 0), Direction::SE, 0));
+        history.record(push((2, 0), Direction::SE, 0));
+
+        assert_eq!(history.moves(), 3);
+        assert_eq!(history.pushes(), 2);
+
+        history.undo();
+        assert_eq!(history.moves(), 2);
+        assert_eq!(history.pushes(), 1);
+
+        history.clear();
+        assert_eq!(history.moves(), 0);
+        assert_eq!(history.peek_redo(), None);
+    }
+}
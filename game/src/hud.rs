@@ -0,0 +1,178 @@
+use ferari::render::{BitmapFont, Hud};
+use ferari::time::TimeScale;
+
+/// Scaling factor of the HUD font glyphs (tile atlas letters are one tile big).
+pub const HUD_FONT_SCALE: f32 = 0.3;
+/// Distance of the HUD from the top-left corner of the screen, in pixels.
+const HUD_MARGIN: i32 = 4;
+/// Extra space between two HUD lines, in pixels.
+const HUD_LINE_GAP: i32 = 6;
+/// Number of lines of the level HUD.
+const LEVEL_HUD_LINES: usize = 5;
+
+/// Counters of the level being played.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelStats<'a> {
+    /// Name of the level
+    pub level_name: &'a str,
+    /// Number of moves
+    pub moves: usize,
+    /// Number of pushes
+    pub pushes: usize,
+    /// Time spent in the level, in seconds
+    pub time: f32,
+    /// Number of boxes standing on a target
+    pub boxes_on_target: usize,
+    /// Number of targets of the level
+    pub targets: usize,
+}
+
+/// Builds the in-game HUD showing the level counters.
+///
+/// # Arguments
+///
+/// * `stats` - counters of the current level
+/// * `font` - font the HUD is drawn with (decides the line spacing)
+///
+/// # Returns
+///
+/// An overlay with one line per counter in the top-left corner of the screen.
+pub fn level_hud(stats: &LevelStats, font: &BitmapFont) -> Hud {
+    let lines: [String; LEVEL_HUD_LINES] = [
+        stats.level_name.to_string(),
+        format!("MOVES {}", stats.moves),
+        format!("PUSHES {}", stats.pushes),
+        format!("TIME {}", format_time(stats.time)),
+        format!("BOXES {} OF {}", stats.boxes_on_target, stats.targets),
+    ];
+
+    let mut hud = Hud::new();
+    for (i, line) in lines.into_iter().enumerate() {
+        hud.text(HUD_MARGIN, line_y(i, font), line);
+    }
+    hud
+}
+
+/// Builds the HUD line showing a paused, slowed down or sped up game.
+///
+/// # Arguments
+///
+/// * `speed` - speed of the game
+/// * `paused` - whether the game is paused
+/// * `font` - font the HUD is drawn with (decides the line spacing)
+///
+/// # Returns
+///
+/// An overlay with one line below the level counters, or `None` at normal speed.
+pub fn speed_hud(speed: TimeScale, paused: bool, font: &BitmapFont) -> Option<Hud> {
+    let mut parts = Vec::new();
+    if paused {
+        parts.push("PAUSED".to_string());
+    }
+    if speed.percent() != 100 {
+        parts.push(format!("SPEED {} PERCENT", speed.percent()));
+    }
+    if speed.instant {
+        parts.push("INSTANT MOVES".to_string());
+    }
+    if parts.is_empty() {
+        return None;
+    }
+
+    let mut hud = Hud::new();
+    hud.text(HUD_MARGIN, line_y(LEVEL_HUD_LINES, font), parts.join("  "));
+    Some(hud)
+}
+
+/// Builds the HUD line showing a status message, e.g. a hint being searched for.
+///
+/// # Arguments
+///
+/// * `message` - text of the line (letters, digits and spaces only)
+/// * `font` - font the HUD is drawn with (decides the line spacing)
+///
+/// # Returns
+///
+/// An overlay with one line below the speed line.
+pub fn status_hud(message: &str, font: &BitmapFont) -> Hud {
+    let mut hud = Hud::new();
+    hud.text(HUD_MARGIN, line_y(LEVEL_HUD_LINES + 1, font), message.to_string());
+    hud
+}
+
+/// Returns the vertical position of a HUD line.
+fn line_y(line: usize, font: &BitmapFont) -> i32 {
+    HUD_MARGIN + line as i32 * (font.line_height + HUD_LINE_GAP)
+}
+
+/// Formats a duration with the glyphs available in the tile atlas (no `:`).
+///
+/// # Arguments
+///
+/// * `seconds` - duration in seconds
+///
+/// # Returns
+///
+/// The duration as minutes and seconds, e.g. `2M05S`.
+pub fn format_time(seconds: f32) -> String {
+    let total = seconds.max(0.0) as u32;
+    format!("{}M{:02}S", total / 60, total % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferari::assets::{Atlas, Frame};
+    use image::RgbaImage;
+    use std::collections::HashMap;
+
+    fn test_font() -> BitmapFont {
+        let frame = Frame { name: "letter_A".into(), x: 0, y: 0, w: 10, h: 20 };
+        let atlas = Atlas {
+            image: RgbaImage::new(10, 20),
+            frames: HashMap::from([(frame.name.clone(), frame)]),
+            tile_size: 10,
+            version: 1,
+        };
+        BitmapFont::from_atlas(&atlas, 1.0)
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0.0), "0M00S");
+        assert_eq!(format_time(65.9), "1M05S");
+        assert_eq!(format_time(-3.0), "0M00S");
+    }
+
+    #[test]
+    fn test_level_hud_lines() {
+        let stats = LevelStats {
+            level_name: "level1",
+            moves: 12,
+            pushes: 3,
+            time: 125.0,
+            boxes_on_target: 1,
+            targets: 2,
+        };
+        let hud = level_hud(&stats, &test_font());
+
+        let lines: Vec<&str> = hud.texts.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(lines, ["level1", "MOVES 12", "PUSHES 3", "TIME 2M05S", "BOXES 1 OF 2"]);
+        assert_eq!(hud.texts[0].y, HUD_MARGIN);
+        assert_eq!(hud.texts[1].y, HUD_MARGIN + 10 + HUD_LINE_GAP);
+    }
+
+    #[test]
+    fn test_speed_hud() {
+        let font = test_font();
+        assert!(speed_hud(TimeScale::default(), false, &font).is_none());
+
+        let hud = speed_hud(TimeScale::default(), true, &font).unwrap();
+        assert_eq!(hud.texts[0].text, "PAUSED");
+        assert_eq!(hud.texts[0].y, line_y(LEVEL_HUD_LINES, &font));
+
+        let speed = TimeScale { scale: 0.5, instant: true };
+        let hud = speed_hud(speed, false, &font).unwrap();
+        assert_eq!(hud.texts[0].text, "SPEED 50 PERCENT  INSTANT MOVES");
+    }
+}
@@ -1,31 +1,71 @@
-use crate::{
-    world::{Camera, Unit},
-    MOVEMENT_SPEEDUP,
-};
+use ferari::world::{Camera, Player, State, Unit, UnitMovement};
 
-use ferari::world::{Player, State, UnitMovement};
+/// Returns the pixel positions of all units, the player first followed by the mobs.
+///
+/// # Arguments
+///
+/// * `cur_state` - The current game state containing all units
+///
+/// # Returns
+///
+/// The `(pixel_x, pixel_y)` of every unit, in the order of [`get_visible_objects`].
+pub fn get_unit_positions(cur_state: &State) -> Vec<(f32, f32)> {
+    std::iter::once(&cur_state.player.unit)
+        .chain(&cur_state.mobs)
+        .map(|unit| (unit.pixel_x, unit.pixel_y))
+        .collect()
+}
 
 /// Returns a list of game objects that are currently visible within the camera's view.
 ///
 /// This function filters all game units (player and mobs) to only include those
 /// that fall within the camera's current field of view. The visibility is determined
-/// by the camera's position and viewport dimensions.
+/// by the camera's position and viewport dimensions. Units are placed between their
+/// positions before and after the last simulation step, so rendering stays smooth
+/// when frames and steps do not line up. Boxes resting in the hole they filled are left
+/// out, the filled hole is part of the static background.
 ///
 /// # Arguments
 ///
 /// * `cur_state` - The current game state containing all units
+/// * `previous` - unit positions before the last step (see [`get_unit_positions`]);
+///   units without one are drawn where they are
+/// * `alpha` - interpolation factor, `0` for the previous and `1` for the current positions
 /// * `camera` - The camera that defines the visible area of the game world
 ///
 /// # Returns
 ///
 /// A vector containing all [`Unit`] objects that are currently visible to the camera.
 /// The player unit is always included first, followed by any visible mobs.
-pub fn get_visible_objects(cur_state: &State, camera: &Camera) -> Vec<Unit> {
+pub fn get_visible_objects(
+    cur_state: &State,
+    previous: &[(f32, f32)],
+    alpha: f32,
+    camera: &Camera,
+) -> Vec<Unit> {
     let mut units = Vec::new();
     units.push(cur_state.player.unit.clone());
     units.extend(cur_state.mobs.clone());
 
-    units.into_iter().filter(|mob| camera.is_visible(mob.pixel_x, mob.pixel_y)).collect()
+    for (unit, &(x, y)) in units.iter_mut().zip(previous) {
+        unit.pixel_x = lerp(x, unit.pixel_x, alpha);
+        unit.pixel_y = lerp(y, unit.pixel_y, alpha);
+    }
+
+    let is_sunk = |idx: usize, unit: &Unit| {
+        let cell = (unit.tile_y * cur_state.grid_width + unit.tile_x) as usize;
+        idx > 0
+            && matches!(unit.movement, UnitMovement::Idle)
+            && cur_state.filled_holes.get(cell) == Some(&Some(idx - 1))
+    };
+
+    units
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, unit)| !is_sunk(*idx, unit))
+        .map(|(_, unit)| unit)
+        .filter(|mob| camera.is_visible(mob.pixel_x, mob.pixel_y))
+        .collect()
 }
 
 /// Performs linear interpolation between two scalar values.
@@ -59,26 +99,27 @@ pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
 /// - The player's facing direction (e.g., `"SE"`, `"NW"`)
 /// - The current frame index within the animation cycle
 ///
-/// The animation frame is determined by elapsed `total_time`, scaled by `MOVEMENT_SPEEDUP`
-/// to maintain consistent animation speed under time-warping effects.
+/// The animation frame is determined by the elapsed game time, which follows the game
+/// speed (see [`ferari::time::TimeScale`]) and stands still while the game is paused.
 ///
 /// # Arguments
 ///
 /// * `player` – reference to the player object containing movement and direction state
-/// * `total_time` – total elapsed game time in seconds
+/// * `total_time` – total elapsed game time in seconds (`Time.scaled_total`)
 ///
 /// # Returns
 ///
 /// A string in the format `"{animation}_{direction}_{frame_index}"`, suitable for
 /// loading the correct sprite from asset resources (e.g., `"running_se_5"`).
 pub fn get_player_sprite(player: &Player, total_time: f64) -> String {
-    let k = 1.0 / 1000.0 / MOVEMENT_SPEEDUP as f64;
+    let k = 1.0 / 1000.0;
     let (prefix, total_frames, period) = match player.unit.movement {
-        UnitMovement::Moving { .. } => ("running", 14, 45.0 * k),
+        UnitMovement::Moving { .. } | UnitMovement::Warping { .. } => ("running", 14, 45.0 * k),
         UnitMovement::Pushing { .. } => ("pushing", 37, 25.0 * k),
         UnitMovement::Idle => ("idle", 31, 45.0 * k),
         UnitMovement::PrePushing { .. } => ("walkingforward", 24, 30.0 * k),
         UnitMovement::PostPushing { .. } => ("walkingback", 23, 30.0 * k),
+        UnitMovement::Rewinding { .. } => ("walkingback", 23, 30.0 * k),
     };
 
     let dir_suffix = player.unit.direction.as_str();
@@ -109,6 +150,7 @@ mod visible_objects_tests {
                 y_speed: 0.0,
                 movement: UnitMovement::default(),
                 direction: ferari::world::Direction::default(),
+                color: None,
             }
         }
     }
@@ -126,6 +168,7 @@ mod visible_objects_tests {
                 mobs: self.mobs.iter().map(|m| m.to_real_unit()).collect(),
                 mob_grid: vec![None; 128 * 128],
                 grid_width: 128,
+                filled_holes: vec![None; 128 * 128],
             }
         }
     }
@@ -136,7 +179,7 @@ mod visible_objects_tests {
         let state = dummy_state.to_real_state();
 
         let camera = Camera::new(0.0, 0.0, 800, 600);
-        let visible = get_visible_objects(&state, &camera);
+        let visible = get_visible_objects(&state, &[], 1.0, &camera);
 
         assert_eq!(visible.len(), 1);
         assert_eq!(visible[0].pixel_x, state.player.unit.pixel_x);
@@ -152,7 +195,7 @@ mod visible_objects_tests {
         let state = dummy_state.to_real_state();
         let camera = Camera::new(0.0, 0.0, 50, 50);
 
-        let visible = get_visible_objects(&state, &camera);
+        let visible = get_visible_objects(&state, &[], 1.0, &camera);
         assert_eq!(visible.len(), 2);
         assert_eq!(visible[1].pixel_x, 10.0);
         assert_eq!(visible[1].pixel_y, 10.0);
@@ -167,7 +210,7 @@ mod visible_objects_tests {
         let state = dummy_state.to_real_state();
         let camera = Camera::new(0.0, 0.0, 50, 50);
 
-        let visible = get_visible_objects(&state, &camera);
+        let visible = get_visible_objects(&state, &[], 1.0, &camera);
         assert_eq!(visible.len(), 1);
         assert_eq!(visible[0].pixel_x, state.player.unit.pixel_x);
     }
@@ -185,13 +228,29 @@ mod visible_objects_tests {
         let state = dummy_state.to_real_state();
         let camera = Camera::new(0.0, 0.0, 50, 50);
 
-        let visible = get_visible_objects(&state, &camera);
+        let visible = get_visible_objects(&state, &[], 1.0, &camera);
         assert_eq!(visible.len(), 3);
         let positions: Vec<_> = visible.iter().map(|u| (u.pixel_x, u.pixel_y)).collect();
         assert!(positions.contains(&(0.0, 0.0)));
         assert!(positions.contains(&(5.0, 5.0)));
         assert!(positions.contains(&(20.0, 20.0)));
     }
+
+    #[test]
+    fn test_get_visible_objects_interpolates_positions() {
+        let dummy_state = DummyState {
+            player: DummyUnit { x: 10.0, y: 0.0 },
+            mobs: vec![DummyUnit { x: 20.0, y: 20.0 }],
+        };
+        let state = dummy_state.to_real_state();
+        let camera = Camera::new(0.0, 0.0, 100, 100);
+        let previous = [(0.0, 0.0), (20.0, 10.0)];
+
+        let visible = get_visible_objects(&state, &previous, 0.5, &camera);
+        let positions: Vec<_> = visible.iter().map(|u| (u.pixel_x, u.pixel_y)).collect();
+        assert_eq!(positions, [(5.0, 0.0), (20.0, 15.0)]);
+        assert_eq!(get_unit_positions(&state), [(10.0, 0.0), (20.0, 20.0)]);
+    }
 }
 
 #[cfg(test)]
@@ -241,6 +300,7 @@ mod player_sprite_tests {
                     y_speed: 0.0,
                     movement: self.movement.clone(),
                     direction: self.direction.clone(),
+                    color: None,
                 },
             }
         }
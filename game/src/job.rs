@@ -0,0 +1,78 @@
+use std::thread;
+
+use crossbeam_channel::{bounded, Receiver, TryRecvError};
+
+/// State of a [`Job`].
+#[derive(Debug, PartialEq)]
+pub enum JobStatus<T> {
+    /// The work is still running
+    Running,
+    /// The work is done
+    Done(T),
+    /// The worker died without a result (it panicked)
+    Failed,
+}
+
+/// Work running on a worker thread, e.g. a solver search.
+///
+/// The game thread polls the job once per frame instead of waiting for it, so a long
+/// search does not stall rendering and input. Dropping the job abandons the result; the
+/// worker finishes its work and exits.
+#[derive(Debug)]
+pub struct Job<T> {
+    /// Receives the result once the work is done
+    receiver: Receiver<T>,
+}
+
+impl<T: Send + 'static> Job<T> {
+    /// Starts the work on a new thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `work` - computation to run
+    ///
+    /// # Returns
+    ///
+    /// A job to poll for the result.
+    pub fn spawn(work: impl FnOnce() -> T + Send + 'static) -> Self {
+        let (sender, receiver) = bounded(1);
+        thread::spawn(move || {
+            // the receiver is gone if the job has been abandoned
+            let _ = sender.send(work());
+        });
+        Self { receiver }
+    }
+
+    /// Checks whether the work is done, without waiting.
+    ///
+    /// # Returns
+    ///
+    /// The result once the work is done; it is returned only once.
+    pub fn poll(&self) -> JobStatus<T> {
+        match self.receiver.try_recv() {
+            Ok(result) => JobStatus::Done(result),
+            Err(TryRecvError::Empty) => JobStatus::Running,
+            Err(TryRecvError::Disconnected) => JobStatus::Failed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_job_returns_result() {
+        let job = Job::spawn(|| 6 * 7);
+        let start = Instant::now();
+        let mut status = job.poll();
+        while status == JobStatus::Running {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::yield_now();
+            status = job.poll();
+        }
+        assert_eq!(status, JobStatus::Done(42));
+        assert_eq!(job.poll(), JobStatus::Failed);
+    }
+}
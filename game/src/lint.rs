@@ -0,0 +1,352 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ferari::assets::{Atlas, GameMap, TileType, XsbCollection, MANIFEST_NAME};
+
+use crate::deadlock::DeadlockDetector;
+
+/// Problem that does not break a level, but is likely a design mistake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintWarning {
+    /// Tile the player can never see up close: not reachable and not under an object
+    UnusedTile { key: String, position: (u32, u32) },
+    /// Box that already stands on a target at the start
+    BoxOnTarget { key: String, position: (u32, u32) },
+    /// Target no box (of its color) can ever be pushed onto
+    UnreachableTarget { key: String, position: (u32, u32) },
+    /// Box that starts on a dead square and can never reach a target
+    DeadBox { key: String, position: (u32, u32) },
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintWarning::UnusedTile { key, position: (x, y) } => {
+                write!(f, "{key} at ({x}, {y}): tile the player can never reach")
+            }
+            LintWarning::BoxOnTarget { key, position: (x, y) } => {
+                write!(f, "{key} at ({x}, {y}): box starts on a target")
+            }
+            LintWarning::UnreachableTarget { key, position: (x, y) } => {
+                write!(f, "{key} at ({x}, {y}): no box can be pushed onto the target")
+            }
+            LintWarning::DeadBox { key, position: (x, y) } => {
+                write!(f, "{key} at ({x}, {y}): box can never be pushed to a target")
+            }
+        }
+    }
+}
+
+/// Size and contents of a level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapStats {
+    /// Width in tiles
+    pub width: u32,
+    /// Height in tiles
+    pub height: u32,
+    /// Number of boxes
+    pub boxes: usize,
+    /// Number of targets
+    pub targets: usize,
+}
+
+impl fmt::Display for MapStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}, {} boxes, {} targets", self.width, self.height, self.boxes, self.targets)
+    }
+}
+
+/// Outcome of checking one level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelReport {
+    /// File of the level, followed by the level title for collections
+    pub source: String,
+    /// Statistics, `None` if the level cannot be parsed
+    pub stats: Option<MapStats>,
+    /// Problems that keep the level from being played
+    pub errors: Vec<String>,
+    /// Likely design mistakes
+    pub warnings: Vec<LintWarning>,
+}
+
+/// Checks a parsed level.
+///
+/// Errors are the validation issues of the map and its assets missing from the atlases.
+/// Warnings are only looked for in levels (menus have no boxes to push).
+///
+/// # Arguments
+///
+/// * `source` - name the report is given
+/// * `map` - the level map
+/// * `tiles_atlas` - atlas of the tiles and objects
+/// * `entities_atlas` - atlas of the mobs
+pub fn lint_map(
+    source: String,
+    map: &GameMap,
+    tiles_atlas: &Atlas,
+    entities_atlas: &Atlas,
+) -> LevelReport {
+    let errors: Vec<String> = map
+        .validate_with_atlases(tiles_atlas, entities_atlas)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    let boxes = map.mobs.values().filter(|mob| !mob.is_player).count();
+    let stats = MapStats {
+        width: map.size[0],
+        height: map.size[1],
+        boxes,
+        targets: map.target_positions.len(),
+    };
+
+    // Warnings rely on the map being sound and follow the plain Sokoban rules
+    let plain = map.links.is_empty() && !map.has_mechanisms();
+    let warnings = if errors.is_empty() && plain { find_warnings(map) } else { Vec::new() };
+
+    LevelReport { source, stats: Some(stats), errors, warnings }
+}
+
+/// Checks every level found in a directory and its subdirectories.
+///
+/// JSON files (except pack manifests) are checked as maps, XSB / SOK files contribute
+/// every level of the collection. Files are visited in path order and named relative to
+/// `dir`.
+///
+/// # Arguments
+///
+/// * `dir` - directory to search
+/// * `tiles_atlas` - atlas of the tiles and objects
+/// * `entities_atlas` - atlas of the mobs
+///
+/// # Returns
+///
+/// One report per level, or per file that cannot be parsed.
+pub fn lint_dir(dir: &Path, tiles_atlas: &Atlas, entities_atlas: &Atlas) -> Vec<LevelReport> {
+    let mut files = Vec::new();
+    collect_level_files(dir, &mut files);
+    files.sort();
+
+    let mut reports = Vec::new();
+    for path in files {
+        let source = path.strip_prefix(dir).unwrap_or(&path).display().to_string();
+        let failed = |err: String| LevelReport {
+            source: source.clone(),
+            stats: None,
+            errors: vec![err],
+            warnings: Vec::new(),
+        };
+
+        if is_xsb(&path) {
+            match XsbCollection::load(&path) {
+                Ok(collection) => {
+                    for (i, level) in collection.levels.iter().enumerate() {
+                        let source = format!("{source} #{} {}", i + 1, level.title);
+                        reports.push(lint_map(source, &level.map, tiles_atlas, entities_atlas));
+                    }
+                }
+                Err(err) => reports.push(failed(err.to_string())),
+            }
+        } else {
+            match GameMap::load_unchecked(&path) {
+                Ok(map) => reports.push(lint_map(source, &map, tiles_atlas, entities_atlas)),
+                Err(err) => reports.push(failed(err.to_string())),
+            }
+        }
+    }
+    reports
+}
+
+/// Adds the level files of a directory tree to a list. Unreadable directories are skipped.
+///
+/// # Arguments
+///
+/// * `dir` - directory to search
+/// * `files` - list the files are added to
+fn collect_level_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            collect_level_files(&path, files);
+        } else if is_xsb(&path)
+            || (has_extension(&path, "json")
+                && path.file_name().is_some_and(|name| name != MANIFEST_NAME))
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// Checks whether the file is an XSB / SOK collection.
+fn is_xsb(path: &Path) -> bool {
+    has_extension(path, "xsb") || has_extension(path, "sok")
+}
+
+/// Checks the extension of a file, ignoring case.
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+/// Looks for design mistakes in a valid level.
+///
+/// # Arguments
+///
+/// * `map` - a map without validation issues
+fn find_warnings(map: &GameMap) -> Vec<LintWarning> {
+    let width = map.size[0];
+    let index = |(x, y): (u32, u32)| (y * width + x) as usize;
+    let Some(player) = map.mobs.values().find(|mob| mob.is_player) else {
+        return Vec::new();
+    };
+    let reachable = map.reachable_from(player.start_position());
+    let detector = DeadlockDetector::new(map);
+
+    let mut warnings = Vec::new();
+
+    let mut tiles: Vec<_> = map.tiles.values().collect();
+    tiles.sort_by(|a, b| a.name.cmp(&b.name));
+    for tile in &tiles {
+        let (x, y) = tile.position();
+        if !reachable[index((x, y))] && !map.has_collidable_object_at(x as i32, y as i32) {
+            let key = format!("tiles.{}", tile.name);
+            warnings.push(LintWarning::UnusedTile { key, position: (x, y) });
+        }
+    }
+
+    let mut boxes: Vec<_> = map.mobs.values().filter(|mob| !mob.is_player).collect();
+    boxes.sort_by(|a, b| a.name.cmp(&b.name));
+    for mob in &boxes {
+        let (x, y) = mob.start_position();
+        let key = format!("mobs.{}", mob.name);
+        if map.accepts_box(x, y, mob.color.as_deref()) {
+            warnings.push(LintWarning::BoxOnTarget { key, position: (x, y) });
+        } else if detector.is_dead_square(x as i32, y as i32, mob.color.as_deref()) {
+            warnings.push(LintWarning::DeadBox { key, position: (x, y) });
+        }
+    }
+
+    // a target only takes boxes of its color
+    let pushable_by = |color: Option<&str>| {
+        let starts = boxes.iter().filter(|mob| mob.color.as_deref() == color);
+        pushable_cells(map, starts.map(|mob| mob.start_position()))
+    };
+    let mut pushable = HashMap::new();
+    for tile in tiles.iter().filter(|tile| matches!(tile.tile_type, TileType::Target)) {
+        let color = tile.color.as_deref();
+        let pushable = pushable.entry(color).or_insert_with(|| pushable_by(color));
+        if !pushable[index(tile.position())] {
+            let key = format!("tiles.{}", tile.name);
+            warnings.push(LintWarning::UnreachableTarget { key, position: tile.position() });
+        }
+    }
+
+    warnings
+}
+
+/// Returns the cells some box can be pushed to, ignoring the other boxes.
+///
+/// # Arguments
+///
+/// * `map` - the level map
+/// * `starts` - start cells of the boxes
+///
+/// # Returns
+///
+/// Flag of every cell, indexed like `walk_map`.
+fn pushable_cells(map: &GameMap, starts: impl Iterator<Item = (u32, u32)>) -> Vec<bool> {
+    let width = map.size[0] as i32;
+    let free = |from: (i32, i32), (x, y): (i32, i32)| {
+        map.is_walkable_from(from, (x, y)) && !map.has_collidable_object_at(x, y)
+    };
+
+    let mut pushable = vec![false; map.walk_map.len()];
+    let mut queue = VecDeque::new();
+    for (x, y) in starts {
+        pushable[(y as i32 * width + x as i32) as usize] = true;
+        queue.push_back((x as i32, y as i32));
+    }
+    while let Some((x, y)) = queue.pop_front() {
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let (nx, ny) = (x + dx, y + dy);
+            // The player steps from behind the box onto its cell, the box onto the next one
+            let behind = (x - dx, y - dy);
+            if free((x, y), (nx, ny))
+                && free((x, y), behind)
+                && !pushable[(ny * width + nx) as usize]
+            {
+                pushable[(ny * width + nx) as usize] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    pushable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn empty_atlas() -> Atlas {
+        Atlas {
+            image: image::RgbaImage::new(1, 1),
+            frames: HashMap::new(),
+            tile_size: 1,
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn test_lint_reports_warnings_and_stats() {
+        let mut map =
+            GameMap::from_xsb("test", &["#######", "#@ $ .#", "#$ * .#", "#######"]).unwrap();
+        let corner = map.objects.iter().find(|(_, object)| object.position() == (0, 0));
+        let corner = corner.unwrap().0.clone();
+        map.objects.remove(&corner);
+        map.object_collidable_map[0] = false;
+
+        let report = lint_map("test".into(), &map, &empty_atlas(), &empty_atlas());
+
+        let stats = MapStats { width: 7, height: 4, boxes: 3, targets: 3 };
+        assert_eq!(report.stats, Some(stats));
+        assert!(!report.errors.is_empty(), "empty atlases have no frames");
+
+        let warnings = find_warnings(&map);
+        let kinds: Vec<_> = warnings
+            .iter()
+            .map(|warning| match warning {
+                LintWarning::UnusedTile { position, .. } => ("unused", *position),
+                LintWarning::BoxOnTarget { position, .. } => ("on target", *position),
+                LintWarning::UnreachableTarget { position, .. } => ("target", *position),
+                LintWarning::DeadBox { position, .. } => ("dead", *position),
+            })
+            .collect();
+        assert_eq!(kinds, [("unused", (0, 0)), ("dead", (1, 2)), ("on target", (3, 2))]);
+    }
+
+    #[test]
+    fn test_lint_reports_unreachable_targets() {
+        let map = GameMap::from_xsb("test", &["######", "#@$ .#", "##.###", "######"]).unwrap();
+
+        let warnings = find_warnings(&map);
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(&warnings[0], LintWarning::UnreachableTarget { position: (2, 2), .. }));
+        assert!(warnings[0].to_string().contains("(2, 2)"));
+    }
+
+    #[test]
+    fn test_shipped_levels_pass() {
+        let (tiles_atlas, entities_atlas) = crate::session::load_atlases().unwrap();
+        let reports = lint_dir(Path::new("../game_levels"), &tiles_atlas, &entities_atlas);
+
+        assert!(reports.len() >= 5);
+        for report in reports {
+            assert_eq!(report.errors, Vec::<String>::new(), "{}", report.source);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::{HashMap, LinkedList};
+
+use ferari::assets::{Behaviour, BehaviourType, GameMap, LevelPack, Mob, Tile, TileType};
+
+use crate::TILE_SIZE;
+
+/// Maximum number of entries in a single menu row.
+const MENU_COLUMNS: usize = 9;
+/// Labels of menu entries in order (the tile atlas only has digits and letters).
+const LABELS: &[u8] = b"123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+/// Link tile of an unlocked entry past the end of [`LABELS`].
+const UNLABELED_LINK_ASSET: &str = "target";
+/// Tile of a locked entry past the end of [`LABELS`].
+const UNLABELED_LOCKED_ASSET: &str = "concrete";
+
+/// Builds the level selection menu of a pack.
+///
+/// Every unlocked level gets a box above its numbered link tile; pushing the box onto
+/// the tile starts the level. Locked levels show their number without box and link.
+///
+/// # Arguments
+///
+/// * `pack` - the level pack
+/// * `solved` - solved flag of every level of the pack
+///
+/// # Returns
+///
+/// A menu map in which `TileType::Link(n)` leads to level `n - 1` of the pack.
+pub fn level_menu(pack: &LevelPack, solved: &[bool]) -> GameMap {
+    let unlocked: Vec<bool> = (0..pack.len()).map(|i| pack.is_unlocked(i, solved)).collect();
+    build_menu(["CHOOSE", "LEVEL"], &unlocked)
+}
+
+/// Builds the pack selection menu.
+///
+/// # Arguments
+///
+/// * `packs` - all discovered level packs
+///
+/// # Returns
+///
+/// A menu map in which `TileType::Link(n)` leads to pack `n - 1`.
+pub fn pack_menu(packs: &[LevelPack]) -> GameMap {
+    build_menu(["CHOOSE", "PACK"], &vec![true; packs.len()])
+}
+
+/// Returns the start tile of the box belonging to a menu entry.
+///
+/// # Arguments
+///
+/// * `idx` - index of the entry (level or pack)
+///
+/// # Returns
+///
+/// Tile coordinates of the box; its link tile is directly below.
+pub fn entry_box_tile(idx: usize) -> (i32, i32) {
+    ((idx % MENU_COLUMNS) as i32, (3 + 3 * (idx / MENU_COLUMNS)) as i32)
+}
+
+/// Lays out a menu in the style of the original hand-made menu level.
+///
+/// Two title rows are followed by blocks of three rows per line of entries: a free row
+/// for the player, a row of boxes and a row of link tiles. The rightmost column is kept
+/// free so the player can walk down to the next block.
+fn build_menu(title: [&str; 2], unlocked: &[bool]) -> GameMap {
+    let columns = unlocked.len().clamp(1, MENU_COLUMNS);
+    let blocks = unlocked.len().div_ceil(MENU_COLUMNS).max(1);
+
+    let title_width = title.iter().map(|line| line.len()).max().unwrap_or(0);
+    let width = (columns + 1).max(title_width);
+    let height = 2 + 3 * blocks;
+
+    let mut tiles = HashMap::new();
+    let mut mobs = HashMap::new();
+    let mut links = HashMap::new();
+    let mut walk_map = vec![TileType::Empty; width * height];
+
+    let mut add_tile = |x: usize, y: usize, asset: String, tile_type: TileType| {
+        let name = format!("letter_{}", tiles.len() + 1);
+        walk_map[y * width + x] = tile_type.clone();
+        let (x, y) = (x as u32, y as u32);
+        let tile = Tile { name: name.clone(), x, y, asset, tile_type, z: 0, color: None };
+        tiles.insert(name, tile);
+    };
+
+    for (y, line) in title.iter().enumerate() {
+        for (x, letter) in line.chars().enumerate() {
+            add_tile(x, y, format!("letter_{letter}"), TileType::Empty);
+        }
+    }
+
+    for (i, &is_unlocked) in unlocked.iter().enumerate() {
+        let (box_x, box_y) = entry_box_tile(i);
+        let (x, y) = (box_x as usize, box_y as usize + 1);
+        // entries past the label table get plain tiles rather than a repeated label
+        let label = LABELS.get(i).map(|&label| format!("letter_{}", label as char));
+        let id = i as u32 + 1;
+
+        if !is_unlocked {
+            let asset = label.unwrap_or_else(|| UNLABELED_LOCKED_ASSET.to_string());
+            add_tile(x, y, asset, TileType::Empty);
+            continue;
+        }
+
+        let asset = label.unwrap_or_else(|| UNLABELED_LINK_ASSET.to_string());
+        add_tile(x, y, asset, TileType::Link(id));
+        links.insert((x as u32, y as u32), id);
+
+        let name = format!("box_{id}");
+        let mob = Mob {
+            name: name.clone(),
+            x_start: x as u32,
+            y_start: box_y as u32,
+            asset: "box".to_string(),
+            is_player: false,
+            behaviour: None,
+            color: None,
+        };
+        mobs.insert(name, mob);
+    }
+
+    let player = Mob {
+        name: "player".to_string(),
+        x_start: 0,
+        y_start: 2,
+        asset: "idle_se_0".to_string(),
+        is_player: true,
+        behaviour: Some(Behaviour {
+            behaviour_type: BehaviourType::Controlled,
+            direction: None,
+            speed: None,
+        }),
+        color: None,
+    };
+    mobs.insert(player.name.clone(), player);
+
+    GameMap {
+        name: "menu".to_string(),
+        tile_size: TILE_SIZE as u32,
+        size: [width as u32, height as u32],
+        mobs,
+        objects: HashMap::new(),
+        tiles,
+        target_positions: LinkedList::new(),
+        links,
+        target_colors: HashMap::new(),
+        walk_map,
+        height_map: vec![0; width * height],
+        object_collidable_map: vec![false; width * height],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferari::assets::{LevelSource, PackLevel, UnlockRule};
+    use ferari::world::State;
+    use std::path::PathBuf;
+
+    fn test_pack(levels: usize, unlock: UnlockRule) -> LevelPack {
+        let level = PackLevel { source: LevelSource::File(PathBuf::new()), requires_solved: 0 };
+        LevelPack {
+            name: "test".to_string(),
+            author: String::new(),
+            unlock,
+            levels: vec![level; levels],
+        }
+    }
+
+    #[test]
+    fn test_level_menu_matches_original_layout() {
+        let menu = level_menu(&test_pack(5, UnlockRule::All), &[]);
+
+        assert_eq!(menu.size, [6, 5]);
+        assert_eq!(menu.mob_count(), 6);
+        assert_eq!(menu.tile_count(), 16);
+        assert_eq!(menu.links.get(&(0, 4)), Some(&1));
+        assert_eq!(menu.links.get(&(4, 4)), Some(&5));
+        assert_eq!(menu.get_mob("box_3").unwrap().start_position(), (2, 3));
+        assert_eq!(menu.get_mob("player").unwrap().start_position(), (0, 2));
+
+        let state = State::new(&menu);
+        assert_eq!(state.mobs.len(), 5);
+    }
+
+    #[test]
+    fn test_locked_levels_have_no_box_and_link() {
+        let menu = level_menu(&test_pack(3, UnlockRule::Sequential), &[true, false, false]);
+
+        assert_eq!(menu.links.len(), 2);
+        assert_eq!(menu.links.get(&(2, 4)), None);
+        assert!(menu.get_mob("box_3").is_none());
+        assert!(menu.iter_tiles().any(|t| t.position() == (2, 4) && t.asset == "letter_3"));
+    }
+
+    #[test]
+    fn test_large_menu_wraps_into_blocks() {
+        let menu = level_menu(&test_pack(20, UnlockRule::All), &[]);
+
+        assert_eq!(menu.size, [10, 11]);
+        assert_eq!(menu.links.get(&(0, 7)), Some(&10));
+        assert_eq!(menu.links.get(&(1, 10)), Some(&20));
+        assert!(menu.iter_tiles().any(|t| t.position() == (0, 7) && t.asset == "letter_A"));
+    }
+
+    #[test]
+    fn test_entries_past_the_labels_are_not_labeled_again() {
+        let count = LABELS.len() + 5;
+        let solved = vec![true; count - 2];
+        let menu = level_menu(&test_pack(count, UnlockRule::Sequential), &solved);
+
+        let mut labels: Vec<&str> = menu
+            .iter_tiles()
+            .filter(|tile| tile.position().1 >= 3 && tile.asset.starts_with("letter_"))
+            .map(|tile| tile.asset.as_str())
+            .collect();
+        assert_eq!(labels.len(), LABELS.len());
+        labels.sort();
+        labels.dedup();
+        assert_eq!(labels.len(), LABELS.len());
+
+        let (x, y) = entry_box_tile(LABELS.len());
+        let link = menu.iter_tiles().find(|tile| tile.position() == (x as u32, y as u32 + 1));
+        assert_eq!(link.unwrap().asset, UNLABELED_LINK_ASSET);
+        assert_eq!(menu.links.get(&(x as u32, y as u32 + 1)), Some(&(LABELS.len() as u32 + 1)));
+        let (x, y) = entry_box_tile(count - 1);
+        let locked = menu.iter_tiles().find(|tile| tile.position() == (x as u32, y as u32 + 1));
+        assert_eq!(locked.unwrap().asset, UNLABELED_LOCKED_ASSET);
+    }
+
+    #[test]
+    fn test_pack_menu() {
+        let packs = vec![test_pack(1, UnlockRule::All), test_pack(2, UnlockRule::All)];
+        let menu = pack_menu(&packs);
+
+        assert_eq!(menu.links.len(), 2);
+        assert!(menu.iter_tiles().any(|t| t.position() == (3, 1) && t.asset == "letter_K"));
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+
+use ferari::input::{Action, InputEvent, InputEventKind, InputSnapshot};
+use ferari::world::Direction;
+
+/// Maximum number of buffered moves.
+const MOVE_BUFFER_SIZE: usize = 2;
+
+/// A buffered movement key press.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BufferedMove {
+    /// Direction of the move
+    direction: Direction,
+    /// Action the move was pressed with
+    action: Action,
+    /// Whether the key has been released since (a tap)
+    released: bool,
+}
+
+/// Movement presses waiting for the player to become idle.
+///
+/// A movement key held when the player becomes idle moves the player by itself, but a
+/// tap during an animation is released before that. The buffer keeps such taps (up to
+/// [`MOVE_BUFFER_SIZE`]) and plays them once the animation has finished. Presses whose
+/// key is still held are dropped then, as the held key already moves the player.
+#[derive(Debug, Default)]
+pub struct MoveBuffer {
+    /// Buffered moves, the oldest first
+    moves: VecDeque<BufferedMove>,
+}
+
+impl MoveBuffer {
+    /// Creates an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Buffers movement presses and marks released ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - input events of this frame, in order
+    pub fn handle(&mut self, events: &[InputEvent]) {
+        for event in events {
+            let Some(direction) = move_direction(event.action) else {
+                continue;
+            };
+
+            match event.kind {
+                InputEventKind::Pressed => {
+                    if self.moves.len() == MOVE_BUFFER_SIZE {
+                        self.moves.pop_front();
+                    }
+                    self.moves.push_back(BufferedMove {
+                        direction,
+                        action: event.action,
+                        released: false,
+                    });
+                }
+                InputEventKind::Released => {
+                    let pressed = self
+                        .moves
+                        .iter_mut()
+                        .rev()
+                        .find(|buffered| buffered.action == event.action && !buffered.released);
+                    if let Some(buffered) = pressed {
+                        buffered.released = true;
+                    }
+                }
+                InputEventKind::Repeated => {}
+            }
+        }
+    }
+
+    /// Takes the next buffered move for an idle player.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - input of this frame
+    ///
+    /// # Returns
+    ///
+    /// The direction of the next tap, or `None` if the held keys decide the move.
+    pub fn next(&mut self, input: &InputSnapshot) -> Option<Direction> {
+        while let Some(buffered) = self.moves.front() {
+            if !buffered.released && input.is_down(buffered.action) {
+                self.moves.pop_front();
+                continue;
+            }
+            return self.moves.pop_front().map(|buffered| buffered.direction);
+        }
+        None
+    }
+
+    /// Drops all buffered moves.
+    pub fn clear(&mut self) {
+        self.moves.clear();
+    }
+}
+
+/// Returns the direction of a movement action.
+fn move_direction(action: Action) -> Option<Direction> {
+    match action {
+        Action::MoveUp => Some(Direction::NE),
+        Action::MoveDown => Some(Direction::SW),
+        Action::MoveLeft => Some(Direction::NW),
+        Action::MoveRight => Some(Direction::SE),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(action: Action, kind: InputEventKind) -> InputEvent {
+        InputEvent { action, kind, time: 0.0 }
+    }
+
+    #[test]
+    fn test_taps_are_played_in_order() {
+        let mut buffer = MoveBuffer::new();
+        buffer.handle(&[
+            event(Action::MoveLeft, InputEventKind::Pressed),
+            event(Action::Undo, InputEventKind::Pressed),
+            event(Action::MoveLeft, InputEventKind::Released),
+            event(Action::MoveUp, InputEventKind::Pressed),
+            event(Action::MoveUp, InputEventKind::Repeated),
+            event(Action::MoveUp, InputEventKind::Released),
+        ]);
+
+        let idle = InputSnapshot::default();
+        assert_eq!(buffer.next(&idle), Some(Direction::NW));
+        assert_eq!(buffer.next(&idle), Some(Direction::NE));
+        assert_eq!(buffer.next(&idle), None);
+    }
+
+    #[test]
+    fn test_held_presses_are_dropped() {
+        let mut buffer = MoveBuffer::new();
+        buffer.handle(&[event(Action::MoveRight, InputEventKind::Pressed)]);
+
+        let held = InputSnapshot { right: true, ..Default::default() };
+        assert_eq!(buffer.next(&held), None);
+        assert_eq!(buffer.next(&InputSnapshot::default()), None);
+    }
+
+    #[test]
+    fn test_buffer_keeps_latest_presses() {
+        let mut buffer = MoveBuffer::new();
+        for action in [Action::MoveUp, Action::MoveDown, Action::MoveLeft] {
+            buffer.handle(&[
+                event(action, InputEventKind::Pressed),
+                event(action, InputEventKind::Released),
+            ]);
+        }
+
+        let idle = InputSnapshot::default();
+        assert_eq!(buffer.next(&idle), Some(Direction::SW));
+        assert_eq!(buffer.next(&idle), Some(Direction::NW));
+
+        buffer.handle(&[event(Action::MoveUp, InputEventKind::Pressed)]);
+        buffer.clear();
+        assert_eq!(buffer.next(&idle), None);
+    }
+}
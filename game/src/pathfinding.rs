@@ -0,0 +1,256 @@
+use std::collections::{HashMap, VecDeque};
+
+use ferari::assets::{GameMap, TileType};
+use ferari::world::{Direction, State};
+
+use crate::behaviour::{climb_map, get_dir_delta, Gates};
+
+/// Directions tried by the searches, in order.
+const DIRECTIONS: [Direction; 4] = [Direction::NE, Direction::SE, Direction::SW, Direction::NW];
+
+/// Number of `(box, player)` positions a push search may visit before giving up.
+const PUSH_SEARCH_LIMIT: usize = 500_000;
+
+/// Walls and boxes of a level, as seen by the searches.
+struct Grid {
+    /// Map width in tiles
+    width: i32,
+    /// Map height in tiles
+    height: i32,
+    /// Cells that neither the player nor a box can enter
+    walls: Vec<bool>,
+    /// Directions in which a step can leave each cell (see [`climb_map`])
+    climb: Vec<[bool; 4]>,
+    /// Cells occupied by a box (index into `State.mobs`)
+    boxes: Vec<Option<usize>>,
+}
+
+impl Grid {
+    /// Builds the grid from the map walkability and the box positions of `State.mob_grid`.
+    ///
+    /// The searches do not model mechanisms, so their cells count as walls, except for
+    /// plates and filled holes (plain floor as far as a step is concerned).
+    fn new(game: &GameMap, state: &State) -> Self {
+        let width = game.size[0] as i32;
+        let height = game.size[1] as i32;
+        let gates = Gates::new(game, state);
+        let is_obstacle = |x: i32, y: i32| match game.tile_type_at(x, y) {
+            Some(TileType::Plate(_)) => false,
+            Some(TileType::Hole) => gates.is_open_hole(x, y),
+            Some(tile_type) => tile_type.is_mechanism(),
+            None => true,
+        };
+
+        let mut walls = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                walls.push(
+                    !game.is_walkable(x, y)
+                        || game.has_collidable_object_at(x, y)
+                        || is_obstacle(x, y),
+                );
+            }
+        }
+
+        let mut boxes = state.mob_grid.clone();
+        boxes.resize(walls.len(), None);
+
+        Self { width, height, walls, climb: climb_map(game), boxes }
+    }
+
+    /// Converts tile coordinates to a cell index.
+    fn cell(&self, x: i32, y: i32) -> Option<usize> {
+        (x >= 0 && y >= 0 && x < self.width && y < self.height)
+            .then_some((y * self.width + x) as usize)
+    }
+
+    /// Returns the neighbouring cell in `direction` if it is not a wall and the height
+    /// difference can be crossed.
+    fn open_neighbour(&self, cell: usize, direction: Direction) -> Option<usize> {
+        let (dx, dy) = get_dir_delta(direction);
+        self.cell(cell as i32 % self.width + dx, cell as i32 / self.width + dy)
+            .filter(|&next| !self.walls[next] && self.climb[cell][direction as usize])
+    }
+
+    /// Checks whether a box other than `moving` stands on `cell`.
+    fn has_other_box(&self, cell: usize, moving: Option<usize>) -> bool {
+        self.boxes[cell].is_some_and(|idx| Some(idx) != moving)
+    }
+}
+
+/// Follows the links of a search from `goal` back to its start.
+fn unwind<K: Copy + Eq + std::hash::Hash>(
+    came_from: &HashMap<K, (K, Direction)>,
+    goal: K,
+) -> Vec<Direction> {
+    let mut path = Vec::new();
+    let mut key = goal;
+    while let Some(&(prev, direction)) = came_from.get(&key) {
+        path.push(direction);
+        key = prev;
+    }
+    path.reverse();
+    path
+}
+
+/// Finds the shortest walk of the player to a tile, without pushing any box.
+///
+/// # Arguments
+///
+/// * `game` - the level
+/// * `state` - current positions of the player and the boxes
+/// * `to` - destination tile `(x, y)`
+///
+/// # Returns
+///
+/// The moves of the walk (empty if the player already stands there), or `None` if the
+/// tile cannot be reached.
+pub fn walk_path(game: &GameMap, state: &State, to: (i32, i32)) -> Option<Vec<Direction>> {
+    let grid = Grid::new(game, state);
+    let player = state.player.unit.tile_x;
+    let from = grid.cell(player, state.player.unit.tile_y)?;
+    let to = grid.cell(to.0, to.1).filter(|&cell| !grid.walls[cell])?;
+
+    let mut came_from = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+
+    while let Some(cell) = queue.pop_front() {
+        if cell == to {
+            return Some(unwind(&came_from, to));
+        }
+        for direction in DIRECTIONS {
+            let Some(next) = grid.open_neighbour(cell, direction) else {
+                continue;
+            };
+            if next != from && !grid.has_other_box(next, None) && !came_from.contains_key(&next) {
+                came_from.insert(next, (cell, direction));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+/// Finds the shortest move sequence that pushes a box to a tile.
+///
+/// The player may walk around and push the box as often as needed, but no other box is
+/// moved. Searches over `(box, player)` positions, so the result is optimal in moves.
+///
+/// # Arguments
+///
+/// * `game` - the level
+/// * `state` - current positions of the player and the boxes
+/// * `box_idx` - index of the box within `State.mobs`
+/// * `to` - destination tile `(x, y)` of the box
+///
+/// # Returns
+///
+/// The moves (walks and pushes) bringing the box to `to`, or `None` if it cannot get there.
+pub fn push_path(
+    game: &GameMap,
+    state: &State,
+    box_idx: usize,
+    to: (i32, i32),
+) -> Option<Vec<Direction>> {
+    let grid = Grid::new(game, state);
+    let unit = state.mobs.get(box_idx)?;
+    let start_box = grid.cell(unit.tile_x, unit.tile_y)?;
+    let player = grid.cell(state.player.unit.tile_x, state.player.unit.tile_y)?;
+    let to = grid.cell(to.0, to.1).filter(|&cell| !grid.walls[cell])?;
+
+    let start = (start_box, player);
+    let mut came_from = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+
+    while let Some((box_cell, player)) = queue.pop_front() {
+        if box_cell == to {
+            return Some(unwind(&came_from, (box_cell, player)));
+        }
+        if came_from.len() > PUSH_SEARCH_LIMIT {
+            return None;
+        }
+
+        for direction in DIRECTIONS {
+            let Some(next) = grid.open_neighbour(player, direction) else {
+                continue;
+            };
+            if grid.has_other_box(next, Some(box_idx)) {
+                continue;
+            }
+
+            let position = if next == box_cell {
+                match grid.open_neighbour(box_cell, direction) {
+                    Some(behind) if !grid.has_other_box(behind, Some(box_idx)) => (behind, next),
+                    _ => continue,
+                }
+            } else {
+                (box_cell, next)
+            };
+
+            if position != start && !came_from.contains_key(&position) {
+                came_from.insert(position, ((box_cell, player), direction));
+                queue.push_back(position);
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::to_lurd;
+    use crate::solver::SolutionStep;
+
+    fn level(rows: &[&str]) -> (GameMap, State) {
+        let game = GameMap::from_xsb("pathfinding", rows).unwrap();
+        let state = State::new(&game);
+        (game, state)
+    }
+
+    fn lurd(directions: &[Direction]) -> String {
+        let steps: Vec<SolutionStep> =
+            directions.iter().map(|&direction| SolutionStep { direction, push: false }).collect();
+        to_lurd(&steps)
+    }
+
+    #[test]
+    fn test_walk_path_goes_around_boxes() {
+        let (game, state) = level(&["######", "#@ $ #", "#    #", "######"]);
+
+        let path = walk_path(&game, &state, (4, 1)).unwrap();
+        assert_eq!(path.len(), 5);
+        assert_eq!(lurd(&path).matches('d').count(), 1);
+
+        assert_eq!(walk_path(&game, &state, (1, 1)), Some(Vec::new()));
+        assert_eq!(walk_path(&game, &state, (3, 1)), None);
+        assert_eq!(walk_path(&game, &state, (0, 0)), None);
+    }
+
+    #[test]
+    fn test_push_path_walks_behind_the_box() {
+        let (game, state) = level(&["######", "#    #", "# $  #", "#@   #", "######"]);
+        let box_idx = state.mob_grid[2 * 6 + 2].unwrap();
+
+        let path = push_path(&game, &state, box_idx, (4, 2)).unwrap();
+        assert_eq!(lurd(&path), "urr");
+
+        let up = push_path(&game, &state, box_idx, (2, 1)).unwrap();
+        assert_eq!(lurd(&up), "ru");
+    }
+
+    #[test]
+    fn test_push_path_respects_walls_and_other_boxes() {
+        let (game, state) = level(&["#######", "#@$ $ #", "#######"]);
+        let first = state.mob_grid[7 + 2].unwrap();
+
+        assert_eq!(
+            push_path(&game, &state, first, (3, 1)).map(|path| lurd(&path)),
+            Some("r".into())
+        );
+        assert_eq!(push_path(&game, &state, first, (5, 1)), None);
+        assert_eq!(push_path(&game, &state, first, (1, 1)), None);
+    }
+}
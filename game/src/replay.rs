@@ -0,0 +1,288 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ferari::input::InputSnapshot;
+use serde::{Deserialize, Serialize};
+
+use crate::save::data_dir;
+
+/// Format version written into replay files (2 records instant moves per frame).
+const REPLAY_VERSION: u32 = 2;
+/// Directory inside the data directory replays are saved to.
+const REPLAY_DIR_NAME: &str = "replays";
+/// File name of the replay of the last played level session.
+const LAST_REPLAY_NAME: &str = "last.json";
+
+/// Single frame (simulation step) of a recorded session.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    /// Duration of the simulation step, in seconds
+    pub delta: f32,
+    /// Whether the animations were finished at the end of the step (instant moves)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub instant: bool,
+    /// Input passed to `make_step`, `None` if unchanged since the previous frame
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<InputSnapshot>,
+}
+
+/// Recorded level session: the frame deltas and the input of every frame.
+///
+/// Feeding the deltas to [`ferari::time::Time::from_deltas`] and the inputs to `make_step`
+/// starting from the initial state of the level reproduces the session exactly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    /// Format version of the replay file
+    pub version: u32,
+    /// Version of the engine the session was recorded with
+    pub engine_version: String,
+    /// Identifier of the level, see [`ferari::assets::LevelPack::level_id`]
+    pub level: String,
+    /// Recorded frames in play order
+    pub frames: Vec<ReplayFrame>,
+    /// Input of the last recorded frame
+    #[serde(skip)]
+    last_input: Option<InputSnapshot>,
+}
+
+impl Replay {
+    /// Starts an empty recording of a level session.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - identifier of the level
+    ///
+    /// # Returns
+    ///
+    /// A replay without frames, tagged with the current engine version.
+    pub fn new(level: &str) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            engine_version: ferari::VERSION.to_string(),
+            level: level.to_string(),
+            frames: Vec::new(),
+            last_input: None,
+        }
+    }
+
+    /// Loads a replay from a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - location of the replay file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Box<dyn Error>>` - Parsed replay on success, error on failure
+    ///   (including replays written by a newer format version).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        let mut replay: Self = serde_json::from_str(&text)?;
+
+        if replay.version > REPLAY_VERSION {
+            return Err(format!("unsupported replay version {}", replay.version).into());
+        }
+
+        replay.last_input = replay.inputs().last();
+        Ok(replay)
+    }
+
+    /// Writes the replay to a file, creating missing directories.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - location of the replay file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let text = serde_json::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, text)
+    }
+
+    /// Appends a frame to the recording.
+    ///
+    /// The input is only stored when it differs from the input of the previous frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - time elapsed since the previous frame, in seconds
+    /// * `instant` - whether the animations were finished at the end of the frame
+    /// * `input` - input passed to `make_step` in this frame
+    pub fn record(&mut self, delta: f32, instant: bool, input: &InputSnapshot) {
+        let changed = self.last_input.as_ref() != Some(input);
+        self.frames.push(ReplayFrame { delta, instant, input: changed.then_some(*input) });
+        self.last_input = Some(*input);
+    }
+
+    /// Returns the number of recorded frames.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Checks whether no frame has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the recorded frame deltas in play order.
+    pub fn deltas(&self) -> impl Iterator<Item = f32> + '_ {
+        self.frames.iter().map(|frame| frame.delta)
+    }
+
+    /// Returns for every recorded frame in play order whether its animations were finished
+    /// at its end (instant moves).
+    pub fn instants(&self) -> impl Iterator<Item = bool> + '_ {
+        self.frames.iter().map(|frame| frame.instant)
+    }
+
+    /// Returns the input of every recorded frame in play order.
+    pub fn inputs(&self) -> impl Iterator<Item = InputSnapshot> + '_ {
+        self.frames.iter().scan(InputSnapshot::default(), |last, frame| {
+            if let Some(input) = frame.input {
+                *last = input;
+            }
+            Some(*last)
+        })
+    }
+}
+
+/// Returns the path of the replay of the last played level session.
+pub fn last_replay_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(REPLAY_DIR_NAME).join(LAST_REPLAY_NAME))
+}
+
+/// Returns the path the latest solution of a level is saved to.
+///
+/// # Arguments
+///
+/// * `level` - identifier of the level
+pub fn solution_replay_path(level: &str) -> Option<PathBuf> {
+    let name: String = level
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    data_dir().map(|dir| dir.join(REPLAY_DIR_NAME).join(format!("{name}.json")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behaviour::make_step;
+    use crate::deadlock::DeadlockDetector;
+    use crate::history::History;
+    use ferari::assets::GameMap;
+    use ferari::time::{Time, FIXED_STEP};
+    use ferari::world::State;
+    use std::env;
+
+    fn positions(state: &State) -> Vec<(i32, i32, f32, f32)> {
+        let player = &state.player.unit;
+        let mut positions = vec![(player.tile_x, player.tile_y, player.pixel_x, player.pixel_y)];
+        positions.extend(state.mobs.iter().map(|m| (m.tile_x, m.tile_y, m.pixel_x, m.pixel_y)));
+        positions
+    }
+
+    /// Plays a session through `make_step`, returning the final state and history.
+    fn play(
+        game: &GameMap,
+        time: &mut Time,
+        inputs: impl Iterator<Item = InputSnapshot>,
+        mut recording: Option<&mut Replay>,
+    ) -> (State, History) {
+        let mut state = State::new(game);
+        let mut history = History::new();
+        let mut deadlocks = DeadlockDetector::new(game);
+
+        for input in inputs {
+            time.advance_recorded(FIXED_STEP);
+            if let Some(replay) = recording.as_mut() {
+                replay.record(time.delta, false, &input);
+            }
+            make_step(&mut state, &input, time.delta, game, &mut history, &mut deadlocks);
+        }
+
+        (state, history)
+    }
+
+    /// Irregular frame times and a push / walk / undo session on `level2`.
+    fn session() -> (Vec<f32>, Vec<InputSnapshot>) {
+        let deltas = (0..400).map(|i| 0.011 + (i % 7) as f32 * 0.003).collect();
+        let mut inputs = Vec::new();
+        for (input, frames) in [
+            (InputSnapshot { left: true, ..Default::default() }, 60),
+            (InputSnapshot::default(), 100),
+            (InputSnapshot { right: true, ..Default::default() }, 20),
+            (InputSnapshot::default(), 50),
+            (InputSnapshot { undo: true, ..Default::default() }, 3),
+            (InputSnapshot::default(), 167),
+        ] {
+            inputs.extend(std::iter::repeat_n(input, frames));
+        }
+        (deltas, inputs)
+    }
+
+    #[test]
+    fn test_record_stores_only_changed_inputs() {
+        let mut replay = Replay::new("pack/level1.json");
+        let right = InputSnapshot { right: true, ..Default::default() };
+
+        replay.record(0.1, false, &InputSnapshot::default());
+        replay.record(0.2, false, &right);
+        replay.record(0.3, false, &right);
+
+        assert_eq!(replay.len(), 3);
+        assert_eq!(replay.frames.iter().filter(|frame| frame.input.is_some()).count(), 2);
+        assert_eq!(replay.deltas().collect::<Vec<_>>(), [0.1, 0.2, 0.3]);
+        assert_eq!(replay.inputs().collect::<Vec<_>>(), [InputSnapshot::default(), right, right]);
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let path = env::temp_dir()
+            .join(format!("ferari_replay_{}", std::process::id()))
+            .join("replay.json");
+        let mut replay = Replay::new("pack/level1.json");
+        replay.record(0.016_667, false, &InputSnapshot { up: true, ..Default::default() });
+        replay.record(0.1 + 0.2, true, &InputSnapshot::default());
+
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(loaded, replay);
+        assert_eq!(loaded.engine_version, ferari::VERSION);
+    }
+
+    #[test]
+    fn test_playback_reproduces_session() {
+        let game = GameMap::load("../game_levels/level2.json").unwrap();
+        let (deltas, inputs) = session();
+
+        let mut replay = Replay::new("Ferari/level2.json");
+        let mut time = Time::from_deltas(deltas);
+        let (recorded, recorded_history) =
+            play(&game, &mut time, inputs.into_iter(), Some(&mut replay));
+
+        let text = serde_json::to_string(&replay).unwrap();
+        let replay: Replay = serde_json::from_str(&text).unwrap();
+        let mut time = Time::from_deltas(replay.deltas());
+        let (played, played_history) = play(&game, &mut time, replay.inputs(), None);
+
+        assert_eq!((recorded_history.moves(), recorded_history.pushes()), (2, 1));
+        assert_eq!(played_history.moves(), recorded_history.moves());
+        assert_eq!(played_history.pushes(), recorded_history.pushes());
+        assert_eq!(positions(&played), positions(&recorded));
+        assert_eq!(played.mob_grid, recorded.mob_grid);
+    }
+
+    #[test]
+    fn test_solution_replay_path_is_a_single_file_name() {
+        if let Some(path) = solution_replay_path("My Pack/level 1.json") {
+            assert_eq!(path.file_name().unwrap(), "My_Pack_level_1_json.json");
+        }
+    }
+}
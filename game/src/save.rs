@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Environment variable that overrides the save directory.
+const DATA_DIR_ENV: &str = "FERARI_DATA_DIR";
+/// File name of the save file inside the data directory.
+const SAVE_FILE_NAME: &str = "progress.json";
+/// Format version written into the save file.
+const SAVE_VERSION: u32 = 1;
+/// Suffix of the temporary file a file is written to before it replaces the file.
+const TMP_EXTENSION: &str = ".tmp";
+/// Suffix of the backup of a file that cannot be parsed.
+const BACKUP_EXTENSION: &str = ".bak";
+
+/// Best results of a single level.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LevelRecord {
+    /// Whether the level has ever been solved
+    #[serde(default)]
+    pub solved: bool,
+    /// Fewest moves of a solution
+    #[serde(default)]
+    pub best_moves: Option<u32>,
+    /// Fewest pushes of a solution
+    #[serde(default)]
+    pub best_pushes: Option<u32>,
+    /// Shortest time of a solution, in seconds
+    #[serde(default)]
+    pub best_time: Option<f32>,
+}
+
+/// Where the player left off.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LastPlayed {
+    /// Name of the pack
+    pub pack: String,
+    /// Index of the level within the pack, `None` for the level menu
+    #[serde(default)]
+    pub level: Option<usize>,
+}
+
+/// Player progress, persisted as JSON in the user data directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Progress {
+    /// Format version of the save file
+    #[serde(default)]
+    version: u32,
+    /// Results per level, keyed by [`ferari::assets::LevelPack::level_id`]
+    #[serde(default)]
+    levels: BTreeMap<String, LevelRecord>,
+    /// Scene the game resumes with
+    #[serde(default)]
+    pub last_played: Option<LastPlayed>,
+    /// Save file location, `None` keeps the progress in memory only
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Progress {
+    /// Loads the progress from the default save file.
+    ///
+    /// A missing save file yields empty progress. If no data directory can be determined,
+    /// the progress is kept in memory only.
+    ///
+    /// # Returns
+    ///
+    /// The loaded progress, bound to its save file.
+    pub fn load_default() -> Self {
+        match data_dir() {
+            Some(dir) => Self::load(dir.join(SAVE_FILE_NAME)),
+            None => {
+                eprintln!("No user data directory found, progress will not be saved");
+                Self::default()
+            }
+        }
+    }
+
+    /// Loads the progress from a save file.
+    ///
+    /// A missing file yields empty progress; an unreadable one is reported and replaced with
+    /// empty progress on the next save. A corrupted one is kept as a `.bak` file next to it
+    /// before starting with empty progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - location of the save file
+    ///
+    /// # Returns
+    ///
+    /// The loaded progress, bound to `path`.
+    pub fn load(path: PathBuf) -> Self {
+        let mut progress = match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|err| {
+                eprintln!("{}: corrupted save file ignored: {err}", path.display());
+                back_up(&path);
+                Self::default()
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                eprintln!("{}: cannot read save file: {err}", path.display());
+                Self::default()
+            }
+        };
+
+        progress.path = Some(path);
+        progress
+    }
+
+    /// Writes the progress to its save file.
+    ///
+    /// The data is written to a temporary file first which then replaces the save file,
+    /// so an interrupted write never leaves a truncated save behind.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` on success (or if the progress is kept in memory only).
+    pub fn save(&mut self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        self.version = SAVE_VERSION;
+
+        let text = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        write_atomic(path, &text)
+    }
+
+    /// Returns the record of a level.
+    ///
+    /// # Arguments
+    ///
+    /// * `level_id` - stable level identifier
+    pub fn record(&self, level_id: &str) -> Option<&LevelRecord> {
+        self.levels.get(level_id)
+    }
+
+    /// Checks whether a level has been solved.
+    pub fn is_solved(&self, level_id: &str) -> bool {
+        self.levels.get(level_id).is_some_and(|record| record.solved)
+    }
+
+    /// Records a solution of a level, keeping the best result of every category.
+    ///
+    /// # Arguments
+    ///
+    /// * `level_id` - stable level identifier
+    /// * `moves` - number of moves of the solution
+    /// * `pushes` - number of pushes of the solution
+    /// * `time` - time the solution took, in seconds
+    ///
+    /// # Returns
+    ///
+    /// The updated record.
+    pub fn record_solution(
+        &mut self,
+        level_id: &str,
+        moves: u32,
+        pushes: u32,
+        time: f32,
+    ) -> LevelRecord {
+        let record = self.levels.entry(level_id.to_string()).or_default();
+
+        record.solved = true;
+        record.best_moves = Some(record.best_moves.map_or(moves, |best| best.min(moves)));
+        record.best_pushes = Some(record.best_pushes.map_or(pushes, |best| best.min(pushes)));
+        record.best_time = Some(record.best_time.map_or(time, |best| best.min(time)));
+
+        *record
+    }
+}
+
+/// Replaces a file without ever leaving it truncated.
+///
+/// The text is written to a temporary file next to it first, which then replaces the file.
+/// Missing directories are created.
+///
+/// # Arguments
+///
+/// * `path` - file to write
+/// * `text` - new content of the file
+pub fn write_atomic(path: &Path, text: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(TMP_EXTENSION);
+    fs::write(&tmp_path, text)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Keeps a copy of a file that cannot be parsed, so it is not lost on the next save.
+///
+/// The file is renamed to `<name>.bak` (an older backup is replaced) and the new location
+/// is reported.
+///
+/// # Arguments
+///
+/// * `path` - file to back up
+pub fn back_up(path: &Path) {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(BACKUP_EXTENSION);
+    let backup = PathBuf::from(backup);
+    match fs::rename(path, &backup) {
+        Ok(()) => eprintln!("{}: kept as {}", path.display(), backup.display()),
+        Err(err) => eprintln!("{}: cannot keep a backup: {err}", path.display()),
+    }
+}
+
+/// Returns the directory the save file lives in.
+///
+/// `$FERARI_DATA_DIR` if set, otherwise the platform data directory
+/// (`$XDG_DATA_HOME/ferari` or `~/.local/share/ferari` on Linux,
+/// `~/Library/Application Support/ferari` on macOS).
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os(DATA_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+
+    let home = env::var_os("HOME").filter(|home| !home.is_empty()).map(PathBuf::from);
+
+    #[cfg(target_os = "macos")]
+    let base = home.map(|home| home.join("Library").join("Application Support"));
+
+    #[cfg(not(target_os = "macos"))]
+    let base = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| Path::new(dir).is_absolute())
+        .map(PathBuf::from)
+        .or_else(|| home.map(|home| home.join(".local").join("share")));
+
+    base.map(|base| base.join("ferari"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a save file path inside an empty scratch directory.
+    fn scratch_save(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("ferari_save_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("nested").join(SAVE_FILE_NAME)
+    }
+
+    #[test]
+    fn test_missing_save_file_is_empty_progress() {
+        let path = scratch_save("missing");
+        let progress = Progress::load(path);
+
+        assert!(!progress.is_solved("pack/level1.json"));
+        assert_eq!(progress.last_played, None);
+    }
+
+    #[test]
+    fn test_record_solution_keeps_best_results() {
+        let mut progress = Progress::default();
+
+        progress.record_solution("pack/a", 30, 10, 20.0);
+        let record = progress.record_solution("pack/a", 40, 8, 15.5);
+
+        assert!(record.solved);
+        assert_eq!(record.best_moves, Some(30));
+        assert_eq!(record.best_pushes, Some(8));
+        assert_eq!(record.best_time, Some(15.5));
+        assert!(progress.is_solved("pack/a"));
+        assert!(!progress.is_solved("pack/b"));
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let path = scratch_save("round_trip");
+        let mut progress = Progress::load(path.clone());
+        progress.record_solution("pack/level2.json", 10, 6, 12.0);
+        progress.last_played = Some(LastPlayed { pack: "pack".into(), level: Some(1) });
+        progress.save().unwrap();
+
+        let loaded = Progress::load(path.clone());
+        let dir = path.parent().unwrap().to_path_buf();
+        let leftovers: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+
+        assert_eq!(leftovers.len(), 1, "temporary file must be renamed");
+        assert_eq!(loaded.record("pack/level2.json"), progress.record("pack/level2.json"));
+        assert_eq!(loaded.last_played, progress.last_played);
+    }
+
+    #[test]
+    fn test_corrupted_save_file_is_replaced() {
+        let path = scratch_save("corrupted");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{ not json").unwrap();
+
+        let mut progress = Progress::load(path.clone());
+        assert!(!progress.is_solved("pack/a"));
+
+        progress.record_solution("pack/a", 1, 1, 1.0);
+        progress.save().unwrap();
+        let loaded = Progress::load(path.clone());
+        let backup = fs::read_to_string(path.with_extension("json.bak"));
+        fs::remove_dir_all(path.parent().unwrap().parent().unwrap()).unwrap();
+
+        assert!(loaded.is_solved("pack/a"));
+        assert_eq!(backup.unwrap(), "{ not json");
+    }
+}
@@ -0,0 +1,1379 @@
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::path::PathBuf;
+
+use ferari::assets::{box_sprite, Atlas, GameMap, LevelPack, MapError, Object, TileType};
+use ferari::input::{Action, InputEvent, InputEventKind, InputSnapshot, KeyBindings};
+use ferari::render::{self, BitmapFont, RenderableEntity};
+use ferari::time::Time;
+use ferari::world::{
+    self, height_step, world_buf_pos_to_tile, world_buf_pos_to_tile_coords, Camera, Direction,
+    State,
+};
+use minifb::Key;
+
+use crate::behaviour::{back_pressed, finish_animations, input_for_direction, make_step, Gates};
+use crate::complete_screen::{CompleteOption, CompleteScreen, LevelSummary};
+use crate::deadlock::DeadlockDetector;
+use crate::game_state::{Command, GameState, Scene, FADE_DURATION};
+use crate::history::History;
+use crate::hud::{level_hud, speed_hud, status_hud, LevelStats, HUD_FONT_SCALE};
+use crate::initiator::{get_player_sprite, get_unit_positions, get_visible_objects, lerp};
+use crate::job::{Job, JobStatus};
+use crate::menu;
+use crate::move_buffer::MoveBuffer;
+use crate::pathfinding::{push_path, walk_path};
+use crate::replay::{last_replay_path, solution_replay_path, Replay};
+use crate::save::{LastPlayed, Progress};
+use crate::settings::Settings;
+use crate::settings_screen::{ScreenUpdate, SettingsScreen};
+use crate::solver::{solve_with_limit, Solution, SolveMode};
+use crate::{LOGIC_HEIGHT, LOGIC_WIDTH, TILE_SIZE};
+
+/// Directory scanned for level packs at startup.
+pub const LEVELS_DIR: &str = "game_levels";
+/// Search budget of in-game hints, small enough to answer within a few seconds.
+const HINT_NODE_LIMIT: usize = 200_000;
+/// Time a notice (key conflicts, game speed, ...) is shown in the HUD, in seconds.
+const NOTICE_DURATION: f32 = 5.0;
+/// Color dead boxes are tinted with.
+const DEAD_BOX_TINT: u32 = 0xFF6060;
+/// Color the box selected with the mouse is tinted with.
+const SELECTED_BOX_TINT: u32 = 0x80C0FF;
+/// Height of the player's hops while a solved level is celebrated, in pixels.
+const CELEBRATION_HOP_HEIGHT: f32 = 6.0;
+/// Hops per second while a solved level is celebrated.
+const CELEBRATION_HOP_RATE: f32 = 3.0;
+/// Time between two blinks of the placed boxes while a solved level is celebrated, in
+/// seconds.
+const CELEBRATION_BLINK_PERIOD: f32 = 0.15;
+/// Color of the world around the level.
+const WORLD_BACKGROUND: u32 = 195213255;
+
+/// Hint search running on a worker thread.
+#[derive(Debug)]
+struct HintSearch {
+    /// Solver run from the position the hint was asked for
+    job: Job<Solution>,
+    /// Whether the whole solution is played, not only its first move
+    whole: bool,
+}
+
+/// Result of a solved level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelResult {
+    /// Identifier of the level, `None` if the level is not part of a pack
+    pub level_id: Option<String>,
+    /// Number of moves
+    pub moves: u32,
+    /// Number of pushes
+    pub pushes: u32,
+    /// Time spent in the level, in seconds
+    pub time: f32,
+}
+
+/// Returns the root directory of the project (assets and levels).
+pub fn project_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..")
+}
+
+/// Loads the tile and entity atlases of the game.
+///
+/// # Returns
+///
+/// * `Result<(Atlas, Atlas), Box<dyn Error>>` - `(tiles, entities)` atlases on success,
+///   error on failure.
+pub fn load_atlases() -> Result<(Atlas, Atlas), Box<dyn Error>> {
+    let assets_path = project_root().join("assets");
+
+    let tiles_path = assets_path.join("tiles/atlas.json");
+    let entities_path = assets_path.join("entities/atlas.json");
+
+    let tiles_atlas = Atlas::load(tiles_path.to_str().unwrap())?;
+    let entities_atlas = Atlas::load(entities_path.to_str().unwrap())?;
+    Ok((tiles_atlas, entities_atlas))
+}
+
+/// Discovers the level packs of the game, reporting (and skipping) broken ones.
+pub fn discover_packs() -> Vec<LevelPack> {
+    LevelPack::discover(project_root().join(LEVELS_DIR))
+        .into_iter()
+        .filter_map(|pack| pack.map_err(|err| eprintln!("Skipping level pack {err}")).ok())
+        .collect()
+}
+
+/// Initializes all core systems required to start a new game level.
+///
+/// This function sets up the rendering pipeline, camera, and game state based on
+/// the provided level data and graphical assets.
+///
+/// # Arguments
+///
+/// * `game` – the preloaded game map containing layout, walkability, and object placement
+/// * `entities_atlas` – texture atlas containing sprites for dynamic entities
+/// * `tiles_atlas` – texture atlas containing static tile graphics
+///
+/// # Returns
+///
+/// A tuple containing:
+/// * [`ferari::Render`] - the fully initialized renderer with pre-rendered static background.
+/// * [`world::Camera`] - a camera centered on the level, configured to the logical screen dimensions.
+/// * [`world::State`] - the initial game state (player position, mob positions, grid occupancy, etc.).
+fn init_level(
+    game: GameMap,
+    entities_atlas: Atlas,
+    tiles_atlas: Atlas,
+) -> (ferari::Render, Camera, State) {
+    // init world_buf
+    let world_width = game.size[0] as usize * TILE_SIZE * 2;
+    let world_height = game.size[1] as usize * TILE_SIZE * 2;
+    let world_buf: Vec<u32> = vec![WORLD_BACKGROUND; world_width * world_height];
+
+    // init render
+    let shadow_map: Vec<u8> = vec![0; world_width * world_height];
+    let mut render =
+        render::Render::new(world_buf, world_height, world_width, entities_atlas, shadow_map);
+
+    // init camera
+    let camera = Camera::new(
+        (world_width / 2) as f32,
+        (world_height / 2) as f32,
+        LOGIC_WIDTH as u16,
+        LOGIC_HEIGHT as u16,
+    );
+
+    // init state of game
+    let state = State::new(&game);
+
+    // prerender
+    render.init(&game, &tiles_atlas);
+
+    (render, camera, state)
+}
+
+/// Returns the map as it currently looks: holes filled by a box use the `hole_filled`
+/// asset and closed doors are blocked by a `door_closed` object.
+///
+/// # Arguments
+///
+/// * `game` - the level map
+/// * `gates` - state of the doors and holes
+///
+/// # Returns
+///
+/// A copy of `game` to prerender the static background from.
+fn mechanism_scenery(game: &GameMap, gates: &Gates) -> GameMap {
+    let mut scenery = game.clone();
+    for tile in scenery.tiles.values_mut() {
+        let (x, y) = (tile.x as i32, tile.y as i32);
+        if tile.tile_type == TileType::Hole && !gates.is_open_hole(x, y) {
+            tile.asset = "hole_filled".to_string();
+        }
+    }
+
+    let width = game.size[0] as i32;
+    for (idx, tile_type) in game.walk_map.iter().enumerate() {
+        let (x, y) = (idx as i32 % width, idx as i32 / width);
+        if matches!(tile_type, TileType::Door(_)) && gates.is_closed(x, y) {
+            let name = format!("door_closed_{x}_{y}");
+            let door = Object {
+                name: name.clone(),
+                x: x as u32,
+                y: y as u32,
+                asset: "door_closed".to_string(),
+                collidable: true,
+                shadow: true,
+            };
+            scenery.objects.insert(name, door);
+        }
+    }
+    scenery
+}
+
+/// Loads the map of a scene: the pack menu, the level menu of a pack or one of its levels.
+///
+/// # Arguments
+///
+/// * `packs` - all discovered level packs
+/// * `scene` - the scene to load
+/// * `progress` - player progress (decides which levels are solved)
+/// * `tiles_atlas` - atlas of the tiles and objects
+/// * `entities_atlas` - atlas of the mobs
+///
+/// # Returns
+///
+/// The map of the scene, or an error if the level file cannot be loaded or uses frames
+/// missing from the atlases.
+fn load_scene(
+    packs: &[LevelPack],
+    scene: Scene,
+    progress: &Progress,
+    tiles_atlas: &Atlas,
+    entities_atlas: &Atlas,
+) -> Result<GameMap, Box<dyn Error>> {
+    match scene {
+        Scene::PackMenu => Ok(menu::pack_menu(packs)),
+        Scene::LevelMenu { pack } => {
+            Ok(menu::level_menu(&packs[pack], &solved_levels(&packs[pack], progress)))
+        }
+        Scene::Level { pack, level } => {
+            let game = packs[pack].load_level(level)?;
+            let missing = game.check_assets(tiles_atlas, entities_atlas);
+            if !missing.is_empty() {
+                return Err(format!("{}: {}", game.name, MapError::Invalid(missing)).into());
+            }
+            Ok(game)
+        }
+    }
+}
+
+/// Returns the solved flag of every level of a pack.
+pub fn solved_levels(pack: &LevelPack, progress: &Progress) -> Vec<bool> {
+    (0..pack.len()).map(|i| pack.level_id(i).is_some_and(|id| progress.is_solved(&id))).collect()
+}
+
+/// Finds a level by its identifier.
+///
+/// # Arguments
+///
+/// * `packs` - all discovered level packs
+/// * `level_id` - identifier of the level, see [`LevelPack::level_id`]
+///
+/// # Returns
+///
+/// Indices `(pack, level)` of the level, or `None` if no pack contains it.
+pub fn find_level(packs: &[LevelPack], level_id: &str) -> Option<(usize, usize)> {
+    packs.iter().enumerate().find_map(|(pack_idx, pack)| {
+        (0..pack.len())
+            .find(|&idx| pack.level_id(idx).as_deref() == Some(level_id))
+            .map(|idx| (pack_idx, idx))
+    })
+}
+
+/// Saves a recorded session, reporting failures instead of interrupting the game.
+///
+/// # Arguments
+///
+/// * `replay` - recorded session
+/// * `path` - replay file, `None` if there is no data directory
+fn save_replay(replay: &Replay, path: Option<PathBuf>) {
+    let Some(path) = path.filter(|_| !replay.is_empty()) else {
+        return;
+    };
+    if let Err(err) = replay.save(&path) {
+        eprintln!("Cannot save replay {}: {err}", path.display());
+    }
+}
+
+/// Fewest moves and fewest pushes of any solution of a level, `None` where not found.
+type OptimalCounts = (Option<usize>, Option<usize>);
+
+/// Searches for the fewest moves and pushes of any solution of a level.
+///
+/// Both searches stop at the hint search budget; this runs on a worker thread.
+///
+/// # Arguments
+///
+/// * `game` - the level
+///
+/// # Returns
+///
+/// The fewest moves and the fewest pushes, `None` where the solver found no solution.
+fn optimal_counts(game: &GameMap) -> OptimalCounts {
+    let start = State::new(game);
+    let optimal = |mode| match solve_with_limit(game, &start, mode, HINT_NODE_LIMIT) {
+        Solution::Solved(steps) => Some(steps),
+        Solution::Unsolvable | Solution::LimitReached | Solution::Unsupported => None,
+    };
+    let moves = optimal(SolveMode::Moves).map(|steps| steps.len());
+    let pushes =
+        optimal(SolveMode::Pushes).map(|steps| steps.iter().filter(|step| step.push).count());
+    (moves, pushes)
+}
+
+/// Game session: menus, the level being played and the transitions between them.
+///
+/// The session owns all game state and is driven one frame at a time by
+/// [`Session::update`] and [`Session::render`], either by the windowed game loop or by
+/// [`crate::headless::Headless`].
+pub struct Session {
+    /// All discovered level packs
+    packs: Vec<LevelPack>,
+    /// Sprites of the player and the boxes
+    entities_atlas: Atlas,
+    /// Static tile graphics
+    tiles_atlas: Atlas,
+    /// Font of the in-game HUD
+    hud_font: BitmapFont,
+    /// Player progress
+    progress: Progress,
+    /// Whether recorded sessions are written to the replay files
+    save_replays: bool,
+    /// User settings (key bindings)
+    settings: Settings,
+    /// Settings screen, `None` while it is closed
+    settings_screen: Option<SettingsScreen>,
+    /// Whether the key bindings changed since the last [`Session::take_changed_bindings`]
+    bindings_changed: bool,
+
+    /// Map of the current scene
+    game: GameMap,
+    /// Renderer of the current scene
+    render: ferari::Render,
+    /// Camera following the player
+    camera: Camera,
+    /// Units of the current scene
+    state: State,
+    /// Unit positions before the last step, rendering interpolates from them
+    prev_positions: Vec<(f32, f32)>,
+
+    /// Scene loaded into `game` and `state`
+    scene: Scene,
+    /// Menu, level, pause, completion or pending scene change
+    game_state: GameState,
+    /// Menu boxes of solved levels, shown as placed
+    solved_boxes: Vec<(i32, i32)>,
+    /// Boxes shown as placed in the current frame (solved levels, boxes on a matching
+    /// target)
+    suc_boxes: Vec<(i32, i32)>,
+    /// Number of boxes standing on a target of their color, shown in the HUD
+    placed_count: usize,
+    /// Time spent in the current level, without pauses
+    level_time: f32,
+    /// Whether the "back to menu" combination was held last frame
+    back_held: bool,
+    /// Whether the restart key was held last frame
+    restart_held: bool,
+    /// Whether the next level key was held last frame
+    next_level_held: bool,
+    /// Whether the previous level key was held last frame
+    previous_level_held: bool,
+    /// Result of the last solved level
+    last_result: Option<LevelResult>,
+
+    /// Move history of the current level
+    history: History,
+    /// Deadlock analysis of the current level
+    deadlocks: DeadlockDetector,
+    /// Doors and holes as drawn into the static background, `None` on maps without
+    /// mechanisms
+    drawn_gates: Option<Gates>,
+    /// Whether an automatic undo is pending
+    deadlock_undo: bool,
+    /// Solver moves waiting to be played
+    autoplay: VecDeque<Direction>,
+    /// Whether a hint key was held last frame
+    hint_held: bool,
+    /// Fewest moves and pushes of the levels solved so far, by level id (or name)
+    optimal_cache: HashMap<String, OptimalCounts>,
+    /// Search for the optimal counts of the last solved level and its id (or name)
+    optimal_search: Option<(String, Job<OptimalCounts>)>,
+    /// Whether the quit key is held since it was pressed for the settings screen
+    quit_held_by_screen: bool,
+    /// Notice shown in the HUD (key conflicts, game speed, ...) and the time it is still
+    /// shown, in seconds
+    notice: Option<(String, f32)>,
+    /// Hint being searched for ("thinking"), `None` if no hint was asked for
+    hint_search: Option<HintSearch>,
+    /// Why the last hint search found no moves, shown until the player moves
+    hint_message: Option<&'static str>,
+    /// Whether the undo key was held when the last undo was applied
+    undo_held: bool,
+    /// Whether the redo key was held when the last redo was applied
+    redo_held: bool,
+    /// Movement taps waiting for the current animation to finish
+    move_buffer: MoveBuffer,
+    /// Box selected with the mouse, to be pushed to the next clicked tile
+    selected_box: Option<usize>,
+    /// Recorded deltas, instant moves and inputs of the replay being played back (`None`
+    /// once its level is left)
+    playback: Option<VecDeque<(f32, bool, InputSnapshot)>>,
+    /// Replay being played back
+    played_replay: Option<Replay>,
+    /// Replay of the last solution, watched with [`CompleteOption::Replay`]
+    last_solution: Option<Replay>,
+    /// Replay to play back once the next scene is entered
+    pending_replay: Option<Replay>,
+    /// Screen with the statistics of the solved level
+    complete_screen: Option<CompleteScreen>,
+    /// Time left of the fade-in of the current scene, in seconds
+    fade_in: f32,
+    /// Recording of the current level session
+    recording: Option<Replay>,
+}
+
+impl Session {
+    /// Creates a session showing the menu of the start pack.
+    ///
+    /// # Arguments
+    ///
+    /// * `packs` - all discovered level packs (at least one)
+    /// * `tiles_atlas` - texture atlas containing static tile graphics
+    /// * `entities_atlas` - texture atlas containing sprites for dynamic entities
+    /// * `progress` - player progress
+    /// * `settings` - user settings
+    /// * `start` - `(pack, level)` to start with; the level menu of the pack if `level` is
+    ///   `None`, the pack menu (or the only pack) if `start` is `None`
+    ///
+    /// # Returns
+    ///
+    /// A session whose first [`Session::update`] enters the requested level.
+    pub fn new(
+        packs: Vec<LevelPack>,
+        tiles_atlas: Atlas,
+        entities_atlas: Atlas,
+        progress: Progress,
+        settings: Settings,
+        start: Option<(usize, Option<usize>)>,
+    ) -> Self {
+        // with a single pack its level menu is the main menu
+        let cur_pack = start.map(|(pack, _)| pack).or((packs.len() == 1).then_some(0));
+        let scene = match (cur_pack, start.and_then(|(_, level)| level)) {
+            (Some(pack), Some(level)) => Scene::Level { pack, level },
+            (Some(pack), None) => Scene::LevelMenu { pack },
+            (None, _) => Scene::PackMenu,
+        };
+        // the menu of the start scene is shown until the first update enters it
+        let menu = match scene {
+            Scene::Level { pack, .. } => Scene::LevelMenu { pack },
+            menu => menu,
+        };
+
+        let game = load_scene(&packs, menu, &progress, &tiles_atlas, &entities_atlas).unwrap();
+        let (render, camera, state) =
+            init_level(game.clone(), entities_atlas.clone(), tiles_atlas.clone());
+        let deadlocks = DeadlockDetector::new(&game);
+        let notice = settings.conflict_notice().map(|notice| (notice, NOTICE_DURATION));
+
+        Self {
+            hud_font: BitmapFont::from_atlas(&tiles_atlas, HUD_FONT_SCALE),
+            packs,
+            entities_atlas,
+            tiles_atlas,
+            progress,
+            save_replays: true,
+            settings,
+            settings_screen: None,
+            bindings_changed: false,
+            game,
+            render,
+            camera,
+            state,
+            scene: menu,
+            game_state: GameState::Transition { scene, remaining: 0.0 },
+            solved_boxes: Vec::new(),
+            suc_boxes: Vec::new(),
+            placed_count: 0,
+            level_time: 0.0,
+            back_held: false,
+            restart_held: false,
+            next_level_held: false,
+            previous_level_held: false,
+            last_result: None,
+            history: History::new(),
+            deadlocks,
+            deadlock_undo: false,
+            drawn_gates: None,
+            autoplay: VecDeque::new(),
+            hint_held: false,
+            optimal_cache: HashMap::new(),
+            optimal_search: None,
+            quit_held_by_screen: false,
+            notice,
+            hint_search: None,
+            hint_message: None,
+            undo_held: false,
+            redo_held: false,
+            move_buffer: MoveBuffer::new(),
+            selected_box: None,
+            prev_positions: Vec::new(),
+            playback: None,
+            played_replay: None,
+            last_solution: None,
+            pending_replay: None,
+            complete_screen: None,
+            fade_in: 0.0,
+            recording: None,
+        }
+    }
+
+    /// Enables or disables writing recorded sessions to the replay files.
+    pub fn set_save_replays(&mut self, save_replays: bool) {
+        self.save_replays = save_replays;
+    }
+
+    /// Returns whether the quit action is taken by the settings screen (a key is being
+    /// bound, or the key that cancelled the binding is still held).
+    pub fn quit_blocked(&self) -> bool {
+        self.quit_held_by_screen || self.settings_screen.as_ref().is_some_and(|s| s.is_capturing())
+    }
+
+    /// Plays back a recorded session instead of reading the input.
+    ///
+    /// The session has to be started in the level of the replay. The recorded deltas
+    /// drive the simulation, whatever the speed of the clock.
+    ///
+    /// # Arguments
+    ///
+    /// * `replay` - recorded session
+    pub fn start_playback(&mut self, replay: &Replay) {
+        let frames = replay.deltas().zip(replay.instants()).zip(replay.inputs());
+        self.playback =
+            Some(frames.map(|((delta, instant), input)| (delta, instant, input)).collect());
+        self.played_replay = Some(replay.clone());
+    }
+
+    /// Returns the player progress.
+    #[cfg(test)]
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// Returns the map of the current scene.
+    pub fn game(&self) -> &GameMap {
+        &self.game
+    }
+
+    /// Returns the units of the current scene.
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Returns the move history of the current level.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Returns the level being played as `(pack, level)` indices, `None` in the menus.
+    pub fn current_level(&self) -> Option<(usize, usize)> {
+        self.game_state.level()
+    }
+
+    /// Returns the state of the game.
+    #[cfg(test)]
+    pub fn game_state(&self) -> GameState {
+        self.game_state
+    }
+
+    /// Returns the completion screen of the solved level, if it is shown.
+    #[cfg(test)]
+    pub fn complete_screen(&self) -> Option<&CompleteScreen> {
+        self.complete_screen.as_ref()
+    }
+
+    /// Returns the notice shown on the HUD status line, if any.
+    #[cfg(test)]
+    pub fn notice(&self) -> Option<&str> {
+        self.notice.as_ref().map(|(notice, _)| notice.as_str())
+    }
+
+    /// Returns the result of the last solved level.
+    pub fn last_result(&self) -> Option<&LevelResult> {
+        self.last_result.as_ref()
+    }
+
+    /// Checks whether a recorded session is being played back.
+    pub fn is_playing_back(&self) -> bool {
+        self.playback.as_ref().is_some_and(|inputs| !inputs.is_empty())
+    }
+
+    /// Handles a left click: walks to a clicked floor tile, selects a clicked box, or
+    /// pushes the selected box to the clicked tile.
+    ///
+    /// The moves are queued like solver hints and played once the player is idle; any
+    /// movement key cancels them.
+    ///
+    /// # Arguments
+    ///
+    /// * `x`, `y` - click position on screen, in logical pixels
+    pub fn click(&mut self, x: f32, y: f32) {
+        let active = matches!(self.game_state, GameState::Menu(_) | GameState::Playing { .. });
+        if !active || self.settings_screen.is_some() || self.is_playing_back() {
+            return;
+        }
+
+        let Some((tile_x, tile_y)) = self.tile_at(x, y) else {
+            self.selected_box = None;
+            return;
+        };
+        let clicked_box = self.state.get_mob_at(tile_x, tile_y);
+
+        let path = match (clicked_box, self.selected_box) {
+            (Some(idx), selected) => {
+                self.selected_box = (selected != Some(idx)).then_some(idx);
+                return;
+            }
+            (None, Some(idx)) => {
+                self.selected_box = None;
+                let path = push_path(&self.game, &self.state, idx, (tile_x, tile_y));
+                if path.is_none() {
+                    self.show_notice("THE BOX CANNOT BE PUSHED THERE");
+                }
+                path
+            }
+            (None, None) => walk_path(&self.game, &self.state, (tile_x, tile_y)),
+        };
+
+        if let Some(path) = path {
+            self.autoplay = path.into();
+            self.move_buffer.clear();
+            self.cancel_hint();
+        }
+    }
+
+    /// Returns the tile of the current scene drawn at a screen position.
+    ///
+    /// Raised tiles are drawn higher up, so the position is tried at every height level of
+    /// the map and the front-most tile actually drawn there wins.
+    fn tile_at(&self, x: f32, y: f32) -> Option<(i32, i32)> {
+        let (world_x, world_y) = self.camera.screen_to_world(x, y);
+        let step = height_step(self.game.tile_size);
+        let [width, height] = self.game.size;
+
+        let mut levels = self.game.height_map.clone();
+        levels.sort_unstable();
+        levels.dedup();
+
+        levels
+            .into_iter()
+            .filter_map(|level| {
+                let (tile_x, tile_y) = world_buf_pos_to_tile(
+                    world_x,
+                    world_y + level as f32 * step,
+                    self.game.tile_size,
+                    self.render.world_width as u32,
+                    self.render.world_height as u32,
+                );
+                let inside =
+                    (0..width as i32).contains(&tile_x) && (0..height as i32).contains(&tile_y);
+                (inside && self.game.height_at(tile_x, tile_y) == level).then_some((tile_x, tile_y))
+            })
+            .max_by_key(|&(tile_x, tile_y)| tile_x + tile_y)
+    }
+
+    /// Returns the key bindings if they have been changed on the settings screen since
+    /// the last call.
+    pub fn take_changed_bindings(&mut self) -> Option<KeyBindings> {
+        std::mem::take(&mut self.bindings_changed).then(|| self.settings.key_bindings.clone())
+    }
+
+    /// Checks whether the player and all boxes stand still.
+    pub fn is_idle(&self) -> bool {
+        let idle = |unit: &world::Unit| matches!(unit.movement, world::UnitMovement::Idle);
+        idle(&self.state.player.unit) && self.state.mobs.iter().all(idle)
+    }
+
+    /// Advances the session by one frame.
+    ///
+    /// Enters the requested scene, applies the input (or the replayed input, hints and
+    /// automatic undo, buffered taps), and checks menu selections and the win condition.
+    /// While the settings screen is open it receives the input instead of the player.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - input of this frame
+    /// * `events` - input events since the previous frame, in order
+    /// * `keys` - keys pressed in this frame (used to rebind keys)
+    /// * `time` - clock, already advanced by this step; its speed follows the settings
+    ///   and the pause, speed and instant moves actions
+    pub fn update(
+        &mut self,
+        mut input: InputSnapshot,
+        events: &[InputEvent],
+        keys: &[Key],
+        time: &mut Time,
+    ) {
+        if let Some(scene) = self.game_state.pending_scene() {
+            self.enter_scene(scene);
+        }
+        self.poll_optimal();
+        if let Some((_, remaining)) = &mut self.notice {
+            *remaining -= time.delta;
+            if *remaining <= 0.0 {
+                self.notice = None;
+            }
+        }
+
+        let screen_open = self.update_settings_screen(&mut input, events, keys);
+        if !screen_open {
+            self.update_time_scale(events);
+        }
+        time.set_time_scale(self.settings.time_scale);
+        self.game_state = self.game_state.tick(time.delta);
+        self.fade_in = (self.fade_in - time.delta).max(0.0);
+        time.set_paused(matches!(self.game_state, GameState::Paused { .. }));
+
+        match self.game_state {
+            GameState::Menu(_) | GameState::Playing { .. } => {}
+            // the input is ignored while the scene fades out
+            GameState::Transition { .. } => return,
+            GameState::LevelComplete { .. } if self.game_state.celebration().is_none() => {
+                let chosen = self.complete_screen.as_mut().filter(|_| !screen_open);
+                match chosen.and_then(|screen| screen.update(events, keys)) {
+                    Some(option) => self.choose(option),
+                    None => self.handle_commands(&input, back_pressed(&input)),
+                }
+                return;
+            }
+            // a paused or celebrated level only takes the level commands
+            GameState::Paused { .. } | GameState::LevelComplete { .. } => {
+                self.handle_commands(&input, back_pressed(&input));
+                return;
+            }
+        }
+
+        self.level_time += time.delta;
+        if screen_open {
+            self.move_buffer.clear();
+        } else {
+            self.move_buffer.handle(events);
+        }
+
+        let mut delta = time.scaled_delta();
+        let mut instant = time.time_scale().instant;
+        if let Some((recorded_delta, recorded_instant, recorded)) =
+            self.playback.as_mut().and_then(|inputs| inputs.pop_front())
+        {
+            // replay: the recorded input and game time replace the keyboard and the speed
+            input = recorded;
+            delta = recorded_delta;
+            instant = recorded_instant;
+            self.deadlock_undo = false;
+            if self.playback.as_ref().is_some_and(|inputs| inputs.is_empty()) {
+                self.show_notice("REPLAY FINISHED");
+            }
+        } else {
+            // undo and redo fire once per key press; a press made during an animation
+            // stays pending until the player and the boxes are idle
+            let idle = self.is_idle();
+            let (undo, redo) = (input.undo, input.redo);
+            input.undo = undo && !self.undo_held;
+            input.redo = redo && !self.redo_held;
+            if idle || !undo {
+                self.undo_held = undo;
+            }
+            if idle || !redo {
+                self.redo_held = redo;
+            }
+
+            // solver hints: manual input cancels the playback
+            if input.up || input.down || input.left || input.right || input.undo || input.redo {
+                self.autoplay.clear();
+                self.cancel_hint();
+            }
+            if input.undo || input.redo {
+                self.move_buffer.clear();
+            }
+
+            let hint_pressed = input.hint || input.solution;
+            if hint_pressed && !self.hint_held && idle && self.hint_search.is_none() {
+                let (game, state) = (self.game.clone(), self.state.clone());
+                let job = Job::spawn(move || {
+                    solve_with_limit(&game, &state, SolveMode::Pushes, HINT_NODE_LIMIT)
+                });
+                self.hint_search = Some(HintSearch { job, whole: input.solution });
+                self.hint_message = None;
+            }
+            self.hint_held = hint_pressed;
+            self.poll_hint();
+
+            if idle {
+                if self.deadlock_undo {
+                    input = InputSnapshot { undo: true, ..Default::default() };
+                    self.deadlock_undo = false;
+                } else if let Some(direction) = self.autoplay.pop_front() {
+                    input = input_for_direction(direction);
+                } else if let Some(direction) = self.move_buffer.next(&input) {
+                    input = input_for_direction(direction);
+                }
+            }
+        }
+
+        if let Some(replay) = &mut self.recording {
+            replay.record(delta, instant, &input);
+        }
+
+        self.prev_positions = get_unit_positions(&self.state);
+        let back = make_step(
+            &mut self.state,
+            &input,
+            delta,
+            &self.game,
+            &mut self.history,
+            &mut self.deadlocks,
+        );
+        if instant {
+            // the step still takes the frame time, only the animations are skipped
+            finish_animations(&mut self.state, &self.game, &mut self.history, &mut self.deadlocks);
+        }
+        self.handle_commands(&input, back.is_some());
+        self.redraw_mechanisms();
+
+        if self.deadlocks.take_fresh_deadlock() {
+            self.autoplay.clear();
+            if self.settings.auto_undo_deadlock {
+                self.show_notice("DEADLOCK  UNDOING THE PUSH");
+                self.deadlock_undo = true;
+            } else {
+                let undo = self.settings.key_bindings.describe(Action::Undo);
+                self.show_notice(format!("DEADLOCK  PRESS {undo} TO UNDO"));
+            }
+        }
+
+        self.camera.center_x = self.state.player.unit.pixel_x.floor();
+        self.camera.center_y = self.state.player.unit.pixel_y.floor();
+
+        self.suc_boxes = self.solved_boxes.clone();
+        self.placed_count = 0;
+
+        match self.game_state {
+            GameState::Menu(_) => self.check_menu_selection(),
+            GameState::Playing { .. } => self.check_level_solved(),
+            _ => {}
+        }
+    }
+
+    /// Draws the current frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - clock of the frame (drives the player animation and the interpolation
+    ///   between the last two steps)
+    /// * `buf` - framebuffer of `LOGIC_WIDTH * LOGIC_HEIGHT` pixels
+    ///
+    /// # Returns
+    ///
+    /// `false` if nothing is visible and the frame has not been drawn.
+    pub fn render(&mut self, time: &Time, buf: &mut [u32]) -> bool {
+        let alpha = time.alpha();
+        let player = &self.state.player.unit;
+        let (prev_x, prev_y) =
+            self.prev_positions.first().copied().unwrap_or((player.pixel_x, player.pixel_y));
+        self.camera.center_x = lerp(prev_x, player.pixel_x, alpha).floor();
+        self.camera.center_y = lerp(prev_y, player.pixel_y, alpha).floor();
+
+        let units_for_render =
+            get_visible_objects(&self.state, &self.prev_positions, alpha, &self.camera);
+        if units_for_render.is_empty() {
+            return false;
+        }
+
+        let selected_tile = self
+            .selected_box
+            .and_then(|idx| self.state.mobs.get(idx))
+            .map(|unit| (unit.tile_x, unit.tile_y));
+        // a solved level is celebrated with a hopping player and blinking boxes
+        let celebration = self.game_state.celebration();
+        let hop = celebration.map_or(0.0, |elapsed| {
+            let phase = elapsed * CELEBRATION_HOP_RATE * std::f32::consts::PI;
+            phase.sin().abs() * CELEBRATION_HOP_HEIGHT
+        });
+        let blink =
+            celebration.is_some_and(|elapsed| (elapsed / CELEBRATION_BLINK_PERIOD) as u32 % 2 == 1);
+        let step = height_step(self.game.tile_size);
+        let visible_entities: Vec<RenderableEntity> = units_for_render
+            .into_iter()
+            .enumerate()
+            .map(|(i, unit)| {
+                let sprite_name = if i == 0 {
+                    get_player_sprite(&self.state.player, time.scaled_total as f64)
+                } else {
+                    let placed = self.suc_boxes.contains(&(unit.tile_x, unit.tile_y)) && !blink;
+                    box_sprite(unit.color.as_deref(), placed)
+                };
+
+                let y = if i == 0 { unit.pixel_y - hop } else { unit.pixel_y };
+                let (tile_x, tile_y) = world_buf_pos_to_tile_coords(
+                    unit.pixel_x,
+                    unit.pixel_y,
+                    self.game.tile_size,
+                    self.render.world_width as u32,
+                    self.render.world_height as u32,
+                );
+                let elevation = self.game.elevation_at(tile_x, tile_y) * step;
+                let entity =
+                    RenderableEntity::new(unit.pixel_x, y, sprite_name).with_elevation(elevation);
+                let is_dead = i != 0
+                    && matches!(unit.movement, world::UnitMovement::Idle)
+                    && self.deadlocks.is_dead_box(unit.tile_x, unit.tile_y);
+                let is_selected = i != 0 && selected_tile == Some((unit.tile_x, unit.tile_y));
+                if is_selected {
+                    entity.with_tint(SELECTED_BOX_TINT)
+                } else if is_dead {
+                    entity.with_tint(DEAD_BOX_TINT)
+                } else {
+                    entity
+                }
+            })
+            .collect();
+
+        self.render.render_frame(&visible_entities, &self.camera, buf);
+
+        let complete_screen = self
+            .complete_screen
+            .as_ref()
+            .filter(|_| celebration.is_none() && self.settings_screen.is_none());
+
+        // HUD with the counters of the level, replaced by the statistics once it is solved
+        if let Some(screen) = complete_screen {
+            let hud = screen.hud(&self.hud_font);
+            self.render.render_hud(&hud, &self.hud_font, &self.camera, buf);
+        } else if matches!(self.scene, Scene::Level { .. }) {
+            let stats = LevelStats {
+                level_name: &self.game.name,
+                moves: self.history.moves(),
+                pushes: self.history.pushes(),
+                time: self.level_time,
+                boxes_on_target: self.placed_count,
+                targets: self.game.target_positions.len(),
+            };
+            let hud = level_hud(&stats, &self.hud_font);
+            self.render.render_hud(&hud, &self.hud_font, &self.camera, buf);
+        }
+
+        if let Some(hud) = speed_hud(time.time_scale(), time.is_paused(), &self.hud_font) {
+            self.render.render_hud(&hud, &self.hud_font, &self.camera, buf);
+        }
+
+        // a fresh notice hides the reason of the last failed hint, not the search itself
+        let thinking = self.hint_search.as_ref().map(|_| "THINKING");
+        let notice = self.notice.as_ref().map(|(notice, _)| notice.as_str());
+        let status = thinking.or(notice).or(self.hint_message);
+        if let Some(status) = status.filter(|_| complete_screen.is_none()) {
+            let hud = status_hud(status, &self.hud_font);
+            self.render.render_hud(&hud, &self.hud_font, &self.camera, buf);
+        }
+
+        if let Some(screen) = &self.settings_screen {
+            let hud = screen.hud(&self.settings, &self.hud_font);
+            self.render.render_hud(&hud, &self.hud_font, &self.camera, buf);
+        }
+
+        let fade = self.game_state.fade().max(self.fade_in / FADE_DURATION);
+        self.render.render_fade(fade, buf);
+
+        true
+    }
+
+    /// Ends the session, saving the recording of the level being played.
+    pub fn finish(&mut self) {
+        if let Some(replay) = self.recording.take() {
+            self.save_replay(&replay, last_replay_path());
+        }
+    }
+
+    /// Opens, updates and closes the settings screen.
+    ///
+    /// While the screen is open the input is consumed by it, so the player stands still.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the screen consumed the input of this frame.
+    fn update_settings_screen(
+        &mut self,
+        input: &mut InputSnapshot,
+        events: &[InputEvent],
+        keys: &[Key],
+    ) -> bool {
+        let settings_pressed = events
+            .iter()
+            .any(|event| event.action == Action::Settings && event.kind == InputEventKind::Pressed);
+
+        // a quit key pressed for the settings screen does not quit until it is released
+        self.quit_held_by_screen &= input.quit;
+        if let Some(screen) = &mut self.settings_screen {
+            self.quit_held_by_screen |= input.quit && screen.is_capturing();
+            match screen.update(events, keys, &mut self.settings) {
+                ScreenUpdate::Open => {}
+                ScreenUpdate::Changed => self.bindings_changed = true,
+                ScreenUpdate::Closed => {
+                    self.settings_screen = None;
+                    self.settings.report_conflicts();
+                    self.notice =
+                        self.settings.conflict_notice().map(|notice| (notice, NOTICE_DURATION));
+                    self.save_settings();
+                }
+            }
+        } else if settings_pressed {
+            self.settings_screen = Some(SettingsScreen::new());
+        } else {
+            return false;
+        }
+
+        *input = InputSnapshot { quit: input.quit, ..Default::default() };
+        true
+    }
+
+    /// Pauses, slows down or speeds up the game on the actions pressed in this frame.
+    ///
+    /// Speed changes are saved to the settings, the pause is not (only levels can be
+    /// paused, see [`GameState::Paused`]).
+    fn update_time_scale(&mut self, events: &[InputEvent]) {
+        let pressed = |action| {
+            events
+                .iter()
+                .any(|event| event.action == action && event.kind == InputEventKind::Pressed)
+        };
+
+        if pressed(Action::Pause) {
+            self.apply(Command::TogglePause);
+            match self.game_state {
+                GameState::Paused { .. } => self.show_notice("PAUSED"),
+                GameState::Playing { .. } => self.show_notice("RESUMED"),
+                _ => {}
+            }
+        }
+
+        let mut speed = self.settings.time_scale;
+        if pressed(Action::SlowDown) {
+            speed = speed.slower();
+        }
+        if pressed(Action::SpeedUp) {
+            speed = speed.faster();
+        }
+        if pressed(Action::InstantMoves) {
+            speed.instant = !speed.instant;
+        }
+
+        if speed != self.settings.time_scale {
+            let instant = if speed.instant { "  INSTANT MOVES" } else { "" };
+            self.show_notice(format!("GAME SPEED {} PERCENT{instant}", speed.percent()));
+            self.settings.time_scale = speed;
+            self.save_settings();
+        }
+    }
+
+    /// Writes the settings to their file, reporting failures.
+    fn save_settings(&mut self) {
+        if let Err(err) = self.settings.save() {
+            eprintln!("Cannot save settings: {err}");
+        }
+    }
+
+    /// Saves a recorded session unless replay files are disabled.
+    fn save_replay(&self, replay: &Replay, path: Option<PathBuf>) {
+        if self.save_replays {
+            save_replay(replay, path);
+        }
+    }
+
+    /// Applies a command to the game state.
+    fn apply(&mut self, command: Command) {
+        self.game_state = self.game_state.apply(command, &self.packs, &self.progress);
+    }
+
+    /// Applies the back, restart and level navigation commands pressed in this frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - input of this frame
+    /// * `back` - whether the input asks to go back to the menu
+    fn handle_commands(&mut self, input: &InputSnapshot, back: bool) {
+        let mut command = None;
+        for (down, held, pressed) in [
+            (back, &mut self.back_held, Command::Back),
+            (input.restart, &mut self.restart_held, Command::Restart),
+            (input.next_level, &mut self.next_level_held, Command::NextLevel),
+            (input.previous_level, &mut self.previous_level_held, Command::PreviousLevel),
+        ] {
+            if down && !*held {
+                command = command.or(Some(pressed));
+            }
+            *held = down;
+        }
+
+        if let Some(command) = command {
+            self.apply(command);
+        }
+    }
+
+    /// Acts on the option chosen on the level-complete screen.
+    fn choose(&mut self, option: CompleteOption) {
+        match option {
+            CompleteOption::Continue => self.apply(Command::Continue),
+            CompleteOption::Retry => self.apply(Command::Restart),
+            CompleteOption::Replay => match self.last_solution.clone() {
+                Some(replay) => {
+                    self.pending_replay = Some(replay);
+                    self.apply(Command::Restart);
+                }
+                None => {
+                    if let Some(screen) = &mut self.complete_screen {
+                        screen.set_notice("THE SOLUTION HAS NOT BEEN RECORDED");
+                    }
+                }
+            },
+        }
+    }
+
+    /// Shows a notice on the HUD status line for [`NOTICE_DURATION`] seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `notice` - text of the notice, with the glyphs of the HUD font only
+    fn show_notice(&mut self, notice: impl Into<String>) {
+        self.notice = Some((notice.into(), NOTICE_DURATION));
+    }
+
+    /// Builds a scene, falling back to the menu if its level cannot be loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - the scene to enter
+    fn enter_scene(&mut self, scene: Scene) {
+        // leaving a level ends its recording or playback
+        if matches!(self.scene, Scene::Level { .. }) {
+            self.playback = None;
+            self.played_replay = None;
+        }
+        if let Some(replay) = self.pending_replay.take() {
+            self.start_playback(&replay);
+        }
+        self.complete_screen = None;
+        self.fade_in = FADE_DURATION;
+        if let Some(replay) = self.recording.take() {
+            self.save_replay(&replay, last_replay_path());
+        }
+
+        let loaded =
+            load_scene(&self.packs, scene, &self.progress, &self.tiles_atlas, &self.entities_atlas);
+        match loaded {
+            Ok(loaded_game) => {
+                // levels with missing frames are rejected when loading, menus are generated
+                let missing = loaded_game.check_assets(&self.tiles_atlas, &self.entities_atlas);
+                for issue in missing {
+                    eprintln!("{}: {issue}", loaded_game.name);
+                }
+
+                self.game = loaded_game;
+                self.scene = scene;
+                self.game_state = GameState::entered(scene);
+                self.level_time = 0.0;
+
+                self.solved_boxes = match scene {
+                    Scene::LevelMenu { pack } => solved_levels(&self.packs[pack], &self.progress)
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, &solved)| solved)
+                        .map(|(i, _)| menu::entry_box_tile(i))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+
+                self.progress.last_played = match scene {
+                    Scene::PackMenu => None,
+                    Scene::LevelMenu { pack } => {
+                        Some(LastPlayed { pack: self.packs[pack].name.clone(), level: None })
+                    }
+                    Scene::Level { pack, level } => {
+                        Some(LastPlayed { pack: self.packs[pack].name.clone(), level: Some(level) })
+                    }
+                };
+                if let Err(err) = self.progress.save() {
+                    eprintln!("Cannot save progress: {err}");
+                }
+
+                (self.render, self.camera, self.state) = init_level(
+                    self.game.clone(),
+                    self.entities_atlas.clone(),
+                    self.tiles_atlas.clone(),
+                );
+                self.history.clear();
+                self.autoplay.clear();
+                self.cancel_hint();
+                self.move_buffer.clear();
+                self.selected_box = None;
+                self.recording = match self.current_level() {
+                    Some((pack, level)) if self.playback.is_none() => {
+                        self.packs[pack].level_id(level).map(|id| Replay::new(&id))
+                    }
+                    _ => None,
+                };
+                self.deadlocks = DeadlockDetector::new(&self.game);
+                self.deadlock_undo = false;
+                self.drawn_gates = None;
+                self.redraw_mechanisms();
+            }
+            Err(err) => {
+                eprintln!("Cannot load level: {err}");
+                let menu = scene.parent(self.packs.len()).unwrap_or(Scene::PackMenu);
+                self.game_state = GameState::transition(menu);
+            }
+        }
+    }
+
+    /// Prerenders the static background again when doors or holes of the level changed
+    /// since it was last drawn. Does nothing on maps without mechanisms.
+    fn redraw_mechanisms(&mut self) {
+        if !self.game.has_mechanisms() {
+            return;
+        }
+
+        let gates = Gates::new(&self.game, &self.state);
+        if self.drawn_gates.as_ref() == Some(&gates) {
+            return;
+        }
+
+        let scenery = mechanism_scenery(&self.game, &gates);
+        self.render.world_buf.fill(WORLD_BACKGROUND);
+        self.render.shadow_map.fill(0);
+        self.render.init(&scenery, &self.tiles_atlas);
+        self.drawn_gates = Some(gates);
+    }
+
+    /// Enters the pack or level whose menu box has been pushed onto its entry tile.
+    fn check_menu_selection(&mut self) {
+        let player_idle = matches!(self.state.player.unit.movement, world::UnitMovement::Idle);
+
+        for unit in &self.state.mobs {
+            if matches!(unit.movement, world::UnitMovement::Idle) && player_idle {
+                let pos = (unit.tile_x as u32, unit.tile_y as u32);
+
+                if let Some(&id) = self.game.links.get(&pos) {
+                    let entry = id as usize - 1;
+                    let selected = match self.scene {
+                        Scene::LevelMenu { pack } => Scene::Level { pack, level: entry },
+                        _ => Scene::LevelMenu { pack: entry },
+                    };
+                    self.game_state = self.game_state.apply(
+                        Command::Select(selected),
+                        &self.packs,
+                        &self.progress,
+                    );
+                    self.suc_boxes.push((unit.tile_x, unit.tile_y));
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Plays the moves of a finished hint search, or keeps the reason why there are none.
+    fn poll_hint(&mut self) {
+        let Some(search) = &self.hint_search else {
+            return;
+        };
+        let steps = match search.job.poll() {
+            JobStatus::Running => return,
+            JobStatus::Done(Solution::Solved(steps)) => steps,
+            JobStatus::Done(Solution::Unsolvable) => {
+                self.hint_message = Some("NO SOLUTION FROM HERE  TRY UNDO");
+                Vec::new()
+            }
+            JobStatus::Done(Solution::LimitReached) | JobStatus::Failed => {
+                self.hint_message = Some("NO HINT FOUND");
+                Vec::new()
+            }
+            JobStatus::Done(Solution::Unsupported) => {
+                self.hint_message = Some("NO HINTS FOR THIS LEVEL");
+                Vec::new()
+            }
+        };
+
+        let count = if search.whole { steps.len() } else { 1 };
+        self.autoplay = steps.iter().take(count).map(|step| step.direction).collect();
+        if !self.autoplay.is_empty() {
+            self.move_buffer.clear();
+        }
+        self.hint_search = None;
+    }
+
+    /// Caches the optimal counts once their search is done and shows them on the
+    /// completion screen.
+    fn poll_optimal(&mut self) {
+        let Some((level_key, job)) = &self.optimal_search else {
+            return;
+        };
+        match job.poll() {
+            JobStatus::Running => return,
+            JobStatus::Done(counts) => {
+                self.optimal_cache.insert(level_key.clone(), counts);
+                if let Some(screen) = &mut self.complete_screen {
+                    screen.set_optimal(counts.0, counts.1);
+                }
+            }
+            JobStatus::Failed => {}
+        }
+        self.optimal_search = None;
+    }
+
+    /// Abandons the running hint search and clears its message.
+    fn cancel_hint(&mut self) {
+        self.hint_search = None;
+        self.hint_message = None;
+    }
+
+    /// Counts the boxes on targets of their color and completes the level once it is solved.
+    fn check_level_solved(&mut self) {
+        let goal_count = self.game.target_positions.len();
+
+        if goal_count == 0 {
+            self.apply(Command::Back);
+            return;
+        }
+
+        let player_idle = matches!(self.state.player.unit.movement, world::UnitMovement::Idle);
+
+        for unit in &self.state.mobs {
+            if matches!(unit.movement, world::UnitMovement::Idle) {
+                let (x, y) = (unit.tile_x as u32, unit.tile_y as u32);
+
+                if self.game.accepts_box(x, y, unit.color.as_deref()) {
+                    self.suc_boxes.push((unit.tile_x, unit.tile_y));
+                    self.placed_count += 1;
+                }
+            }
+        }
+
+        if self.placed_count == goal_count && player_idle {
+            let level_id =
+                self.current_level().and_then(|(pack, level)| self.packs[pack].level_id(level));
+            if let Some(replay) = &self.recording {
+                self.save_replay(replay, solution_replay_path(&replay.level));
+            }
+
+            let moves = self.history.moves() as u32;
+            let pushes = self.history.pushes() as u32;
+            let level_time = self.level_time;
+            let previous_best = level_id
+                .as_ref()
+                .and_then(|level_id| self.progress.record(level_id))
+                .copied()
+                .filter(|record| record.solved);
+
+            // a played back solution is not a new result
+            if let Some(level_id) = level_id.as_ref().filter(|_| self.playback.is_none()) {
+                self.progress.record_solution(level_id, moves, pushes, level_time);
+            }
+
+            // the optimal counts are searched for once per level, in the background
+            let level_key = level_id.clone().unwrap_or_else(|| self.game.name.clone());
+            let (optimal_moves, optimal_pushes) = match self.optimal_cache.get(&level_key) {
+                Some(&counts) => counts,
+                None => {
+                    let searching =
+                        matches!(&self.optimal_search, Some((key, _)) if *key == level_key);
+                    if !searching {
+                        let game = self.game.clone();
+                        self.optimal_search =
+                            Some((level_key, Job::spawn(move || optimal_counts(&game))));
+                    }
+                    (None, None)
+                }
+            };
+
+            let result = LevelResult { level_id, moves, pushes, time: level_time };
+            let summary = LevelSummary {
+                level_name: self.game.name.clone(),
+                result: result.clone(),
+                best: previous_best,
+                optimal_moves,
+                optimal_pushes,
+            };
+
+            self.complete_screen = Some(CompleteScreen::new(summary));
+            self.last_solution = self.recording.clone().or_else(|| self.played_replay.clone());
+            self.last_result = Some(result);
+            self.apply(Command::Solved);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ferari::input::{key_name, parse_key, Action, KeyBindings, RepeatSettings};
+use ferari::time::TimeScale;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::save::{back_up, data_dir, write_atomic};
+
+/// File name of the settings file inside the data directory.
+const SETTINGS_FILE_NAME: &str = "settings.json";
+/// Format version written into the settings file.
+const SETTINGS_VERSION: u32 = 1;
+
+/// User settings, persisted as JSON next to the save file.
+#[derive(Debug, Default, Serialize)]
+pub struct Settings {
+    /// Format version of the settings file
+    version: u32,
+    /// Keys bound to the game actions
+    pub key_bindings: KeyBindings,
+    /// Initial delay and rate of repeated actions
+    pub key_repeat: RepeatSettings,
+    /// Game speed and instant moves
+    pub time_scale: TimeScale,
+    /// Whether a push that deadlocks the level is undone automatically
+    pub auto_undo_deadlock: bool,
+    /// Settings file location, `None` keeps the settings in memory only
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Settings {
+    /// Loads the settings from the default settings file.
+    ///
+    /// # Returns
+    ///
+    /// The loaded settings (defaults if there is no settings file), bound to the file.
+    pub fn load_default() -> Self {
+        match data_dir() {
+            Some(dir) => Self::load(dir.join(SETTINGS_FILE_NAME)),
+            None => Self::default(),
+        }
+    }
+
+    /// Loads the settings from a file.
+    ///
+    /// A missing file yields the default settings. The entries are read one by one: an
+    /// invalid entry (e.g. an action with an unknown key name) is reported and keeps its
+    /// default, the other entries are kept. A file that is not a JSON object at all is
+    /// reported and kept as a `.bak` file next to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - location of the settings file
+    ///
+    /// # Returns
+    ///
+    /// The loaded settings, bound to `path`.
+    pub fn load(path: PathBuf) -> Self {
+        let mut settings = match fs::read_to_string(&path) {
+            Ok(text) => match serde_json::from_str(&text) {
+                Ok(entries) => Self::from_entries(entries, &path),
+                Err(err) => {
+                    eprintln!("{}: invalid settings ignored: {err}", path.display());
+                    back_up(&path);
+                    Self::default()
+                }
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                eprintln!("{}: cannot read settings: {err}", path.display());
+                Self::default()
+            }
+        };
+
+        settings.path = Some(path);
+        settings
+    }
+
+    /// Reads the entries of a settings file, keeping the valid ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - entries of the settings file
+    /// * `path` - location of the settings file (for the warnings)
+    ///
+    /// # Returns
+    ///
+    /// The settings, with defaults for the missing and invalid entries.
+    fn from_entries(entries: Map<String, Value>, path: &Path) -> Self {
+        let mut settings = Self::default();
+
+        for (name, value) in entries {
+            let result = match name.as_str() {
+                "version" => parse(value).map(|version| settings.version = version),
+                "key_bindings" => {
+                    parse_bindings(value, &mut settings.key_bindings, path);
+                    Ok(())
+                }
+                "key_repeat" => parse(value).map(|repeat| settings.key_repeat = repeat),
+                "time_scale" => parse(value).map(|scale| settings.time_scale = scale),
+                "auto_undo_deadlock" => parse(value).map(|on| settings.auto_undo_deadlock = on),
+                _ => Err("unknown setting".to_string()),
+            };
+            if let Err(err) = result {
+                eprintln!("{}: setting {name} ignored: {err}", path.display());
+            }
+        }
+
+        settings
+    }
+
+    /// Writes the settings to their file.
+    ///
+    /// The settings are written to a temporary file first which then replaces the settings
+    /// file, so an interrupted write never leaves a truncated file behind.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` on success (or if the settings are kept in memory only).
+    pub fn save(&mut self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        self.version = SETTINGS_VERSION;
+
+        let text = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        write_atomic(path, &text)
+    }
+
+    /// Describes the keys bound to more than one action in a single HUD line.
+    ///
+    /// # Returns
+    ///
+    /// The first conflict and the number of further ones, e.g.
+    /// `KEY W BOUND TO MOVE UP AND UNDO`, or `None` without conflicts.
+    pub fn conflict_notice(&self) -> Option<String> {
+        let conflicts = self.key_bindings.conflicts();
+        let (key, actions) = conflicts.first()?;
+        let labels: Vec<&str> = actions.iter().map(|action| action.label()).collect();
+
+        let mut notice = format!("KEY {} BOUND TO {}", key_name(*key), labels.join(" AND "));
+        if conflicts.len() > 1 {
+            notice.push_str(&format!("  AND {} MORE", conflicts.len() - 1));
+        }
+        Some(notice)
+    }
+
+    /// Prints a warning for every key bound to more than one action.
+    pub fn report_conflicts(&self) {
+        for (key, actions) in self.key_bindings.conflicts() {
+            let labels: Vec<&str> = actions.iter().map(|action| action.label()).collect();
+            eprintln!("Key {} is bound to several actions: {}", key_name(key), labels.join(", "));
+        }
+    }
+}
+
+/// Reads a settings entry.
+fn parse<T: DeserializeOwned>(value: Value) -> Result<T, String> {
+    serde_json::from_value(value).map_err(|err| err.to_string())
+}
+
+/// Reads the key bindings entry action by action.
+///
+/// The keys of an action replace its default keys; an action that is unknown or has an
+/// unknown key name is reported and keeps its default keys.
+///
+/// # Arguments
+///
+/// * `value` - the `key_bindings` entry
+/// * `bindings` - bindings to change
+/// * `path` - location of the settings file (for the warnings)
+fn parse_bindings(value: Value, bindings: &mut KeyBindings, path: &Path) {
+    let entries: Map<String, Value> = match parse(value) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("{}: key bindings ignored: {err}", path.display());
+            return;
+        }
+    };
+
+    for (name, value) in entries {
+        let action = parse::<Action>(Value::String(name.clone()));
+        let keys = parse::<Vec<String>>(value).and_then(|names| {
+            names
+                .iter()
+                .map(|name| parse_key(name).ok_or(format!("unknown key '{name}'")))
+                .collect::<Result<Vec<_>, _>>()
+        });
+
+        match action.and_then(|action| keys.map(|keys| (action, keys))) {
+            Ok((action, keys)) => {
+                bindings.clear(action);
+                for key in keys {
+                    bindings.bind(action, key);
+                }
+            }
+            Err(err) => eprintln!("{}: key binding {name} ignored: {err}", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferari::input::Action;
+    use minifb::Key;
+    use std::env;
+
+    #[test]
+    fn test_settings_round_trip() {
+        let dir = env::temp_dir().join(format!("ferari_settings_{}", std::process::id()));
+        let path = dir.join(SETTINGS_FILE_NAME);
+
+        let mut settings = Settings::load(path.clone());
+        assert_eq!(settings.key_bindings, KeyBindings::default());
+
+        settings.key_bindings.bind(Action::Undo, Key::Backspace);
+        settings.time_scale = TimeScale { scale: 2.0, instant: true };
+        settings.auto_undo_deadlock = true;
+        settings.save().unwrap();
+        let loaded = Settings::load(path.clone());
+
+        fs::write(&path, r#"{"key_bindings": {"undo": ["NoSuchKey"]}}"#).unwrap();
+        let invalid = Settings::load(path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.key_bindings.keys(Action::Undo), [Key::Z, Key::Backspace]);
+        assert_eq!(loaded.time_scale, TimeScale { scale: 2.0, instant: true });
+        assert!(loaded.auto_undo_deadlock);
+        assert_eq!(invalid.key_bindings, KeyBindings::default());
+    }
+
+    #[test]
+    fn test_conflict_notice() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.conflict_notice(), None);
+
+        settings.key_bindings.bind(Action::Undo, Key::W);
+        assert_eq!(settings.conflict_notice().unwrap(), "KEY W BOUND TO MOVE UP AND UNDO");
+        settings.key_bindings.bind(Action::Redo, Key::R);
+        assert!(settings.conflict_notice().unwrap().ends_with("  AND 1 MORE"));
+    }
+
+    #[test]
+    fn test_invalid_entries_keep_the_valid_ones() {
+        let dir = env::temp_dir().join(format!("ferari_settings_entries_{}", std::process::id()));
+        let path = dir.join(SETTINGS_FILE_NAME);
+        fs::create_dir_all(&dir).unwrap();
+        let text = r#"{
+            "key_bindings": {"undo": ["NoSuchKey"], "redo": ["U"], "fly": ["F"]},
+            "time_scale": "fast",
+            "auto_undo_deadlock": true,
+            "colour": "blue"
+        }"#;
+        fs::write(&path, text).unwrap();
+
+        let mut settings = Settings::load(path.clone());
+        settings.save().unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        let leftovers = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(settings.key_bindings.keys(Action::Undo), [Key::Z]);
+        assert_eq!(settings.key_bindings.keys(Action::Redo), [Key::U]);
+        assert_eq!(settings.time_scale, TimeScale::default());
+        assert!(settings.auto_undo_deadlock);
+        assert!(saved.contains(r#""auto_undo_deadlock": true"#));
+        assert_eq!(leftovers, 1, "temporary file must be renamed");
+    }
+
+    #[test]
+    fn test_unparsable_file_is_kept_as_backup() {
+        let dir = env::temp_dir().join(format!("ferari_settings_backup_{}", std::process::id()));
+        let path = dir.join(SETTINGS_FILE_NAME);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "{ not json").unwrap();
+
+        let settings = Settings::load(path.clone());
+        let backup = fs::read_to_string(dir.join("settings.json.bak"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(settings.key_bindings, KeyBindings::default());
+        assert_eq!(backup.unwrap(), "{ not json");
+    }
+}
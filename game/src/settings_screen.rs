@@ -0,0 +1,407 @@
+use ferari::input::{Action, InputEvent, InputEventKind, KeyBindings};
+use ferari::render::{BitmapFont, Hud};
+use minifb::Key;
+
+use crate::settings::Settings;
+
+/// Distance of the screen text from the top-left corner of the screen, in pixels.
+const SCREEN_MARGIN: i32 = 4;
+/// Extra space between two lines, in pixels.
+const SCREEN_LINE_GAP: i32 = 4;
+/// Indentation of the selected line, in pixels.
+const SELECTED_INDENT: i32 = 16;
+/// Number of rows shown at once; the list scrolls with the selection.
+const VISIBLE_ROWS: usize = 9;
+/// Key that starts rebinding the selected action.
+const REBIND_KEY: Key = Key::Enter;
+/// Key that removes all keys of the selected action.
+const CLEAR_KEY: Key = Key::Backspace;
+/// Key that stops waiting for a key to bind.
+const CANCEL_KEY: Key = Key::Escape;
+/// Actions that always keep a key: without them the screen can neither be navigated
+/// nor closed.
+const ESSENTIAL_ACTIONS: [Action; 5] =
+    [Action::MoveUp, Action::MoveDown, Action::MoveLeft, Action::MoveRight, Action::Settings];
+/// Shown instead of the help when the last key of an essential action would be removed.
+const LAST_KEY_NOTICE: &str = "THE LAST KEY OF THIS ACTION CANNOT BE REMOVED";
+/// Row of the game speed.
+const SPEED_ROW: usize = 0;
+/// Row of the instant moves switch.
+const INSTANT_ROW: usize = 1;
+/// Row of the automatic undo of deadlocking pushes switch.
+const AUTO_UNDO_ROW: usize = 2;
+/// Row of the first action, the actions follow in the order of [`Action::ALL`].
+const FIRST_ACTION_ROW: usize = 3;
+/// Number of rows of the screen.
+const ROWS: usize = FIRST_ACTION_ROW + Action::ALL.len();
+
+/// Outcome of a settings screen frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenUpdate {
+    /// The screen stays open, nothing changed
+    Open,
+    /// The screen stays open, the settings changed
+    Changed,
+    /// The screen has been closed
+    Closed,
+}
+
+/// Settings screen with the game speed, the switches and every action with its keys.
+///
+/// The movement actions select a row (holding them repeats). On the game speed, instant
+/// moves and automatic undo rows left and right change the value. On an action row `Enter` waits
+/// for a key and binds it to the selected action (or unbinds it if it is already bound),
+/// `Escape` stops waiting, and `Backspace` removes all keys of the action. The movement
+/// and settings actions keep at least one key. The settings action closes the screen.
+/// Keys bound to several actions are marked as conflicts.
+#[derive(Debug, Clone, Default)]
+pub struct SettingsScreen {
+    /// Index of the selected row
+    selected: usize,
+    /// Whether the next pressed key is bound to the selected action
+    capturing: bool,
+    /// Why the last key press was refused, shown until the next key press
+    notice: Option<&'static str>,
+}
+
+impl SettingsScreen {
+    /// Opens the screen with the first row selected.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the selected action.
+    ///
+    /// # Returns
+    ///
+    /// The action, or `None` if the game speed or a switch row is selected.
+    pub fn selected(&self) -> Option<Action> {
+        self.selected.checked_sub(FIRST_ACTION_ROW).map(|i| Action::ALL[i])
+    }
+
+    /// Returns whether the screen is waiting for a key to bind.
+    ///
+    /// While it waits, the pressed key belongs to the screen and does not trigger its
+    /// action, e.g. quitting the game.
+    pub fn is_capturing(&self) -> bool {
+        self.capturing
+    }
+
+    /// Handles the input of a frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - input events of this frame, in order
+    /// * `keys` - keys pressed in this frame, in press order
+    /// * `settings` - settings being edited
+    pub fn update(
+        &mut self,
+        events: &[InputEvent],
+        keys: &[Key],
+        settings: &mut Settings,
+    ) -> ScreenUpdate {
+        let pressed = |action| {
+            events
+                .iter()
+                .any(|event| event.action == action && event.kind == InputEventKind::Pressed)
+        };
+        let pressed_or_repeated =
+            |action| events.iter().any(|event| event.is_press_or_repeat(action));
+        let bindings = &mut settings.key_bindings;
+        let speed = &mut settings.time_scale;
+        if !keys.is_empty() || events.iter().any(|event| event.kind == InputEventKind::Pressed) {
+            self.notice = None;
+        }
+        // whether `action` may lose `key`: essential actions keep at least one key
+        let may_unbind = |bindings: &KeyBindings, action, key| {
+            !ESSENTIAL_ACTIONS.contains(&action)
+                || bindings.keys(action).iter().any(|&other| other != key)
+        };
+
+        let update = match self.selected() {
+            Some(action) if self.capturing => match keys.first() {
+                Some(&CANCEL_KEY) => {
+                    self.capturing = false;
+                    ScreenUpdate::Open
+                }
+                Some(&key) => {
+                    self.capturing = false;
+                    if !bindings.keys(action).contains(&key) {
+                        bindings.bind(action, key);
+                        ScreenUpdate::Changed
+                    } else if may_unbind(bindings, action, key) {
+                        bindings.unbind(action, key);
+                        ScreenUpdate::Changed
+                    } else {
+                        self.notice = Some(LAST_KEY_NOTICE);
+                        ScreenUpdate::Open
+                    }
+                }
+                None => ScreenUpdate::Open,
+            },
+            Some(_) if keys.contains(&REBIND_KEY) => {
+                self.capturing = true;
+                ScreenUpdate::Open
+            }
+            Some(action) if keys.contains(&CLEAR_KEY) => {
+                if !ESSENTIAL_ACTIONS.contains(&action) {
+                    bindings.clear(action);
+                    ScreenUpdate::Changed
+                } else {
+                    self.notice = Some(LAST_KEY_NOTICE);
+                    ScreenUpdate::Open
+                }
+            }
+            _ if pressed(Action::Settings) => ScreenUpdate::Closed,
+            None if self.selected == SPEED_ROW && pressed_or_repeated(Action::MoveLeft) => {
+                *speed = speed.slower();
+                ScreenUpdate::Changed
+            }
+            None if self.selected == SPEED_ROW && pressed_or_repeated(Action::MoveRight) => {
+                *speed = speed.faster();
+                ScreenUpdate::Changed
+            }
+            None if pressed(Action::MoveLeft)
+                || pressed(Action::MoveRight)
+                || keys.contains(&REBIND_KEY) =>
+            {
+                match self.selected {
+                    INSTANT_ROW => speed.instant = !speed.instant,
+                    AUTO_UNDO_ROW => {
+                        settings.auto_undo_deadlock = !settings.auto_undo_deadlock;
+                    }
+                    _ => return ScreenUpdate::Open,
+                }
+                ScreenUpdate::Changed
+            }
+            _ => {
+                if pressed_or_repeated(Action::MoveUp) {
+                    self.selected = (self.selected + ROWS - 1) % ROWS;
+                } else if pressed_or_repeated(Action::MoveDown) {
+                    self.selected = (self.selected + 1) % ROWS;
+                }
+                ScreenUpdate::Open
+            }
+        };
+
+        update
+    }
+
+    /// Builds the overlay of the screen.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - settings to show
+    /// * `font` - font the overlay is drawn with (decides the line spacing)
+    pub fn hud(&self, settings: &Settings, font: &BitmapFont) -> Hud {
+        let bindings = &settings.key_bindings;
+        let conflicts = bindings.conflicts();
+        let first = self.selected.saturating_sub(VISIBLE_ROWS / 2).min(ROWS - VISIBLE_ROWS);
+
+        let mut lines = vec![(0, "SETTINGS".to_string())];
+        for row in (0..ROWS).skip(first).take(VISIBLE_ROWS) {
+            let selected = row == self.selected;
+            let line = match row.checked_sub(FIRST_ACTION_ROW).map(|i| Action::ALL[i]) {
+                None if row == SPEED_ROW => {
+                    format!("GAME SPEED  {} PERCENT", settings.time_scale.percent())
+                }
+                None if row == INSTANT_ROW => {
+                    format!("INSTANT MOVES  {}", on_off(settings.time_scale.instant))
+                }
+                None => format!("AUTO UNDO DEADLOCKS  {}", on_off(settings.auto_undo_deadlock)),
+                Some(action) => {
+                    let keys = if selected && self.capturing {
+                        "PRESS A KEY".to_string()
+                    } else {
+                        bindings.describe(action)
+                    };
+                    let conflict = bindings
+                        .keys(action)
+                        .iter()
+                        .any(|key| conflicts.iter().any(|(conflicting, _)| conflicting == key));
+
+                    let mut line = format!("{}  {}", action.label(), keys);
+                    if conflict {
+                        line.push_str("  CONFLICT");
+                    }
+                    line
+                }
+            };
+            lines.push((if selected { SELECTED_INDENT } else { 0 }, line));
+        }
+        let help = self.notice.unwrap_or(match self.selected() {
+            Some(_) if self.capturing => "ESCAPE CANCEL",
+            Some(_) => "ENTER ADD OR REMOVE KEY  BACKSPACE CLEAR",
+            None => "LEFT RIGHT CHANGE",
+        });
+        lines.push((0, help.to_string()));
+        lines.push((0, format!("{} CLOSE", bindings.describe(Action::Settings))));
+
+        let mut hud = Hud::new();
+        for (i, (indent, line)) in lines.into_iter().enumerate() {
+            let y = SCREEN_MARGIN + i as i32 * (font.line_height + SCREEN_LINE_GAP);
+            hud.text(SCREEN_MARGIN + indent, y, line);
+        }
+        hud
+    }
+}
+
+/// Returns the label of a switch.
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferari::assets::{Atlas, Frame};
+    use image::RgbaImage;
+    use std::collections::HashMap;
+
+    fn event(action: Action, kind: InputEventKind) -> [InputEvent; 1] {
+        [InputEvent { action, kind, time: 0.0 }]
+    }
+
+    /// Opens the screen with the first action (`MoveUp`) selected.
+    fn screen_on_first_action(settings: &mut Settings) -> SettingsScreen {
+        let mut screen = SettingsScreen::new();
+        for _ in 0..FIRST_ACTION_ROW {
+            screen.update(&event(Action::MoveDown, InputEventKind::Pressed), &[], settings);
+        }
+        screen
+    }
+
+    #[test]
+    fn test_navigation_reacts_to_presses_and_repeats() {
+        let mut settings = Settings::default();
+        let mut screen = SettingsScreen::new();
+        assert_eq!(screen.selected(), None);
+
+        let down = event(Action::MoveDown, InputEventKind::Pressed);
+        assert_eq!(screen.update(&down, &[], &mut settings), ScreenUpdate::Open);
+        screen.update(&event(Action::MoveDown, InputEventKind::Repeated), &[], &mut settings);
+        screen.update(&event(Action::MoveDown, InputEventKind::Released), &[], &mut settings);
+        assert_eq!(screen.selected(), None);
+        screen.update(&down, &[], &mut settings);
+        assert_eq!(screen.selected(), Some(Action::MoveUp));
+
+        for _ in 0..FIRST_ACTION_ROW + 1 {
+            screen.update(&event(Action::MoveUp, InputEventKind::Pressed), &[], &mut settings);
+        }
+        assert_eq!(screen.selected(), Some(Action::Quit));
+
+        let repeat = event(Action::Settings, InputEventKind::Repeated);
+        assert_eq!(screen.update(&repeat, &[], &mut settings), ScreenUpdate::Open);
+        let close = event(Action::Settings, InputEventKind::Pressed);
+        assert_eq!(screen.update(&close, &[], &mut settings), ScreenUpdate::Closed);
+    }
+
+    #[test]
+    fn test_rebinding_toggles_the_pressed_key() {
+        let mut settings = Settings::default();
+        let mut screen = screen_on_first_action(&mut settings);
+        let idle = [];
+
+        assert_eq!(screen.update(&idle, &[Key::Enter], &mut settings), ScreenUpdate::Open);
+        assert_eq!(screen.update(&idle, &[], &mut settings), ScreenUpdate::Open);
+        assert_eq!(screen.update(&idle, &[Key::Z], &mut settings), ScreenUpdate::Changed);
+        let bindings = &settings.key_bindings;
+        assert_eq!(bindings.keys(Action::MoveUp), [Key::W, Key::Up, Key::Z]);
+        assert_eq!(bindings.conflicts().len(), 1);
+
+        screen.update(&idle, &[Key::Enter], &mut settings);
+        screen.update(&idle, &[Key::W], &mut settings);
+        assert_eq!(settings.key_bindings.keys(Action::MoveUp), [Key::Up, Key::Z]);
+
+        let undo = Action::ALL.iter().position(|&action| action == Action::Undo).unwrap();
+        screen.selected = FIRST_ACTION_ROW + undo;
+        assert_eq!(screen.update(&idle, &[Key::Backspace], &mut settings), ScreenUpdate::Changed);
+        assert!(settings.key_bindings.keys(Action::Undo).is_empty());
+    }
+
+    #[test]
+    fn test_essential_actions_keep_their_last_key() {
+        let mut settings = Settings::default();
+        let mut screen = screen_on_first_action(&mut settings);
+        let idle = [];
+
+        assert_eq!(screen.update(&idle, &[Key::Backspace], &mut settings), ScreenUpdate::Open);
+        assert_eq!(settings.key_bindings.keys(Action::MoveUp), [Key::W, Key::Up]);
+        assert_eq!(screen.notice, Some(LAST_KEY_NOTICE));
+
+        screen.update(&idle, &[Key::Enter], &mut settings);
+        screen.update(&idle, &[Key::W], &mut settings);
+        screen.update(&idle, &[Key::Enter], &mut settings);
+        assert_eq!(screen.update(&idle, &[Key::Up], &mut settings), ScreenUpdate::Open);
+        assert_eq!(settings.key_bindings.keys(Action::MoveUp), [Key::Up]);
+        assert_eq!(screen.notice, Some(LAST_KEY_NOTICE));
+    }
+
+    #[test]
+    fn test_capture_is_cancelled_with_escape() {
+        let mut settings = Settings::default();
+        let mut screen = screen_on_first_action(&mut settings);
+
+        screen.update(&[], &[Key::Enter], &mut settings);
+        assert!(screen.is_capturing());
+        assert_eq!(screen.update(&[], &[Key::Escape], &mut settings), ScreenUpdate::Open);
+
+        assert!(!screen.is_capturing());
+        assert_eq!(settings.key_bindings.keys(Action::MoveUp), [Key::W, Key::Up]);
+    }
+
+    #[test]
+    fn test_speed_and_switch_rows_change_settings() {
+        let mut settings = Settings::default();
+        let mut screen = SettingsScreen::new();
+
+        let right = event(Action::MoveRight, InputEventKind::Pressed);
+        assert_eq!(screen.update(&right, &[], &mut settings), ScreenUpdate::Changed);
+        screen.update(&event(Action::MoveRight, InputEventKind::Repeated), &[], &mut settings);
+        assert_eq!(settings.time_scale.percent(), 400);
+        screen.update(&event(Action::MoveLeft, InputEventKind::Pressed), &[], &mut settings);
+        assert_eq!(settings.time_scale.percent(), 200);
+
+        screen.update(&event(Action::MoveDown, InputEventKind::Pressed), &[], &mut settings);
+        assert_eq!(screen.update(&[], &[Key::Enter], &mut settings), ScreenUpdate::Changed);
+        assert!(settings.time_scale.instant);
+        screen.update(&event(Action::MoveLeft, InputEventKind::Repeated), &[], &mut settings);
+        assert!(settings.time_scale.instant, "Repeats should not toggle the switch");
+        screen.update(&right, &[], &mut settings);
+        assert!(!settings.time_scale.instant);
+
+        screen.update(&event(Action::MoveDown, InputEventKind::Pressed), &[], &mut settings);
+        assert_eq!(screen.update(&right, &[], &mut settings), ScreenUpdate::Changed);
+        assert!(settings.auto_undo_deadlock);
+    }
+
+    #[test]
+    fn test_hud_marks_selection_and_conflicts() {
+        let frame = Frame { name: "letter_A".into(), x: 0, y: 0, w: 10, h: 20 };
+        let atlas = Atlas {
+            image: RgbaImage::new(10, 20),
+            frames: HashMap::from([(frame.name.clone(), frame)]),
+            tile_size: 10,
+            version: 1,
+        };
+        let font = BitmapFont::from_atlas(&atlas, 1.0);
+        let mut settings = Settings::default();
+        settings.key_bindings.bind(Action::Undo, Key::W);
+
+        let hud = SettingsScreen::new().hud(&settings, &font);
+
+        assert_eq!(hud.texts[0].text, "SETTINGS");
+        assert_eq!(hud.texts[1].text, "GAME SPEED  100 PERCENT");
+        assert_eq!(hud.texts[1].x, SCREEN_MARGIN + SELECTED_INDENT);
+        assert_eq!(hud.texts[2].text, "INSTANT MOVES  OFF");
+        assert_eq!(hud.texts[3].text, "AUTO UNDO DEADLOCKS  OFF");
+        assert_eq!(hud.texts[4].text, "MOVE UP  W Up  CONFLICT");
+        assert_eq!(hud.texts[4].x, SCREEN_MARGIN);
+        assert_eq!(hud.texts[VISIBLE_ROWS + 1].text, "LEFT RIGHT CHANGE");
+        assert_eq!(hud.texts.len(), VISIBLE_ROWS + 3);
+    }
+}
@@ -0,0 +1,878 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+use ferari::assets::GameMap;
+use ferari::world::{Direction, State};
+
+use crate::behaviour::{climb_map, get_dir_delta};
+use crate::deadlock::{color_classes, DeadlockDetector};
+
+/// Default number of search nodes expanded before the solver gives up.
+pub const MAX_EXPANDED_NODES: usize = 2_000_000;
+
+/// Directions tried by the solver, in order.
+const DIRECTIONS: [Direction; 4] = [Direction::NE, Direction::SE, Direction::SW, Direction::NW];
+
+/// Marker for cells from which a box can never reach a target.
+const UNREACHABLE: u32 = u32::MAX;
+
+/// Optimization criterion of the solver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SolveMode {
+    /// Minimize the number of box pushes
+    Pushes,
+    /// Minimize the number of player moves
+    Moves,
+}
+
+/// A single player move of a solution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolutionStep {
+    /// Direction of the move
+    pub direction: Direction,
+    /// Whether the move pushes a box
+    pub push: bool,
+}
+
+/// Outcome of a solver run.
+#[derive(Debug, PartialEq)]
+pub enum Solution {
+    /// The level is solvable with the given move sequence (empty if already solved)
+    Solved(Vec<SolutionStep>),
+    /// No sequence of moves places all boxes on targets
+    Unsolvable,
+    /// The search exceeded its node limit without a result
+    LimitReached,
+    /// The level has mechanisms (ice, holes, teleporters, ...) the solver does not model
+    Unsupported,
+}
+
+/// Static, solver-friendly view of a level.
+struct Board {
+    /// Map width in tiles
+    width: i32,
+    /// Map height in tiles
+    height: i32,
+    /// Cells that neither the player nor a box can enter
+    walls: Vec<bool>,
+    /// Directions in which a step can leave each cell (see [`climb_map`])
+    climb: Vec<[bool; 4]>,
+    /// Indices of all target cells
+    targets: Vec<usize>,
+    /// Colors of the targets; the index of a color is the class of its boxes and targets
+    /// (class `0` is the plain color)
+    colors: Vec<Option<String>>,
+    /// Color class of each target
+    target_classes: Vec<usize>,
+    /// Minimum number of pushes needed to bring a box from each cell to each target,
+    /// ignoring all other boxes (indexed by target, then by cell)
+    target_distance: Vec<Vec<u32>>,
+    /// Minimum number of pushes needed to bring a box from each cell to any target
+    /// (`UNREACHABLE` for dead squares)
+    push_distance: Vec<u32>,
+}
+
+/// Search node, stored in an arena and linked to its parent by index.
+struct Node {
+    /// Player cell
+    player: usize,
+    /// Color classes and cells of the boxes, sorted
+    boxes: Vec<(usize, usize)>,
+    /// Cost from the initial position
+    cost: u32,
+    /// Arena index of the parent node (the root points to itself)
+    parent: usize,
+    /// Player cell and direction of the move or push leading to this node
+    action: (usize, Direction),
+    /// Set when a cheaper node for the same position has been found
+    superseded: bool,
+}
+
+impl Board {
+    /// Builds the board from the map walkability and collidable objects.
+    fn new(game: &GameMap) -> Self {
+        let width = game.size[0] as i32;
+        let height = game.size[1] as i32;
+
+        let mut walls = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                walls.push(!game.is_walkable(x, y) || game.has_collidable_object_at(x, y));
+            }
+        }
+
+        let targets: Vec<usize> = game
+            .target_positions
+            .iter()
+            .map(|&(x, y)| (y as i32 * width + x as i32) as usize)
+            .collect();
+
+        let (colors, target_classes) = color_classes(game);
+
+        let mut board = Self {
+            width,
+            height,
+            walls,
+            climb: climb_map(game),
+            targets,
+            colors,
+            target_classes,
+            target_distance: Vec::new(),
+            push_distance: Vec::new(),
+        };
+        board.target_distance =
+            board.targets.iter().map(|&target| board.compute_push_distance(target)).collect();
+        board.push_distance = (0..board.walls.len())
+            .map(|cell| board.target_distance.iter().map(|d| d[cell]).min().unwrap_or(UNREACHABLE))
+            .collect();
+        board
+    }
+
+    /// Returns the color class of a box (a class without targets for colors no target has).
+    fn class_of(&self, color: Option<&str>) -> usize {
+        self.colors.iter().position(|c| c.as_deref() == color).unwrap_or(self.colors.len())
+    }
+
+    /// Returns the neighbouring cell in `direction`, or `None` if it is outside the map.
+    fn neighbour(&self, idx: usize, direction: Direction) -> Option<usize> {
+        let (dx, dy) = get_dir_delta(direction);
+        let x = idx as i32 % self.width + dx;
+        let y = idx as i32 / self.width + dy;
+
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+
+        Some((y * self.width + x) as usize)
+    }
+
+    /// Returns the neighbouring cell in `direction` if it can be entered.
+    fn open_neighbour(&self, idx: usize, direction: Direction) -> Option<usize> {
+        self.neighbour(idx, direction)
+            .filter(|&n| !self.walls[n] && self.climb[idx][direction as usize])
+    }
+
+    /// Computes push distances to `target` by pulling a box backwards from it.
+    ///
+    /// A box can be pulled from `b` to `b - d` if both `b - d` and `b - 2d` are free,
+    /// which is exactly the reverse of pushing it from `b - d` to `b`.
+    fn compute_push_distance(&self, target: usize) -> Vec<u32> {
+        let mut distance = vec![UNREACHABLE; self.walls.len()];
+        let mut queue = VecDeque::from([target]);
+        distance[target] = 0;
+
+        while let Some(cell) = queue.pop_front() {
+            for direction in DIRECTIONS {
+                let Some(box_cell) = self.open_neighbour(cell, direction) else {
+                    continue;
+                };
+                if self.open_neighbour(box_cell, direction).is_none() {
+                    continue;
+                }
+
+                if distance[box_cell] == UNREACHABLE {
+                    distance[box_cell] = distance[cell] + 1;
+                    queue.push_back(box_cell);
+                }
+            }
+        }
+
+        distance
+    }
+
+    /// Returns the cells the player can walk to without pushing, and the smallest of them
+    /// (used to identify equivalent player positions).
+    fn reachable(&self, player: usize, occupied: &[bool]) -> (Vec<bool>, usize) {
+        let mut visited = vec![false; self.walls.len()];
+        let mut queue = VecDeque::from([player]);
+        let mut min_cell = player;
+        visited[player] = true;
+
+        while let Some(cell) = queue.pop_front() {
+            min_cell = min_cell.min(cell);
+            for direction in DIRECTIONS {
+                if let Some(next) = self.open_neighbour(cell, direction) {
+                    if !visited[next] && !occupied[next] {
+                        visited[next] = true;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        (visited, min_cell)
+    }
+
+    /// Finds the shortest walk (without pushing) from `from` to `to`.
+    fn walk_path(&self, from: usize, to: usize, occupied: &[bool]) -> Vec<Direction> {
+        let mut came_from: Vec<Option<(usize, Direction)>> = vec![None; self.walls.len()];
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(cell) = queue.pop_front() {
+            if cell == to {
+                break;
+            }
+            for direction in DIRECTIONS {
+                if let Some(next) = self.open_neighbour(cell, direction) {
+                    if next != from && came_from[next].is_none() && !occupied[next] {
+                        came_from[next] = Some((cell, direction));
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        let mut path = Vec::new();
+        let mut cell = to;
+        while let Some((prev, direction)) = came_from[cell] {
+            path.push(direction);
+            cell = prev;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Returns an occupancy grid for the given boxes.
+    fn occupancy(&self, boxes: &[(usize, usize)]) -> Vec<bool> {
+        let mut occupied = vec![false; self.walls.len()];
+        for &(_, b) in boxes {
+            occupied[b] = true;
+        }
+        occupied
+    }
+
+    /// Returns the color class of the box on each cell (`None` for free cells).
+    fn box_classes(&self, boxes: &[(usize, usize)]) -> Vec<Option<usize>> {
+        let mut classes = vec![None; self.walls.len()];
+        for &(class, b) in boxes {
+            classes[b] = Some(class);
+        }
+        classes
+    }
+
+    /// Checks whether every target is covered by a box of its color.
+    fn is_solved(&self, boxes: &[(usize, usize)]) -> bool {
+        self.targets
+            .iter()
+            .zip(&self.target_classes)
+            .all(|(&target, &class)| boxes.binary_search(&(class, target)).is_ok())
+    }
+
+    /// Checks whether a box may be placed on `cell` without creating a simple deadlock.
+    fn allows_box(&self, cell: usize, prune_dead: bool) -> bool {
+        !prune_dead || self.push_distance[cell] != UNREACHABLE
+    }
+
+    /// Admissible lower bound on the remaining cost.
+    ///
+    /// Every box has to reach its own target of its color, so the cheapest assignment of
+    /// boxes to targets by push distance bounds the remaining pushes (and therefore moves)
+    /// from below. With surplus boxes not every box has to be placed, so no estimate is made.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(bound)` - the lower bound.
+    /// * `None` - if boxes cannot be assigned to distinct reachable targets (a deadlock).
+    fn heuristic(&self, boxes: &[(usize, usize)]) -> Option<u32> {
+        if boxes.len() != self.targets.len() {
+            return Some(0);
+        }
+
+        let costs: Vec<Vec<u32>> = boxes
+            .iter()
+            .map(|&(class, b)| {
+                let distances = self.target_distance.iter().zip(&self.target_classes);
+                distances
+                    .map(
+                        |(distance, &target_class)| {
+                            if target_class == class {
+                                distance[b]
+                            } else {
+                                UNREACHABLE
+                            }
+                        },
+                    )
+                    .collect()
+            })
+            .collect();
+
+        min_cost_assignment(&costs)
+    }
+}
+
+/// Solves the square assignment problem with the Hungarian algorithm.
+///
+/// # Arguments
+///
+/// * `costs` - square matrix, `costs[i][j]` is the cost of assigning row `i` to column `j`
+///   (`UNREACHABLE` if not allowed)
+///
+/// # Returns
+///
+/// The minimal total cost, or `None` if every assignment uses a forbidden pair.
+fn min_cost_assignment(costs: &[Vec<u32>]) -> Option<u32> {
+    const FORBIDDEN: i64 = 1 << 32;
+    let n = costs.len();
+    let cost = |i: usize, j: usize| match costs[i - 1][j - 1] {
+        UNREACHABLE => FORBIDDEN,
+        c => c as i64,
+    };
+
+    // Potentials and matching are 1-indexed, column 0 is a virtual start
+    let mut u = vec![0i64; n + 1];
+    let mut v = vec![0i64; n + 1];
+    let mut row_of = vec![0usize; n + 1];
+    let mut way = vec![0usize; n + 1];
+
+    for i in 1..=n {
+        row_of[0] = i;
+        let mut j0 = 0;
+        let mut min_v = vec![i64::MAX; n + 1];
+        let mut used = vec![false; n + 1];
+
+        loop {
+            used[j0] = true;
+            let i0 = row_of[j0];
+            let mut delta = i64::MAX;
+            let mut j1 = 0;
+
+            for j in 1..=n {
+                if used[j] {
+                    continue;
+                }
+                let reduced = cost(i0, j) - u[i0] - v[j];
+                if reduced < min_v[j] {
+                    min_v[j] = reduced;
+                    way[j] = j0;
+                }
+                if min_v[j] < delta {
+                    delta = min_v[j];
+                    j1 = j;
+                }
+            }
+
+            for j in 0..=n {
+                if used[j] {
+                    u[row_of[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_v[j] -= delta;
+                }
+            }
+
+            j0 = j1;
+            if row_of[j0] == 0 {
+                break;
+            }
+        }
+
+        loop {
+            let j1 = way[j0];
+            row_of[j0] = row_of[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let total: i64 = (1..=n).map(|j| cost(row_of[j], j)).sum();
+    (total < FORBIDDEN).then_some(total as u32)
+}
+
+/// Moves a box in the sorted box list.
+fn move_box(boxes: &[(usize, usize)], from: usize, to: usize) -> Vec<(usize, usize)> {
+    let mut moved: Vec<(usize, usize)> =
+        boxes.iter().map(|&(class, b)| (class, if b == from { to } else { b })).collect();
+    moved.sort_unstable();
+    moved
+}
+
+/// Solves the level from the given state.
+///
+/// The search is an A* over box configurations. In [`SolveMode::Pushes`] each node is a
+/// box configuration together with the area reachable by the player, and edges are single
+/// pushes; walking between pushes is filled in afterwards. In [`SolveMode::Moves`] each node
+/// stores the exact player position and edges are single moves. Positions that put a box on
+/// a dead square (a cell from which no target can be reached) are pruned. Boxes of a color
+/// only count on targets of the same color (see [`GameMap::accepts_box`]).
+///
+/// # Arguments
+///
+/// * `game` - the level map (walls, collidable objects and targets)
+/// * `state` - the position to solve from: player tile and tiles of all mobs (boxes)
+/// * `mode` - optimization criterion
+///
+/// # Returns
+///
+/// A [`Solution`] with the move sequence, or the reason why there is none.
+pub fn solve(game: &GameMap, state: &State, mode: SolveMode) -> Solution {
+    solve_with_limit(game, state, mode, MAX_EXPANDED_NODES)
+}
+
+/// Same as [`solve`], but gives up after expanding `max_nodes` search nodes.
+///
+/// Useful when the answer is needed quickly, e.g. for in-game hints.
+pub fn solve_with_limit(
+    game: &GameMap,
+    state: &State,
+    mode: SolveMode,
+    max_nodes: usize,
+) -> Solution {
+    if game.has_mechanisms() {
+        return Solution::Unsupported;
+    }
+
+    let board = Board::new(game);
+    let deadlocks = DeadlockDetector::new(game);
+    let to_idx = |x: i32, y: i32| (y * board.width + x) as usize;
+
+    let player = to_idx(state.player.unit.tile_x, state.player.unit.tile_y);
+    let mut boxes: Vec<(usize, usize)> = state
+        .mobs
+        .iter()
+        .map(|m| (board.class_of(m.color.as_deref()), to_idx(m.tile_x, m.tile_y)))
+        .collect();
+    boxes.sort_unstable();
+
+    if boxes.len() < board.targets.len() {
+        return Solution::Unsolvable;
+    }
+
+    // With surplus boxes a box on a dead square may simply stay there
+    let prune_dead = boxes.len() == board.targets.len();
+    let freezes = |classes: &[Option<usize>], from: usize, to: usize| {
+        let mut after = classes.to_vec();
+        after[to] = after[from].take();
+        deadlocks.is_push_deadlock(&after, to)
+    };
+    let Some(root_estimate) = board.heuristic(&boxes) else {
+        return Solution::Unsolvable;
+    };
+
+    let root = Node {
+        player,
+        boxes,
+        cost: 0,
+        parent: 0,
+        action: (player, Direction::SE),
+        superseded: false,
+    };
+    let mut nodes = vec![root];
+    let mut seen: HashMap<(usize, Vec<(usize, usize)>), usize> = HashMap::new();
+    let mut open = BinaryHeap::new();
+
+    let root_key = match mode {
+        SolveMode::Pushes => board.reachable(player, &board.occupancy(&nodes[0].boxes)).1,
+        SolveMode::Moves => player,
+    };
+    seen.insert((root_key, nodes[0].boxes.clone()), 0);
+    // Among equally promising nodes the deepest one is expanded first
+    open.push(Reverse((root_estimate, Reverse(0u32), 0usize)));
+
+    let mut expanded = 0;
+    while let Some(Reverse((_, Reverse(cost), idx))) = open.pop() {
+        if nodes[idx].superseded {
+            continue;
+        }
+        if board.is_solved(&nodes[idx].boxes) {
+            return Solution::Solved(reconstruct(&board, &nodes, idx, mode));
+        }
+
+        expanded += 1;
+        if expanded > max_nodes {
+            return Solution::LimitReached;
+        }
+
+        let occupied = board.occupancy(&nodes[idx].boxes);
+        let classes = board.box_classes(&nodes[idx].boxes);
+        let mut successors = Vec::new();
+
+        match mode {
+            SolveMode::Pushes => {
+                let (reachable, _) = board.reachable(nodes[idx].player, &occupied);
+                for &(_, b) in &nodes[idx].boxes {
+                    for direction in DIRECTIONS {
+                        let Some(next) = board.open_neighbour(b, direction) else {
+                            continue;
+                        };
+                        // the player has to be able to step from behind onto the box cell
+                        let behind = board.neighbour(b, opposite(direction)).filter(|&p| {
+                            reachable[p] && board.open_neighbour(p, direction) == Some(b)
+                        });
+                        let Some(behind) = behind else {
+                            continue;
+                        };
+                        if occupied[next] || !board.allows_box(next, prune_dead) {
+                            continue;
+                        }
+                        if prune_dead && freezes(&classes, b, next) {
+                            continue;
+                        }
+
+                        successors.push((
+                            b,
+                            move_box(&nodes[idx].boxes, b, next),
+                            (behind, direction),
+                        ));
+                    }
+                }
+            }
+            SolveMode::Moves => {
+                let player = nodes[idx].player;
+                for direction in DIRECTIONS {
+                    let Some(next) = board.open_neighbour(player, direction) else {
+                        continue;
+                    };
+
+                    if !occupied[next] {
+                        successors.push((next, nodes[idx].boxes.clone(), (player, direction)));
+                        continue;
+                    }
+
+                    let Some(box_next) = board.open_neighbour(next, direction) else {
+                        continue;
+                    };
+                    if occupied[box_next] || !board.allows_box(box_next, prune_dead) {
+                        continue;
+                    }
+                    if prune_dead && freezes(&classes, next, box_next) {
+                        continue;
+                    }
+
+                    successors.push((
+                        next,
+                        move_box(&nodes[idx].boxes, next, box_next),
+                        (player, direction),
+                    ));
+                }
+            }
+        }
+
+        for (new_player, new_boxes, action) in successors {
+            let key = match mode {
+                SolveMode::Pushes => board.reachable(new_player, &board.occupancy(&new_boxes)).1,
+                SolveMode::Moves => new_player,
+            };
+            let new_cost = cost + 1;
+
+            if let Some(&known) = seen.get(&(key, new_boxes.clone())) {
+                if nodes[known].cost <= new_cost {
+                    continue;
+                }
+                nodes[known].superseded = true;
+            }
+
+            let Some(estimate) = board.heuristic(&new_boxes) else {
+                continue;
+            };
+            nodes.push(Node {
+                player: new_player,
+                boxes: new_boxes.clone(),
+                cost: new_cost,
+                parent: idx,
+                action,
+                superseded: false,
+            });
+            seen.insert((key, new_boxes), nodes.len() - 1);
+            open.push(Reverse((new_cost + estimate, Reverse(new_cost), nodes.len() - 1)));
+        }
+    }
+
+    Solution::Unsolvable
+}
+
+/// Returns the direction opposite to `direction`.
+fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::NE => Direction::SW,
+        Direction::SW => Direction::NE,
+        Direction::SE => Direction::NW,
+        Direction::NW => Direction::SE,
+    }
+}
+
+/// Rebuilds the player move sequence leading from the root to node `goal`.
+fn reconstruct(board: &Board, nodes: &[Node], goal: usize, mode: SolveMode) -> Vec<SolutionStep> {
+    let mut chain = Vec::new();
+    let mut idx = goal;
+    while idx != 0 {
+        chain.push(idx);
+        idx = nodes[idx].parent;
+    }
+    chain.reverse();
+
+    let mut steps = Vec::new();
+    let mut player = nodes[0].player;
+    let mut boxes = nodes[0].boxes.clone();
+
+    for idx in chain {
+        let (from, direction) = nodes[idx].action;
+
+        if mode == SolveMode::Pushes {
+            let walk = board.walk_path(player, from, &board.occupancy(&boxes));
+            steps.extend(walk.into_iter().map(|direction| SolutionStep { direction, push: false }));
+        }
+
+        let push = nodes[idx].boxes != boxes;
+        steps.push(SolutionStep { direction, push });
+
+        player = nodes[idx].player;
+        boxes = nodes[idx].boxes.clone();
+    }
+
+    steps
+}
+
+/// Formats a move sequence in the standard LURD notation.
+///
+/// Moves are written as `u`, `d`, `l`, `r` (upper-case for pushes), where up is the
+/// `W` key direction (`NE`), right is `D` (`SE`), down is `S` (`SW`) and left is `A` (`NW`).
+///
+/// # Arguments
+///
+/// * `steps` - move sequence returned by [`solve`]
+///
+/// # Returns
+///
+/// The LURD string.
+pub fn to_lurd(steps: &[SolutionStep]) -> String {
+    steps
+        .iter()
+        .map(|step| {
+            let c = match step.direction {
+                Direction::NE => 'u',
+                Direction::SE => 'r',
+                Direction::SW => 'd',
+                Direction::NW => 'l',
+            };
+            if step.push {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Parses a move sequence in the LURD notation written by [`to_lurd`].
+///
+/// # Arguments
+///
+/// * `lurd` - LURD string; whitespace is ignored
+///
+/// # Returns
+///
+/// * `Ok(Vec<SolutionStep>)` - the moves, pushes marked by upper-case letters.
+/// * `Err(char)` - the first character that is not a LURD move.
+pub fn from_lurd(lurd: &str) -> Result<Vec<SolutionStep>, char> {
+    lurd.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| {
+            let direction = match c.to_ascii_lowercase() {
+                'u' => Direction::NE,
+                'r' => Direction::SE,
+                'd' => Direction::SW,
+                'l' => Direction::NW,
+                _ => return Err(c),
+            };
+            Ok(SolutionStep { direction, push: c.is_ascii_uppercase() })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ferari::assets::TileType;
+    use std::collections::LinkedList;
+
+    /// Builds a level from an XSB-like layout (`#` wall, `.` target, `$` box, `*` box on target,
+    /// `@` player).
+    pub(crate) fn map_from_layout(layout: &[&str]) -> (GameMap, State) {
+        let height = layout.len();
+        let width = layout.iter().map(|row| row.len()).max().unwrap();
+        let mut walk_map = vec![TileType::Empty; width * height];
+        let mut target_positions = LinkedList::new();
+        let mut player = (0, 0);
+        let mut boxes = Vec::new();
+
+        for (y, row) in layout.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    '#' => walk_map[y * width + x] = TileType::Wall,
+                    '.' => target_positions.push_back((x as u32, y as u32)),
+                    '$' => boxes.push((x as i32, y as i32)),
+                    '*' => {
+                        target_positions.push_back((x as u32, y as u32));
+                        boxes.push((x as i32, y as i32));
+                    }
+                    '@' => player = (x as i32, y as i32),
+                    _ => {}
+                }
+            }
+        }
+
+        let game = GameMap {
+            name: "test".to_string(),
+            tile_size: 128,
+            size: [width as u32, height as u32],
+            mobs: HashMap::new(),
+            objects: HashMap::new(),
+            tiles: HashMap::new(),
+            target_positions,
+            links: HashMap::new(),
+            target_colors: HashMap::new(),
+            walk_map,
+            height_map: vec![0; width * height],
+            object_collidable_map: vec![false; width * height],
+        };
+
+        let mut state = State { grid_width: width as i32, ..State::default() };
+        state.player.unit.tile_x = player.0;
+        state.player.unit.tile_y = player.1;
+        for (x, y) in boxes {
+            state.mobs.push(ferari::world::Unit { tile_x: x, tile_y: y, ..Default::default() });
+        }
+
+        (game, state)
+    }
+
+    /// Replays the steps on the board and checks that they are legal and solve the level.
+    fn verify(game: &GameMap, state: &State, steps: &[SolutionStep]) -> bool {
+        let board = Board::new(game);
+        let to_idx = |x: i32, y: i32| (y * board.width + x) as usize;
+        let mut player = to_idx(state.player.unit.tile_x, state.player.unit.tile_y);
+        let mut boxes: Vec<(usize, usize)> = state
+            .mobs
+            .iter()
+            .map(|m| (board.class_of(m.color.as_deref()), to_idx(m.tile_x, m.tile_y)))
+            .collect();
+        boxes.sort_unstable();
+        let occupied = |boxes: &[(usize, usize)], cell| boxes.iter().any(|&(_, b)| b == cell);
+
+        for step in steps {
+            let Some(next) = board.open_neighbour(player, step.direction) else {
+                return false;
+            };
+            if occupied(&boxes, next) {
+                let Some(box_next) = board.open_neighbour(next, step.direction) else {
+                    return false;
+                };
+                if occupied(&boxes, box_next) || !step.push {
+                    return false;
+                }
+                boxes = move_box(&boxes, next, box_next);
+            } else if step.push {
+                return false;
+            }
+            player = next;
+        }
+
+        board.is_solved(&boxes)
+    }
+
+    #[test]
+    fn test_solve_simple_corridor() {
+        let (game, state) = map_from_layout(&["#######", "#@ $ .#", "#######"]);
+
+        let Solution::Solved(steps) = solve(&game, &state, SolveMode::Moves) else {
+            panic!("corridor must be solvable");
+        };
+        assert_eq!(to_lurd(&steps), "rRR");
+        assert_eq!(from_lurd("rRR"), Ok(steps.clone()));
+        assert_eq!(from_lurd("rRx"), Err('x'));
+        assert!(verify(&game, &state, &steps));
+    }
+
+    #[test]
+    fn test_solve_box_in_corner_is_unsolvable() {
+        let (game, state) = map_from_layout(&["#####", "#$  #", "# @.#", "#####"]);
+
+        assert_eq!(solve(&game, &state, SolveMode::Pushes), Solution::Unsolvable);
+        assert_eq!(solve(&game, &state, SolveMode::Moves), Solution::Unsolvable);
+    }
+
+    #[test]
+    fn test_solve_already_solved() {
+        let (game, mut state) = map_from_layout(&["#####", "#@ .#", "#####"]);
+        state.mobs.push(ferari::world::Unit { tile_x: 3, tile_y: 1, ..Default::default() });
+
+        assert_eq!(solve(&game, &state, SolveMode::Pushes), Solution::Solved(vec![]));
+    }
+
+    #[test]
+    fn test_push_and_move_optimal_modes() {
+        // Reaching the far side of the box costs moves but no pushes
+        let (game, state) = map_from_layout(&["######", "#    #", "# $@ #", "#.   #", "######"]);
+
+        let Solution::Solved(by_pushes) = solve(&game, &state, SolveMode::Pushes) else {
+            panic!("level must be solvable");
+        };
+        let Solution::Solved(by_moves) = solve(&game, &state, SolveMode::Moves) else {
+            panic!("level must be solvable");
+        };
+
+        assert!(verify(&game, &state, &by_pushes));
+        assert!(verify(&game, &state, &by_moves));
+        assert_eq!(by_pushes.iter().filter(|s| s.push).count(), 2);
+        assert!(by_moves.len() <= by_pushes.len());
+    }
+
+    #[test]
+    fn test_solve_colored_boxes() {
+        // the boxes cannot pass each other, so only one coloring of the targets is solvable
+        let (mut game, mut state) = map_from_layout(&["#######", "#.$@$.#", "#######"]);
+        let red = Some("red".to_string());
+        state.mobs.iter_mut().find(|m| m.tile_x == 4).unwrap().color = red.clone();
+
+        game.target_colors.insert((5, 1), "red".to_string());
+        let Solution::Solved(steps) = solve(&game, &state, SolveMode::Moves) else {
+            panic!("level must be solvable");
+        };
+        assert!(verify(&game, &state, &steps));
+        assert_eq!(to_lurd(&steps), "RlL");
+
+        game.target_colors.clear();
+        game.target_colors.insert((1, 1), "red".to_string());
+        assert_eq!(solve(&game, &state, SolveMode::Pushes), Solution::Unsolvable);
+    }
+
+    #[test]
+    fn test_push_must_not_climb_a_ledge() {
+        // the box and its target lie on a plateau the player cannot climb onto, so the
+        // push from the lower floor onto the plateau is not possible
+        let rows = ["#######", "#     #", "#@ $  #", "#  .  #", "#######"];
+        let (mut game, state) = map_from_layout(&rows);
+        for y in 1..4 {
+            for x in 3..6 {
+                game.height_map[y * 7 + x] = 1;
+            }
+        }
+
+        assert_eq!(solve(&game, &state, SolveMode::Pushes), Solution::Unsolvable);
+        assert_eq!(solve(&game, &state, SolveMode::Moves), Solution::Unsolvable);
+    }
+
+    #[test]
+    fn test_min_cost_assignment() {
+        let costs = vec![vec![4, 1, 3], vec![2, 0, 5], vec![3, 2, 2]];
+        assert_eq!(min_cost_assignment(&costs), Some(5));
+
+        let blocked = vec![vec![1, UNREACHABLE], vec![2, UNREACHABLE]];
+        assert_eq!(min_cost_assignment(&blocked), None);
+    }
+
+    #[test]
+    fn test_solve_game_levels() {
+        for level in ["level1", "level2", "level3", "level4"] {
+            let game = GameMap::load(format!("../game_levels/{level}.json")).unwrap();
+            let state = State::new(&game);
+
+            let Solution::Solved(steps) = solve(&game, &state, SolveMode::Pushes) else {
+                panic!("{level} must be solvable");
+            };
+            assert!(verify(&game, &state, &steps), "{level}: invalid solution");
+        }
+    }
+}